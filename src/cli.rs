@@ -0,0 +1,122 @@
+use anyhow::{Result, anyhow};
+use sqlx::PgPool;
+
+use crate::modules::action::{EventLog, LogManager};
+use crate::modules::action::shadow::ShadowBook;
+use crate::modules::action::symbol_switch::SymbolSwitchStore;
+use crate::config::okx::OkxEndpoints;
+use crate::config::risk_profile::RiskProfile;
+use crate::modules::action::TradeExecutor;
+use crate::modules::brain::MemorySystem;
+use crate::modules::evolution::backfill;
+use crate::modules::evolution::stats::DEFAULT_WINDOW_DAYS;
+use crate::modules::perception::MarketDataFetcher;
+use crate::utils::http_client::HttpClientFactory;
+
+/// 命令行子命令入口
+/// 用法: rust_trader <command> [args...]
+pub async fn run(args: &[String], pool: &PgPool) -> Result<()> {
+    let logger = LogManager::new(pool.clone());
+
+    match args[0].as_str() {
+        "stats" => {
+            let window_days = match args.get(1) {
+                Some(d) => d.parse::<i32>().map_err(|_| anyhow!("Invalid window days: {}", d))?,
+                None => DEFAULT_WINDOW_DAYS,
+            };
+            let report = logger.fetch_performance_stats(window_days).await?;
+            println!("📐 Performance Stats\n{}", report);
+            Ok(())
+        }
+        "reasoning" => {
+            let order_id = args.get(1).ok_or_else(|| anyhow!("Usage: reasoning <order_id>"))?;
+            match logger.fetch_reasoning(order_id).await? {
+                Some(rec) => {
+                    println!("🧠 Order {} | {} {} | {} | strategy {}",
+                        order_id, rec.symbol, rec.direction, rec.created_at,
+                        rec.strategy_version.as_deref().unwrap_or("unknown"));
+                    println!("{}", rec.reasoning.as_deref().filter(|r| !r.is_empty()).unwrap_or("(no reasoning recorded)"));
+                }
+                None => println!("No trade found for order id {}", order_id),
+            }
+            Ok(())
+        }
+        "shadow" => {
+            let window_days = match args.get(1) {
+                Some(d) => d.parse::<i32>().map_err(|_| anyhow!("Invalid window days: {}", d))?,
+                None => DEFAULT_WINDOW_DAYS,
+            };
+            let report = ShadowBook::new(pool.clone()).report(window_days).await?;
+            println!("👻 Shadow Mode Performance\n{}", report);
+            Ok(())
+        }
+        "versions" => {
+            let window_days = match args.get(1) {
+                Some(d) => d.parse::<i32>().map_err(|_| anyhow!("Invalid window days: {}", d))?,
+                None => DEFAULT_WINDOW_DAYS,
+            };
+            let versions = logger.fetch_version_stats(window_days).await?;
+            println!("🧪 Strategy Versions (last {}d)", window_days);
+            if versions.is_empty() {
+                println!("No closed trades in the window.");
+            }
+            for v in versions {
+                println!("{}", v);
+            }
+            Ok(())
+        }
+        "timeline" => {
+            let id = args.get(1).ok_or_else(|| anyhow!("Usage: timeline <order_id|correlation_id>"))?;
+            let events = EventLog::new(pool.clone()).timeline(id).await?;
+            if events.is_empty() {
+                println!("No events found for {}", id);
+            }
+            // 每行一个 JSON 事件，可直接重定向为 JSONL 导出
+            for ev in events {
+                println!("{}", serde_json::json!({
+                    "ts": ev.ts,
+                    "correlation_id": ev.correlation_id,
+                    "symbol": ev.symbol,
+                    "kind": ev.kind,
+                    "order_id": ev.order_id,
+                    "payload": ev.payload,
+                }));
+            }
+            Ok(())
+        }
+        "backfill" => {
+            let risk_profile = RiskProfile::load()?;
+            let lookback_days = match args.get(1) {
+                Some(d) => d.parse::<u32>().ok().filter(|d| (1..=90).contains(d)).ok_or_else(|| anyhow!("Invalid lookback days: {} (1-90)", d))?,
+                None => risk_profile.backfill.lookback_days,
+            };
+            let okx = OkxEndpoints::from_env()?;
+            let std_client = HttpClientFactory::create()?;
+            let direct_client = HttpClientFactory::create_direct()?;
+            let executor = TradeExecutor::new(std_client.clone(), &okx, risk_profile.execution.clone(), risk_profile.settle_ccy(), risk_profile.paper.clone());
+            let fetcher = MarketDataFetcher::new(std_client, &okx, &risk_profile.indicators, &risk_profile.regime);
+            let qdrant_url = std::env::var("QDRANT_URL").unwrap_or("http://localhost:6334".to_string());
+            let memory = MemorySystem::new(qdrant_url, direct_client, risk_profile.memory.clone())?;
+            memory.init().await?;
+
+            println!("📼 Replaying the last {} days of OKX fills into memory...", lookback_days);
+            let report = backfill::run(pool, &executor, &fetcher, &memory, lookback_days, risk_profile.backfill.max_trades).await?;
+            println!("📼 Backfill done: {}", report);
+            Ok(())
+        }
+        // 运行中的主循环在下一轮读取开关，无需重启
+        cmd @ ("enable" | "disable") => {
+            let symbol = args.get(1).ok_or_else(|| anyhow!("Usage: {} <symbol> [reason]", cmd))?;
+            let enabled = cmd == "enable";
+            let reason = args[2..].join(" ");
+            SymbolSwitchStore::new(pool.clone()).set(symbol, enabled, &reason).await?;
+            if enabled {
+                println!("▶️ {} new entries enabled (takes effect next cycle)", symbol);
+            } else {
+                println!("⏸️ {} new entries disabled (takes effect next cycle, open positions are still managed)", symbol);
+            }
+            Ok(())
+        }
+        other => Err(anyhow!("Unknown command '{}'. Available: stats [days], reasoning <order_id>, shadow [days], versions [days], timeline <order_id|correlation_id>, backfill [days], enable <symbol>, disable <symbol> [reason]", other)),
+    }
+}
@@ -1 +1,2 @@
-pub mod risk_profile;
\ No newline at end of file
+pub mod risk_profile;
+pub mod okx;
\ No newline at end of file
@@ -0,0 +1,117 @@
+// 文件名: okx.rs
+// OKX 连接端点：REST / WebSocket 地址与模拟盘标记统一从这里读取，执行器、行情拉取与 WS 客户端共用一份配置
+
+use std::env;
+
+use anyhow::{bail, Result};
+use url::Url;
+
+use crate::error::TraderError;
+
+pub const LIVE_REST_URL: &str = "https://www.okx.com";
+pub const LIVE_WS_URL: &str = "wss://ws.okx.com:8443/ws/v5/public";
+/// 模拟盘行情 WS (REST 与实盘同域名，靠 x-simulated-trading 头区分)
+pub const DEMO_WS_URL: &str = "wss://wspap.okx.com:8443/ws/v5/public";
+/// 模拟盘请求头，REST 请求 (含公共行情) 在模拟盘模式下统一携带
+pub const SIMULATED_HEADER: &str = "x-simulated-trading";
+
+const OKX_DOMAIN: &str = "okx.com";
+const DEMO_WS_HOST: &str = "wspap.okx.com";
+
+#[derive(Debug, Clone)]
+pub struct OkxEndpoints {
+    /// REST 根地址 (不含结尾的 /)
+    pub rest_url: String,
+    pub ws_url: String,
+    /// OKX_SIMULATED=1：所有 REST 请求携带模拟盘请求头
+    pub simulated: bool,
+}
+
+impl Default for OkxEndpoints {
+    fn default() -> Self {
+        Self { rest_url: LIVE_REST_URL.to_string(), ws_url: LIVE_WS_URL.to_string(), simulated: false }
+    }
+}
+
+impl OkxEndpoints {
+    /// 读取 OKX_BASE_URL / OKX_WS_URL / OKX_SIMULATED 并校验；未配置 WS 地址时按模拟盘标记选择官方实盘或模拟盘地址
+    pub fn from_env() -> Result<Self> {
+        let simulated = env::var("OKX_SIMULATED").unwrap_or("0".to_string()) == "1";
+        let non_empty = |key: &str| env::var(key).ok().map(|v| v.trim().to_string()).filter(|v| !v.is_empty());
+        Self::from_parts(non_empty("OKX_BASE_URL"), non_empty("OKX_WS_URL"), simulated)
+    }
+
+    pub fn from_parts(rest_url: Option<String>, ws_url: Option<String>, simulated: bool) -> Result<Self> {
+        let endpoints = Self {
+            rest_url: rest_url.unwrap_or(LIVE_REST_URL.to_string()).trim_end_matches('/').to_string(),
+            ws_url: ws_url.unwrap_or_else(|| if simulated { DEMO_WS_URL } else { LIVE_WS_URL }.to_string()),
+            simulated,
+        };
+        endpoints.validate()?;
+        Ok(endpoints)
+    }
+
+    /// 启动时检查 REST 与 WS 是否指向同一环境：
+    /// - 协议必须是 http(s) / ws(s)
+    /// - 官方域名与自建地址不能混用 (否则行情与下单来自不同的撮合)
+    /// - 官方域名下，模拟盘必须使用 wspap 行情，实盘不能使用 wspap
+    pub fn validate(&self) -> Result<()> {
+        let rest = Url::parse(&self.rest_url)
+            .map_err(|e| TraderError::Config(format!("OKX_BASE_URL '{}' is not a valid URL: {}", self.rest_url, e)))?;
+        let ws = Url::parse(&self.ws_url)
+            .map_err(|e| TraderError::Config(format!("OKX_WS_URL '{}' is not a valid URL: {}", self.ws_url, e)))?;
+        if !matches!(rest.scheme(), "http" | "https") || !matches!(ws.scheme(), "ws" | "wss") {
+            bail!(TraderError::Config(format!(
+                "OKX endpoints need an http(s) REST URL and a ws(s) WebSocket URL, got '{}' and '{}'", self.rest_url, self.ws_url
+            )));
+        }
+
+        let rest_host = rest.host_str().unwrap_or_default();
+        let ws_host = ws.host_str().unwrap_or_default();
+        let is_official = |host: &str| host == OKX_DOMAIN || host.ends_with(&format!(".{}", OKX_DOMAIN));
+        match (is_official(rest_host), is_official(ws_host)) {
+            (true, true) => {
+                let demo_ws = ws_host == DEMO_WS_HOST;
+                if demo_ws != self.simulated {
+                    bail!(TraderError::Config(format!(
+                        "OKX_SIMULATED={} but OKX_WS_URL '{}' is the {} feed. Use {} for {} trading or unset OKX_WS_URL",
+                        u8::from(self.simulated), self.ws_url, if demo_ws { "demo" } else { "live" },
+                        if self.simulated { DEMO_WS_URL } else { LIVE_WS_URL }, if self.simulated { "demo" } else { "live" }
+                    )));
+                }
+            }
+            (false, false) => {}
+            _ => bail!(TraderError::Config(format!(
+                "OKX REST '{}' and WebSocket '{}' point to different environments (official vs self-hosted). Configure both OKX_BASE_URL and OKX_WS_URL",
+                self.rest_url, self.ws_url
+            ))),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ws_default_follows_simulated_flag() {
+        let live = OkxEndpoints::from_parts(None, None, false).unwrap();
+        assert_eq!((live.rest_url.as_str(), live.ws_url.as_str()), (LIVE_REST_URL, LIVE_WS_URL));
+        let demo = OkxEndpoints::from_parts(Some("https://www.okx.com/".to_string()), None, true).unwrap();
+        assert_eq!((demo.rest_url.as_str(), demo.ws_url.as_str()), (LIVE_REST_URL, DEMO_WS_URL));
+    }
+
+    #[test]
+    fn rejects_inconsistent_endpoints() {
+        // 实盘配了模拟盘行情 / 模拟盘配了实盘行情
+        assert!(OkxEndpoints::from_parts(None, Some(DEMO_WS_URL.to_string()), false).is_err());
+        assert!(OkxEndpoints::from_parts(None, Some(LIVE_WS_URL.to_string()), true).is_err());
+        // 自建 REST 搭配官方 WS
+        assert!(OkxEndpoints::from_parts(Some("http://localhost:8080".to_string()), None, true).is_err());
+        assert!(OkxEndpoints::from_parts(Some("localhost:8080".to_string()), None, false).is_err());
+
+        let mock = OkxEndpoints::from_parts(Some("http://localhost:8080".to_string()), Some("ws://localhost:8081/ws/v5/public".to_string()), true);
+        assert!(mock.is_ok());
+    }
+}
@@ -1,13 +1,97 @@
 use serde::Deserialize;
 use config::{Config, File};
-use anyhow::Result;
+use anyhow::{Result, bail};
+use std::collections::HashMap;
+use std::time::Duration;
+use chrono::{DateTime, NaiveTime, Utc, Weekday};
+use crate::error::TraderError;
+use crate::modules::perception::math::IchimokuPeriods;
 
 #[allow(dead_code)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct TimingConfig {
     pub cycle_rest_sec: u64,
+    /// 进化任务的默认间隔 (秒)，下面三项未单独配置时使用
     pub evolution_sec: u64,
+    /// 已实现盈亏同步间隔 (秒)，未配置时使用 evolution_sec
+    #[serde(default)]
+    pub pnl_sync_sec: Option<u64>,
+    /// 亏损复盘间隔 (秒)，未配置时使用 evolution_sec
+    #[serde(default)]
+    pub autopsy_sec: Option<u64>,
+    /// 踏空扫描间隔 (秒)，未配置时使用 evolution_sec
+    #[serde(default)]
+    pub scanner_sec: Option<u64>,
+    /// 组合再平衡间隔 (秒)，未配置时使用 evolution_sec ([rebalance] 开启时才运行)
+    #[serde(default)]
+    pub rebalance_sec: Option<u64>,
     pub symbol_gap_sec: u64,
+    /// OKX 维护期间查询系统状态的间隔 (秒)
+    #[serde(default = "default_maintenance_poll_sec")]
+    pub maintenance_poll_sec: u64,
+    /// 动态休眠的基准波动率 (ATR 占价格的百分比)，波动率为其 2 倍时休眠时间减半
+    #[serde(default = "default_base_volatility_pct")]
+    pub base_volatility_pct: f64,
+    /// 动态休眠的下限 (秒)，防止高波动时刷接口
+    #[serde(default = "default_min_rest_sec")]
+    pub min_rest_sec: u64,
+    /// 新闻 / Reddit 舆情缓存有效期 (秒)，期间各轮循环复用，0 = 每轮重新抓取
+    #[serde(default = "default_sentiment_ttl_sec")]
+    pub sentiment_ttl_sec: u64,
+    /// 启动观察期 (秒)：期间照常同步行情、保护和平掉已有持仓、发送报告，但不开新仓；0 = 启动即正常交易
+    #[serde(default)]
+    pub startup_grace_sec: u64,
+}
+
+fn default_maintenance_poll_sec() -> u64 { 60 }
+fn default_sentiment_ttl_sec() -> u64 { 900 }
+fn default_base_volatility_pct() -> f64 { 0.5 }
+fn default_min_rest_sec() -> u64 { 60 }
+
+impl TimingConfig {
+    /// 两个币种分析之间的间隔，避免触发 OKX / LLM 限频
+    pub fn symbol_gap(&self) -> Duration {
+        Duration::from_secs(self.symbol_gap_sec)
+    }
+
+    pub fn maintenance_poll(&self) -> Duration {
+        Duration::from_secs(self.maintenance_poll_sec)
+    }
+
+    pub fn sentiment_ttl(&self) -> Duration {
+        Duration::from_secs(self.sentiment_ttl_sec)
+    }
+
+    pub fn startup_grace(&self) -> Duration {
+        Duration::from_secs(self.startup_grace_sec)
+    }
+
+    pub fn pnl_sync_interval(&self) -> Duration {
+        Duration::from_secs(self.pnl_sync_sec.unwrap_or(self.evolution_sec))
+    }
+
+    pub fn autopsy_interval(&self) -> Duration {
+        Duration::from_secs(self.autopsy_sec.unwrap_or(self.evolution_sec))
+    }
+
+    pub fn scanner_interval(&self) -> Duration {
+        Duration::from_secs(self.scanner_sec.unwrap_or(self.evolution_sec))
+    }
+
+    pub fn rebalance_interval(&self) -> Duration {
+        Duration::from_secs(self.rebalance_sec.unwrap_or(self.evolution_sec))
+    }
+
+    /// 按本轮最大 ATR% 计算休眠时间：波动越大休眠越短，最长为 cycle_rest_sec 的 2 倍，最短 min_rest_sec
+    pub fn rest_interval(&self, max_atr_pct: f64) -> Duration {
+        let base = Duration::from_secs(self.cycle_rest_sec);
+        if !(max_atr_pct.is_finite() && max_atr_pct > 0.0 && self.base_volatility_pct > 0.0) {
+            return base;
+        }
+        let volatility_ratio = (max_atr_pct / self.base_volatility_pct).max(0.5);
+        let adjusted_secs = (base.as_secs_f64() / volatility_ratio).max(self.min_rest_sec as f64).max(1.0);
+        Duration::from_secs(adjusted_secs as u64)
+    }
 }
 
 #[allow(dead_code)]
@@ -18,6 +102,39 @@ pub struct IndicatorConfig {
     pub atr_period: usize,
     pub ema_fast: usize,
     pub ema_slow: usize,
+    /// 每次分析拉取的 1H K 线根数，超过 100 时自动翻页拼接 (长周期指标需要更多预热数据)
+    #[serde(default = "default_kline_limit")]
+    pub kline_limit: usize,
+    /// 资金费结算周期 (小时)，OKX 永续默认 8 小时；用于把单期费率折算为年化成本
+    #[serde(default = "default_funding_interval_hours")]
+    pub funding_interval_hours: f64,
+    /// 一目均衡表转换线周期
+    #[serde(default = "default_ichimoku_tenkan")]
+    pub ichimoku_tenkan: usize,
+    /// 一目均衡表基准线周期 (同时作为先行带的位移)
+    #[serde(default = "default_ichimoku_kijun")]
+    pub ichimoku_kijun: usize,
+    /// 一目均衡表先行带 B 周期
+    #[serde(default = "default_ichimoku_senkou_b")]
+    pub ichimoku_senkou_b: usize,
+}
+
+fn default_kline_limit() -> usize { 100 }
+/// kline_limit 上限 (20 页)
+const MAX_KLINE_LIMIT: usize = 2000;
+fn default_funding_interval_hours() -> f64 { 8.0 }
+fn default_ichimoku_tenkan() -> usize { 9 }
+fn default_ichimoku_kijun() -> usize { 26 }
+fn default_ichimoku_senkou_b() -> usize { 52 }
+
+impl IndicatorConfig {
+    pub fn ichimoku_periods(&self) -> IchimokuPeriods {
+        IchimokuPeriods {
+            tenkan: self.ichimoku_tenkan,
+            kijun: self.ichimoku_kijun,
+            senkou_b: self.ichimoku_senkou_b,
+        }
+    }
 }
 
 #[allow(dead_code)]
@@ -28,16 +145,1361 @@ pub struct ThresholdConfig {
     pub scanner_pump_pct: f64,
 }
 
+/// 开仓方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryMode {
+    /// 直接市价开仓 (默认)
+    #[default]
+    Market,
+    /// 先挂 post-only 限价单吃 maker 返佣，超时未成交再改价，最后市价补齐
+    MakerFirst,
+}
+
+/// 交易品种类型
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TradingMode {
+    /// USDT 永续合约 (默认)：支持杠杆与双向持仓
+    #[default]
+    Swap,
+    /// 现货：无杠杆、只能做多，allowed_symbols 需改为 "BTC-USDT" 形式
+    Spot,
+}
+
+/// TP/SL 挂单方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TpSlMode {
+    /// 随入场单附带 attachAlgoOrds (默认)
+    #[default]
+    Attached,
+    /// 入场成交后单独挂 OCO 算法单，可独立修改止损 (如移动到保本)
+    Oco,
+}
+
+/// 允许执行的交易动作 (HOLD 始终允许)
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AllowedAction {
+    Buy,
+    Sell,
+    CloseLong,
+    CloseShort,
+}
+
+fn default_allowed_actions() -> Vec<AllowedAction> {
+    vec![AllowedAction::Buy, AllowedAction::Sell, AllowedAction::CloseLong, AllowedAction::CloseShort]
+}
+
+/// 下单执行参数
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExecutionConfig {
+    /// TP/SL 触发价距离入场价的最少 tick 数，不足则自动放宽 (防止 OKX 拒单)
+    #[serde(default = "default_tpsl_min_ticks")]
+    pub tpsl_min_ticks: u32,
+    #[serde(default)]
+    pub entry_mode: EntryMode,
+    /// maker 挂单每轮等待成交的秒数
+    #[serde(default = "default_maker_timeout_sec")]
+    pub maker_timeout_sec: u64,
+    /// maker 挂单未成交时的改价次数，用完后市价补齐剩余数量
+    #[serde(default = "default_maker_reprice_count")]
+    pub maker_reprice_count: u32,
+    /// 本程序限价挂单的最长存活秒数：每轮对账时撤掉超时未成交的挂单 (含重启前遗留、带 clOrdId 前缀的挂单，手动挂单不动)；0 = 不检查
+    #[serde(default = "default_pending_order_timeout_sec")]
+    pub pending_order_timeout_sec: u64,
+    #[serde(default)]
+    pub tpsl_mode: TpSlMode,
+    #[serde(default)]
+    pub trading_mode: TradingMode,
+    /// 开仓前允许的最大买卖价差 (bp)，超过则跳过本次开仓；0 = 不检查。可在 [[symbol_overrides]] 中按币种覆盖
+    #[serde(default = "default_max_spread_bps")]
+    pub max_spread_bps: f64,
+    /// 成交均价相对分析价的最大不利滑点 (0.005 = 0.5%)，超过则立即平掉该笔成交；0 = 不检查
+    #[serde(default = "default_max_slippage_pct")]
+    pub max_slippage_pct: f64,
+    /// 分析用价格的最长时效 (秒)：WS 价格超时则回退 REST 价格，REST 价格也超时则本轮跳过该币种
+    #[serde(default = "default_max_price_age_sec")]
+    pub max_price_age_sec: u64,
+    /// 保证金/计价币种 (USDT / USDC)，不填时从 allowed_symbols 推导
+    #[serde(default)]
+    pub settle_ccy: Option<String>,
+    /// 允许的交易动作，不在列表中的决策强制改为 HOLD (如只做多时去掉 "sell")。可在 [[symbol_overrides]] 中按币种覆盖
+    #[serde(default = "default_allowed_actions")]
+    pub allowed_actions: Vec<AllowedAction>,
+    /// 阶梯止盈：成交后按比例分批挂多个 reduce-only 止盈单，第一档成交后止损移到保本位；为空 = 单一 TP
+    /// 模型在决策中给出 tp_ladder 时优先使用模型的阶梯
+    #[serde(default)]
+    pub tp_ladder: Vec<TpRung>,
+    /// 启动时账户持仓模式不是双向持仓 (long_short_mode) 时自动切换；有持仓时永不切换，只告警
+    #[serde(default)]
+    pub auto_fix_pos_mode: bool,
+}
+
+/// 阶梯止盈的一档：在 r_multiple 倍止损距离处平掉初始仓位的 fraction
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct TpRung {
+    #[serde(alias = "r")]
+    pub r_multiple: f64,
+    pub fraction: f64,
+}
+
+/// 合法的止盈阶梯：每档 r_multiple > 0 且严格递增，fraction 在 (0, 1]，比例合计不超过 1
+pub fn is_valid_ladder(rungs: &[TpRung]) -> bool {
+    let rungs_ok = rungs.iter().all(|r| r.r_multiple > 0.0 && r.fraction > 0.0 && r.fraction <= 1.0);
+    let ascending = rungs.windows(2).all(|w| w[1].r_multiple > w[0].r_multiple);
+    rungs_ok && ascending && rungs.iter().map(|r| r.fraction).sum::<f64>() <= 1.0 + 1e-9
+}
+
+fn default_tpsl_min_ticks() -> u32 { 5 }
+fn default_max_spread_bps() -> f64 { 20.0 }
+fn default_max_slippage_pct() -> f64 { 0.005 }
+fn default_max_price_age_sec() -> u64 { 60 }
+fn default_maker_timeout_sec() -> u64 { 10 }
+fn default_maker_reprice_count() -> u32 { 1 }
+fn default_pending_order_timeout_sec() -> u64 { 120 }
+
+impl ExecutionConfig {
+    pub fn max_price_age(&self) -> Duration {
+        Duration::from_secs(self.max_price_age_sec)
+    }
+}
+
+impl Default for ExecutionConfig {
+    fn default() -> Self {
+        Self {
+            tpsl_min_ticks: default_tpsl_min_ticks(),
+            entry_mode: EntryMode::default(),
+            maker_timeout_sec: default_maker_timeout_sec(),
+            maker_reprice_count: default_maker_reprice_count(),
+            pending_order_timeout_sec: default_pending_order_timeout_sec(),
+            tpsl_mode: TpSlMode::default(),
+            trading_mode: TradingMode::default(),
+            max_spread_bps: default_max_spread_bps(),
+            max_slippage_pct: default_max_slippage_pct(),
+            max_price_age_sec: default_max_price_age_sec(),
+            settle_ccy: None,
+            allowed_actions: default_allowed_actions(),
+            tp_ladder: Vec::new(),
+            auto_fix_pos_mode: false,
+        }
+    }
+}
+
+/// 金字塔加仓参数 (同方向持仓再次开仓)
+#[derive(Debug, Deserialize, Clone)]
+pub struct PyramidingConfig {
+    /// 默认关闭：同方向再次开仓按普通开仓处理，不检查浮盈与加仓次数
+    #[serde(default = "default_pyramiding_enabled")]
+    pub enabled: bool,
+    /// 持仓浮盈 (按均价计算的价格涨跌幅) 超过该值才允许加仓
+    #[serde(default = "default_pyramiding_min_profit_pct")]
+    pub min_profit_pct: f64,
+    /// 单方向最多加仓次数
+    #[serde(default = "default_pyramiding_max_adds")]
+    pub max_adds: u32,
+    /// 单次加仓数量上限 = 现有持仓张数 × 该比例
+    #[serde(default = "default_pyramiding_add_size_ratio")]
+    pub add_size_ratio: f64,
+}
+
+fn default_pyramiding_enabled() -> bool { false }
+fn default_pyramiding_min_profit_pct() -> f64 { 0.01 }
+fn default_pyramiding_max_adds() -> u32 { 2 }
+fn default_pyramiding_add_size_ratio() -> f64 { 0.5 }
+
+impl Default for PyramidingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_pyramiding_enabled(),
+            min_profit_pct: default_pyramiding_min_profit_pct(),
+            max_adds: default_pyramiding_max_adds(),
+            add_size_ratio: default_pyramiding_add_size_ratio(),
+        }
+    }
+}
+
+/// 保本止损参数
+#[derive(Debug, Deserialize, Clone)]
+pub struct BreakevenConfig {
+    /// 默认关闭：移动止损会改变策略的盈亏分布，需要按回测结果显式开启
+    #[serde(default = "default_breakeven_enabled")]
+    pub enabled: bool,
+    /// 浮盈达到 N 倍初始风险 (R = |入场价 - 止损价|) 时移动止损
+    #[serde(default = "default_breakeven_trigger_r")]
+    pub trigger_r: f64,
+    /// 新止损相对入场价的偏移 (0.001 = 入场价之上 0.1%，覆盖手续费)
+    #[serde(default = "default_breakeven_offset_pct")]
+    pub offset_pct: f64,
+}
+
+fn default_breakeven_enabled() -> bool { false }
+fn default_breakeven_trigger_r() -> f64 { 1.0 }
+fn default_breakeven_offset_pct() -> f64 { 0.001 }
+
+impl Default for BreakevenConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_breakeven_enabled(),
+            trigger_r: default_breakeven_trigger_r(),
+            offset_pct: default_breakeven_offset_pct(),
+        }
+    }
+}
+
+/// 反手守卫 (对应 Prompt 中的 "friction averse" 规则)
+#[derive(Debug, Deserialize, Clone)]
+pub struct AntiFlipConfig {
+    #[serde(default = "default_anti_flip_enabled")]
+    pub enabled: bool,
+    /// 距该币种上次操作多少秒内的反手需要更高置信度
+    #[serde(default = "default_anti_flip_window_sec")]
+    pub window_sec: u64,
+    /// 窗口内反手所需的最低 AI 胜率
+    #[serde(default = "default_anti_flip_min_win_rate")]
+    pub min_win_rate: f64,
+}
+
+fn default_anti_flip_enabled() -> bool { true }
+fn default_anti_flip_window_sec() -> u64 { 3600 }
+fn default_anti_flip_min_win_rate() -> f64 { 0.7 }
+
+impl Default for AntiFlipConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_anti_flip_enabled(),
+            window_sec: default_anti_flip_window_sec(),
+            min_win_rate: default_anti_flip_min_win_rate(),
+        }
+    }
+}
+
+/// 决策反复横跳检测：同一币种短时间内方向来回反转 (开多 → 平多 → 开多) 说明模型在追噪音，
+/// 达到阈值后压制为 HOLD，直到旧决策移出窗口
+#[derive(Debug, Deserialize, Clone)]
+pub struct ThrashingConfig {
+    #[serde(default = "default_thrashing_enabled")]
+    pub enabled: bool,
+    /// 统计窗口 (秒)
+    #[serde(default = "default_thrashing_window_sec")]
+    pub window_sec: u64,
+    /// 窗口内方向反转次数 (含本次) 达到该值时压制
+    #[serde(default = "default_thrashing_max_reversals")]
+    pub max_reversals: usize,
+    /// 每个币种最多保留的决策条数
+    #[serde(default = "default_thrashing_history_len")]
+    pub history_len: usize,
+}
+
+fn default_thrashing_enabled() -> bool { true }
+fn default_thrashing_window_sec() -> u64 { 7200 }
+fn default_thrashing_max_reversals() -> usize { 2 }
+fn default_thrashing_history_len() -> usize { 6 }
+
+impl Default for ThrashingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_thrashing_enabled(),
+            window_sec: default_thrashing_window_sec(),
+            max_reversals: default_thrashing_max_reversals(),
+            history_len: default_thrashing_history_len(),
+        }
+    }
+}
+
+/// 最短持仓时间：开仓 (含加仓) 后一段时间内不主动平仓或反手，防止模型在噪音中频繁进出
+#[derive(Debug, Deserialize, Clone)]
+pub struct MinHoldConfig {
+    #[serde(default = "default_min_hold_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_min_hold_sec")]
+    pub min_hold_sec: u64,
+    /// 浮亏达到该 R 倍数 (R = 入场价到止损价的距离) 时不受最短持仓限制，允许立即止损
+    #[serde(default = "default_min_hold_stop_override_r")]
+    pub stop_override_r: f64,
+}
+
+fn default_min_hold_enabled() -> bool { true }
+fn default_min_hold_sec() -> u64 { 1800 }
+fn default_min_hold_stop_override_r() -> f64 { 1.0 }
+
+impl Default for MinHoldConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_min_hold_enabled(),
+            min_hold_sec: default_min_hold_sec(),
+            stop_override_r: default_min_hold_stop_override_r(),
+        }
+    }
+}
+
+/// RAG 记忆系统参数
+#[derive(Debug, Deserialize, Clone)]
+pub struct MemoryConfig {
+    /// Embedding 输入最大字符数，需与所选模型的上下文长度匹配 (豆包 4096 token ≈ 8000 字符)
+    #[serde(default = "default_max_embed_chars")]
+    pub max_embed_chars: usize,
+    /// 超长时的处理方式：truncate / summarize
+    #[serde(default)]
+    pub embed_strategy: EmbedStrategy,
+    /// summarize 使用的压缩模型
+    #[serde(default = "default_summary_model")]
+    pub summary_model: String,
+    /// 每次召回的 "历史错误" 记忆条数
+    #[serde(default = "default_recall_limit")]
+    pub mistake_limit: u64,
+    /// 每次召回的 "错过机会" 记忆条数
+    #[serde(default = "default_recall_limit")]
+    pub missed_limit: u64,
+    /// 每次召回的 "成功交易" 记忆条数 (来自 backfill 回放的盈利平仓)，0 = 不召回
+    #[serde(default = "default_success_limit")]
+    pub success_limit: u64,
+    /// 最低余弦相似度，低于该值的记忆不进入 Prompt (0 = 不过滤)
+    #[serde(default)]
+    pub min_score: f32,
+    /// 连续多少次 Embedding 失败 (重试耗尽或配置错误) 后熔断，0 = 不熔断
+    #[serde(default = "default_embed_max_failures")]
+    pub embed_max_failures: u32,
+    /// 熔断持续时间 (秒)，期间召回直接返回空、写入直接失败
+    #[serde(default = "default_embed_cooldown_sec")]
+    pub embed_cooldown_sec: u64,
+    /// 启动时先做一次 Embedding 自检 (校验 Key/模型/向量维度并测量延迟)
+    #[serde(default = "default_embed_startup_check")]
+    pub startup_check: bool,
+    /// 自检失败时拒绝启动；false 时只告警，以无记忆召回的状态继续交易
+    #[serde(default)]
+    pub startup_check_strict: bool,
+    /// Embedding 服务商，更换后向量维度可能变化，需要同时更换 Qdrant 集合
+    #[serde(default)]
+    pub embedding_provider: EmbeddingProviderKind,
+    /// embedding_provider = openai 时使用的模型
+    #[serde(default = "default_openai_embedding_model")]
+    pub openai_model: String,
+    /// 向量维度，None = 按服务商/模型的默认值 (豆包 2560，text-embedding-3-small 1536，-large 3072)
+    /// OpenAI text-embedding-3 系列会通过 dimensions 参数返回该维度
+    #[serde(default)]
+    pub embedding_dim: Option<u64>,
+}
+
+fn default_max_embed_chars() -> usize { 8000 }
+fn default_summary_model() -> String { "deepseek-chat".to_string() }
+fn default_recall_limit() -> u64 { 2 }
+fn default_success_limit() -> u64 { 1 }
+fn default_embed_max_failures() -> u32 { 3 }
+fn default_embed_cooldown_sec() -> u64 { 300 }
+fn default_embed_startup_check() -> bool { true }
+fn default_openai_embedding_model() -> String { "text-embedding-3-small".to_string() }
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            max_embed_chars: default_max_embed_chars(),
+            embed_strategy: EmbedStrategy::default(),
+            summary_model: default_summary_model(),
+            mistake_limit: default_recall_limit(),
+            missed_limit: default_recall_limit(),
+            success_limit: default_success_limit(),
+            min_score: 0.0,
+            embed_max_failures: default_embed_max_failures(),
+            embed_cooldown_sec: default_embed_cooldown_sec(),
+            startup_check: default_embed_startup_check(),
+            startup_check_strict: false,
+            embedding_provider: EmbeddingProviderKind::default(),
+            openai_model: default_openai_embedding_model(),
+            embedding_dim: None,
+        }
+    }
+}
+
+/// LLM 采样参数
+#[derive(Debug, Deserialize, Clone)]
+pub struct LlmConfig {
+    /// 采样温度 (0 ~ 2)，越低越确定
+    #[serde(default = "default_llm_temperature")]
+    pub temperature: f64,
+    /// 单次回复最大 token 数 (含推理过程)，不配置时使用服务端默认值
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// true = 使用推理模型 (deepseek-reasoner)，false = 使用对话模型 (deepseek-chat)，更快更省
+    #[serde(default = "default_llm_reasoning")]
+    pub reasoning: bool,
+    /// LLM 未给出 sl 时的兜底止损 = 该倍数 × ATR%
+    #[serde(default = "default_llm_fallback_sl_atr_mult")]
+    pub fallback_sl_atr_mult: f64,
+    /// LLM 未给出 tp 时的兜底止盈 = 止损 × 该盈亏比
+    #[serde(default = "default_llm_fallback_target_rr")]
+    pub fallback_target_rr: f64,
+    /// 每轮循环最多调用 analyze 的次数，0 = 不限制
+    #[serde(default)]
+    pub max_calls_per_cycle: u32,
+    /// 滚动一小时内最多调用 analyze 的次数，0 = 不限制
+    #[serde(default)]
+    pub max_calls_per_hour: u32,
+    /// 两阶段决策：先用快速模型判断行情是否值得分析 (consider / skip)，skip 时直接 HOLD，不调用完整模型
+    #[serde(default)]
+    pub prescreen: bool,
+    /// 预筛使用的模型
+    #[serde(default = "default_llm_prescreen_model")]
+    pub prescreen_model: String,
+    /// 结构化输出 (OpenAI 兼容的 response_format)，约束模型只输出合法 JSON
+    #[serde(default)]
+    pub response_format: ResponseFormatMode,
+}
+
+/// response_format 的使用方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseFormatMode {
+    /// 按 API 端点判断：api.openai.com 使用 json_schema，DeepSeek 使用 json_object，其它端点不发送
+    #[default]
+    Auto,
+    /// 不发送 response_format，完全依赖文本中提取 JSON
+    Off,
+    /// {"type": "json_object"}：保证输出合法 JSON，字段仍由提示词约束
+    JsonObject,
+    /// {"type": "json_schema"}：同时发送与 AiDecision 字段一致的 schema
+    JsonSchema,
+}
+
+fn default_llm_temperature() -> f64 { 0.1 }
+fn default_llm_reasoning() -> bool { true }
+fn default_llm_fallback_sl_atr_mult() -> f64 { 2.0 }
+fn default_llm_fallback_target_rr() -> f64 { 2.0 }
+fn default_llm_prescreen_model() -> String { "deepseek-chat".to_string() }
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            temperature: default_llm_temperature(),
+            max_tokens: None,
+            reasoning: default_llm_reasoning(),
+            fallback_sl_atr_mult: default_llm_fallback_sl_atr_mult(),
+            fallback_target_rr: default_llm_fallback_target_rr(),
+            max_calls_per_cycle: 0,
+            max_calls_per_hour: 0,
+            prescreen: false,
+            prescreen_model: default_llm_prescreen_model(),
+            response_format: ResponseFormatMode::default(),
+        }
+    }
+}
+
+/// 通知限流参数
+#[derive(Debug, Deserialize, Clone)]
+pub struct NotifyConfig {
+    /// 每分钟最多发送的普通消息数 (Critical 不受限)
+    #[serde(default = "default_notify_max_per_minute")]
+    pub max_per_minute: u32,
+    /// 相同内容在此窗口内只发送一次，其余合并计数
+    #[serde(default = "default_notify_dedup_window_sec")]
+    pub dedup_window_sec: u64,
+    /// Critical 消息首次发送 (失败后已即时重试一次) 仍失败时，从补发队列重发的最多次数 (每轮主循环一次)
+    #[serde(default = "default_notify_retry_attempts")]
+    pub retry_attempts: u32,
+    /// Critical 消息发送失败后进入补发队列的上限，溢出时丢弃最旧的
+    #[serde(default = "default_notify_max_pending")]
+    pub max_pending: usize,
+}
+
+fn default_notify_max_per_minute() -> u32 { 10 }
+fn default_notify_dedup_window_sec() -> u64 { 300 }
+fn default_notify_retry_attempts() -> u32 { 3 }
+fn default_notify_max_pending() -> usize { 20 }
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            max_per_minute: default_notify_max_per_minute(),
+            dedup_window_sec: default_notify_dedup_window_sec(),
+            retry_attempts: default_notify_retry_attempts(),
+            max_pending: default_notify_max_pending(),
+        }
+    }
+}
+
+/// Embedding 输入超过 max_embed_chars 时的处理方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbedStrategy {
+    /// 从尾部截断 (默认)：关键指标在前，超长时舆情被截掉
+    #[default]
+    Truncate,
+    /// 先用便宜的对话模型压缩新闻与社媒两段舆情，使完整上下文放得下；失败时退回截断
+    Summarize,
+}
+
+/// Embedding 服务商
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingProviderKind {
+    /// 火山引擎豆包 (默认)：VOLC_API_KEY / VOLC_MODEL / VOLC_ENDPOINT
+    #[default]
+    Volcengine,
+    /// OpenAI 兼容接口：OPENAI_API_KEY / OPENAI_EMBEDDING_BASE_URL，模型见 openai_model
+    Openai,
+}
+
+/// 目标保证金超过可用余额时的处理方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AvailableFallback {
+    /// 目标保证金 (已受 max_order_size_pct 限制) 截到可用余额 (默认)：余额略低于目标时只少下差额，没有断崖
+    #[default]
+    MaxOrderPct,
+    /// 只用 95% 可用余额：为手续费与滑点留出余量，避免余额不足被拒单
+    Grab,
+}
+
+/// 凯利仓位参数
+#[derive(Debug, Deserialize, Clone)]
+pub struct KellyConfig {
+    /// 凯利系数乘数 (0.5 = 半凯利)
+    #[serde(default = "default_kelly_multiplier")]
+    pub multiplier: f64,
+    /// AI 胜率软顶，超过则截断并重算凯利值，防止重仓
+    #[serde(default = "default_win_rate_cap")]
+    pub win_rate_cap: f64,
+    /// AI 胜率下限，低于该值的开仓信号强制 HOLD (按截断前的原始胜率判断，0 = 不限制)
+    #[serde(default)]
+    pub min_win_rate: f64,
+    #[serde(default)]
+    pub available_fallback: AvailableFallback,
+    /// 预留资金：权益的该比例从可用余额中扣除，凯利仓位永不动用 (保留加仓余地与保证金缓冲，0 = 不预留)
+    #[serde(default)]
+    pub reserve_pct: f64,
+}
+
+fn default_kelly_multiplier() -> f64 { 0.5 }
+fn default_win_rate_cap() -> f64 { 0.75 }
+
+impl Default for KellyConfig {
+    fn default() -> Self {
+        Self {
+            multiplier: default_kelly_multiplier(),
+            win_rate_cap: default_win_rate_cap(),
+            min_win_rate: 0.0,
+            available_fallback: AvailableFallback::default(),
+            reserve_pct: 0.0,
+        }
+    }
+}
+
+/// 回撤降仓曲线
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DrawdownCurve {
+    /// 线性：比例从 1 线性降到 min_scale
+    #[default]
+    Linear,
+    /// 指数：min_scale ^ (回撤 / 上限)，回撤初期降得更快
+    Exponential,
+}
+
+/// 回撤降仓：回撤逐步逼近最大回撤 (MAX_DRAWDOWN_LIMIT) 时按比例缩小 max_order_size_pct，而不是到线才一刀切
+#[derive(Debug, Deserialize, Clone)]
+pub struct DrawdownScalingConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub curve: DrawdownCurve,
+    /// 回撤达到上限时保留的仓位比例
+    #[serde(default = "default_drawdown_min_scale")]
+    pub min_scale: f64,
+}
+
+fn default_drawdown_min_scale() -> f64 { 0.1 }
+
+impl Default for DrawdownScalingConfig {
+    fn default() -> Self {
+        Self { enabled: false, curve: DrawdownCurve::default(), min_scale: default_drawdown_min_scale() }
+    }
+}
+
+/// 熔断参数
+#[derive(Debug, Deserialize, Clone)]
+pub struct CircuitBreakerConfig {
+    /// 连续多少笔交易下单失败 (重试耗尽) 后停止开仓，0 = 不熔断
+    #[serde(default = "default_max_order_failures")]
+    pub max_order_failures: u32,
+    /// 连续多少笔亏损平仓后暂停开新仓，0 = 不检查 (默认关闭：旧版本按开仓账单回填的记录不计入，
+    /// 需要积累足够的按平仓成交回填的记录后再开启)
+    #[serde(default = "default_max_consecutive_losses")]
+    pub max_consecutive_losses: u32,
+    /// 连亏暂停的冷却时间 (分钟)，期间出现盈利平仓则提前恢复
+    #[serde(default = "default_loss_cooldown_min")]
+    pub loss_cooldown_min: u64,
+}
+
+fn default_max_order_failures() -> u32 { 3 }
+fn default_max_consecutive_losses() -> u32 { 0 }
+fn default_loss_cooldown_min() -> u64 { 240 }
+
+impl CircuitBreakerConfig {
+    pub fn loss_cooldown(&self) -> Duration {
+        Duration::from_secs(self.loss_cooldown_min * 60)
+    }
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self {
+            max_order_failures: default_max_order_failures(),
+            max_consecutive_losses: default_max_consecutive_losses(),
+            loss_cooldown_min: default_loss_cooldown_min(),
+        }
+    }
+}
+
+/// 下单重试策略：开仓失败只是错过机会，平仓失败意味着持仓失去保护，两者分开配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct RetryConfig {
+    /// 开仓可重试错误 (网络/限频) 的最大尝试次数
+    #[serde(default = "default_retry_open_attempts")]
+    pub open_attempts: u32,
+    #[serde(default = "default_retry_open_delay_ms")]
+    pub open_delay_ms: u64,
+    /// 平仓的最大尝试次数 (平仓不区分错误类型，失败即重试)
+    #[serde(default = "default_retry_close_attempts")]
+    pub close_attempts: u32,
+    #[serde(default = "default_retry_close_delay_ms")]
+    pub close_delay_ms: u64,
+    /// 平仓连续失败多少次后立即发送紧急通知 (不等重试耗尽)，0 = 只在耗尽时通知
+    #[serde(default = "default_retry_close_escalate_after")]
+    pub close_escalate_after: u32,
+    /// 平仓重试耗尽后以市价 reduce-only 平掉全部持仓
+    #[serde(default)]
+    pub flatten_all_on_close_failure: bool,
+}
+
+fn default_retry_open_attempts() -> u32 { 10 }
+fn default_retry_open_delay_ms() -> u64 { 1000 }
+fn default_retry_close_attempts() -> u32 { 10 }
+fn default_retry_close_delay_ms() -> u64 { 1000 }
+fn default_retry_close_escalate_after() -> u32 { 3 }
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            open_attempts: default_retry_open_attempts(),
+            open_delay_ms: default_retry_open_delay_ms(),
+            close_attempts: default_retry_close_attempts(),
+            close_delay_ms: default_retry_close_delay_ms(),
+            close_escalate_after: default_retry_close_escalate_after(),
+            flatten_all_on_close_failure: false,
+        }
+    }
+}
+
+impl RetryConfig {
+    pub fn open_delay(&self) -> Duration {
+        Duration::from_millis(self.open_delay_ms)
+    }
+
+    pub fn close_delay(&self) -> Duration {
+        Duration::from_millis(self.close_delay_ms)
+    }
+}
+
+/// 保证金安全监控：维持保证金占权益的比例 (mmr / eq，1.0 = 强平线) 过高时停止开仓并主动减仓
+#[derive(Debug, Deserialize, Clone)]
+pub struct MarginConfig {
+    #[serde(default = "default_margin_enabled")]
+    pub enabled: bool,
+    /// 维持保证金 / 权益超过该值进入危险状态
+    #[serde(default = "default_margin_danger_ratio")]
+    pub danger_ratio: f64,
+    /// 回落到该值以下才解除危险状态 (避免在阈值附近反复切换)
+    #[serde(default = "default_margin_recover_ratio")]
+    pub recover_ratio: f64,
+    /// 危险状态下减掉浮亏最大的持仓 (会主动市价平仓，默认关闭)；false 时只停止开新仓
+    #[serde(default = "default_margin_reduce_largest_loss")]
+    pub reduce_largest_loss: bool,
+    /// 每次减仓的比例
+    #[serde(default = "default_margin_reduce_fraction")]
+    pub reduce_fraction: f64,
+    /// 两次减仓之间的最短间隔 (秒)，给成交与保证金重算留时间；0 = 每次进入危险状态只减一次
+    #[serde(default = "default_margin_reduce_cooldown_sec")]
+    pub reduce_cooldown_sec: u64,
+}
+
+fn default_margin_enabled() -> bool { true }
+fn default_margin_danger_ratio() -> f64 { 0.5 }
+fn default_margin_recover_ratio() -> f64 { 0.35 }
+fn default_margin_reduce_largest_loss() -> bool { false }
+fn default_margin_reduce_fraction() -> f64 { 0.5 }
+fn default_margin_reduce_cooldown_sec() -> u64 { 600 }
+
+impl Default for MarginConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_margin_enabled(),
+            danger_ratio: default_margin_danger_ratio(),
+            recover_ratio: default_margin_recover_ratio(),
+            reduce_largest_loss: default_margin_reduce_largest_loss(),
+            reduce_fraction: default_margin_reduce_fraction(),
+            reduce_cooldown_sec: default_margin_reduce_cooldown_sec(),
+        }
+    }
+}
+
+impl MarginConfig {
+    /// None = 每次进入危险状态只减一次
+    pub fn reduce_cooldown(&self) -> Option<Duration> {
+        (self.reduce_cooldown_sec > 0).then(|| Duration::from_secs(self.reduce_cooldown_sec))
+    }
+}
+
+/// 资金费率触发后的处理方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FundingAction {
+    /// 跳过本次开仓 (默认)
+    #[default]
+    Skip,
+    /// 按 downgrade_factor 缩小仓位后照常开仓
+    Downgrade,
+}
+
+/// 资金费率开仓过滤：费率对持仓方向不利 (多头付费 / 空头付费) 且超过阈值时跳过或缩小开仓
+/// 阈值为单个结算周期的费率 (与 OKX fundingRate 同口径，0.0005 = 每期 0.05%)
+#[derive(Debug, Deserialize, Clone)]
+pub struct FundingFilterConfig {
+    #[serde(default = "default_funding_filter_enabled")]
+    pub enabled: bool,
+    /// 费率高于该值时限制开多
+    #[serde(default = "default_funding_max_long_rate")]
+    pub max_long_rate: f64,
+    /// 费率低于该值 (负数) 时限制开空
+    #[serde(default = "default_funding_min_short_rate")]
+    pub min_short_rate: f64,
+    #[serde(default)]
+    pub action: FundingAction,
+    /// action = downgrade 时的仓位系数
+    #[serde(default = "default_funding_downgrade_factor")]
+    pub downgrade_factor: f64,
+}
+
+fn default_funding_filter_enabled() -> bool { true }
+fn default_funding_max_long_rate() -> f64 { 0.0005 }
+fn default_funding_min_short_rate() -> f64 { -0.0005 }
+fn default_funding_downgrade_factor() -> f64 { 0.5 }
+
+impl Default for FundingFilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_funding_filter_enabled(),
+            max_long_rate: default_funding_max_long_rate(),
+            min_short_rate: default_funding_min_short_rate(),
+            action: FundingAction::default(),
+            downgrade_factor: default_funding_downgrade_factor(),
+        }
+    }
+}
+
+/// 资金费侵蚀利润时的处理
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum FundingCarryAction {
+    /// 直接平仓 (默认)
+    #[default]
+    Close,
+    /// 不平仓，在持仓描述中强烈提示大脑考虑平仓
+    Bias,
+}
+
+/// 资金费持仓成本：持仓期间累计支付的资金费超过浮盈的一定比例时平仓或提示平仓
+/// 收取资金费 (净额为正) 的持仓永不触发
+#[derive(Debug, Deserialize, Clone)]
+pub struct FundingCarryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    /// 累计支付资金费 / 浮盈 达到该比例时触发
+    #[serde(default = "default_funding_carry_max_ratio")]
+    pub max_funding_to_profit: f64,
+    /// 累计支付低于该金额 (USD) 时不检查，避免刚过一次结算就触发
+    #[serde(default = "default_funding_carry_min_paid_usd")]
+    pub min_paid_usd: f64,
+    #[serde(default)]
+    pub action: FundingCarryAction,
+}
+
+fn default_funding_carry_max_ratio() -> f64 { 0.5 }
+fn default_funding_carry_min_paid_usd() -> f64 { 1.0 }
+
+impl Default for FundingCarryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_funding_to_profit: default_funding_carry_max_ratio(),
+            min_paid_usd: default_funding_carry_min_paid_usd(),
+            action: FundingCarryAction::default(),
+        }
+    }
+}
+
+/// 自动选币的排序依据
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DiscoveryRank {
+    /// 24h 成交额
+    Volume,
+    /// 1H ATR%
+    Volatility,
+    /// 成交额排名与 ATR% 排名的平均 (默认)
+    #[default]
+    Blend,
+}
+
+/// 自动选币：按 24h 成交额与 ATR% 从 allowed_symbols (安全白名单) 中挑选前 N 个作为交易池
+#[derive(Debug, Deserialize, Clone)]
+pub struct DiscoveryConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default = "default_discovery_top_n")]
+    pub top_n: usize,
+    /// 24h 成交额下限 (计价币)，低于则不入选
+    #[serde(default)]
+    pub min_volume_usd: f64,
+    /// 1H ATR% 下限 (1.0 = 1%)，低于则不入选
+    #[serde(default)]
+    pub min_atr_pct: f64,
+    #[serde(default)]
+    pub rank_by: DiscoveryRank,
+    /// 重新选币间隔 (小时)
+    #[serde(default = "default_discovery_refresh_hours")]
+    pub refresh_hours: u64,
+}
+
+fn default_discovery_top_n() -> usize { 5 }
+fn default_discovery_refresh_hours() -> u64 { 24 }
+
+impl Default for DiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            top_n: default_discovery_top_n(),
+            min_volume_usd: 0.0,
+            min_atr_pct: 0.0,
+            rank_by: DiscoveryRank::default(),
+            refresh_hours: default_discovery_refresh_hours(),
+        }
+    }
+}
+
+impl DiscoveryConfig {
+    pub fn refresh_interval(&self) -> Duration {
+        Duration::from_secs(self.refresh_hours * 3600)
+    }
+}
+
+/// 历史成交回放 (`backfill` 命令)：把 OKX 上的历史平仓写入记忆
+#[derive(Debug, Deserialize, Clone)]
+pub struct BackfillConfig {
+    /// 回看天数 (OKX 成交明细最多保留 3 个月)，命令行参数优先
+    #[serde(default = "default_backfill_lookback_days")]
+    pub lookback_days: u32,
+    /// 单次最多写入的记忆条数 (每条需要一次 Embedding 与一次历史 K 线请求)
+    #[serde(default = "default_backfill_max_trades")]
+    pub max_trades: usize,
+}
+
+fn default_backfill_lookback_days() -> u32 { 30 }
+fn default_backfill_max_trades() -> usize { 200 }
+
+impl Default for BackfillConfig {
+    fn default() -> Self {
+        Self { lookback_days: default_backfill_lookback_days(), max_trades: default_backfill_max_trades() }
+    }
+}
+
+/// 杠杆爬坡的一档：累计盈利平仓笔数达到 min_wins 后，杠杆上限放宽到 max_leverage
+#[derive(Debug, Deserialize, Clone)]
+pub struct LeverageTier {
+    pub min_wins: u64,
+    pub max_leverage: f64,
+}
+
+/// 杠杆爬坡：新部署先用低杠杆，按 trade_logs 中的盈利平仓笔数逐档解锁，最终不超过 max_leverage
+#[derive(Debug, Deserialize, Clone)]
+pub struct LeverageRampConfig {
+    #[serde(default = "default_leverage_ramp_enabled")]
+    pub enabled: bool,
+    /// 按 min_wins 升序排列；未达到第一档时杠杆上限为 1x
+    #[serde(default = "default_leverage_ramp_tiers")]
+    pub tiers: Vec<LeverageTier>,
+}
+
+fn default_leverage_ramp_enabled() -> bool { true }
+fn default_leverage_ramp_tiers() -> Vec<LeverageTier> {
+    vec![
+        LeverageTier { min_wins: 0, max_leverage: 2.0 },
+        LeverageTier { min_wins: 5, max_leverage: 5.0 },
+        LeverageTier { min_wins: 15, max_leverage: 10.0 },
+    ]
+}
+
+impl Default for LeverageRampConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_leverage_ramp_enabled(),
+            tiers: default_leverage_ramp_tiers(),
+        }
+    }
+}
+
+/// 干跑 (DRY_RUN=1) 模拟账本参数
+#[derive(Debug, Deserialize, Clone)]
+pub struct PaperConfig {
+    /// 模拟初始资金 (结算币)
+    #[serde(default = "default_paper_starting_equity")]
+    pub starting_equity: f64,
+    /// 模拟成交手续费率 (按名义价值收取，0.0005 = 0.05% taker)
+    #[serde(default = "default_paper_fee_rate")]
+    pub fee_rate: f64,
+}
+
+fn default_paper_starting_equity() -> f64 { 10_000.0 }
+fn default_paper_fee_rate() -> f64 { 0.0005 }
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        Self {
+            starting_equity: default_paper_starting_equity(),
+            fee_rate: default_paper_fee_rate(),
+        }
+    }
+}
+
+/// 离线夹具：新闻/Reddit 从本地文件读取，便于回放与复现 (不影响行情与下单)
+#[derive(Debug, Deserialize, Clone)]
+pub struct FixtureConfig {
+    /// true = 读取本地夹具文件，false = 实时抓取；环境变量 FIXTURE_MODE=1 可强制开启
+    #[serde(default)]
+    pub enabled: bool,
+    /// CoinDesk RSS 原始 XML
+    #[serde(default = "default_fixture_news_path")]
+    pub news_path: String,
+    /// Reddit hot.json 原始响应
+    #[serde(default = "default_fixture_reddit_path")]
+    pub reddit_path: String,
+}
+
+fn default_fixture_news_path() -> String { "fixtures/news_rss.xml".to_string() }
+fn default_fixture_reddit_path() -> String { "fixtures/reddit_hot.json".to_string() }
+
+impl Default for FixtureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            news_path: default_fixture_news_path(),
+            reddit_path: default_fixture_reddit_path(),
+        }
+    }
+}
+
+impl FixtureConfig {
+    /// 配置开关或 FIXTURE_MODE=1 任一成立即启用
+    pub fn is_active(&self) -> bool {
+        self.enabled || std::env::var("FIXTURE_MODE").is_ok_and(|v| v == "1")
+    }
+}
+
+/// 相关性敞口守卫：BTC/ETH/山寨高度相关，同向多仓实际是一笔放大的杠杆单
+#[derive(Debug, Deserialize, Clone)]
+pub struct CorrelationConfig {
+    #[serde(default = "default_correlation_enabled")]
+    pub enabled: bool,
+    /// 收益率相关系数达到该值视为相关
+    #[serde(default = "default_correlation_threshold")]
+    pub threshold: f64,
+    /// 相关币种同方向净名义价值上限 (权益的倍数)，新仓位超出部分削减，无剩余额度则跳过
+    #[serde(default = "default_correlation_max_net_exposure")]
+    pub max_net_exposure: f64,
+    /// 计算相关系数使用的最近 K 线根数
+    #[serde(default = "default_correlation_lookback")]
+    pub lookback: usize,
+}
+
+fn default_correlation_enabled() -> bool { true }
+fn default_correlation_threshold() -> f64 { 0.7 }
+fn default_correlation_max_net_exposure() -> f64 { 2.0 }
+fn default_correlation_lookback() -> usize { 48 }
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_correlation_enabled(),
+            threshold: default_correlation_threshold(),
+            max_net_exposure: default_correlation_max_net_exposure(),
+            lookback: default_correlation_lookback(),
+        }
+    }
+}
+
+/// 市场状态分类阈值 (ADX / 布林带宽度 / ATR%)，分类结果注入给大脑
+#[derive(Debug, Deserialize, Clone)]
+pub struct RegimeConfig {
+    /// ADX 达到该值视为趋势市，否则为震荡市
+    #[serde(default = "default_regime_trend_adx")]
+    pub trend_adx: f64,
+    /// 1H ATR% (1.0 = 1%) 达到该值视为高波动 (优先于趋势/震荡判断)
+    #[serde(default = "default_regime_high_vol_atr_pct")]
+    pub high_vol_atr_pct: f64,
+    /// 布林带宽度 (上轨 - 下轨) / 中轨 达到该值同样视为高波动
+    #[serde(default = "default_regime_high_vol_bandwidth")]
+    pub high_vol_bandwidth: f64,
+    /// 强趋势中拒绝逆势开仓 (均值回归)：ADX >= counter_trend_adx 时只允许顺 EMA 趋势方向开仓
+    #[serde(default)]
+    pub block_counter_trend: bool,
+    #[serde(default = "default_regime_counter_trend_adx")]
+    pub counter_trend_adx: f64,
+    /// 横盘震荡中拒绝顺势开仓 (趋势跟随)：CHOP(14) >= chop_threshold 时只允许逆 EMA 趋势方向 (均值回归) 开仓
+    #[serde(default)]
+    pub block_choppy_entries: bool,
+    #[serde(default = "default_regime_chop_threshold")]
+    pub chop_threshold: f64,
+}
+
+fn default_regime_trend_adx() -> f64 { 25.0 }
+fn default_regime_high_vol_atr_pct() -> f64 { 2.0 }
+fn default_regime_high_vol_bandwidth() -> f64 { 0.08 }
+fn default_regime_counter_trend_adx() -> f64 { 40.0 }
+fn default_regime_chop_threshold() -> f64 { 61.8 }
+
+impl Default for RegimeConfig {
+    fn default() -> Self {
+        Self {
+            trend_adx: default_regime_trend_adx(),
+            high_vol_atr_pct: default_regime_high_vol_atr_pct(),
+            high_vol_bandwidth: default_regime_high_vol_bandwidth(),
+            block_counter_trend: false,
+            counter_trend_adx: default_regime_counter_trend_adx(),
+            block_choppy_entries: false,
+            chop_threshold: default_regime_chop_threshold(),
+        }
+    }
+}
+
+/// 进入停机窗口时对已有持仓止损的处理
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BlackoutStopAction {
+    /// 保持原止损 (默认)
+    #[default]
+    Keep,
+    /// 把止损到现价的距离缩小到 tighten_factor 倍 (只收紧不放宽，窗口结束后不恢复)
+    Tighten,
+}
+
+/// 一次性停机窗口 (RFC3339 时间，带时区偏移时自动换算为 UTC)
+#[derive(Debug, Deserialize, Clone)]
+pub struct BlackoutWindow {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[serde(default)]
+    pub label: String,
+}
+
+/// 每周重复的停机窗口 (UTC)，如 day = "Wed", start = "18:00", duration_min = 90
+#[derive(Debug, Deserialize, Clone)]
+pub struct WeeklyBlackout {
+    pub day: String,
+    pub start: String,
+    pub duration_min: u32,
+    #[serde(default)]
+    pub label: String,
+}
+
+impl WeeklyBlackout {
+    /// 解析星期与开始时间 (HH:MM, UTC)
+    pub fn schedule(&self) -> Result<(Weekday, NaiveTime), String> {
+        let day = self.day.parse::<Weekday>().map_err(|_| format!("invalid day '{}'", self.day))?;
+        let start = NaiveTime::parse_from_str(&self.start, "%H:%M").map_err(|_| format!("invalid start '{}' (expected HH:MM)", self.start))?;
+        Ok((day, start))
+    }
+}
+
+/// 重大事件 (CPI / FOMC 等) 停机窗口：窗口内不分析、不开仓也不主动平仓，交易所止损照常生效
+#[derive(Debug, Deserialize, Clone)]
+pub struct BlackoutConfig {
+    #[serde(default = "default_blackout_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub windows: Vec<BlackoutWindow>,
+    #[serde(default)]
+    pub weekly: Vec<WeeklyBlackout>,
+    #[serde(default)]
+    pub stop_action: BlackoutStopAction,
+    #[serde(default = "default_blackout_tighten_factor")]
+    pub tighten_factor: f64,
+}
+
+fn default_blackout_enabled() -> bool { true }
+fn default_blackout_tighten_factor() -> f64 { 0.5 }
+
+impl Default for BlackoutConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_blackout_enabled(),
+            windows: Vec::new(),
+            weekly: Vec::new(),
+            stop_action: BlackoutStopAction::default(),
+            tighten_factor: default_blackout_tighten_factor(),
+        }
+    }
+}
+
+/// 组合总敞口上限：所有持仓名义价值之和 (不分方向、不计相关性) 不超过权益的倍数
+#[derive(Debug, Deserialize, Clone)]
+pub struct ExposureConfig {
+    #[serde(default = "default_exposure_enabled")]
+    pub enabled: bool,
+    /// 总名义价值上限 (权益的倍数)，新仓位超出部分削减，无剩余额度则跳过
+    #[serde(default = "default_exposure_max_gross_multiple")]
+    pub max_gross_multiple: f64,
+}
+
+fn default_exposure_enabled() -> bool { true }
+fn default_exposure_max_gross_multiple() -> f64 { 3.0 }
+
+impl Default for ExposureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: default_exposure_enabled(),
+            max_gross_multiple: default_exposure_max_gross_multiple(),
+        }
+    }
+}
+
+/// 再平衡目标的分配方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RebalanceMode {
+    /// 等风险 (默认)：目标名义价值与 ATR% 成反比，波动大的币种持仓更小
+    #[default]
+    EqualRisk,
+    /// 按 target_weights 配置的权重分配，未列出的币种不参与
+    TargetWeights,
+}
+
+/// 组合再平衡：定期削减超出目标权重的持仓 (只减仓，不加仓)
+#[derive(Debug, Deserialize, Clone)]
+pub struct RebalanceConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub mode: RebalanceMode,
+    /// target_weights 模式下各币种的相对权重 (无需归一化)
+    #[serde(default)]
+    pub target_weights: HashMap<String, f64>,
+    /// 超出目标的容忍比例：名义价值 > 目标 × (1 + tolerance) 时才削减
+    #[serde(default = "default_rebalance_tolerance")]
+    pub tolerance: f64,
+    /// 削减金额 (名义价值) 低于此值时不下单，避免频繁小额成交
+    #[serde(default = "default_rebalance_min_trim_usd")]
+    pub min_trim_usd: f64,
+}
+
+fn default_rebalance_tolerance() -> f64 { 0.25 }
+fn default_rebalance_min_trim_usd() -> f64 { 20.0 }
+
+impl Default for RebalanceConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            mode: RebalanceMode::default(),
+            target_weights: HashMap::new(),
+            tolerance: default_rebalance_tolerance(),
+            min_trim_usd: default_rebalance_min_trim_usd(),
+        }
+    }
+}
+
+/// 从 instId 推导计价/结算币种："BTC-USDC-SWAP" / "BTC-USDC" -> "USDC"
+pub fn settle_ccy_of(inst_id: &str) -> Option<&str> {
+    inst_id.split('-').nth(1).filter(|c| !c.is_empty())
+}
+
+/// 杠杆来源
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LeverageMode {
+    /// 使用模型给出的杠杆 (默认)，夹紧到 [1, 杠杆上限]
+    #[default]
+    Model,
+    /// 忽略模型的杠杆数字，按凯利值在 curve 上插值得到杠杆上限的比例
+    Confidence,
+}
+
+/// 置信度曲线上的一个点：凯利值为 kelly 时使用杠杆上限的 fraction 倍
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct LeveragePoint {
+    pub kelly: f64,
+    pub fraction: f64,
+}
+
+/// 置信度杠杆：弱信号低杠杆，强信号逐步放大到上限
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct LeverageScalingConfig {
+    #[serde(default)]
+    pub mode: LeverageMode,
+    /// 按 kelly 升序的折线，两点之间线性插值，两端之外取端点值
+    #[serde(default = "default_leverage_curve")]
+    pub curve: Vec<LeveragePoint>,
+}
+
+fn default_leverage_curve() -> Vec<LeveragePoint> {
+    vec![
+        LeveragePoint { kelly: 0.05, fraction: 0.2 },
+        LeveragePoint { kelly: 0.15, fraction: 0.5 },
+        LeveragePoint { kelly: 0.30, fraction: 1.0 },
+    ]
+}
+
+impl Default for LeverageScalingConfig {
+    fn default() -> Self {
+        Self { mode: LeverageMode::default(), curve: default_leverage_curve() }
+    }
+}
+
+/// 模型给出的 TP/SL 比例的允许范围，超出时夹紧到边界 (防止 50% 止盈 / 0.1% 止损这类离群值)
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct TpSlClampConfig {
+    #[serde(default = "default_clamp_min_tp_pct")]
+    pub min_tp_pct: f64,
+    #[serde(default = "default_clamp_max_tp_pct")]
+    pub max_tp_pct: f64,
+    #[serde(default = "default_clamp_min_sl_pct")]
+    pub min_sl_pct: f64,
+    #[serde(default = "default_clamp_max_sl_pct")]
+    pub max_sl_pct: f64,
+}
+
+fn default_clamp_min_tp_pct() -> f64 { 0.005 }
+fn default_clamp_max_tp_pct() -> f64 { 0.30 }
+fn default_clamp_min_sl_pct() -> f64 { 0.003 }
+fn default_clamp_max_sl_pct() -> f64 { 0.15 }
+
+impl Default for TpSlClampConfig {
+    fn default() -> Self {
+        Self {
+            min_tp_pct: default_clamp_min_tp_pct(),
+            max_tp_pct: default_clamp_max_tp_pct(),
+            min_sl_pct: default_clamp_min_sl_pct(),
+            max_sl_pct: default_clamp_max_sl_pct(),
+        }
+    }
+}
+
+/// 全局 TP/SL 范围与各币种覆盖后的范围 (交给 DecisionMaker 在解析决策时使用)
+#[derive(Debug, Clone, Default)]
+pub struct TpSlClamps {
+    global: TpSlClampConfig,
+    per_symbol: HashMap<String, TpSlClampConfig>,
+}
+
+impl TpSlClamps {
+    pub fn for_symbol(&self, symbol: &str) -> &TpSlClampConfig {
+        self.per_symbol.get(symbol).unwrap_or(&self.global)
+    }
+}
+
+/// 单个币种的参数覆盖，未填写的字段沿用全局配置
+#[derive(Debug, Deserialize, Clone)]
+pub struct SymbolOverride {
+    pub symbol: String,
+    #[serde(default)]
+    pub max_spread_bps: Option<f64>,
+    #[serde(default)]
+    pub allowed_actions: Option<Vec<AllowedAction>>,
+    #[serde(default)]
+    pub min_tp_pct: Option<f64>,
+    #[serde(default)]
+    pub max_tp_pct: Option<f64>,
+    #[serde(default)]
+    pub min_sl_pct: Option<f64>,
+    #[serde(default)]
+    pub max_sl_pct: Option<f64>,
+    /// 该币种的杠杆上限 (只能低于全局 max_leverage)
+    #[serde(default)]
+    pub max_leverage: Option<f64>,
+    /// false = 暂停该币种的新开仓 (行情、持仓管理、平仓照常)，需重启生效；运行时开关见 `enable` / `disable` 命令
+    #[serde(default)]
+    pub enabled: Option<bool>,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Deserialize, Clone)]
 pub struct RiskProfile {
     pub max_leverage: f64,
     pub max_order_size_pct: f64,
+    /// 单笔最大亏损 (美元)：按止损距离限制仓位，打到止损时亏损不超过该金额 (不含手续费与滑点)；None = 不限制
+    #[serde(default)]
+    pub max_loss_per_trade_usd: Option<f64>,
     pub daily_drawdown_limit: f64,
     pub allowed_symbols: Vec<String>,
     pub timing: TimingConfig,
     pub indicators: IndicatorConfig,
     pub thresholds: ThresholdConfig,
+    #[serde(default)]
+    pub execution: ExecutionConfig,
+    #[serde(default)]
+    pub pyramiding: PyramidingConfig,
+    #[serde(default)]
+    pub breakeven: BreakevenConfig,
+    #[serde(default)]
+    pub kelly: KellyConfig,
+    #[serde(default)]
+    pub drawdown_scaling: DrawdownScalingConfig,
+    #[serde(default)]
+    pub anti_flip: AntiFlipConfig,
+    #[serde(default)]
+    pub thrashing: ThrashingConfig,
+    #[serde(default)]
+    pub min_hold: MinHoldConfig,
+    #[serde(default)]
+    pub memory: MemoryConfig,
+    #[serde(default)]
+    pub notify: NotifyConfig,
+    #[serde(default)]
+    pub llm: LlmConfig,
+    #[serde(default)]
+    pub circuit_breaker: CircuitBreakerConfig,
+    #[serde(default)]
+    pub retry: RetryConfig,
+    #[serde(default)]
+    pub margin: MarginConfig,
+    #[serde(default)]
+    pub funding_filter: FundingFilterConfig,
+    #[serde(default)]
+    pub funding_carry: FundingCarryConfig,
+    #[serde(default)]
+    pub exposure: ExposureConfig,
+    #[serde(default)]
+    pub rebalance: RebalanceConfig,
+    #[serde(default)]
+    pub regime: RegimeConfig,
+    #[serde(default)]
+    pub blackout: BlackoutConfig,
+    #[serde(default)]
+    pub fixtures: FixtureConfig,
+    #[serde(default)]
+    pub paper: PaperConfig,
+    #[serde(default)]
+    pub correlation: CorrelationConfig,
+    #[serde(default)]
+    pub leverage_ramp: LeverageRampConfig,
+    #[serde(default)]
+    pub discovery: DiscoveryConfig,
+    #[serde(default)]
+    pub backfill: BackfillConfig,
+    #[serde(default)]
+    pub tpsl_clamp: TpSlClampConfig,
+    #[serde(default)]
+    pub leverage_scaling: LeverageScalingConfig,
+    #[serde(default)]
+    pub symbol_overrides: Vec<SymbolOverride>,
 }
 
 impl RiskProfile {
@@ -47,11 +1509,474 @@ impl RiskProfile {
             .build()?;
 
         let profile: RiskProfile = settings.try_deserialize()?;
+        profile.validate()?;
         Ok(profile)
     }
+
+    /// 结算币种：优先取 execution.settle_ccy，否则取 allowed_symbols 的计价币 (validate 已保证一致)
+    pub fn settle_ccy(&self) -> String {
+        self.execution.settle_ccy.clone()
+            .or_else(|| self.allowed_symbols.first().and_then(|s| settle_ccy_of(s)).map(str::to_string))
+            .unwrap_or_else(|| "USDT".to_string())
+    }
+
+    fn symbol_override(&self, symbol: &str) -> Option<&SymbolOverride> {
+        self.symbol_overrides.iter().find(|o| o.symbol == symbol)
+    }
+
+    /// 该币种允许的最大价差 (bp)，优先取 [[symbol_overrides]]
+    pub fn max_spread_bps(&self, symbol: &str) -> f64 {
+        self.symbol_override(symbol)
+            .and_then(|o| o.max_spread_bps)
+            .unwrap_or(self.execution.max_spread_bps)
+    }
+
+    /// 该币种允许的交易动作，优先取 [[symbol_overrides]]
+    pub fn allowed_actions(&self, symbol: &str) -> &[AllowedAction] {
+        self.symbol_override(symbol)
+            .and_then(|o| o.allowed_actions.as_deref())
+            .unwrap_or(&self.execution.allowed_actions)
+    }
+
+    /// 该币种的杠杆上限：[[symbol_overrides]] 的 max_leverage 与全局 max_leverage 取较小值
+    pub fn symbol_enabled(&self, symbol: &str) -> bool {
+        self.symbol_override(symbol).and_then(|o| o.enabled).unwrap_or(true)
+    }
+
+    pub fn symbol_max_leverage(&self, symbol: &str) -> f64 {
+        self.symbol_override(symbol)
+            .and_then(|o| o.max_leverage)
+            .map_or(self.max_leverage, |l| l.min(self.max_leverage))
+    }
+
+    /// 该币种的 TP/SL 范围，[[symbol_overrides]] 中填写的边界覆盖全局 [tpsl_clamp]
+    pub fn tpsl_clamp(&self, symbol: &str) -> TpSlClampConfig {
+        let global = &self.tpsl_clamp;
+        let Some(o) = self.symbol_override(symbol) else { return global.clone() };
+        TpSlClampConfig {
+            min_tp_pct: o.min_tp_pct.unwrap_or(global.min_tp_pct),
+            max_tp_pct: o.max_tp_pct.unwrap_or(global.max_tp_pct),
+            min_sl_pct: o.min_sl_pct.unwrap_or(global.min_sl_pct),
+            max_sl_pct: o.max_sl_pct.unwrap_or(global.max_sl_pct),
+        }
+    }
+
+    pub fn tpsl_clamps(&self) -> TpSlClamps {
+        TpSlClamps {
+            global: self.tpsl_clamp.clone(),
+            per_symbol: self.symbol_overrides.iter().map(|o| (o.symbol.clone(), self.tpsl_clamp(&o.symbol))).collect(),
+        }
+    }
+
+    /// 启动时校验关键参数，防止配置错误导致 API 刷屏或扫描失效
+    pub fn validate(&self) -> Result<()> {
+        if self.timing.symbol_gap_sec > 60 {
+            bail!(TraderError::Config(format!("timing.symbol_gap_sec = {} is too large (max 60s per symbol)", self.timing.symbol_gap_sec)));
+        }
+        let pump = self.thresholds.scanner_pump_pct;
+        if !(pump > 0.0 && pump < 1.0) {
+            bail!(TraderError::Config(format!("thresholds.scanner_pump_pct = {} must be between 0 and 1 (e.g. 0.05 = 5%)", pump)));
+        }
+        if !(self.drawdown_scaling.min_scale > 0.0 && self.drawdown_scaling.min_scale <= 1.0) {
+            bail!(TraderError::Config(format!("drawdown_scaling.min_scale = {} must be in (0, 1]", self.drawdown_scaling.min_scale)));
+        }
+        if let Some(usd) = self.max_loss_per_trade_usd.filter(|u| u.is_nan() || *u <= 0.0) {
+            bail!(TraderError::Config(format!("max_loss_per_trade_usd = {} must be > 0 (remove it to disable)", usd)));
+        }
+        if !(0.0..1.0).contains(&self.kelly.reserve_pct) {
+            bail!(TraderError::Config(format!("kelly.reserve_pct = {} must be in [0, 1)", self.kelly.reserve_pct)));
+        }
+        if self.kelly.min_win_rate > self.kelly.win_rate_cap {
+            bail!(TraderError::Config(format!("kelly.min_win_rate ({}) must not exceed kelly.win_rate_cap ({})", self.kelly.min_win_rate, self.kelly.win_rate_cap)));
+        }
+        // 权益/可用余额只按一种结算币读取，混合 USDT 与 USDC 合约会导致仓位计算错误
+        let mut ccys: Vec<&str> = self.allowed_symbols.iter().filter_map(|s| settle_ccy_of(s)).collect();
+        ccys.dedup();
+        if ccys.len() > 1 {
+            bail!(TraderError::Config(format!("allowed_symbols mix settlement currencies {:?}; run one instance per currency", ccys)));
+        }
+        if let (Some(cfg), Some(derived)) = (&self.execution.settle_ccy, ccys.first()) {
+            if cfg != derived {
+                bail!(TraderError::Config(format!("execution.settle_ccy = {} does not match allowed_symbols ({})", cfg, derived)));
+            }
+        }
+        for o in &self.symbol_overrides {
+            if o.max_spread_bps.is_some_and(|bps| bps.is_nan() || bps < 0.0) {
+                bail!(TraderError::Config(format!("symbol_overrides[{}].max_spread_bps must be >= 0", o.symbol)));
+            }
+        }
+        if self.paper.starting_equity.is_nan() || self.paper.starting_equity <= 0.0 || !(0.0..0.01).contains(&self.paper.fee_rate) {
+            bail!(TraderError::Config(format!("paper.starting_equity ({}) must be positive and paper.fee_rate ({}) within [0, 0.01)", self.paper.starting_equity, self.paper.fee_rate)));
+        }
+        let ich = &self.indicators;
+        if ich.ichimoku_tenkan == 0 || ich.ichimoku_kijun == 0 || ich.ichimoku_senkou_b == 0 {
+            bail!(TraderError::Config("indicators.ichimoku_* periods must be positive".to_string()));
+        }
+        // 翻页请求数随根数线性增长，设个上限防止拖慢每轮循环
+        if !(2..=MAX_KLINE_LIMIT).contains(&ich.kline_limit) {
+            bail!(TraderError::Config(format!("indicators.kline_limit = {} must be between 2 and {}", ich.kline_limit, MAX_KLINE_LIMIT)));
+        }
+        // 先行带需要 senkou_b + kijun 根
+        if ich.ichimoku_senkou_b.max(ich.ichimoku_tenkan).max(ich.ichimoku_kijun) + ich.ichimoku_kijun > ich.kline_limit {
+            bail!(TraderError::Config(format!("indicators.ichimoku_senkou_b ({}) + ichimoku_kijun ({}) exceeds kline_limit ({})", ich.ichimoku_senkou_b, ich.ichimoku_kijun, ich.kline_limit)));
+        }
+        if !is_valid_ladder(&self.execution.tp_ladder) {
+            bail!(TraderError::Config(format!(
+                "execution.tp_ladder {:?}: r_multiple must be positive and ascending, fraction in (0, 1] and fractions sum <= 1",
+                self.execution.tp_ladder
+            )));
+        }
+        let margin = &self.margin;
+        let ratios_ok = margin.recover_ratio > 0.0 && margin.recover_ratio < margin.danger_ratio && margin.danger_ratio < 1.0;
+        if !(ratios_ok && margin.reduce_fraction > 0.0 && margin.reduce_fraction <= 1.0) {
+            bail!(TraderError::Config(format!(
+                "margin: need 0 < recover_ratio ({}) < danger_ratio ({}) < 1 and reduce_fraction ({}) in (0, 1]",
+                margin.recover_ratio, margin.danger_ratio, margin.reduce_fraction
+            )));
+        }
+        let funding = &self.funding_filter;
+        if !(funding.max_long_rate >= 0.0 && funding.min_short_rate <= 0.0 && funding.downgrade_factor > 0.0 && funding.downgrade_factor <= 1.0) {
+            bail!(TraderError::Config(format!(
+                "funding_filter: need max_long_rate ({}) >= 0, min_short_rate ({}) <= 0 and downgrade_factor ({}) in (0, 1]",
+                funding.max_long_rate, funding.min_short_rate, funding.downgrade_factor
+            )));
+        }
+        if self.memory.embedding_dim == Some(0) {
+            bail!(TraderError::Config("memory.embedding_dim must be > 0".to_string()));
+        }
+        let carry = &self.funding_carry;
+        if !(carry.max_funding_to_profit > 0.0 && carry.min_paid_usd >= 0.0) {
+            bail!(TraderError::Config(format!(
+                "funding_carry: max_funding_to_profit ({}) must be > 0 and min_paid_usd ({}) >= 0",
+                carry.max_funding_to_profit, carry.min_paid_usd
+            )));
+        }
+        if let Some(o) = self.symbol_overrides.iter().find(|o| o.max_leverage.is_some_and(|l| l.is_nan() || l < 1.0)) {
+            bail!(TraderError::Config(format!("symbol_overrides ({}): max_leverage = {:?} must be >= 1", o.symbol, o.max_leverage)));
+        }
+        let curve = &self.leverage_scaling.curve;
+        if self.leverage_scaling.mode == LeverageMode::Confidence && curve.is_empty() {
+            bail!(TraderError::Config("leverage_scaling.mode = \"confidence\" needs at least one curve point".to_string()));
+        }
+        if curve.iter().any(|p| p.kelly.is_nan() || !(p.fraction > 0.0 && p.fraction <= 1.0))
+            || curve.windows(2).any(|w| w[1].kelly <= w[0].kelly) {
+            bail!(TraderError::Config("leverage_scaling.curve must be sorted by ascending kelly with fraction in (0, 1]".to_string()));
+        }
+        let clamp_scopes = std::iter::once(("global".to_string(), self.tpsl_clamp.clone()))
+            .chain(self.symbol_overrides.iter().map(|o| (o.symbol.clone(), self.tpsl_clamp(&o.symbol))));
+        for (scope, c) in clamp_scopes {
+            if !(c.min_tp_pct > 0.0 && c.min_tp_pct <= c.max_tp_pct && c.min_sl_pct > 0.0 && c.min_sl_pct <= c.max_sl_pct && c.max_sl_pct < 1.0) {
+                bail!(TraderError::Config(format!(
+                    "tpsl_clamp ({}): need 0 < min_tp_pct ({}) <= max_tp_pct ({}) and 0 < min_sl_pct ({}) <= max_sl_pct ({}) < 1",
+                    scope, c.min_tp_pct, c.max_tp_pct, c.min_sl_pct, c.max_sl_pct
+                )));
+            }
+        }
+        if !(1..=90).contains(&self.backfill.lookback_days) {
+            bail!(TraderError::Config(format!("backfill.lookback_days = {} must be between 1 and 90 (OKX keeps 3 months of fills)", self.backfill.lookback_days)));
+        }
+        let exec = &self.execution;
+        if exec.pending_order_timeout_sec > 0 && exec.pending_order_timeout_sec <= exec.maker_timeout_sec {
+            bail!(TraderError::Config(format!(
+                "execution.pending_order_timeout_sec ({}) must be 0 or longer than maker_timeout_sec ({})",
+                exec.pending_order_timeout_sec, exec.maker_timeout_sec
+            )));
+        }
+        if exec.max_price_age_sec == 0 {
+            bail!(TraderError::Config("execution.max_price_age_sec must be at least 1".to_string()));
+        }
+        let blackout = &self.blackout;
+        if let Some(w) = blackout.windows.iter().find(|w| w.end <= w.start) {
+            bail!(TraderError::Config(format!("blackout window '{}': end {} must be after start {}", w.label, w.end, w.start)));
+        }
+        for w in &blackout.weekly {
+            if let Err(e) = w.schedule() {
+                bail!(TraderError::Config(format!("blackout.weekly '{}': {}", w.label, e)));
+            }
+            if w.duration_min == 0 || w.duration_min >= 7 * 24 * 60 {
+                bail!(TraderError::Config(format!("blackout.weekly '{}': duration_min ({}) must be in (0, 1 week)", w.label, w.duration_min)));
+            }
+        }
+        if !(blackout.tighten_factor > 0.0 && blackout.tighten_factor < 1.0) {
+            bail!(TraderError::Config(format!("blackout.tighten_factor = {} must be in (0, 1)", blackout.tighten_factor)));
+        }
+        let regime = &self.regime;
+        if [regime.trend_adx, regime.counter_trend_adx].iter().any(|a| !(0.0..=100.0).contains(a)) || regime.high_vol_atr_pct <= 0.0 || regime.high_vol_bandwidth <= 0.0 {
+            bail!(TraderError::Config(format!(
+                "regime: trend_adx ({}) and counter_trend_adx ({}) must be in [0, 100], high_vol_atr_pct ({}) and high_vol_bandwidth ({}) > 0",
+                regime.trend_adx, regime.counter_trend_adx, regime.high_vol_atr_pct, regime.high_vol_bandwidth
+            )));
+        }
+        if !(regime.chop_threshold > 0.0 && regime.chop_threshold <= 100.0) {
+            bail!(TraderError::Config(format!("regime.chop_threshold = {} must be in (0, 100]", regime.chop_threshold)));
+        }
+        let thrash = &self.thrashing;
+        if thrash.enabled && (thrash.window_sec == 0 || thrash.max_reversals == 0 || thrash.history_len < 2) {
+            bail!(TraderError::Config(format!(
+                "thrashing: window_sec ({}) and max_reversals ({}) must be > 0, history_len ({}) >= 2",
+                thrash.window_sec, thrash.max_reversals, thrash.history_len
+            )));
+        }
+        if self.retry.open_attempts == 0 || self.retry.close_attempts == 0 {
+            bail!(TraderError::Config(format!(
+                "retry.open_attempts = {} and retry.close_attempts = {} must both be >= 1",
+                self.retry.open_attempts, self.retry.close_attempts
+            )));
+        }
+        if self.exposure.max_gross_multiple.is_nan() || self.exposure.max_gross_multiple <= 0.0 {
+            bail!(TraderError::Config(format!("exposure.max_gross_multiple = {} must be > 0", self.exposure.max_gross_multiple)));
+        }
+        let corr = &self.correlation;
+        let rb = &self.rebalance;
+        if rb.tolerance.is_nan() || rb.tolerance < 0.0 || rb.min_trim_usd.is_nan() || rb.min_trim_usd < 0.0 {
+            bail!(TraderError::Config(format!("rebalance: tolerance ({}) and min_trim_usd ({}) must be >= 0", rb.tolerance, rb.min_trim_usd)));
+        }
+        if let Some((symbol, w)) = rb.target_weights.iter().find(|(_, w)| w.is_nan() || **w < 0.0) {
+            bail!(TraderError::Config(format!("rebalance.target_weights.{} = {} must be >= 0", symbol, w)));
+        }
+        if rb.enabled && rb.mode == RebalanceMode::TargetWeights && rb.target_weights.values().all(|w| *w <= 0.0) {
+            bail!(TraderError::Config("rebalance.mode = \"target_weights\" needs at least one positive entry in rebalance.target_weights".to_string()));
+        }
+        if !(-1.0..=1.0).contains(&corr.threshold) || corr.max_net_exposure.is_nan() || corr.max_net_exposure <= 0.0 || corr.lookback < 10 {
+            bail!(TraderError::Config(format!(
+                "correlation: threshold ({}) must be in [-1, 1], max_net_exposure ({}) > 0, lookback ({}) >= 10",
+                corr.threshold, corr.max_net_exposure, corr.lookback
+            )));
+        }
+        let funding_h = self.indicators.funding_interval_hours;
+        if funding_h.is_nan() || funding_h <= 0.0 || funding_h > 24.0 {
+            bail!(TraderError::Config(format!("indicators.funding_interval_hours = {} must be in (0, 24]", funding_h)));
+        }
+        if self.timing.base_volatility_pct.is_nan() || self.timing.base_volatility_pct <= 0.0 {
+            bail!(TraderError::Config(format!("timing.base_volatility_pct = {} must be positive", self.timing.base_volatility_pct)));
+        }
+        if !(0.0..=2.0).contains(&self.llm.temperature) {
+            bail!(TraderError::Config(format!("llm.temperature = {} must be between 0 and 2", self.llm.temperature)));
+        }
+        if !(self.llm.fallback_sl_atr_mult > 0.0 && self.llm.fallback_target_rr > 0.0) {
+            bail!(TraderError::Config(format!("llm.fallback_sl_atr_mult ({}) and llm.fallback_target_rr ({}) must be positive", self.llm.fallback_sl_atr_mult, self.llm.fallback_target_rr)));
+        }
+        if self.llm.max_tokens == Some(0) {
+            bail!(TraderError::Config("llm.max_tokens must be positive (remove it to use the server default)".to_string()));
+        }
+        if self.discovery.enabled && (self.discovery.top_n == 0 || self.discovery.refresh_hours == 0) {
+            bail!(TraderError::Config(format!(
+                "discovery: top_n ({}) and refresh_hours ({}) must be positive",
+                self.discovery.top_n, self.discovery.refresh_hours
+            )));
+        }
+        let tiers = &self.leverage_ramp.tiers;
+        if tiers.iter().any(|t| t.max_leverage.is_nan() || t.max_leverage < 1.0) {
+            bail!(TraderError::Config("leverage_ramp.tiers: max_leverage must be >= 1".to_string()));
+        }
+        if tiers.windows(2).any(|w| w[1].min_wins <= w[0].min_wins || w[1].max_leverage < w[0].max_leverage) {
+            bail!(TraderError::Config("leverage_ramp.tiers must be sorted by ascending min_wins with non-decreasing max_leverage".to_string()));
+        }
+        Ok(())
+    }
+    
+    /// 测试用最小配置，其余字段取默认值
+    #[cfg(test)]
+    pub fn for_tests() -> Self {
+        Self::from_toml_str(r#"
+            max_leverage = 10.0
+            max_order_size_pct = 0.10
+            daily_drawdown_limit = 0.10
+            allowed_symbols = ["BTC-USDT-SWAP", "ETH-USDT-SWAP"]
+            [timing]
+            cycle_rest_sec = 300
+            evolution_sec = 3600
+            symbol_gap_sec = 2
+            [indicators]
+            kline_interval = "1H"
+            rsi_period = 14
+            atr_period = 14
+            ema_fast = 20
+            ema_slow = 50
+            [thresholds]
+            autopsy_roe_pct = -0.02
+            scanner_pump_pct = 0.05
+        "#).expect("test profile")
+    }
+
+    #[cfg(test)]
+    pub fn from_toml_str(toml: &str) -> Result<Self> {
+        let settings = Config::builder()
+            .add_source(File::from_str(toml, config::FileFormat::Toml))
+            .build()?;
+        Ok(settings.try_deserialize()?)
+    }
     
     #[allow(dead_code)]
     pub fn is_symbol_allowed(&self, symbol: &str) -> bool {
         self.allowed_symbols.contains(&symbol.to_string())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbol_gap_uses_configured_seconds() {
+        let mut p = RiskProfile::for_tests();
+        p.timing.symbol_gap_sec = 7;
+        assert_eq!(p.timing.symbol_gap(), Duration::from_secs(7));
+    }
+
+    #[test]
+    fn evolution_tasks_default_to_combined_interval() {
+        let mut p = RiskProfile::for_tests();
+        p.timing.evolution_sec = 3600;
+        assert_eq!(p.timing.pnl_sync_interval(), Duration::from_secs(3600));
+        assert_eq!(p.timing.autopsy_interval(), Duration::from_secs(3600));
+        assert_eq!(p.timing.scanner_interval(), Duration::from_secs(3600));
+
+        p.timing.pnl_sync_sec = Some(300);
+        p.timing.autopsy_sec = Some(86_400);
+        assert_eq!(p.timing.pnl_sync_interval(), Duration::from_secs(300));
+        assert_eq!(p.timing.autopsy_interval(), Duration::from_secs(86_400));
+        assert_eq!(p.timing.scanner_interval(), Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn validate_accepts_defaults() {
+        assert!(RiskProfile::for_tests().validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_insane_symbol_gap() {
+        let mut p = RiskProfile::for_tests();
+        p.timing.symbol_gap_sec = 61;
+        assert!(p.validate().is_err());
+    }
+
+    #[test]
+    fn validate_rejects_win_rate_floor_above_cap() {
+        let mut p = RiskProfile::for_tests();
+        p.kelly.min_win_rate = 0.8;
+        p.kelly.win_rate_cap = 0.75;
+        assert!(p.validate().is_err());
+        p.kelly.min_win_rate = 0.75;
+        assert!(p.validate().is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_out_of_range_pump_pct() {
+        for bad in [0.0, -0.05, 1.0, 5.0] {
+            let mut p = RiskProfile::for_tests();
+            p.thresholds.scanner_pump_pct = bad;
+            assert!(p.validate().is_err(), "pump pct {} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn validate_rejects_bad_llm_sampling() {
+        for bad in [-0.1, 2.5, f64::NAN] {
+            let mut p = RiskProfile::for_tests();
+            p.llm.temperature = bad;
+            assert!(p.validate().is_err(), "temperature {} should be rejected", bad);
+        }
+        let mut p = RiskProfile::for_tests();
+        p.llm.max_tokens = Some(0);
+        assert!(p.validate().is_err());
+        p.llm.max_tokens = Some(8000);
+        assert!(p.validate().is_ok());
+    }
+
+    #[test]
+    fn rest_interval_scales_with_volatility() {
+        let mut p = RiskProfile::for_tests();
+        p.timing.cycle_rest_sec = 300;
+        let t = &p.timing;
+        // 基准波动率 0.5%：保持原间隔
+        assert_eq!(t.rest_interval(0.5), Duration::from_secs(300));
+        // 波动率翻倍：休眠减半
+        assert_eq!(t.rest_interval(1.0), Duration::from_secs(150));
+        // 低波动：最多延长到 2 倍
+        assert_eq!(t.rest_interval(0.1), Duration::from_secs(600));
+        // 极端波动：不低于 min_rest_sec
+        assert_eq!(t.rest_interval(10.0), Duration::from_secs(60));
+        // 无数据 / 异常输入：回退到基础间隔
+        assert_eq!(t.rest_interval(0.0), Duration::from_secs(300));
+        assert_eq!(t.rest_interval(-1.0), Duration::from_secs(300));
+        assert_eq!(t.rest_interval(f64::NAN), Duration::from_secs(300));
+    }
+
+    #[test]
+    fn rest_interval_never_zero() {
+        let mut p = RiskProfile::for_tests();
+        p.timing.cycle_rest_sec = 10;
+        p.timing.min_rest_sec = 0;
+        assert!(p.timing.rest_interval(1000.0) >= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn symbol_override_replaces_global_values() {
+        let p = RiskProfile::from_toml_str(r#"
+            max_leverage = 10.0
+            max_order_size_pct = 0.10
+            daily_drawdown_limit = 0.10
+            allowed_symbols = ["BTC-USDT-SWAP", "DOGE-USDT-SWAP"]
+            [timing]
+            cycle_rest_sec = 300
+            evolution_sec = 3600
+            symbol_gap_sec = 2
+            [indicators]
+            kline_interval = "1H"
+            rsi_period = 14
+            atr_period = 14
+            ema_fast = 20
+            ema_slow = 50
+            [thresholds]
+            autopsy_roe_pct = -0.02
+            scanner_pump_pct = 0.05
+            [execution]
+            max_spread_bps = 10.0
+            [[symbol_overrides]]
+            symbol = "DOGE-USDT-SWAP"
+            max_spread_bps = 30.0
+            allowed_actions = ["buy", "close_long"]
+            max_sl_pct = 0.25
+            max_leverage = 3.0
+            enabled = false
+        "#).unwrap();
+        assert!(p.symbol_enabled("BTC-USDT-SWAP"));
+        assert!(!p.symbol_enabled("DOGE-USDT-SWAP"));
+        assert_eq!(p.max_spread_bps("BTC-USDT-SWAP"), 10.0);
+        assert_eq!(p.max_spread_bps("DOGE-USDT-SWAP"), 30.0);
+        assert_eq!(p.allowed_actions("BTC-USDT-SWAP").len(), 4);
+        assert_eq!(p.allowed_actions("DOGE-USDT-SWAP"), &[AllowedAction::Buy, AllowedAction::CloseLong]);
+        assert_eq!(p.tpsl_clamp("BTC-USDT-SWAP"), TpSlClampConfig::default());
+        // 只覆盖填写的边界
+        let doge = p.tpsl_clamps().for_symbol("DOGE-USDT-SWAP").clone();
+        assert_eq!((doge.max_sl_pct, doge.min_sl_pct), (0.25, TpSlClampConfig::default().min_sl_pct));
+        assert_eq!(p.tpsl_clamps().for_symbol("ETH-USDT-SWAP"), &TpSlClampConfig::default());
+        assert_eq!(p.symbol_max_leverage("DOGE-USDT-SWAP"), 3.0);
+        assert_eq!(p.symbol_max_leverage("BTC-USDT-SWAP"), 10.0);
+    }
+
+    #[test]
+    fn settle_ccy_derived_from_symbols() {
+        assert_eq!(settle_ccy_of("BTC-USDC-SWAP"), Some("USDC"));
+        assert_eq!(settle_ccy_of("ETH-USDT"), Some("USDT"));
+        assert_eq!(settle_ccy_of("BTC"), None);
+
+        let mut p = RiskProfile::for_tests();
+        assert_eq!(p.settle_ccy(), "USDT");
+        p.allowed_symbols = vec!["BTC-USDC-SWAP".into(), "ETH-USDC-SWAP".into()];
+        assert!(p.validate().is_ok());
+        assert_eq!(p.settle_ccy(), "USDC");
+    }
+
+    #[test]
+    fn validate_rejects_mixed_settlement_currencies() {
+        let mut p = RiskProfile::for_tests();
+        p.allowed_symbols = vec!["BTC-USDT-SWAP".into(), "ETH-USDC-SWAP".into()];
+        assert!(p.validate().is_err());
+
+        let mut p = RiskProfile::for_tests();
+        p.execution.settle_ccy = Some("USDC".into());
+        assert!(p.validate().is_err());
+    }
+}
@@ -0,0 +1,1392 @@
+// 文件名: cycle.rs
+// 主循环的一轮：维护/停机检查 -> 账户快照与风控 -> 逐币种分析下单 -> 进化 -> 计算休眠时间
+// main 只负责构建依赖并循环调用 run_cycle，跨轮次的状态保存在 CycleState 中
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::time::sleep;
+use tracing::{info, error, warn};
+
+use crate::config::risk_profile::{BlackoutStopAction, FundingCarryAction, RiskProfile};
+use crate::utils::notifier::{NotifierHub, PositionReportItem, Priority};
+use crate::utils::money;
+use crate::modules::perception::regime;
+use crate::modules::perception::{MarketData, NewsSentinel, RedditSentinel, BookCache};
+use crate::modules::perception::ws_client::PriceCache;
+use crate::modules::perception::discovery;
+use crate::modules::brain::MemorySystem;
+use crate::modules::brain::llm::{DecisionEngine, TradeAction};
+use crate::modules::brain::rag::{self, MemoryHealthEvent};
+use crate::modules::brain::budget::{self, LlmBudget};
+use crate::modules::action::{Exchange, EventKind};
+use crate::modules::action::journal::TradeJournal;
+use crate::modules::action::entry::{place_entry, EntryOrder, EntryOutcome, OrderDeps};
+use crate::modules::action::close::{close_position, flatten_all, CloseOrder, CloseOutcome};
+use crate::modules::action::shadow::{ShadowBook, ShadowEntry};
+use crate::modules::action::sizing::{calculate_position_size_kelly, SizingRequest};
+use crate::modules::action::executor::PositionSummary;
+use crate::modules::evolution::{AutopsyDoctor, ExitReason, OpportunityScanner, PnlMonitor};
+use crate::modules::evolution::stats::REPORT_WINDOW_DAYS;
+use crate::modules::risk::{pyramiding, breakeven, anti_flip, spread, correlation, leverage_ramp, margin, min_hold, funding, exposure, rebalance};
+use crate::modules::risk::margin::{MarginEvent, MarginMonitor};
+use crate::modules::risk::maintenance::{MaintenanceHold, MaintenanceEvent};
+use crate::modules::risk::blackout::{self, BlackoutHold, BlackoutEvent};
+use crate::modules::risk::circuit_breaker::{LossStreakBreaker, LossStreakEvent, OrderFailureBreaker};
+use crate::modules::risk::staleness::{self, PriceSource};
+use crate::modules::risk::thrashing::DecisionHistory;
+use crate::modules::risk::grace::StartupGrace;
+use crate::modules::action::symbol_switch::{SymbolGate, SymbolSwitchStore};
+
+/// 主循环依赖 (启动时构建一次，每轮只读)
+pub struct Deps {
+    pub risk_profile: RiskProfile,
+    pub notifier: Arc<NotifierHub>,
+    pub fetcher: Arc<dyn MarketData>,
+    pub news_sentinel: Arc<NewsSentinel>,
+    pub reddit_sentinel: Arc<RedditSentinel>,
+    pub memory_sys: Arc<MemorySystem>,
+    pub brain: Arc<dyn DecisionEngine>,
+    /// 交易所与数据库只通过 trait 访问，测试中替换为 MockExchange / MemoryJournal
+    pub exchange: Arc<dyn Exchange>,
+    pub journal: Arc<dyn TradeJournal>,
+    /// 单币种运行时开关 (暂停新开仓)
+    pub symbol_switches: SymbolSwitchStore,
+    pub autopsy: AutopsyDoctor,
+    pub scanner: OpportunityScanner,
+    pub pnl_monitor: PnlMonitor,
+    /// 影子模式：只记录信号与假设收益，不触碰账户
+    pub shadow_book: Option<ShadowBook>,
+    pub price_cache: PriceCache,
+    pub book_cache: BookCache,
+    /// 交易池变化时通知 WebSocket 重新订阅
+    pub universe_tx: watch::Sender<Vec<String>>,
+    /// 启动时的资金基准 (0 = 获取失败，不做回撤检查)
+    pub initial_capital: f64,
+    pub max_drawdown: f64,
+    /// SWAP / SPOT (自动选币使用)
+    pub inst_type: &'static str,
+    /// 现货模式：无杠杆、不能做空
+    pub is_spot: bool,
+    pub max_leverage: f64,
+    pub report_interval: Duration,
+    /// LLM 调用预算，与调试接口的 /decide 共用
+    pub llm_budget: Arc<Mutex<LlmBudget>>,
+}
+
+/// 跨轮次保存的状态
+pub struct CycleState {
+    pub universe: Vec<String>,
+    pub last_discovery: Instant,
+    /// 进化任务各自的上次运行时间 (间隔见 [timing])
+    pub last_pnl_sync: Instant,
+    pub last_autopsy: Instant,
+    pub last_scan: Instant,
+    pub last_rebalance: Instant,
+    pub last_report_time: Instant,
+    /// 金字塔加仓计数 (key: "symbol:side")，持仓消失后清零
+    pub pyramid_adds: HashMap<String, u32>,
+    /// 每个币种最近一次成交操作 (时间, 方向 long/short)，用于反手守卫
+    pub last_actions: HashMap<String, (Instant, &'static str)>,
+    /// 每个币种最近的模型决策方向，用于检测反复横跳
+    pub decision_history: HashMap<String, DecisionHistory>,
+    /// OKX 维护期间暂停交易
+    pub maintenance_hold: MaintenanceHold,
+    pub blackout_hold: BlackoutHold,
+    /// 连续下单失败熔断
+    pub order_breaker: OrderFailureBreaker,
+    /// 连续亏损平仓暂停 (笔数来自 PnL 同步)
+    pub loss_breaker: LossStreakBreaker,
+    /// 保证金危险状态 (带回差)
+    pub margin_monitor: MarginMonitor,
+    /// 杠杆爬坡：按累计盈利平仓笔数逐档放宽杠杆上限
+    pub leverage_tier: leverage_ramp::LeverageRamp,
+    pub profitable_trades: u64,
+    /// 上一轮各币种的 ATR% (LLM 预算不足时决定分析顺序)
+    pub symbol_volatility: HashMap<String, f64>,
+    /// 当前被暂停新开仓的币种
+    pub symbol_gate: SymbolGate,
+    /// 启动观察期内只管理已有持仓
+    pub startup_grace: StartupGrace,
+}
+
+impl CycleState {
+    pub fn new(risk_profile: &RiskProfile, universe: Vec<String>) -> Self {
+        let now = Instant::now();
+        Self {
+            universe,
+            last_discovery: now,
+            last_pnl_sync: now,
+            last_autopsy: now,
+            last_scan: now,
+            last_rebalance: now,
+            last_report_time: now,
+            pyramid_adds: HashMap::new(),
+            last_actions: HashMap::new(),
+            decision_history: HashMap::new(),
+            maintenance_hold: MaintenanceHold::default(),
+            blackout_hold: BlackoutHold::default(),
+            order_breaker: OrderFailureBreaker::new(&risk_profile.circuit_breaker),
+            loss_breaker: LossStreakBreaker::new(&risk_profile.circuit_breaker),
+            margin_monitor: MarginMonitor::default(),
+            leverage_tier: leverage_ramp::LeverageRamp::default(),
+            profitable_trades: 0,
+            symbol_volatility: HashMap::new(),
+            symbol_gate: SymbolGate::default(),
+            startup_grace: StartupGrace::new(risk_profile.timing.startup_grace(), now),
+        }
+    }
+}
+
+/// 一轮的结果：调用方按返回的时长休眠后进入下一轮
+#[derive(Debug, PartialEq)]
+pub enum CycleOutcome {
+    /// OKX 维护或重大事件停机窗口中，本轮未分析、未交易
+    Paused(Duration),
+    /// 正常完成，休眠时间由本轮最大波动率决定
+    Completed(Duration),
+}
+
+impl CycleOutcome {
+    pub fn rest(&self) -> Duration {
+        match self {
+            CycleOutcome::Paused(d) | CycleOutcome::Completed(d) => *d,
+        }
+    }
+}
+
+pub async fn run_cycle(deps: &Deps, state: &mut CycleState) -> CycleOutcome {
+    let Deps {
+        risk_profile, notifier, fetcher, news_sentinel, reddit_sentinel, memory_sys, brain, exchange,
+        journal, symbol_switches, autopsy, scanner, pnl_monitor, shadow_book, price_cache, book_cache,
+        universe_tx, llm_budget, ..
+    } = deps;
+    let (initial_capital, max_drawdown, inst_type, is_spot, max_leverage) =
+        (deps.initial_capital, deps.max_drawdown, deps.inst_type, deps.is_spot, deps.max_leverage);
+    let report_interval = deps.report_interval;
+    let CycleState {
+        universe, last_discovery, last_pnl_sync, last_autopsy, last_scan, last_rebalance, last_report_time, pyramid_adds, last_actions, decision_history, maintenance_hold,
+        blackout_hold, order_breaker, loss_breaker, margin_monitor, leverage_tier, symbol_volatility, symbol_gate, startup_grace, ..
+    } = state;
+    let orders = OrderDeps { exchange: exchange.as_ref(), journal: journal.as_ref(), notifier: notifier.as_ref(), retry: &risk_profile.retry };
+    // 影子模式不触碰账户：下单、改止损、重挂 TP/SL、撤单都只在实盘执行
+    let live = shadow_book.is_none();
+
+    // OKX 维护期间所有接口都会失败：只轮询系统状态，不交易、不刷日志
+    match exchange.fetch_active_maintenance().await {
+        Ok(status) => match maintenance_hold.update(status) {
+            MaintenanceEvent::Entered(title) => {
+                warn!("🛠️ OKX maintenance in progress ({}). Trading paused.", title);
+                notifier.send_text(&format!("🛠️ OKX 系统维护中: {}，交易已暂停，维护结束后自动恢复", title), Priority::Critical).await;
+            }
+            MaintenanceEvent::Resumed => {
+                info!("✅ OKX maintenance finished. Resuming trading.");
+                notifier.send_text("✅ OKX 维护结束，交易已恢复", Priority::Critical).await;
+            }
+            MaintenanceEvent::Unchanged => {}
+        },
+        // 状态接口本身失败不阻断交易，由下游请求自行重试
+        Err(e) => warn!("Failed to query OKX system status: {}", e),
+    }
+    if maintenance_hold.is_active() {
+        return CycleOutcome::Paused(risk_profile.timing.maintenance_poll());
+    }
+
+    // 重大事件停机窗口：不分析、不下单，交易所止损照常生效
+    match blackout_hold.update(blackout::active_window(&risk_profile.blackout, chrono::Utc::now())) {
+        BlackoutEvent::Entered(label) => {
+            warn!("📅 Entering news blackout ({}). Trading paused.", label);
+            notifier.send_text(&format!("📅 进入重大事件停机窗口: {}，暂停分析与交易", label), Priority::Critical).await;
+            if live && risk_profile.blackout.stop_action == BlackoutStopAction::Tighten {
+                match exchange.fetch_positions().await {
+                    Ok(positions) => for pos in positions.iter().filter(|p| p.size > 0.0) {
+                        let direction = if pos.side == "short" { "sell" } else { "buy" };
+                        let Ok(Some(levels)) = journal.fetch_open_trade_levels(&pos.symbol, direction).await else { continue };
+                        let Some(new_sl) = blackout::tightened_stop(&pos.side, levels.sl_price, pos.mark_px, risk_profile.blackout.tighten_factor) else { continue };
+                        match exchange.amend_stop(&pos.symbol, &pos.side, new_sl).await {
+                            Ok(_) => {
+                                let _ = journal.update_stop_price(levels.id, new_sl).await;
+                                let _ = journal.set_stop(&pos.symbol, &pos.side, new_sl).await;
+                                info!("📅 [{}] {} stop tightened for blackout: {:.4} -> {:.4} (mark {:.4})", pos.symbol, pos.side, levels.sl_price, new_sl, pos.mark_px);
+                            }
+                            Err(e) => warn!("⚠️ [{}] Blackout stop tightening failed: {}", pos.symbol, e),
+                        }
+                    },
+                    Err(e) => warn!("⚠️ Could not load positions to tighten stops: {}", e),
+                }
+            }
+        }
+        BlackoutEvent::Exited => {
+            info!("📅 News blackout over. Resuming trading.");
+            notifier.send_text("📅 重大事件停机窗口结束，交易已恢复", Priority::Critical).await;
+        }
+        BlackoutEvent::Unchanged => {}
+    }
+    if blackout_hold.is_active() {
+        return CycleOutcome::Paused(risk_profile.timing.maintenance_poll());
+    }
+
+    // 定期重新选币；失败或结果为空时保留当前交易池
+    if risk_profile.discovery.enabled && last_discovery.elapsed() >= risk_profile.discovery.refresh_interval() {
+        *last_discovery = Instant::now();
+        match discovery::discover(fetcher.as_ref(), &risk_profile.discovery, &risk_profile.allowed_symbols, inst_type).await {
+            Ok(list) if !list.is_empty() => {
+                let added: Vec<String> = list.iter().filter(|s| !universe.contains(s)).cloned().collect();
+                let removed: Vec<String> = universe.iter().filter(|s| !list.contains(s)).cloned().collect();
+                if added.is_empty() && removed.is_empty() {
+                    info!("🔭 Trading universe unchanged: {:?}", universe);
+                } else {
+                    let missing = exchange.missing_instruments(&added).await;
+                    for symbol in &missing {
+                        if let Err(e) = exchange.refresh_instrument(symbol).await {
+                            warn!("Instrument refresh failed for newly selected {}: {}", symbol, e);
+                        }
+                    }
+                    // 已在缓存中的新币种补充阶梯持仓上限 (refresh_instrument 已为缺失的币种加载)
+                    let cached: Vec<String> = added.iter().filter(|s| !missing.contains(s)).cloned().collect();
+                    exchange.load_position_tiers(&cached).await;
+                    let msg = format!("🔭 交易池已更新: 新增 {:?}，移出 {:?} (移出币种的持仓仍会继续管理)", added, removed);
+                    info!("{}", msg);
+                    notifier.send_text(&msg, Priority::Normal).await;
+                    *universe = list;
+                    let _ = universe_tx.send(universe.clone());
+                }
+            }
+            Ok(_) => warn!("🔭 Discovery selected no symbols. Keeping current universe."),
+            Err(e) => warn!("🔭 Discovery failed: {}. Keeping current universe.", e),
+        }
+    }
+
+    info!("==================== 📊 SYSTEM STATUS ====================");
+    notifier.flush_pending().await;
+
+    // Qdrant 断线/恢复各通知一次
+    for event in memory_sys.take_health_events() {
+        let msg = match event {
+            MemoryHealthEvent::Lost(e) => format!("🧠 Qdrant 不可用，已切换为无记忆模式 (自动重连中): {}", e),
+            MemoryHealthEvent::Restored => "🧠 Qdrant 已恢复，记忆召回重新启用".to_string(),
+        };
+        notifier.send_text(&msg, Priority::Critical).await;
+    }
+    
+    // 本轮只拉取一次账户快照，交易逻辑与状态报告共用
+    let (equity, available_equity, maintenance_margin, all_positions) = match exchange.fetch_account_snapshot().await {
+        Ok(snap) => {
+            // 只在拉取成功时对账，避免接口失败时清空本地持仓记录
+            if let Err(e) = journal.reconcile_positions(&snap.positions).await {
+                warn!("Failed to reconcile position cache: {}", e);
+            }
+            exchange.prune_protection(&snap.positions).await;
+            if live {
+                // 部分平仓/加仓后按新的持仓数量重挂 TP/SL
+                if let Err(e) = exchange.resize_protection(&snap.positions).await {
+                    warn!("⚠️ TP/SL resize check failed: {}", e);
+                }
+                // 入场后未能挂上的保护单每轮补挂，直到挂上或持仓平掉
+                for r in exchange.retry_unprotected(&snap.positions).await {
+                    match &r.error {
+                        None => {
+                            info!("🛡️ [{}] Deferred TP/SL placed for {} after {} retries", r.symbol, r.pos_side, r.attempts);
+                            notifier.send_text(&format!("🛡️ [{}] {} 持仓的止盈止损已补挂", r.symbol, r.pos_side), Priority::Normal).await;
+                        }
+                        Some(e) => {
+                            error!("🔥 [{}] Position {} still UNPROTECTED after {} retries: {}", r.symbol, r.pos_side, r.attempts, e);
+                            let msg = format!("🚨 [{}] {} 持仓仍没有止盈止损 (第 {} 次补挂失败): {}，请人工检查", r.symbol, r.pos_side, r.attempts, e);
+                            notifier.send_text(&msg, Priority::Critical).await;
+                        }
+                    }
+                }
+                // 超时未成交的限价挂单是隐藏敞口，每轮撤掉
+                match exchange.reconcile_pending_orders().await {
+                    Ok(cancelled) => for c in cancelled {
+                        let msg = format!("🧹 [{}] 限价{}单 {} 挂单 {} 秒未成交，已自动撤单 (已成交 {})",
+                            c.symbol, if c.side == "buy" { "买" } else { "卖" }, c.order_id, c.age_sec, c.filled_sz);
+                        warn!("🧹 [{}] Auto-cancelled stale limit {} after {}s (filled {})", c.symbol, c.order_id, c.age_sec, c.filled_sz);
+                        notifier.send_text(&msg, Priority::Normal).await;
+                    },
+                    Err(e) => warn!("⚠️ Pending order reconciliation failed: {}", e),
+                }
+            }
+            (snap.balance.total_equity, snap.balance.available_balance, snap.balance.maintenance_margin, snap.positions)
+        }
+        Err(e) => { error!("Failed to fetch account snapshot: {}", e); (0.0, 0.0, 0.0, vec![]) }
+    };
+
+    let drawdown = if initial_capital > 0.0 && equity > 0.0 { (initial_capital - equity) / initial_capital } else { 0.0 };
+    if drawdown > max_drawdown {
+        let alert = format!("🔥 严重警告: 最大回撤触发! ({:.2}%). 系统已暂停.", drawdown * 100.0);
+        error!("{}", alert);
+        notifier.send_text(&alert, Priority::Critical).await;
+    }
+
+    // 保证金监控：维持保证金占权益过高时停止开新仓，并主动减掉浮亏最大的持仓
+    if equity > 0.0 {
+        let usage = margin::maintenance_usage(maintenance_margin, equity);
+        match margin_monitor.update(&risk_profile.margin, usage) {
+            MarginEvent::Entered(ratio) => {
+                let alert = format!("🚨 保证金危险: 维持保证金占权益 {:.1}% (阈值 {:.1}%)，已停止开新仓{}",
+                    ratio * 100.0, risk_profile.margin.danger_ratio * 100.0,
+                    if live && risk_profile.margin.reduce_largest_loss { "并开始减仓" } else { "" });
+                error!("{}", alert);
+                notifier.send_text(&alert, Priority::Critical).await;
+            }
+            MarginEvent::Recovered(ratio) => {
+                let msg = format!("✅ 保证金恢复安全: 维持保证金占权益 {:.1}%，恢复开仓", ratio * 100.0);
+                info!("{}", msg);
+                notifier.send_text(&msg, Priority::Critical).await;
+            }
+            MarginEvent::Unchanged => {}
+        }
+        if live && margin_monitor.cut_due(&risk_profile.margin, Instant::now()) {
+            if let Some(pos) = margin::largest_loss(&all_positions) {
+                let (min_sz, lot_sz) = exchange.instrument_meta(&pos.symbol).await.map_or((0.0, 0.0), |m| (m.min_sz, m.lot_sz));
+                // 按 lot_sz 向下取整后下单，日志与通知使用实际发出的数量
+                let raw_qty = (pos.size * risk_profile.margin.reduce_fraction).max(min_sz);
+                let qty = money::to_f64(money::snap_to_grid(money::dec(raw_qty), money::dec(lot_sz), false)).min(pos.size);
+                if qty <= 0.0 || qty < min_sz {
+                    info!("✂️ [{}] Margin reduction of {:.6} {} is below the minimum order size, skipped", pos.symbol, raw_qty, pos.side);
+                } else {
+                    let close_side = if pos.side == "short" { "buy" } else { "sell" };
+                    match exchange.execute_order(&pos.symbol, close_side, &pos.side, qty, pos.mark_px, 0.0, 0.0, None, true).await {
+                        Ok(_) => {
+                            margin_monitor.record_cut(Instant::now());
+                            let msg = format!("✂️ 保证金减仓: {} {} 减掉 {} / {} (浮亏 {:.2}，维持保证金占比 {:.1}%)",
+                                pos.symbol, pos.side, qty, pos.size, pos.upl, usage * 100.0);
+                            warn!("{}", msg);
+                            notifier.send_text(&msg, Priority::Critical).await;
+                        }
+                        Err(e) => error!("🔥 [{}] Margin reduction of {} {} failed: {}", pos.symbol, pos.side, qty, e),
+                    }
+                }
+            }
+        }
+    }
+
+    pyramid_adds.retain(|key, _| all_positions.iter().any(|p| format!("{}:{}", p.symbol, p.side) == *key));
+
+    match loss_breaker.update(pnl_monitor.loss_streak(), Instant::now()) {
+        LossStreakEvent::Halted(streak) => {
+            let alert = format!("📉 连续 {} 笔亏损平仓，暂停开新仓 {} 分钟 (平仓仍会执行，出现盈利平仓则提前恢复)",
+                streak, risk_profile.circuit_breaker.loss_cooldown_min);
+            warn!("{}", alert);
+            notifier.send_text(&alert, Priority::Critical).await;
+        }
+        LossStreakEvent::ResumedOnWin => {
+            let msg = "✅ 出现盈利平仓，连亏暂停解除，恢复开仓".to_string();
+            info!("{}", msg);
+            notifier.send_text(&msg, Priority::Critical).await;
+        }
+        LossStreakEvent::ResumedAfterCooldown => {
+            let msg = format!("✅ 连亏暂停冷却结束 (当前连亏 {} 笔)，恢复开仓", loss_breaker.streak());
+            info!("{}", msg);
+            notifier.send_text(&msg, Priority::Critical).await;
+        }
+        LossStreakEvent::Unchanged => {}
+    }
+
+    if startup_grace.take_ended(Instant::now()) {
+        let msg = "✅ 启动观察期结束，恢复开新仓".to_string();
+        info!("{}", msg);
+        notifier.send_text(&msg, Priority::Critical).await;
+    }
+
+    // 查询失败时沿用上一轮的盈利笔数
+    match journal.count_profitable_trades().await {
+        Ok(n) => state.profitable_trades = n,
+        Err(e) => warn!("Failed to count profitable trades for leverage ramp: {}", e),
+    }
+    let effective_leverage = leverage_ramp::effective_max_leverage(&risk_profile.leverage_ramp, state.profitable_trades, max_leverage);
+    if let Some(tier) = leverage_tier.update(&risk_profile.leverage_ramp, state.profitable_trades) {
+        let msg = format!("🪜 杠杆爬坡解锁第 {} 档: 累计盈利平仓 {} 笔，杠杆上限提升至 {:.1}x", tier + 1, state.profitable_trades, effective_leverage);
+        info!("{}", msg);
+        notifier.send_text(&msg, Priority::Normal).await;
+    }
+
+    // [New] 保本止损：浮盈达到 trigger_r 后把止损移到入场价附近
+    if live && risk_profile.breakeven.enabled {
+        for pos in &all_positions {
+            let direction = if pos.side == "short" { "sell" } else { "buy" };
+            let levels = match journal.fetch_open_trade_levels(&pos.symbol, direction).await {
+                Ok(Some(l)) => l,
+                Ok(None) => continue,
+                Err(e) => { warn!("Failed to load trade levels for {}: {}", pos.symbol, e); continue; }
+            };
+            let mark = price_cache.get(&pos.symbol).map(|e| e.value().0).unwrap_or(pos.mark_px);
+            if let Some(new_sl) = breakeven::breakeven_stop(&risk_profile.breakeven, &pos.side, levels.entry_price, levels.sl_price, mark) {
+                match exchange.amend_stop(&pos.symbol, &pos.side, new_sl).await {
+                    Ok(_) => {
+                        // 交易所止损已移动，本地记录失败只影响 R 倍数计算，不回滚
+                        if let Err(e) = journal.update_stop_price(levels.id, new_sl).await {
+                            warn!("⚠️ [{}] Failed to record breakeven stop {} for trade {}: {}", pos.symbol, new_sl, levels.id, e);
+                        }
+                        if let Err(e) = journal.set_stop(&pos.symbol, &pos.side, new_sl).await {
+                            warn!("⚠️ [{}] Failed to cache breakeven stop {}: {}", pos.symbol, new_sl, e);
+                        }
+                        let msg = format!("🛡️ 保本止损已移动: {} {} | 入场 {:.4} | 止损 {:.4} -> {:.4} | 现价 {:.4}",
+                            pos.symbol, pos.side, levels.entry_price, levels.sl_price, new_sl, mark);
+                        info!("{}", msg);
+                        notifier.send_text(&msg, Priority::Normal).await;
+                    }
+                    Err(e) => warn!("⚠️ [{}] Breakeven stop amend failed: {}", pos.symbol, e),
+                }
+            }
+        }
+    }
+
+    // 阶梯止盈：第一档成交 (持仓减少) 后把剩余仓位的止损移到保本位
+    for pos in all_positions.iter().filter(|p| live && p.size > 0.0) {
+        let Some(entry) = exchange.ladder_first_tp_filled(&pos.symbol, &pos.side, pos.size).await else { continue };
+        let new_sl = breakeven::breakeven_price(&risk_profile.breakeven, &pos.side, entry);
+        match exchange.amend_stop(&pos.symbol, &pos.side, new_sl).await {
+            Ok(_) => {
+                let direction = if pos.side == "short" { "sell" } else { "buy" };
+                match journal.fetch_open_trade_levels(&pos.symbol, direction).await {
+                    Ok(Some(levels)) => if let Err(e) = journal.update_stop_price(levels.id, new_sl).await {
+                        warn!("⚠️ [{}] Failed to record ladder breakeven stop {} for trade {}: {}", pos.symbol, new_sl, levels.id, e);
+                    },
+                    Ok(None) => {}
+                    Err(e) => warn!("Failed to load trade levels for {}: {}", pos.symbol, e),
+                }
+                if let Err(e) = journal.set_stop(&pos.symbol, &pos.side, new_sl).await {
+                    warn!("⚠️ [{}] Failed to cache ladder breakeven stop {}: {}", pos.symbol, new_sl, e);
+                }
+                let msg = format!("🪜 阶梯止盈第一档已成交: {} {} | 剩余 {} | 止损移至保本 {:.4}", pos.symbol, pos.side, pos.size, new_sl);
+                info!("{}", msg);
+                notifier.send_text(&msg, Priority::Normal).await;
+            }
+            Err(e) => warn!("⚠️ [{}] Ladder breakeven stop amend failed: {}", pos.symbol, e),
+        }
+    }
+
+    if last_report_time.elapsed() >= report_interval && equity > 0.0 {
+        let total_pnl_pct = (equity - initial_capital) / initial_capital * 100.0;
+        let mut report_items: Vec<PositionReportItem> = Vec::with_capacity(all_positions.len());
+        for p in &all_positions {
+            let sl = journal.stop_price(&p.symbol, &p.side).await.unwrap_or_else(|e| {
+                warn!("[{}] Failed to load stop price for the report: {}", p.symbol, e);
+                None
+            });
+            report_items.push(PositionReportItem::new(p, sl));
+        }
+        let closed = match journal.fetch_closed_summary(report_interval.as_secs()).await {
+            Ok(c) => Some(c),
+            Err(e) => { warn!("Failed to summarize closed trades: {}", e); None }
+        };
+        let stats = match journal.fetch_performance_stats(REPORT_WINDOW_DAYS).await {
+            Ok(s) => Some(s),
+            Err(e) => { warn!("Failed to compute performance stats: {}", e); None }
+        };
+        let versions = journal.fetch_version_stats(REPORT_WINDOW_DAYS).await.unwrap_or_else(|e| {
+            warn!("Failed to compute strategy version stats: {}", e);
+            Vec::new()
+        });
+        notifier.send_status_report(equity, total_pnl_pct, report_items, closed.as_ref(), stats.as_ref(), &versions, exchange.is_paper()).await;
+        if let Some(book) = &shadow_book {
+            match book.report(REPORT_WINDOW_DAYS).await {
+                Ok(report) => notifier.send_text(&format!("👻 影子模式绩效: {}", report), Priority::Normal).await,
+                Err(e) => warn!("Failed to compute shadow report: {}", e),
+            }
+        }
+        *last_report_time = Instant::now();
+    }
+
+    info!("==========================================================");
+
+    let raw_reddit = match reddit_sentinel.analyze_sentiment().await {
+        Ok(t) => t, Err(e) => format!("Error fetching Reddit: {}", e),
+    };
+    let raw_news = match news_sentinel.fetch_raw_headlines("GLOBAL").await {
+        Ok(m) => m, Err(e) => format!("Error fetching News: {}", e),
+    };
+
+    info!("📰 Global Context Ready: News ({} chars), Reddit ({} chars)", raw_news.len(), raw_reddit.len());
+
+    // [New] Dynamic Heartbeat variables
+    let mut max_atr_pct = 0.0;
+
+    llm_budget.lock().unwrap_or_else(|e| e.into_inner()).start_cycle();
+
+    // 单币种开关：读取失败时沿用上一轮的状态
+    match symbol_switches.load().await {
+        Ok(runtime) => {
+            let mut symbols = risk_profile.allowed_symbols.clone();
+            symbols.extend(universe.iter().filter(|s| !risk_profile.allowed_symbols.contains(s)).cloned());
+            for change in symbol_gate.update(&symbols, risk_profile, &runtime) {
+                let msg = if change.enabled {
+                    info!("▶️ [{}] New entries re-enabled", change.symbol);
+                    format!("▶️ {} 已恢复开仓", change.symbol)
+                } else {
+                    warn!("⏸️ [{}] New entries disabled: {}", change.symbol, change.reason);
+                    format!("⏸️ {} 已暂停新开仓 (持仓与止盈止损照常管理): {}", change.symbol, change.reason)
+                };
+                notifier.send_text(&msg, Priority::Normal).await;
+            }
+        }
+        Err(e) => warn!("Failed to load symbol switches: {}", e),
+    }
+    let open_symbols: Vec<&str> = all_positions.iter().filter(|p| p.size > 0.0).map(|p| p.symbol.as_str()).collect();
+    // 交易池之外但仍有持仓的币种 (被移出交易池) 也要继续分析，保证能平仓
+    let mut active_symbols = universe.clone();
+    for s in &open_symbols {
+        if !active_symbols.iter().any(|a| a == s) && risk_profile.allowed_symbols.iter().any(|a| a == s) {
+            active_symbols.push(s.to_string());
+        }
+    }
+    let symbol_order = budget::prioritize_symbols(&active_symbols, &open_symbols, symbol_volatility);
+
+    for (idx, symbol) in symbol_order.iter().enumerate() {
+        info!("🔍 Analyzing {}...", symbol);
+
+        let market_state_res = fetcher.snapshot(symbol, raw_reddit.clone(), raw_news.clone()).await;
+        
+        let mut market_state = match market_state_res {
+            Ok(s) => s,
+            Err(e) => {
+                error!("Fetch error for {}: {}", symbol, e);
+                continue; 
+            }
+        };
+
+        // Calculate ATR % for heartbeat logic
+        if market_state.price > 0.0 {
+            let current_atr_pct = (market_state.indicators.atr_14 / market_state.price) * 100.0;
+            symbol_volatility.insert(symbol.clone(), current_atr_pct);
+            if current_atr_pct > max_atr_pct {
+                max_atr_pct = current_atr_pct;
+            }
+        }
+
+        let max_price_age = risk_profile.execution.max_price_age();
+        let ws_quote = price_cache.get(symbol).map(|e| (e.value().0, e.value().1.elapsed()));
+        let rest_age = Duration::from_millis(chrono::Utc::now().timestamp_millis().saturating_sub(market_state.price_time_ms).max(0) as u64);
+        match staleness::fresh_price(ws_quote, (market_state.price, rest_age), max_price_age) {
+            Some((price, PriceSource::Ws)) => market_state.price = price,
+            Some((_, PriceSource::Rest)) => {
+                if let Some((_, ws_age)) = ws_quote {
+                    warn!("⚠️ WS Data Stale for {} ({:?} ago). Falling back to REST price.", symbol, ws_age);
+                }
+            }
+            None => {
+                warn!("⏸️ [{}] No fresh price (WS {:?}, REST {:?} ago, max {:?}). Skipping symbol this cycle.",
+                    symbol, ws_quote.map(|(_, age)| age), rest_age, max_price_age);
+                continue;
+            }
+        }
+
+        if let Some(book) = &shadow_book {
+            match book.mark(symbol, market_state.price).await {
+                Ok(closed) => {
+                    for (trade, reason, ret) in closed {
+                        let msg = format!("👻 [影子] {} {} 平仓 ({}) | 入场 {:.4} -> {:.4} | 假设收益 {:+.2}%",
+                            trade.symbol, trade.side, reason.as_str(), trade.entry_price, market_state.price, ret * 100.0);
+                        info!("{}", msg);
+                        notifier.send_text(&msg, Priority::Normal).await;
+                    }
+                }
+                Err(e) => warn!("👻 [{}] Failed to mark shadow trades: {}", symbol, e),
+            }
+        }
+
+        // 资金费持仓成本：累计支付的资金费吞噬浮盈时平仓 (不占用 LLM 预算) 或提示大脑
+        // 资金费账单只按币种记录，同时持有多空时无法区分方向，跳过
+        let mut carry_note = None;
+        let open_sides: Vec<&PositionSummary> = all_positions.iter().filter(|p| p.symbol == *symbol && p.size > 0.0).collect();
+        if let ([pos], true) = (open_sides.as_slice(), risk_profile.funding_carry.enabled) {
+            let paid = journal.funding_paid(symbol, &pos.side).await.unwrap_or_else(|e| {
+                warn!("Failed to load funding paid for {} {}: {}", symbol, pos.side, e);
+                0.0
+            });
+            if let Some(reason) = funding::check_carry(&risk_profile.funding_carry, paid, pos.upl) {
+                match risk_profile.funding_carry.action {
+                    // 影子模式不平真实持仓，只提示
+                    FundingCarryAction::Close if !live => {
+                        info!("👻 [{}] Funding carry close of {} skipped in shadow mode: {}", symbol, pos.side, reason);
+                    }
+                    FundingCarryAction::Close => {
+                        warn!("💸 [{}] Closing {} position: {}", symbol, pos.side, reason);
+                        let outcome = close_position(&orders, &CloseOrder {
+                            correlation_id: uuid::Uuid::new_v4(), symbol, pos_side: &pos.side, qty: pos.size,
+                            price: market_state.price, reason: &format!("资金费侵蚀利润: {}", reason),
+                        }).await;
+                        if let CloseOutcome::Closed = outcome {
+                            let pos_side = if pos.side == "short" { "short" } else { "long" };
+                            last_actions.insert(symbol.clone(), (Instant::now(), pos_side));
+                            let msg = format!("💸 [{}] 资金费触发平仓 ({})\n累计支付资金费 ${:.2}，浮盈 ${:.2}，超过上限 {:.0}%",
+                                symbol, pos.side, paid, pos.upl, risk_profile.funding_carry.max_funding_to_profit * 100.0);
+                            notifier.send_text(&msg, Priority::Normal).await;
+                            sleep(risk_profile.timing.symbol_gap()).await;
+                            continue;
+                        }
+                    }
+                    FundingCarryAction::Bias => {
+                        warn!("💸 [{}] Funding drag on {} position: {}", symbol, pos.side, reason);
+                        carry_note = Some(format!(" ⚠️ FUNDING DRAG: {}. Strongly consider closing this position.", reason));
+                    }
+                }
+            }
+        }
+
+        // 预算在记忆召回前检查，用尽时连同 Embedding 调用一起跳过
+        if !llm_budget.lock().unwrap_or_else(|e| e.into_inner()).try_acquire(Instant::now()) {
+            warn!("💸 LLM call budget exhausted (per cycle {}, per hour {}). Skipping {} remaining symbols this cycle: {:?}",
+                risk_profile.llm.max_calls_per_cycle, risk_profile.llm.max_calls_per_hour,
+                symbol_order.len() - idx, &symbol_order[idx..]);
+            break;
+        }
+
+        let ctx_str = rag::embedding_context(&market_state, brain.as_ref(), &risk_profile.memory).await;
+        info!("\n================ [DEBUG] EMBEDDING INPUT START ================\n{}\n================ [DEBUG] EMBEDDING INPUT END ================", ctx_str);
+
+        let memories = memory_sys.recall_memories(&ctx_str).await.unwrap_or_default();
+
+        let long_pos = all_positions.iter().find(|p| p.symbol == *symbol && p.side == "long" && p.size > 0.0);
+        let short_pos = all_positions.iter().find(|p| p.symbol == *symbol && p.side == "short" && p.size > 0.0);
+        
+        // 持仓时长以本地持仓缓存为准 (OKX 重启后无法提供)
+        let mut held_hours: HashMap<String, f64> = HashMap::new();
+        // 最短持仓期内的方向 -> 剩余时间 (浮亏达到 stop_override_r 时不限制)
+        let mut hold_blocks: HashMap<String, Duration> = HashMap::new();
+        for p in [long_pos, short_pos].into_iter().flatten() {
+            if let Ok(Some(entry)) = journal.get_entry(symbol, &p.side).await {
+                held_hours.insert(p.side.clone(), entry.age().as_secs_f64() / 3600.0);
+                if let Some(remaining) = min_hold::hold_remaining(&risk_profile.min_hold, entry.since_entry()) {
+                    let direction = if p.side == "short" { "sell" } else { "buy" };
+                    let r = match journal.fetch_open_trade_levels(symbol, direction).await {
+                        Ok(Some(l)) => breakeven::unrealized_r(&p.side, l.entry_price, l.sl_price, market_state.price),
+                        _ => None,
+                    };
+                    if !min_hold::stop_override(&risk_profile.min_hold, r) {
+                        hold_blocks.insert(p.side.clone(), remaining);
+                    }
+                }
+            }
+        }
+
+        // 持仓描述中带上均价、加仓次数与持仓时长，让大脑区分"加仓"与"新开仓"
+        let describe = |label: &str, p: &PositionSummary| {
+            let adds = pyramid_adds.get(&format!("{}:{}", symbol, p.side)).copied().unwrap_or(0);
+            let held = held_hours.get(&p.side).map(|h| format!(", held {:.1}h", h)).unwrap_or_default();
+            format!("{}: {} @ avg {} (PnL ${}, adds {}/{}{})", label, p.size, p.avg_px, p.upl, adds, risk_profile.pyramiding.max_adds, held)
+        };
+        let pos_info = match (long_pos, short_pos) {
+            (Some(l), Some(s)) => format!("{}, {}", describe("Long", l), describe("Short", s)),
+            (Some(l), None) => describe("Long", l),
+            (None, Some(s)) => describe("Short", s),
+            (None, None) => "No active positions".to_string(),
+        };
+        let pos_info = pos_info + carry_note.as_deref().unwrap_or_default();
+
+        // 杠杆上限：全局 / 爬坡档位 / 单币种覆盖取最小
+        let symbol_leverage = effective_leverage.min(risk_profile.symbol_max_leverage(symbol));
+        match brain.analyze(&market_state, &memories, &pos_info, symbol_leverage).await {
+            Ok(mut decision) => {
+                info!("[{}] 🎯 Decision: {:?} (Reason: {})", symbol, decision.action, decision.reason);
+
+                // 胜率下限：与凯利无关，直接控制信号的选择性 (在胜率软顶截断之前判断)
+                let predicted_win_rate = decision.win_rate;
+                let vetoed_action = decision.action.clone();
+                if decision.enforce_min_win_rate(risk_profile.kelly.min_win_rate) {
+                    warn!("🚫 [{}] {:?} vetoed: predicted win rate {:.2} < min_win_rate {:.2}. Forcing HOLD.",
+                        symbol, vetoed_action, predicted_win_rate, risk_profile.kelly.min_win_rate);
+                }
+                // 动作白名单：方向控制独立于模型判断
+                let filtered_action = decision.action.clone();
+                if decision.restrict_actions(risk_profile.allowed_actions(symbol)) {
+                    warn!("🚫 [{}] {:?} filtered: not in allowed_actions {:?}. Forcing HOLD.",
+                        symbol, filtered_action, risk_profile.allowed_actions(symbol));
+                }
+                // 反复横跳：先判断再记录模型本轮的动作 (压制前)，模型持续来回反转时保持压制
+                let history = decision_history.entry(symbol.clone()).or_default();
+                let thrash = history.check(&risk_profile.thrashing, &decision.action, Instant::now());
+                history.record(&risk_profile.thrashing, &decision.action, Instant::now());
+                if let Err(reason) = thrash {
+                    warn!("🏓 [{}] {:?} dampened: decision thrashing detected ({}). Forcing HOLD.", symbol, decision.action, reason);
+                    decision.action = TradeAction::Hold;
+                }
+
+                // 事件流水：本次决策及其后续订单、盈亏共用同一个 correlation_id
+                let correlation_id = uuid::Uuid::new_v4();
+                journal.append_event(correlation_id, symbol, EventKind::Decision, None, serde_json::json!({
+                    "action": format!("{:?}", decision.action),
+                    "reason": decision.reason,
+                    "win_rate": decision.win_rate,
+                    "kelly_fraction": decision.kelly_fraction,
+                    "tp_pct": decision.tp_pct,
+                    "sl_pct": decision.sl_pct,
+                    "leverage": decision.leverage,
+                    "strategy_version": decision.strategy_version,
+                    "positions": pos_info,
+                    "context": market_state,
+                })).await;
+
+                // 影子模式：记录本来会执行的动作后直接进入下一个币种
+                if let Some(book) = &shadow_book {
+                    let price = market_state.price;
+                    let result = match decision.action {
+                        TradeAction::Buy | TradeAction::Sell => {
+                            let side = if let TradeAction::Buy = decision.action { "long" } else { "short" };
+                            if is_spot && side == "short" {
+                                Ok(())
+                            } else {
+                                book.open(&ShadowEntry {
+                                    symbol, side, price,
+                                    tp_pct: decision.tp_pct,
+                                    sl_pct: decision.sl_pct,
+                                    leverage: decision.leverage,
+                                    strategy_version: &decision.strategy_version,
+                                    reason: &decision.reason,
+                                }).await.map(|opened| if opened {
+                                    info!("👻 [{}] Shadow {} opened @ {:.4} (TP {:.2}% / SL {:.2}%, {}x)",
+                                        symbol, side, price, decision.tp_pct * 100.0, decision.sl_pct * 100.0, decision.leverage);
+                                })
+                            }
+                        }
+                        TradeAction::CloseLong | TradeAction::CloseShort => {
+                            let side = if let TradeAction::CloseLong = decision.action { "long" } else { "short" };
+                            match book.open_trades(symbol).await {
+                                Ok(trades) => {
+                                    let mut res = Ok(());
+                                    for trade in trades.iter().filter(|t| t.side == side) {
+                                        match book.close(trade, price, ExitReason::ManualClose).await {
+                                            Ok(ret) => info!("👻 [{}] Shadow {} closed by signal @ {:.4}: {:+.2}%", symbol, side, price, ret * 100.0),
+                                            Err(e) => res = Err(e),
+                                        }
+                                    }
+                                    res
+                                }
+                                Err(e) => Err(e),
+                            }
+                        }
+                        TradeAction::Hold => Ok(()),
+                    };
+                    if let Err(e) = result {
+                        warn!("👻 [{}] Failed to record shadow decision: {}", symbol, e);
+                    }
+                    sleep(risk_profile.timing.symbol_gap()).await;
+                    continue;
+                }
+
+                // 平仓与反向开仓都受最短持仓限制 (Buy 反手的是空单，Sell 反手的是多单)
+                let held_side = match decision.action {
+                    TradeAction::CloseLong | TradeAction::Sell => Some("long"),
+                    TradeAction::CloseShort | TradeAction::Buy => Some("short"),
+                    TradeAction::Hold => None,
+                };
+                let hold_remaining = held_side.and_then(|s| hold_blocks.get(s)).copied();
+
+                match decision.action {
+                    TradeAction::Sell if is_spot => {
+                        info!("🪙 [{}] SELL (open short) ignored in spot mode.", symbol);
+                    },
+                    _ if hold_remaining.is_some() => {
+                        info!("⏳ [{}] {:?} deferred: {} position is within the minimum hold ({}s remaining).",
+                            symbol, decision.action, held_side.unwrap_or_default(), hold_remaining.unwrap_or_default().as_secs());
+                    },
+                    TradeAction::Buy | TradeAction::Sell if symbol_gate.blocked(symbol).is_some() => {
+                        warn!("⏸️ [{}] {:?} skipped: new entries disabled ({}).", symbol, decision.action, symbol_gate.blocked(symbol).unwrap_or_default());
+                    },
+                    TradeAction::Buy | TradeAction::Sell if margin_monitor.is_danger() => {
+                        warn!("🚨 [{}] {:?} skipped: margin usage above danger threshold.", symbol, decision.action);
+                    },
+                    TradeAction::Buy | TradeAction::Sell if order_breaker.is_halted() => {
+                        warn!("⛔ [{}] {:?} skipped: trading halted after {} consecutive order failures.", symbol, decision.action, order_breaker.consecutive());
+                    },
+                    TradeAction::Buy | TradeAction::Sell if startup_grace.is_active(Instant::now()) => {
+                        warn!("🕊️ [{}] {:?} skipped: startup grace period ({}s left), managing existing positions only.", symbol, decision.action,
+                            startup_grace.remaining(Instant::now()).unwrap_or_default().as_secs());
+                    },
+                    TradeAction::Buy | TradeAction::Sell if loss_breaker.is_halted() => {
+                        warn!("📉 [{}] {:?} skipped: paused after {} consecutive losses ({}s cooldown left).", symbol, decision.action,
+                            loss_breaker.streak(), loss_breaker.remaining(Instant::now()).unwrap_or_default().as_secs());
+                    },
+                    TradeAction::Buy | TradeAction::Sell => {
+                        // [Fix] Win Rate Soft Cap
+                        // 强制将胜率限制在 win_rate_cap 以内，防止凯利公式全仓梭哈
+                        let raw_win_rate = decision.win_rate;
+                        if decision.apply_win_rate_cap(risk_profile.kelly.win_rate_cap) {
+                            warn!("⚠️ AI WinRate ({:.2}) capped to {:.2} for safety.", raw_win_rate, risk_profile.kelly.win_rate_cap);
+                        }
+
+                        let pos_side = if let TradeAction::Buy = decision.action { "long" } else { "short" };
+                        let opposite_pos = if pos_side == "long" { short_pos } else { long_pos };
+                        let last_action = last_actions.get(symbol.as_str()).copied();
+                        let flip = anti_flip::is_flip(pos_side, opposite_pos.is_some(), last_action.map(|(_, s)| s));
+                        // 重启后没有操作记录时，按反向持仓最近一次开仓/加仓的时间计算冷却
+                        let opposite_entry = match (last_action, opposite_pos) {
+                            (None, Some(p)) => match journal.get_entry(symbol, &p.side).await {
+                                Ok(entry) => entry.map(|e| e.since_entry().unwrap_or_else(|| e.age())),
+                                Err(e) => { warn!("Failed to load entry time for {} {}: {}", symbol, p.side, e); None }
+                            },
+                            _ => None,
+                        };
+                        let since_last = anti_flip::since_last_action(last_action.map(|(t, _)| t.elapsed()), opposite_entry);
+                        if let Err(reason) = anti_flip::check_flip(&risk_profile.anti_flip, flip, since_last, decision.win_rate) {
+                            warn!("🔁 [{}] Flip to {} VETOED: {}", symbol, pos_side, reason);
+                            sleep(risk_profile.timing.symbol_gap()).await;
+                            continue;
+                        }
+                        if let Err(reason) = regime::check_counter_trend(&risk_profile.regime, market_state.indicators.adx_14, &market_state.indicators.trend_signal, pos_side) {
+                            warn!("🧭 [{}] Entry VETOED by regime filter: {}", symbol, reason);
+                            sleep(risk_profile.timing.symbol_gap()).await;
+                            continue;
+                        }
+                        if let Err(reason) = regime::check_choppiness(&risk_profile.regime, market_state.indicators.chop_14, &market_state.indicators.trend_signal, pos_side) {
+                            warn!("🌀 [{}] Entry VETOED by choppiness filter: {}", symbol, reason);
+                            sleep(risk_profile.timing.symbol_gap()).await;
+                            continue;
+                        }
+
+                        // 资金费率守卫：持仓方向需要支付的资金费过高时跳过或缩小开仓
+                        let funding_scale = match funding::check_funding(&risk_profile.funding_filter, pos_side, market_state.funding_rate) {
+                            funding::FundingVerdict::Allow => 1.0,
+                            funding::FundingVerdict::Skip(reason) => {
+                                warn!("💸 [{}] {} entry VETOED: {}", symbol, pos_side, reason);
+                                sleep(risk_profile.timing.symbol_gap()).await;
+                                continue;
+                            }
+                            funding::FundingVerdict::Downgrade(factor, reason) => {
+                                warn!("💸 [{}] {} entry downgraded x{:.2}: {}", symbol, pos_side, factor, reason);
+                                factor
+                            }
+                        };
+
+                        // 盘口数据 10 秒内有效，否则从 REST 拉取
+                        let quote = match book_cache.get(symbol).filter(|b| b.ts.elapsed() < Duration::from_secs(10)).map(|b| (b.bid, b.ask)) {
+                            Some(q) => Some(q),
+                            None => exchange.fetch_best_quote(symbol).await.ok(),
+                        };
+                        // 价差守卫：盘口过宽时市价单摩擦过大，跳过本次开仓
+                        if let Some((bid, ask)) = quote {
+                            match spread::check_spread(bid, ask, risk_profile.max_spread_bps(symbol)) {
+                                Ok(bps) => info!("📏 [{}] Spread {:.1}bp", symbol, bps),
+                                Err(reason) => {
+                                    warn!("🚫 [{}] Entry skipped: {}", symbol, reason);
+                                    sleep(risk_profile.timing.symbol_gap()).await;
+                                    continue;
+                                }
+                            }
+                        }
+
+                        let mut qty = calculate_position_size_kelly(&SizingRequest {
+                            symbol, equity, available_equity,
+                            kelly_fraction: decision.kelly_fraction,
+                            leverage: decision.leverage,
+                            price: market_state.price,
+                            sl_pct: decision.sl_pct,
+                            drawdown, max_drawdown,
+                        }, risk_profile, exchange.as_ref()).await * funding_scale;
+
+                        let side = if let TradeAction::Buy = decision.action { "buy" } else { "sell" };
+
+                        // [New] 同方向已有持仓 => 加仓语义，仅浮盈时允许，且数量受限
+                        let existing_pos = if pos_side == "long" { long_pos } else { short_pos };
+                        let pyramid_key = format!("{}:{}", symbol, pos_side);
+                        let mut entry_price = market_state.price;
+                        if let Some(pos) = existing_pos {
+                            let adds_done = pyramid_adds.get(&pyramid_key).copied().unwrap_or(0);
+                            let (min_sz, lot_sz) = exchange.instrument_meta(symbol).await.map_or((0.0, 0.0), |m| (m.min_sz, m.lot_sz));
+                            match pyramiding::evaluate_add(&risk_profile.pyramiding, pos, market_state.price, adds_done, lot_sz, min_sz) {
+                                Ok(max_add) => {
+                                    if let Some(max_add) = max_add.filter(|m| qty > *m) {
+                                        info!("🔺 [{}] Pyramid add capped: {:.4} -> {:.4} contracts", symbol, qty, max_add);
+                                        qty = max_add;
+                                    }
+                                    entry_price = pyramiding::averaged_entry(pos.size, pos.avg_px, qty, market_state.price);
+                                }
+                                Err(reason) => {
+                                    warn!("🚫 [{}] Pyramid add blocked: {}", symbol, reason);
+                                    qty = 0.0;
+                                }
+                            }
+                        }
+
+                        // 相关性守卫：相关币种的同向净敞口超限时削减或跳过
+                        if qty > 0.0 && risk_profile.correlation.enabled {
+                            let meta = exchange.instrument_meta(symbol).await;
+                            let unit_notional = market_state.price * if is_spot { 1.0 } else { meta.as_ref().map_or(0.0, |m| m.face_value) };
+                            let new_notional = qty * unit_notional;
+                            let mut exposures = Vec::new();
+                            match fetcher.closes(symbol).await {
+                                Ok(own_closes) => {
+                                    for p in all_positions.iter().filter(|p| p.size > 0.0) {
+                                        let corr = if p.symbol == *symbol { Some(1.0) } else {
+                                            match fetcher.closes(&p.symbol).await {
+                                                Ok(other) => correlation::return_correlation(&own_closes, &other, risk_profile.correlation.lookback),
+                                                Err(_) => None,
+                                            }
+                                        };
+                                        // 无法计算时按相关处理 (加密货币整体高度联动)
+                                        exposures.push(correlation::Exposure { correlation: corr.unwrap_or(1.0), side: p.side.as_str(), notional: p.notional_usd });
+                                    }
+                                }
+                                Err(e) => warn!("⚠️ [{}] Correlation check skipped, no kline history: {}", symbol, e),
+                            }
+                            let allowed = correlation::allowed_notional(&risk_profile.correlation, equity, pos_side, new_notional, &exposures);
+                            if allowed < new_notional {
+                                let min_sz = meta.as_ref().map_or(0.0, |m| m.min_sz);
+                                let reduced = if unit_notional > 0.0 { allowed / unit_notional } else { 0.0 };
+                                if reduced <= 0.0 || reduced < min_sz {
+                                    warn!("🔗 [{}] Entry skipped: correlated {} exposure would exceed {:.1}x equity", symbol, pos_side, risk_profile.correlation.max_net_exposure);
+                                    qty = 0.0;
+                                } else {
+                                    info!("🔗 [{}] Size reduced by correlation guard: {:.4} -> {:.4} (notional ${:.0} -> ${:.0})", symbol, qty, reduced, new_notional, allowed);
+                                    qty = reduced;
+                                }
+                            }
+                        }
+
+                        // 组合总敞口守卫：所有持仓名义价值之和不超过 max_gross_multiple 倍权益
+                        if qty > 0.0 && risk_profile.exposure.enabled {
+                            let meta = exchange.instrument_meta(symbol).await;
+                            let unit_notional = market_state.price * if is_spot { 1.0 } else { meta.as_ref().map_or(0.0, |m| m.face_value) };
+                            let new_notional = qty * unit_notional;
+                            let gross = exposure::gross_notional(&all_positions);
+                            let allowed = exposure::allowed_gross_notional(&risk_profile.exposure, equity, gross, new_notional);
+                            if allowed < new_notional {
+                                let min_sz = meta.as_ref().map_or(0.0, |m| m.min_sz);
+                                let reduced = if unit_notional > 0.0 { allowed / unit_notional } else { 0.0 };
+                                if reduced <= 0.0 || reduced < min_sz {
+                                    warn!("📊 [{}] Entry skipped: gross exposure ${:.0} + ${:.0} would exceed {:.1}x equity", symbol, gross, new_notional, risk_profile.exposure.max_gross_multiple);
+                                    qty = 0.0;
+                                } else {
+                                    info!("📊 [{}] Size reduced by gross exposure cap: {:.4} -> {:.4} (notional ${:.0} -> ${:.0})", symbol, qty, reduced, new_notional, allowed);
+                                    qty = reduced;
+                                }
+                            }
+                        }
+
+                        if qty > 0.0 {
+                            let signal_label = if existing_pos.is_some() { format!("{} (ADD)", side) } else { side.to_string() };
+                            // 模型给出的阶梯优先，否则使用配置的阶梯 (为空 = 单一 TP)
+                            let tp_ladder = if decision.tp_ladder.is_empty() { &risk_profile.execution.tp_ladder } else { &decision.tp_ladder };
+                            let outcome = place_entry(&orders, &EntryOrder {
+                                correlation_id, symbol, side, pos_side, qty,
+                                state: &market_state, decision: &decision, entry_price,
+                                tp_ladder, quote, signal_label: &signal_label,
+                            }).await;
+                            match outcome {
+                                EntryOutcome::Filled(_) => {
+                                    if existing_pos.is_some() {
+                                        *pyramid_adds.entry(pyramid_key.clone()).or_insert(0) += 1;
+                                    }
+                                    last_actions.insert(symbol.clone(), (Instant::now(), pos_side));
+                                    order_breaker.record_success();
+                                }
+                                EntryOutcome::Failed => {
+                                    if order_breaker.record_failure() {
+                                        let alert = format!("⛔ 连续 {} 笔交易下单失败，已停止开新仓 (平仓仍会执行)。请检查 API Key / 账户状态后重启程序。", order_breaker.consecutive());
+                                        error!("{}", alert);
+                                        notifier.send_text(&alert, Priority::Critical).await;
+                                    }
+                                }
+                                EntryOutcome::Rejected => {}
+                            }
+                        }
+                    },
+                    TradeAction::CloseLong | TradeAction::CloseShort => {
+                        let pos_side = if let TradeAction::CloseLong = decision.action { "long" } else { "short" };
+                        let pos = if pos_side == "long" { long_pos } else { short_pos };
+                        if let Some(pos) = pos {
+                            let outcome = close_position(&orders, &CloseOrder {
+                                correlation_id, symbol, pos_side, qty: pos.size, price: market_state.price, reason: &decision.reason,
+                            }).await;
+                            match outcome {
+                                CloseOutcome::Closed => {
+                                    last_actions.insert(symbol.clone(), (Instant::now(), pos_side));
+                                    order_breaker.record_success();
+                                }
+                                CloseOutcome::Failed => {
+                                    if order_breaker.record_failure() {
+                                        notifier.send_text(&format!("⛔ 连续 {} 笔交易下单失败，已停止开新仓。请检查 API Key / 账户状态后重启程序。", order_breaker.consecutive()), Priority::Critical).await;
+                                    }
+                                    // 最后手段：平掉全部持仓 (重新拉取，包含本轮新开的仓位)
+                                    if risk_profile.retry.flatten_all_on_close_failure {
+                                        let positions = exchange.fetch_positions().await.unwrap_or_else(|_| all_positions.clone());
+                                        error!("🧯 [{}] Close failed after retries. Flattening all {} positions.", symbol, positions.len());
+                                        let failed = flatten_all(exchange.as_ref(), &positions).await;
+                                        let msg = if failed.is_empty() {
+                                            format!("🧯 [{}] 平仓重试耗尽，已按配置市价平掉全部持仓", symbol)
+                                        } else {
+                                            format!("🧯 [{}] 平仓重试耗尽，已尝试平掉全部持仓，以下持仓仍未平掉，请立即人工处理: {}", symbol, failed.join(", "))
+                                        };
+                                        notifier.send_text(&msg, Priority::Critical).await;
+                                    }
+                                }
+                            }
+                        }
+                    },
+                    TradeAction::Hold => {}
+                }
+            },
+            Err(e) => error!("[{}] Brain Error: {}", symbol, e),
+        }
+        sleep(risk_profile.timing.symbol_gap()).await;
+    }
+
+    // 进化任务各自按 [timing] 的间隔运行
+    let timing = &risk_profile.timing;
+    if last_pnl_sync.elapsed() > timing.pnl_sync_interval() {
+        // 影子模式没有真实成交，跳过账单同步 (需要账户权限)
+        if shadow_book.is_none() {
+            info!("🧬 Evolution: syncing realized PnL...");
+            if let Err(e) = pnl_monitor.sync_realized_pnl().await { error!("PnL Sync Failed: {}", e); }
+        }
+        *last_pnl_sync = Instant::now();
+    }
+    if last_autopsy.elapsed() > timing.autopsy_interval() {
+        info!("🧬 Evolution: running autopsy...");
+        let _ = autopsy.perform_daily_review().await;
+        *last_autopsy = Instant::now();
+    }
+    if last_scan.elapsed() > timing.scanner_interval() {
+        info!("🧬 Evolution: scanning missed opportunities...");
+        for symbol in universe.iter() { let _ = scanner.scan_missed_opportunities(symbol).await; }
+        *last_scan = Instant::now();
+    }
+    // 再平衡会下 reduce-only 单，影子模式跳过
+    if live && risk_profile.rebalance.enabled && last_rebalance.elapsed() > timing.rebalance_interval() {
+        info!("⚖️ Evolution: checking portfolio weights...");
+        rebalance_portfolio(exchange.as_ref(), notifier, risk_profile, symbol_volatility).await;
+        *last_rebalance = Instant::now();
+    }
+
+    // [New] Dynamic Sleep Logic: 波动越大休眠越短 (参数见 [timing])
+    let dynamic_rest = risk_profile.timing.rest_interval(max_atr_pct);
+
+    info!("💤 Cycle done. Volatility: {:.2}%. Sleeping {}s...", max_atr_pct, dynamic_rest.as_secs());
+    CycleOutcome::Completed(dynamic_rest)
+}
+
+/// 组合再平衡：按 [rebalance] 的目标权重削减超配持仓 (reduce-only 市价单)，结果汇总为一条通知
+async fn rebalance_portfolio(exchange: &dyn Exchange, notifier: &NotifierHub, risk_profile: &RiskProfile, atr_pct: &HashMap<String, f64>) {
+    let snap = match exchange.fetch_account_snapshot().await {
+        Ok(s) => s,
+        Err(e) => {
+            warn!("⚖️ Rebalance skipped, account snapshot failed: {}", e);
+            return;
+        }
+    };
+    let gross_cap = risk_profile.exposure.enabled.then(|| snap.balance.total_equity.max(0.0) * risk_profile.exposure.max_gross_multiple);
+    let trims = rebalance::plan_trims(&risk_profile.rebalance, &snap.positions, atr_pct, gross_cap);
+    if trims.is_empty() {
+        info!("⚖️ Portfolio within target weights, nothing to trim");
+        return;
+    }
+
+    let mut lines = Vec::new();
+    for trim in trims {
+        let Some(pos) = snap.positions.iter().find(|p| p.symbol == trim.symbol && p.side == trim.side) else { continue };
+        let (min_sz, lot_sz) = exchange.instrument_meta(&trim.symbol).await.map_or((0.0, 0.0), |m| (m.min_sz, m.lot_sz));
+        let qty = money::to_f64(money::snap_to_grid(money::dec(trim.qty), money::dec(lot_sz), false)).min(pos.size);
+        if qty <= 0.0 || qty < min_sz {
+            info!("⚖️ [{}] Trim of {:.6} {} is below the minimum order size, skipped", trim.symbol, trim.qty, trim.side);
+            continue;
+        }
+        let close_side = if trim.side == "short" { "buy" } else { "sell" };
+        match exchange.execute_order(&trim.symbol, close_side, &trim.side, qty, pos.mark_px, 0.0, 0.0, None, true).await {
+            Ok(_) => {
+                info!("⚖️ [{}] Trimmed {} {} / {} (notional {:.0} -> target {:.0})", trim.symbol, trim.side, qty, pos.size, trim.notional, trim.target_notional);
+                lines.push(format!("{} {} 减掉 {} / {} (名义价值 {:.0} → 目标 {:.0})", trim.symbol, trim.side, qty, pos.size, trim.notional, trim.target_notional));
+            }
+            Err(e) => {
+                error!("⚖️ [{}] Rebalance trim of {} {} failed: {}", trim.symbol, trim.side, qty, e);
+                lines.push(format!("{} {} 减仓失败: {}", trim.symbol, trim.side, e));
+            }
+        }
+    }
+    if !lines.is_empty() {
+        notifier.send_text(&format!("⚖️ 组合再平衡:\n{}", lines.join("\n")), Priority::Normal).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::postgres::PgPoolOptions;
+    use crate::config::okx::OkxEndpoints;
+    use crate::config::risk_profile::NotifyConfig;
+    use crate::modules::action::TradeExecutor;
+    use crate::modules::action::exchange::mock::MockExchange;
+    use crate::modules::action::journal::mock::MemoryJournal;
+    use crate::modules::action::executor::PositionSummary;
+    use crate::modules::brain::llm::mock::{decision, ScriptedBrain};
+    use crate::modules::perception::MarketDataFetcher;
+    use crate::modules::perception::fetcher::mock::{market_state, StaticMarket};
+    use crate::utils::notifier::mock::RecordingNotifier;
+
+    const SYMBOL: &str = "BTC-USDT-SWAP";
+
+    /// 交易所 / 流水 / 行情 / 大脑为 mock；其余 Postgres 与 Qdrant 句柄指向不可达地址，调用失败只告警
+    struct Harness {
+        exchange: Arc<MockExchange>,
+        journal: Arc<MemoryJournal>,
+        brain: Arc<ScriptedBrain>,
+        notifications: RecordingNotifier,
+        deps: Deps,
+    }
+
+    fn unreachable_pool() -> sqlx::PgPool {
+        PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(100))
+            .connect_lazy("postgres://trader@127.0.0.1:1/trader")
+            .expect("lazy pool")
+    }
+
+    async fn harness(mut risk_profile: RiskProfile, exchange: MockExchange, action: TradeAction) -> Harness {
+        risk_profile.allowed_symbols = vec![SYMBOL.to_string()];
+        risk_profile.timing.symbol_gap_sec = 0;
+        let client = reqwest::Client::new();
+        let ttl = Duration::from_secs(60);
+        let pool = unreachable_pool();
+        let okx = OkxEndpoints { rest_url: "http://127.0.0.1:1".to_string(), ..OkxEndpoints::default() };
+        let memory_sys = Arc::new(MemorySystem::new("http://127.0.0.1:1".to_string(), client.clone(), risk_profile.memory.clone()).expect("qdrant client"));
+        // 连接失败后进入无记忆模式，召回不再发起 Embedding 请求
+        let _ = memory_sys.init().await;
+        let okx_fetcher = Arc::new(MarketDataFetcher::new(client.clone(), &okx, &risk_profile.indicators, &risk_profile.regime));
+        let executor = Arc::new(TradeExecutor::new(client.clone(), &okx, risk_profile.execution.clone(), risk_profile.settle_ccy(), risk_profile.paper.clone()));
+
+        let exchange = Arc::new(exchange);
+        let journal = Arc::new(MemoryJournal::default());
+        let brain = Arc::new(ScriptedBrain::new(decision(action)));
+        let notifications = RecordingNotifier::default();
+        let (universe_tx, _) = watch::channel(vec![SYMBOL.to_string()]);
+        let deps = Deps {
+            notifier: Arc::new(NotifierHub::with_backends(vec![Box::new(notifications.clone())], NotifyConfig::default())),
+            fetcher: Arc::new(StaticMarket::default().with_state(market_state(SYMBOL, 50_000.0))),
+            news_sentinel: Arc::new(NewsSentinel::new(client.clone(), Some("fixtures/news_rss.xml".to_string()), ttl)),
+            reddit_sentinel: Arc::new(RedditSentinel::new(client, Some("fixtures/reddit_hot.json".to_string()), ttl)),
+            brain: brain.clone(),
+            exchange: exchange.clone(),
+            journal: journal.clone(),
+            symbol_switches: SymbolSwitchStore::new(pool.clone()),
+            autopsy: AutopsyDoctor::new(pool.clone(), memory_sys.clone()),
+            scanner: OpportunityScanner::new(pool.clone(), okx_fetcher, memory_sys.clone(), risk_profile.thresholds.scanner_pump_pct),
+            pnl_monitor: PnlMonitor::new(pool, executor),
+            memory_sys,
+            shadow_book: None,
+            price_cache: Arc::new(dashmap::DashMap::new()),
+            book_cache: Arc::new(dashmap::DashMap::new()),
+            universe_tx,
+            initial_capital: exchange.total_equity,
+            max_drawdown: 0.10,
+            inst_type: "SWAP",
+            is_spot: false,
+            max_leverage: risk_profile.max_leverage,
+            report_interval: Duration::from_secs(3600),
+            llm_budget: Arc::new(Mutex::new(LlmBudget::new(&risk_profile.llm))),
+            risk_profile,
+        };
+        Harness { exchange, journal, brain, notifications, deps }
+    }
+
+    #[tokio::test]
+    async fn hold_decision_is_logged_without_touching_orders() {
+        let exchange = MockExchange::new(10_000.0, 10_000.0).with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+        let h = harness(RiskProfile::for_tests(), exchange, TradeAction::Hold).await;
+        let mut state = CycleState::new(&h.deps.risk_profile, vec![SYMBOL.to_string()]);
+
+        let outcome = run_cycle(&h.deps, &mut state).await;
+        assert!(matches!(outcome, CycleOutcome::Completed(_)));
+        assert_eq!(h.brain.positions_seen.lock().unwrap().clone(), vec!["No active positions".to_string()]);
+        let events = h.journal.events.lock().unwrap().clone();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].kind, events[0].payload["action"].as_str()), (EventKind::Decision, Some("Hold")));
+        assert!(h.exchange.placed_orders().is_empty());
+        // 实盘模式每轮都维护挂单
+        assert_eq!(h.exchange.amendments(), vec!["resize_protection", "retry_unprotected", "reconcile_pending_orders"]);
+    }
+
+    #[tokio::test]
+    async fn llm_budget_is_shared_with_the_debug_api() {
+        let mut risk = RiskProfile::for_tests();
+        risk.llm.max_calls_per_hour = 1;
+        let exchange = MockExchange::new(10_000.0, 10_000.0).with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+        let h = harness(risk, exchange, TradeAction::Buy).await;
+        // 调试接口 (/decide) 先用掉了本小时的唯一一次调用
+        assert!(h.deps.llm_budget.lock().unwrap().try_acquire(Instant::now()));
+
+        let mut state = CycleState::new(&h.deps.risk_profile, vec![SYMBOL.to_string()]);
+        run_cycle(&h.deps, &mut state).await;
+        assert!(h.brain.positions_seen.lock().unwrap().is_empty());
+        assert!(h.exchange.placed_orders().is_empty());
+    }
+
+    /// 一次完整的 Buy：夹具舆情 -> 固定决策 -> 过滤 -> 凯利仓位 -> 下单 -> 落库 -> 通知
+    #[tokio::test]
+    async fn buy_decision_produces_sized_order_log_and_notification() {
+        let risk = RiskProfile::for_tests();
+        let exchange = MockExchange::new(10_000.0, 10_000.0).with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+        let h = harness(risk, exchange, TradeAction::Buy).await;
+        let mut state = CycleState::new(&h.deps.risk_profile, vec![SYMBOL.to_string()]);
+
+        let outcome = run_cycle(&h.deps, &mut state).await;
+        assert!(matches!(outcome, CycleOutcome::Completed(_)));
+
+        let placed = h.exchange.placed_orders();
+        assert_eq!(placed.len(), 1);
+        let qty = placed[0].size;
+        assert_eq!((placed[0].side.as_str(), placed[0].pos_side.as_str(), placed[0].leverage), ("buy", "long", Some(5)));
+        assert!(qty >= 1.0);
+        // 保证金不超过 max_order_size_pct × 权益
+        assert!(qty * 50_000.0 * 0.01 / 5.0 <= 10_000.0 * h.deps.risk_profile.max_order_size_pct + 1e-6);
+
+        let trades = h.journal.trades.lock().unwrap().clone();
+        assert_eq!(trades.len(), 1);
+        assert_eq!((trades[0].symbol.as_str(), trades[0].direction.as_str(), trades[0].order_id.as_str()), (SYMBOL, "buy", "mock-1"));
+        assert_eq!(trades[0].strategy_version, "test-v1");
+        assert!((trades[0].tp_price - 52_000.0).abs() < 1e-6 && (trades[0].sl_price - 49_000.0).abs() < 1e-6);
+        assert!((trades[0].initial_margin - qty * 50_000.0 * 0.01 / 5.0).abs() < 1e-6);
+        assert_eq!(trades[0].entry_price, 50_000.0);
+        assert_eq!(h.journal.entries.lock().unwrap().clone(), vec![(SYMBOL.to_string(), "long".to_string(), qty)]);
+
+        // 决策与订单共用同一个 correlation_id，决策上下文带有夹具舆情
+        let events = h.journal.events.lock().unwrap().clone();
+        assert_eq!(events.len(), 2);
+        assert_eq!((events[0].kind, events[1].kind), (EventKind::Decision, EventKind::Entry));
+        assert_eq!(events[0].correlation_id, events[1].correlation_id);
+        assert_eq!(events[1].order_id.as_deref(), Some("mock-1"));
+        assert!(!events[0].payload["context"]["news_sentiment"].as_str().unwrap_or_default().is_empty());
+        assert!(!events[0].payload["context"]["reddit_sentiment"].as_str().unwrap_or_default().is_empty());
+
+        assert!(h.notifications.messages().iter().any(|m| m.contains(SYMBOL) && m.contains("BUY")));
+        assert_eq!(state.last_actions.get(SYMBOL).map(|(_, side)| *side), Some("long"));
+    }
+
+    #[tokio::test]
+    async fn shadow_mode_never_touches_the_account() {
+        let mut risk = RiskProfile::for_tests();
+        risk.breakeven.enabled = true;
+        risk.margin.reduce_largest_loss = true;
+        // 浮亏的多单 + 维持保证金超过危险阈值：实盘模式下会减仓
+        let losing = PositionSummary {
+            symbol: SYMBOL.to_string(), size: 4.0, upl: -150.0, side: "long".to_string(), avg_px: 52_000.0, mark_px: 50_000.0,
+            leverage: 5, notional_usd: 2_000.0, margin_usd: 400.0,
+        };
+        let exchange = MockExchange { positions: vec![losing], maintenance_margin: 8_000.0, ..MockExchange::new(10_000.0, 10_000.0) }
+            .with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+        let mut h = harness(risk, exchange, TradeAction::Buy).await;
+        h.deps.shadow_book = Some(ShadowBook::new(unreachable_pool()));
+        let mut state = CycleState::new(&h.deps.risk_profile, vec![SYMBOL.to_string()]);
+
+        let outcome = run_cycle(&h.deps, &mut state).await;
+        assert!(matches!(outcome, CycleOutcome::Completed(_)));
+        assert!(state.margin_monitor.is_danger());
+        assert_eq!(h.brain.positions_seen.lock().unwrap().len(), 1);
+        assert!(h.exchange.placed_orders().is_empty());
+        assert!(h.exchange.amendments().is_empty());
+        assert!(h.journal.stops.lock().unwrap().is_empty());
+        assert!(h.journal.trades.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn funding_carry_close_only_runs_live() {
+        let mut risk = RiskProfile::for_tests();
+        risk.funding_carry.enabled = true;
+        let winner = PositionSummary {
+            symbol: SYMBOL.to_string(), size: 4.0, upl: 20.0, side: "long".to_string(), avg_px: 49_500.0, mark_px: 50_000.0,
+            leverage: 5, notional_usd: 2_000.0, margin_usd: 400.0,
+        };
+        for shadow in [false, true] {
+            let exchange = MockExchange { positions: vec![winner.clone()], ..MockExchange::new(10_000.0, 10_000.0) }
+                .with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+            let mut h = harness(risk.clone(), exchange, TradeAction::Hold).await;
+            // 累计资金费 $15 = 浮盈的 75%，超过默认上限 50%
+            *h.journal.funding.lock().unwrap() = 15.0;
+            if shadow {
+                h.deps.shadow_book = Some(ShadowBook::new(unreachable_pool()));
+            }
+            let mut state = CycleState::new(&h.deps.risk_profile, vec![SYMBOL.to_string()]);
+            run_cycle(&h.deps, &mut state).await;
+
+            let placed = h.exchange.placed_orders();
+            if shadow {
+                assert!(placed.is_empty());
+                assert_eq!(h.brain.positions_seen.lock().unwrap().len(), 1);
+            } else {
+                assert_eq!(placed.len(), 1);
+                assert_eq!((placed[0].side.as_str(), placed[0].pos_side.as_str(), placed[0].size, placed[0].reduce_only), ("sell", "long", 4.0, true));
+                // 平仓后本轮不再询问大脑
+                assert!(h.brain.positions_seen.lock().unwrap().is_empty());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn rebalance_only_trims_live() {
+        let mut risk = RiskProfile::for_tests();
+        risk.rebalance.enabled = true;
+        risk.timing.rebalance_sec = Some(0);
+        // 总敞口上限 = 0.1 × 权益 = $1000，持仓名义价值 $2000 需要削减一半
+        risk.exposure.max_gross_multiple = 0.1;
+        let oversized = PositionSummary {
+            symbol: SYMBOL.to_string(), size: 4.0, upl: 0.0, side: "long".to_string(), avg_px: 50_000.0, mark_px: 50_000.0,
+            leverage: 5, notional_usd: 2_000.0, margin_usd: 400.0,
+        };
+        for shadow in [false, true] {
+            let exchange = MockExchange { positions: vec![oversized.clone()], ..MockExchange::new(10_000.0, 10_000.0) }
+                .with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+            let mut h = harness(risk.clone(), exchange, TradeAction::Hold).await;
+            if shadow {
+                h.deps.shadow_book = Some(ShadowBook::new(unreachable_pool()));
+            }
+            let mut state = CycleState::new(&h.deps.risk_profile, vec![SYMBOL.to_string()]);
+            run_cycle(&h.deps, &mut state).await;
+
+            let placed = h.exchange.placed_orders();
+            if shadow {
+                assert!(placed.is_empty());
+            } else {
+                assert_eq!(placed.len(), 1);
+                assert_eq!((placed[0].side.as_str(), placed[0].size, placed[0].reduce_only), ("sell", 2.0, true));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn margin_cut_waits_for_the_cooldown() {
+        let mut risk = RiskProfile::for_tests();
+        risk.margin.reduce_largest_loss = true;
+        let losing = PositionSummary {
+            symbol: SYMBOL.to_string(), size: 4.0, upl: -150.0, side: "long".to_string(), avg_px: 52_000.0, mark_px: 50_000.0,
+            leverage: 5, notional_usd: 2_000.0, margin_usd: 400.0,
+        };
+        let exchange = MockExchange { positions: vec![losing], maintenance_margin: 8_000.0, ..MockExchange::new(10_000.0, 10_000.0) }
+            .with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+        let h = harness(risk, exchange, TradeAction::Hold).await;
+        let mut state = CycleState::new(&h.deps.risk_profile, vec![SYMBOL.to_string()]);
+
+        // 连续两轮都处于危险状态，冷却期内只减一次
+        run_cycle(&h.deps, &mut state).await;
+        run_cycle(&h.deps, &mut state).await;
+        let placed = h.exchange.placed_orders();
+        assert_eq!(placed.len(), 1);
+        assert_eq!((placed[0].side.as_str(), placed[0].size, placed[0].reduce_only), ("sell", 2.0, true));
+    }
+
+    #[tokio::test]
+    async fn margin_cut_is_snapped_to_the_lot_size() {
+        let mut risk = RiskProfile::for_tests();
+        risk.margin.reduce_largest_loss = true;
+        // (持仓张数, 最小下单量, 期望减仓张数)：5 × 0.5 = 2.5 向下取整为 2；不足最小下单量时不下单
+        for (size, min_sz, expected) in [(5.0, 1.0, Some(2.0)), (0.6, 1.0, None)] {
+            let losing = PositionSummary {
+                symbol: SYMBOL.to_string(), size, upl: -150.0, side: "short".to_string(), avg_px: 48_000.0, mark_px: 50_000.0,
+                leverage: 5, notional_usd: 2_000.0, margin_usd: 400.0,
+            };
+            let exchange = MockExchange { positions: vec![losing], maintenance_margin: 8_000.0, ..MockExchange::new(10_000.0, 10_000.0) }
+                .with_instrument(SYMBOL, 0.01, min_sz, 1.0);
+            let h = harness(risk.clone(), exchange, TradeAction::Hold).await;
+            let mut state = CycleState::new(&h.deps.risk_profile, vec![SYMBOL.to_string()]);
+            run_cycle(&h.deps, &mut state).await;
+
+            let cuts: Vec<f64> = h.exchange.placed_orders().iter().filter(|o| o.reduce_only).map(|o| o.size).collect();
+            assert_eq!(cuts, expected.into_iter().collect::<Vec<_>>());
+            let notified = h.notifications.messages().iter().any(|m| m.contains("保证金减仓"));
+            assert_eq!(notified, expected.is_some());
+            if let Some(qty) = expected {
+                assert!(h.notifications.messages().iter().any(|m| m.contains(&format!("减掉 {} / {}", qty, size))));
+            }
+        }
+    }
+}
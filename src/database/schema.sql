@@ -29,6 +29,102 @@ CREATE TABLE IF NOT EXISTS trade_logs (
     created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
 );
 
+-- 3. 增量字段 (幂等迁移，已存在时跳过)
+-- 入场价：新开仓为成交参考价，金字塔加仓后为加权均价
+ALTER TABLE trade_logs ADD COLUMN IF NOT EXISTS entry_price DECIMAL(20, 8);
+-- 计划止盈/止损价 (保本止损移动后 sl_price 同步更新)
+ALTER TABLE trade_logs ADD COLUMN IF NOT EXISTS tp_price DECIMAL(20, 8);
+ALTER TABLE trade_logs ADD COLUMN IF NOT EXISTS sl_price DECIMAL(20, 8);
+-- LLM 原始推理过程 (截断后存储)，用于审计与复盘
+ALTER TABLE trade_logs ADD COLUMN IF NOT EXISTS reasoning TEXT;
+-- 平仓原因: tp_hit / sl_hit / manual_close / time_stop
+ALTER TABLE trade_logs ADD COLUMN IF NOT EXISTS exit_reason VARCHAR(20);
+-- 平仓时间：PnlMonitor 按持仓归零的平仓成交回填 realized_pnl 时写入该成交时间，定时报告按此统计本期平仓
+ALTER TABLE trade_logs ADD COLUMN IF NOT EXISTS closed_at TIMESTAMP WITH TIME ZONE;
+-- 使持仓归零的平仓订单号 (同一持仓的开仓/加仓记录共用)；旧版本按开仓账单回填的记录为 NULL
+ALTER TABLE trade_logs ADD COLUMN IF NOT EXISTS close_ord_id VARCHAR(64);
+
+-- 4. 机会扫描去重表：同一根暴涨 K 线只生成一条错过机会记忆
+CREATE TABLE IF NOT EXISTS scanner_pumps (
+    symbol VARCHAR(20) NOT NULL,
+    pump_ts BIGINT NOT NULL, -- 暴涨 K 线开盘时间 (毫秒)
+    change_pct DECIMAL(10, 4),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (symbol, pump_ts)
+);
+
+-- 5. 持仓缓存表：记录入场价与开仓时间，每轮与 OKX 持仓对账 (重启后持仓时长不丢失)
+CREATE TABLE IF NOT EXISTS positions (
+    symbol VARCHAR(20) NOT NULL,
+    side VARCHAR(10) NOT NULL, -- long / short
+    entry_price DECIMAL(20, 8),
+    size DECIMAL(20, 8) NOT NULL,
+    opened_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (symbol, side)
+);
+-- 客户端 TP/SL 触发价 (干跑/现货模式没有交易所条件单，由 TpSlMonitor 按 WS 价格执行，触发后清空防止重复平仓)
+ALTER TABLE positions ADD COLUMN IF NOT EXISTS tp_price DECIMAL(20, 8);
+ALTER TABLE positions ADD COLUMN IF NOT EXISTS sl_price DECIMAL(20, 8);
+-- 最近一次开仓/加仓时间 (最短持仓时间从这里起算)
+ALTER TABLE positions ADD COLUMN IF NOT EXISTS last_entry_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP;
+
+-- 6. 影子模式交易表 (SHADOW_MODE=1)：只记录信号与按实时价格跟踪的假设收益，不涉及真实账户
+CREATE TABLE IF NOT EXISTS shadow_trades (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    symbol VARCHAR(20) NOT NULL,
+    side VARCHAR(10) NOT NULL, -- long / short
+    entry_price DECIMAL(20, 8) NOT NULL,
+    tp_price DECIMAL(20, 8),
+    sl_price DECIMAL(20, 8),
+    leverage INT NOT NULL DEFAULT 1,
+    strategy_version VARCHAR(50),
+    reason TEXT,
+    exit_price DECIMAL(20, 8),
+    return_pct DECIMAL(12, 6), -- 保证金口径收益率 (含杠杆)
+    exit_reason VARCHAR(20),
+    opened_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP,
+    closed_at TIMESTAMP WITH TIME ZONE
+);
+
+-- 7. 资金费账单 (OKX bills type=8)：按 billId 去重，平仓回填 realized_pnl 时计入持仓期间的资金费
+CREATE TABLE IF NOT EXISTS funding_bills (
+    bill_id VARCHAR(32) PRIMARY KEY,
+    symbol VARCHAR(20) NOT NULL,
+    amount DECIMAL(20, 8) NOT NULL,
+    ts TIMESTAMP WITH TIME ZONE NOT NULL
+);
+
+-- 8. 决策事件流水 (只追加)：decision -> entry/close -> realized_pnl 通过 correlation_id 串联，用于回放任意一笔交易
+CREATE TABLE IF NOT EXISTS event_log (
+    id BIGSERIAL PRIMARY KEY,
+    correlation_id UUID, -- 同一次决策产生的所有事件共用 (早于事件流水的订单回填盈亏时为 NULL)
+    symbol VARCHAR(20) NOT NULL,
+    kind VARCHAR(20) NOT NULL, -- decision / entry / close / realized_pnl
+    order_id VARCHAR(64),
+    payload JSONB NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_event_log_correlation ON event_log (correlation_id);
+CREATE INDEX IF NOT EXISTS idx_event_log_order ON event_log (order_id);
+
+-- 9. 历史成交回放去重表：backfill 命令把 OKX 历史平仓写入记忆后记录平仓订单号，重复执行时跳过
+CREATE TABLE IF NOT EXISTS memory_backfill (
+    close_ord_id VARCHAR(64) PRIMARY KEY,
+    symbol VARCHAR(20) NOT NULL,
+    memory_type VARCHAR(20) NOT NULL, -- success / mistake
+    net_pnl DECIMAL(20, 8),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
+-- 10. 单币种运行时开关：命令行 enable / disable 写入，主循环每轮读取，优先于配置中的 symbol_overrides.enabled
+CREATE TABLE IF NOT EXISTS symbol_switches (
+    symbol VARCHAR(20) PRIMARY KEY,
+    enabled BOOLEAN NOT NULL,
+    reason TEXT,
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT CURRENT_TIMESTAMP
+);
+
 -- =========================================================
 -- ⚠️ 数据库迁移指南 (如果你已经运行过旧版):
 -- 请进入 docker 容器内的 postgres 执行以下命令，或删除 data 目录重建
@@ -36,4 +132,4 @@ CREATE TABLE IF NOT EXISTS trade_logs (
 -- ALTER TABLE trade_logs RENAME COLUMN pnl_percentage TO realized_pnl;
 -- ALTER TABLE trade_logs ADD COLUMN initial_margin DECIMAL(10, 4);
 -- ALTER TABLE trade_logs ADD COLUMN is_reviewed BOOLEAN DEFAULT FALSE;
--- =========================================================
\ No newline at end of file
+-- =========================================================
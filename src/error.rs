@@ -0,0 +1,92 @@
+use thiserror::Error;
+
+/// OKX 系统繁忙/限频类错误码，稍后重试可能成功
+const RETRYABLE_OKX_CODES: &[&str] = &["50001", "50004", "50011", "50013", "50026"];
+
+/// Postgres "对象已存在" 类 SQLSTATE (幂等建表/加列时可忽略)
+/// 42P07 表, 42701 列, 42710 对象 (扩展/索引), 42P06 schema, 42723 函数
+const ALREADY_EXISTS_SQLSTATES: &[&str] = &["42P07", "42701", "42710", "42P06", "42723"];
+
+/// 全局错误类型：按来源区分，调用方按类型 (而非错误文本) 判断重试与忽略
+/// 在 anyhow 链路中传递，需要分类时用 `classify` 取回
+#[derive(Debug, Error)]
+pub enum TraderError {
+    /// OKX 返回 code != "0" 的业务错误
+    #[error("OKX Biz Error: {code} | Msg: {msg}")]
+    Exchange { code: String, msg: String },
+    /// 网络/HTTP 层错误 (超时、5xx、重试耗尽)
+    #[error("Network Error: {0}")]
+    Network(String),
+    /// 配置缺失或取值非法
+    #[error("Config Error: {0}")]
+    Config(String),
+    /// 风控主动拒绝 (如成交滑点超限)，重试只会重复同样的结果
+    #[error("Rejected: {0}")]
+    Rejected(String),
+    /// 订单已发出但最终状态未知 (可能已部分成交)，重试会重复开仓，需要人工核对
+    #[error("Order state uncertain: {0}")]
+    Uncertain(String),
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+impl TraderError {
+    /// 从 anyhow 错误链中取回 TraderError (不是本类型时返回 None)
+    pub fn classify(err: &anyhow::Error) -> Option<&TraderError> {
+        err.chain().find_map(|e| e.downcast_ref::<TraderError>())
+    }
+
+    /// 是否值得重试：网络错误与 OKX 系统繁忙/限频；其他业务错误 (余额不足、参数错误等) 重试无意义
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            TraderError::Network(_) => true,
+            TraderError::Exchange { code, .. } => RETRYABLE_OKX_CODES.contains(&code.as_str()),
+            TraderError::Config(_) | TraderError::Rejected(_) | TraderError::Uncertain(_) => false,
+            TraderError::Database(e) => matches!(e, sqlx::Error::PoolTimedOut | sqlx::Error::Io(_)),
+        }
+    }
+
+    /// 数据库对象已存在 (CREATE/ALTER 的幂等执行)
+    pub fn is_already_exists(&self) -> bool {
+        match self {
+            TraderError::Database(sqlx::Error::Database(db)) => db.code()
+                .is_some_and(|code| ALREADY_EXISTS_SQLSTATES.contains(&code.as_ref())),
+            _ => false,
+        }
+    }
+}
+
+/// anyhow 错误是否值得重试；未分类的错误保守地视为可重试
+pub fn is_retryable(err: &anyhow::Error) -> bool {
+    TraderError::classify(err).is_none_or(TraderError::is_retryable)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    fn biz(code: &str) -> anyhow::Error {
+        TraderError::Exchange { code: code.to_string(), msg: "test".to_string() }.into()
+    }
+
+    #[test]
+    fn exchange_errors_classified_by_code() {
+        assert!(is_retryable(&biz("50011")));
+        assert!(!is_retryable(&biz("51008")));
+    }
+
+    #[test]
+    fn classification_survives_context() {
+        let err = Err::<(), _>(biz("51008")).context("Open long failed").unwrap_err();
+        assert!(matches!(TraderError::classify(&err), Some(TraderError::Exchange { code, .. }) if code == "51008"));
+        assert!(!is_retryable(&err));
+    }
+
+    #[test]
+    fn unclassified_errors_are_retryable() {
+        assert!(is_retryable(&anyhow::anyhow!("something odd")));
+        assert!(is_retryable(&TraderError::Network("timeout".to_string()).into()));
+        assert!(!is_retryable(&TraderError::Config("bad".to_string()).into()));
+    }
+}
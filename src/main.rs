@@ -2,9 +2,12 @@ mod config;
 mod database;
 mod utils;
 mod modules;
+mod cli;
+mod cycle;
+mod error;
 
 use std::time::{Duration, Instant};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::time::sleep;
 use tracing::{info, error, warn};
 use sqlx::postgres::{PgPoolOptions, PgPool};
@@ -14,60 +17,25 @@ use std::fs;
 use chrono::Local;
 use dashmap::DashMap;
 
-use crate::config::risk_profile::RiskProfile;
+use crate::config::okx::OkxEndpoints;
+use crate::config::risk_profile::{RiskProfile, TradingMode};
+use crate::cycle::{CycleState, Deps};
+use crate::error::TraderError;
 use crate::utils::http_client::HttpClientFactory;
-use crate::utils::notifier::{DingTalkNotifier, PositionReportItem};
-use crate::modules::perception::{MarketDataFetcher, NewsSentinel, RedditSentinel, OkxWsClient};
-use crate::modules::brain::{MemorySystem, DecisionMaker, llm::TradeAction};
-use crate::modules::action::{TradeExecutor, LogManager};
+use crate::utils::notifier::{NotifierHub, PositionReportItem, Priority};
+use crate::modules::perception::{MarketDataFetcher, NewsSentinel, RedditSentinel, OkxWsClient, BookCache};
+use crate::modules::perception::discovery;
+use crate::modules::brain::{MemorySystem, DecisionMaker};
+use crate::modules::brain::rag::SLOW_EMBED_LATENCY;
+use crate::modules::brain::budget::LlmBudget;
+use crate::modules::action::{TradeExecutor, LogManager, Exchange, PositionStore, EventLog, PgJournal};
+use crate::modules::action::tpsl_monitor::TpSlMonitor;
+use crate::modules::action::executor::{check_pos_mode, PosModeCheck, REQUIRED_POS_MODE};
+use crate::modules::action::shadow::{self, ShadowBook};
+use crate::modules::action::symbol_switch::SymbolSwitchStore;
 use crate::modules::evolution::{AutopsyDoctor, OpportunityScanner, PnlMonitor};
-
-async fn calculate_position_size_kelly(
-    equity: f64, 
-    available_equity: f64, 
-    kelly_fraction: f64, 
-    max_pct_limit: f64, 
-    leverage: u32, 
-    price: f64, 
-    symbol: &str, 
-    executor: &TradeExecutor
-) -> f64 {
-    let safe_kelly = kelly_fraction * 0.5;
-    let actual_pct = if safe_kelly > max_pct_limit { max_pct_limit } else if safe_kelly < 0.01 { 0.01 } else { safe_kelly };
-    
-    let face_val = executor.get_face_value(symbol).await;
-    let min_sz = executor.get_min_size(symbol).await; 
-
-    if price * face_val == 0.0 { return 0.0; }
-
-    let min_cost_margin = (price * face_val * min_sz) / (leverage as f64);
-    
-    if available_equity < min_cost_margin {
-        warn!("💰 资金不足: {} 最小 {}张合约需 ${:.2} (杠杆{}x)，但可用余额仅 ${:.2}。跳过。", 
-            symbol, min_sz, min_cost_margin, leverage, available_equity);
-        return 0.0; 
-    }
-
-    let mut margin_amount = equity * actual_pct; 
-    
-    if margin_amount > available_equity {
-        margin_amount = available_equity * 0.95; 
-    }
-
-    let notional_value = margin_amount * (leverage as f64);
-    let mut contracts = notional_value / (price * face_val);
-    
-    if contracts < min_sz {
-        contracts = min_sz;
-    }
-    
-    let final_cost = (contracts * price * face_val) / (leverage as f64);
-    if final_cost > available_equity {
-        return 0.0;
-    }
-    
-    contracts
-}
+use crate::modules::api::{self, ApiState};
+use tokio::sync::watch;
 
 async fn init_database(pool: &PgPool) -> anyhow::Result<()> {
     info!("Checking database schema...");
@@ -78,7 +46,8 @@ async fn init_database(pool: &PgPool) -> anyhow::Result<()> {
             for stmt in statements {
                 if stmt.is_empty() { continue; }
                 if let Err(e) = sqlx::query(stmt).execute(pool).await {
-                    if !e.to_string().contains("already exists") && !e.to_string().contains("duplicate column") {
+                    let e = TraderError::from(e);
+                    if !e.is_already_exists() {
                         warn!("Schema warning: {}", e);
                     }
                 }
@@ -107,47 +76,108 @@ async fn main() -> anyhow::Result<()> {
         .acquire_timeout(Duration::from_secs(10))
         .connect(&db_url)
         .await
-        .map_err(|e| {
+        .inspect_err(|_| {
             error!("CRITICAL: DB Connection Failed! Is Docker running?");
-            e
         })?;
 
     init_database(&pool).await?;
 
+    // 命令行子命令 (如 `cargo run -- stats 30`)，执行完即退出，不进入交易循环
+    let args: Vec<String> = env::args().skip(1).collect();
+    if !args.is_empty() {
+        return cli::run(&args, &pool).await;
+    }
+
     // 2. 模块初始化
+    let okx = OkxEndpoints::from_env()?;
+    info!("🌐 OKX endpoints: REST {} | WS {}{}", okx.rest_url, okx.ws_url, if okx.simulated { " (simulated)" } else { "" });
     let std_client = HttpClientFactory::create()?;
     let direct_client = HttpClientFactory::create_direct()?;
     
-    let notifier = Arc::new(DingTalkNotifier::new(direct_client.clone()));
-    let fetcher = Arc::new(MarketDataFetcher::new(std_client.clone()));
-    let news_sentinel = Arc::new(NewsSentinel::new(std_client.clone()));
-    let reddit_sentinel = Arc::new(RedditSentinel::new(std_client.clone()));
+    let notifier = Arc::new(NotifierHub::from_env(direct_client.clone(), risk_profile.notify.clone()));
+    let fetcher = Arc::new(MarketDataFetcher::new(std_client.clone(), &okx, &risk_profile.indicators, &risk_profile.regime));
+    let fixtures = &risk_profile.fixtures;
+    let (news_fixture, reddit_fixture) = if fixtures.is_active() {
+        info!("🧪 Fixture mode: news from {}, reddit from {}", fixtures.news_path, fixtures.reddit_path);
+        (Some(fixtures.news_path.clone()), Some(fixtures.reddit_path.clone()))
+    } else {
+        (None, None)
+    };
+    let news_sentinel = Arc::new(NewsSentinel::new(std_client.clone(), news_fixture, risk_profile.timing.sentiment_ttl()));
+    let reddit_sentinel = Arc::new(RedditSentinel::new(std_client.clone(), reddit_fixture, risk_profile.timing.sentiment_ttl()));
     
-    let memory_sys = Arc::new(MemorySystem::new(qdrant_url, direct_client.clone()).expect("Failed to init Qdrant client"));
+    let memory_sys = Arc::new(MemorySystem::new(qdrant_url, direct_client.clone(), risk_profile.memory.clone()).expect("Failed to init Qdrant client"));
     if let Err(e) = memory_sys.init().await {
-        error!("Failed to initialize Qdrant collection: {}", e);
+        error!("Failed to initialize Qdrant collection: {}. Continuing without memory.", e);
+    }
+    // Embedding 自检：Key/模型/维度配置错误在交易开始前暴露，而不是在循环里反复重试
+    if risk_profile.memory.startup_check {
+        info!("🧪 Running embedding self-test...");
+        match memory_sys.self_test().await {
+            Ok(latency) if latency > SLOW_EMBED_LATENCY => {
+                warn!("🐢 Embedding self-test passed but took {:.2?}. The API path is slow, every recall will pay this latency.", latency);
+            }
+            Ok(latency) => info!("✅ Embedding self-test passed ({:.2?})", latency),
+            Err(e) if risk_profile.memory.startup_check_strict => {
+                error!("CRITICAL: Embedding self-test failed: {:#}. System cannot start (memory.startup_check_strict = true).", e);
+                return Err(e);
+            }
+            Err(e) => {
+                error!("🚨 Embedding self-test failed: {:#}. Memory recall will not work until this is fixed.", e);
+                notifier.send_text(&format!("🚨 Embedding 自检失败，记忆召回不可用: {}", e), Priority::Critical).await;
+            }
+        }
     }
 
-    let brain = Arc::new(DecisionMaker::new(direct_client.clone()));
-    let executor = Arc::new(TradeExecutor::new(std_client.clone()));
+    let brain = Arc::new(DecisionMaker::new(direct_client.clone(), risk_profile.llm.clone()).with_tpsl_clamps(risk_profile.tpsl_clamps())
+        .with_leverage_scaling(risk_profile.leverage_scaling.clone()));
+    let executor = Arc::new(TradeExecutor::new(std_client.clone(), &okx, risk_profile.execution.clone(), risk_profile.settle_ccy(), risk_profile.paper.clone()));
+    // 下单与仓位计算只依赖 Exchange trait
+    let exchange: Arc<dyn Exchange> = executor.clone();
     let logger = Arc::new(LogManager::new(pool.clone()));
+    let event_log = EventLog::new(pool.clone());
+    let journal = Arc::new(PgJournal::new(logger.clone(), PositionStore::new(pool.clone()), event_log.clone()));
+    let position_store = PositionStore::new(pool.clone());
     let autopsy = AutopsyDoctor::new(pool.clone(), memory_sys.clone());
-    let scanner = OpportunityScanner::new(pool.clone(), fetcher.clone(), memory_sys.clone());
+    let scanner = OpportunityScanner::new(pool.clone(), fetcher.clone(), memory_sys.clone(), risk_profile.thresholds.scanner_pump_pct);
     let pnl_monitor = PnlMonitor::new(pool.clone(), executor.clone());
+    // 影子模式：只记录信号与假设收益，不触碰账户
+    let shadow_book = shadow::is_enabled().then(|| {
+        info!("👻 SHADOW MODE: decisions are logged to shadow_trades, no orders are sent and the account is never touched");
+        ShadowBook::new(pool.clone())
+    });
 
     // 3. 交易所元数据同步
-    if let Err(e) = executor.init_instruments_cache().await {
+    if let Err(e) = executor.init_instruments_cache(&risk_profile.allowed_symbols).await {
         error!("CRITICAL: Init instruments failed: {}. System cannot start.", e);
         return Err(e); 
     }
+    // allowed_symbols 中缺失元数据的币种 (拼写错误/已下架/交易模式不符) 仓位恒为 0，启动时单独重试一次并显式告警
+    let mut unknown_symbols = Vec::new();
+    for symbol in executor.missing_instruments(&risk_profile.allowed_symbols).await {
+        match executor.refresh_instrument(&symbol).await {
+            Ok(true) => info!("✅ [{}] Instrument meta recovered by targeted refresh", symbol),
+            Ok(false) => unknown_symbols.push(symbol),
+            Err(e) => {
+                warn!("Targeted instrument refresh failed for {}: {}", symbol, e);
+                unknown_symbols.push(symbol);
+            }
+        }
+    }
+    if !unknown_symbols.is_empty() {
+        error!("🚨 No instrument meta for {:?} (misconfigured or delisted?). These symbols will NEVER trade.", unknown_symbols);
+        notifier.send_text(&format!("🚨 以下币种在 OKX 找不到合约信息 (拼写错误或已下架?)，将永远不会开仓: {}", unknown_symbols.join(", ")), Priority::Critical).await;
+    }
 
     // 4. 获取初始资金基准
     info!("💰 Establishing Risk Baseline...");
     let mut initial_capital = 0.0;
+    let mut startup_positions = vec![];
     for i in 1..=5 {
-        match executor.fetch_account_summary().await {
-            Ok(cap) => {
-                initial_capital = cap.total_equity;
+        match exchange.fetch_account_snapshot().await {
+            Ok(snap) => {
+                initial_capital = snap.balance.total_equity;
+                startup_positions = snap.positions;
                 info!("✅ Risk Baseline Set: ${:.2}", initial_capital);
                 break;
             }
@@ -161,21 +191,13 @@ async fn main() -> anyhow::Result<()> {
     if initial_capital == 0.0 {
         let msg = "🔥 CRITICAL: Could not fetch Initial Capital!";
         error!("{}", msg);
-        notifier.send_text(msg).await;
+        notifier.send_text(msg, Priority::Critical).await;
     } else {
-        let startup_positions = match executor.fetch_positions().await {
-            Ok(p) => p,
-            Err(e) => { warn!("Failed to fetch positions on startup: {}", e); vec![] }
-        };
-
-        let report_items: Vec<PositionReportItem> = startup_positions.iter().map(|p| PositionReportItem {
-            symbol: p.symbol.clone(),
-            side: p.side.clone(),
-            notional_usdt: p.notional_usd,
-            margin_usdt: p.margin_usd,
-            upl: p.upl,
-            leverage: p.leverage,
-        }).collect();
+        let mut report_items: Vec<PositionReportItem> = Vec::with_capacity(startup_positions.len());
+        for p in &startup_positions {
+            let sl = position_store.stop_price(&p.symbol, &p.side).await.unwrap_or(None);
+            report_items.push(PositionReportItem::new(p, sl));
+        }
 
         notifier.send_startup_report(
             initial_capital, 
@@ -184,222 +206,137 @@ async fn main() -> anyhow::Result<()> {
         ).await;
     }
 
-    // 5. 启动 WebSocket
-    let price_cache = Arc::new(DashMap::new());
-    let ws_client = OkxWsClient::new(price_cache.clone());
-    let symbols_clone = risk_profile.allowed_symbols.clone();
-    tokio::spawn(async move {
-        ws_client.run(symbols_clone).await;
-    });
-
-    // 6. 循环变量
-    let mut last_evolution_time = Instant::now();
-    let mut last_report_time = Instant::now();
-    
-    let evolution_interval = Duration::from_secs(risk_profile.timing.evolution_sec);
-    let report_interval = Duration::from_secs(3600); 
-    let base_rest_interval = Duration::from_secs(risk_profile.timing.cycle_rest_sec);
-
-    info!("✅ System initialized. Loop starting...");
-
-    loop {
-        info!("==================== 📊 SYSTEM STATUS ====================");
-        
-        let (equity, available_equity) = match executor.fetch_account_summary().await {
-            Ok(balance) => (balance.total_equity, balance.available_balance),
-            Err(e) => { error!("Failed to fetch balance: {}", e); (0.0, 0.0) }
-        };
-
-        if initial_capital > 0.0 && equity > 0.0 {
-            let drawdown = (initial_capital - equity) / initial_capital;
-            if drawdown > max_drawdown {
-                let alert = format!("🔥 严重警告: 最大回撤触发! ({:.2}%). 系统已暂停.", drawdown * 100.0);
-                error!("{}", alert);
-                notifier.send_text(&alert).await;
+    // 永续下单带 posSide：单向持仓模式下每笔订单都会被拒，启动时检查 (持仓快照失败时不切换)
+    let is_swap = risk_profile.execution.trading_mode != TradingMode::Spot;
+    if is_swap && !executor.is_paper() && shadow_book.is_none() && initial_capital > 0.0 {
+        match executor.fetch_position_mode().await {
+            Ok(mode) => {
+                info!("🔧 Position mode: detected {}, required {}", mode, REQUIRED_POS_MODE);
+                match check_pos_mode(&mode, risk_profile.execution.auto_fix_pos_mode, startup_positions.len()) {
+                    PosModeCheck::Ok => {}
+                    PosModeCheck::Switch => match executor.set_position_mode(REQUIRED_POS_MODE).await {
+                        Ok(()) => {
+                            info!("🔧 Position mode switched: {} -> {}", mode, REQUIRED_POS_MODE);
+                            notifier.send_text(&format!("🔧 账户持仓模式已自动切换: {} -> {}", mode, REQUIRED_POS_MODE), Priority::Critical).await;
+                        }
+                        Err(e) => {
+                            error!("🚨 Failed to switch position mode {} -> {}: {}. Every order will be rejected.", mode, REQUIRED_POS_MODE, e);
+                            notifier.send_text(&format!("🚨 持仓模式自动切换失败 ({} -> {}): {}，所有订单都会被拒，请在 OKX 手动切换为双向持仓", mode, REQUIRED_POS_MODE, e), Priority::Critical).await;
+                        }
+                    },
+                    PosModeCheck::Mismatch(reason) => {
+                        error!("🚨 Position mode is {} but {} is required ({}). Every order will be rejected.", mode, REQUIRED_POS_MODE, reason);
+                        notifier.send_text(&format!("🚨 账户持仓模式为 {}，策略需要 {} (双向持仓)，所有订单都会被拒。未自动切换: {}", mode, REQUIRED_POS_MODE, reason), Priority::Critical).await;
+                    }
+                }
             }
+            Err(e) => warn!("⚠️ Failed to read account position mode: {}", e),
         }
+    }
 
-        let all_positions = match executor.fetch_positions().await {
-            Ok(p) => p, 
-            Err(e) => { error!("Failed to fetch positions: {}", e); vec![] }
-        };
-
-        if last_report_time.elapsed() >= report_interval && equity > 0.0 {
-            let total_pnl_pct = (equity - initial_capital) / initial_capital * 100.0;
-            let report_items: Vec<PositionReportItem> = all_positions.iter().map(|p| PositionReportItem {
-                symbol: p.symbol.clone(),
-                side: p.side.clone(),
-                notional_usdt: p.notional_usd,
-                margin_usdt: p.margin_usd,
-                upl: p.upl,
-                leverage: p.leverage,
-            }).collect();
-            notifier.send_status_report(equity, total_pnl_pct, report_items).await;
-            last_report_time = Instant::now();
+    // 5. 交易池：默认全部 allowed_symbols；开启自动选币时从中选出前 N 个，并定期刷新
+    let inst_type = if risk_profile.execution.trading_mode == TradingMode::Spot { "SPOT" } else { "SWAP" };
+    let mut universe = risk_profile.allowed_symbols.clone();
+    if risk_profile.discovery.enabled {
+        match discovery::discover(fetcher.as_ref(), &risk_profile.discovery, &risk_profile.allowed_symbols, inst_type).await {
+            Ok(list) if !list.is_empty() => universe = list,
+            Ok(_) => warn!("🔭 Discovery selected no symbols. Falling back to allowed_symbols."),
+            Err(e) => warn!("🔭 Discovery failed: {}. Falling back to allowed_symbols.", e),
         }
+        info!("🔭 Trading universe: {:?}", universe);
+        let extra: Vec<String> = universe.iter().filter(|s| !risk_profile.allowed_symbols.contains(s)).cloned().collect();
+        executor.load_position_tiers(&extra).await;
+    }
+    let (universe_tx, universe_rx) = watch::channel(universe.clone());
 
-        info!("==========================================================");
-
-        let raw_reddit = match reddit_sentinel.analyze_sentiment().await {
-            Ok(t) => t, Err(e) => format!("Error fetching Reddit: {}", e),
-        };
-        let raw_news = match news_sentinel.fetch_raw_headlines("GLOBAL").await {
-            Ok(m) => m, Err(e) => format!("Error fetching News: {}", e),
-        };
-
-        info!("📰 Global Context Ready: News ({} chars), Reddit ({} chars)", raw_news.len(), raw_reddit.len());
+    // 启动 WebSocket (交易池变化时自动重新订阅)
+    let price_cache = Arc::new(DashMap::new());
+    let book_cache: BookCache = Arc::new(DashMap::new());
+    let ws_client = OkxWsClient::new(&okx, price_cache.clone(), book_cache.clone());
+    tokio::spawn(async move {
+        ws_client.run(universe_rx).await;
+    });
 
-        // [New] Dynamic Heartbeat variables
-        let mut max_atr_pct = 0.0;
+    // 干跑/现货：TP/SL 由客户端按 WS 价格执行 (影子模式自行跟踪假设交易)
+    if executor.client_side_tpsl() && shadow_book.is_none() {
+        let monitor = TpSlMonitor::new(PositionStore::new(pool.clone()), executor.clone(), price_cache.clone(), notifier.clone());
+        tokio::spawn(monitor.run());
+    }
 
-        for symbol in &risk_profile.allowed_symbols {
-            info!("🔍 Analyzing {}...", symbol);
+    // 现货模式：无杠杆、不能做空
+    let is_spot = risk_profile.execution.trading_mode == TradingMode::Spot;
+    let max_leverage = if is_spot { 1.0 } else { risk_profile.max_leverage };
+    if is_spot {
+        info!("🪙 Trading mode: SPOT (no leverage, long only)");
+    }
 
-            let market_state_res = fetcher.snapshot(symbol, raw_reddit.clone(), raw_news.clone()).await;
-            
-            let mut market_state = match market_state_res {
-                Ok(s) => s,
-                Err(e) => {
-                    error!("Fetch error for {}: {}", symbol, e);
-                    continue; 
-                }
+    // 调试 HTTP 接口 (DEBUG_API=1)：与主循环共用模块句柄，只读/试跑，不下单
+    // LLM 调用预算由主循环与调试接口共用
+    let llm_budget = Arc::new(Mutex::new(LlmBudget::new(&risk_profile.llm)));
+    match api::config_from_env() {
+        Ok(Some((addr, token))) => {
+            let state = ApiState {
+                exchange: exchange.clone(),
+                fetcher: fetcher.clone(),
+                brain: brain.clone(),
+                memory: memory_sys.clone(),
+                news: news_sentinel.clone(),
+                reddit: reddit_sentinel.clone(),
+                allowed_symbols: risk_profile.allowed_symbols.clone(),
+                max_leverage,
+                memory_config: risk_profile.memory.clone(),
+                llm_budget: llm_budget.clone(),
             };
-
-            // Calculate ATR % for heartbeat logic
-            if market_state.price > 0.0 {
-                let current_atr_pct = (market_state.indicators.atr_14 / market_state.price) * 100.0;
-                if current_atr_pct > max_atr_pct {
-                    max_atr_pct = current_atr_pct;
+            tokio::spawn(async move {
+                if let Err(e) = api::serve(addr, token, state).await {
+                    error!("Debug API failed: {:#}", e);
                 }
-            }
-
-            if let Some(entry) = price_cache.get(symbol) {
-                let (ws_price, ts) = *entry.value();
-                if ts.elapsed() < Duration::from_secs(60) {
-                    market_state.price = ws_price;
-                } else {
-                    warn!("⚠️ WS Data Stale for {} ({:?} ago). Falling back to REST price.", symbol, ts.elapsed());
-                }
-            }
-
-            let ctx_str = market_state.to_context_string();
-            info!("\n================ [DEBUG] EMBEDDING INPUT START ================\n{}\n================ [DEBUG] EMBEDDING INPUT END ================", ctx_str);
-
-            let memories = memory_sys.recall_memories(&ctx_str).await.unwrap_or_default();
-
-            let long_pos = all_positions.iter().find(|p| p.symbol == *symbol && p.side == "long" && p.size > 0.0);
-            let short_pos = all_positions.iter().find(|p| p.symbol == *symbol && p.side == "short" && p.size > 0.0);
-            
-            let pos_info = match (long_pos, short_pos) {
-                (Some(l), Some(s)) => format!("Long: {} (PnL ${}), Short: {} (PnL ${})", l.size, l.upl, s.size, s.upl),
-                (Some(l), None) => format!("Long: {} (PnL ${})", l.size, l.upl),
-                (None, Some(s)) => format!("Short: {} (PnL ${})", s.size, s.upl),
-                (None, None) => "No active positions".to_string(),
-            };
-
-            match brain.analyze(&market_state, &memories, &pos_info, risk_profile.max_leverage).await {
-                Ok(mut decision) => {
-                    info!("[{}] 🎯 Decision: {:?} (Reason: {})", symbol, decision.action, decision.reason);
-
-                    match decision.action {
-                        TradeAction::Buy | TradeAction::Sell => {
-                            // [Fix] Win Rate Soft Cap
-                            // 强制将胜率限制在 0.75 以内，防止凯利公式全仓梭哈
-                            if decision.win_rate > 0.75 {
-                                warn!("⚠️ AI WinRate ({:.2}) capped to 0.75 for safety.", decision.win_rate);
-                                decision.win_rate = 0.75;
-                                // 重新计算 kelly fraction
-                                let p = decision.win_rate;
-                                let b = decision.risk_reward_ratio;
-                                decision.kelly_fraction = if b > 0.0 { p - ((1.0 - p) / b) } else { 0.0 };
-                            }
-
-                            let qty = calculate_position_size_kelly(
-                                equity, available_equity, decision.kelly_fraction, risk_profile.max_order_size_pct, 
-                                decision.leverage, market_state.price, symbol, &executor
-                            ).await;
-
-                            if qty > 0.0 {
-                                let side = if let TradeAction::Buy = decision.action { "buy" } else { "sell" };
-                                let pos_side = if let TradeAction::Buy = decision.action { "long" } else { "short" };
-                                
-                                for attempt in 1..=10 {
-                                    match executor.execute_order(symbol, side, pos_side, qty, market_state.price, decision.tp_pct, decision.sl_pct, Some(decision.leverage)).await {
-                                        Ok(res) => {
-                                            info!("✅ [{}] Order Sent: {}", symbol, res.order_id);
-                                            let face_val = executor.get_face_value(symbol).await;
-                                            let initial_margin = (qty * market_state.price * face_val) / (decision.leverage as f64);
-                                            let _ = logger.log_trade(symbol, side, &market_state, &res.order_id, initial_margin).await;
-                                            notifier.send_trade_signal(
-                                                symbol, side, qty, market_state.price, 
-                                                &decision.reason, decision.tp_pct, decision.sl_pct
-                                            ).await;
-                                            break; 
-                                        },
-                                        Err(e) => {
-                                            warn!("❌ [{}] Order Failed (Attempt {}/10): {}. Retrying in 1s...", symbol, attempt, e);
-                                            sleep(Duration::from_secs(1)).await;
-                                        }
-                                    }
-                                }
-                            }
-                        },
-                        TradeAction::CloseLong => {
-                            if let Some(pos) = long_pos {
-                                for attempt in 1..=10 {
-                                    if let Ok(_) = executor.execute_order(symbol, "sell", "long", pos.size, market_state.price, 0.0, 0.0, None).await {
-                                        info!("Long Closed: {}", symbol);
-                                        notifier.send_trade_signal(symbol, "CLOSE LONG", pos.size, market_state.price, &decision.reason, 0.0, 0.0).await;
-                                        break;
-                                    } else {
-                                        warn!("❌ Close Long Failed (Attempt {}/10). Retrying...", attempt);
-                                        sleep(Duration::from_secs(1)).await;
-                                    }
-                                }
-                            }
-                        },
-                        TradeAction::CloseShort => {
-                            if let Some(pos) = short_pos {
-                                for attempt in 1..=10 {
-                                    if let Ok(_) = executor.execute_order(symbol, "buy", "short", pos.size, market_state.price, 0.0, 0.0, None).await {
-                                        info!("Short Closed: {}", symbol);
-                                        notifier.send_trade_signal(symbol, "CLOSE SHORT", pos.size, market_state.price, &decision.reason, 0.0, 0.0).await;
-                                        break;
-                                    } else {
-                                        warn!("❌ Close Short Failed (Attempt {}/10). Retrying...", attempt);
-                                        sleep(Duration::from_secs(1)).await;
-                                    }
-                                }
-                            }
-                        },
-                        TradeAction::Hold => {}
-                    }
-                },
-                Err(e) => error!("[{}] Brain Error: {}", symbol, e),
-            }
-            sleep(Duration::from_millis(500)).await;
+            });
         }
+        Ok(None) => {}
+        Err(e) => error!("Debug API disabled: {}", e),
+    }
 
-        if last_evolution_time.elapsed() > evolution_interval {
-            info!("🧬 Running Evolution...");
-            if let Err(e) = pnl_monitor.sync_realized_pnl().await { error!("PnL Sync Failed: {}", e); }
-            let _ = autopsy.perform_daily_review().await;
-            for symbol in &risk_profile.allowed_symbols { let _ = scanner.scan_missed_opportunities(symbol).await; }
-            last_evolution_time = Instant::now();
-        }
+    // 6. 主循环：每轮的逻辑见 cycle.rs
+    let mut state = CycleState::new(&risk_profile, universe);
+    // 启动观察期：崩溃重启后先核对状态再恢复开仓
+    if let Some(grace) = state.startup_grace.remaining(Instant::now()) {
+        let msg = format!("🕊️ 启动观察期 {} 分钟：期间只同步、保护和平掉已有持仓，不开新仓。请核对持仓与止损状态，观察期结束后自动恢复交易",
+            grace.as_secs().div_ceil(60));
+        warn!("🕊️ Startup grace period: no new entries for {:?}", grace);
+        notifier.send_text(&msg, Priority::Critical).await;
+    }
+    let deps = Deps {
+        report_interval: Duration::from_secs(3600),
+        llm_budget,
+        risk_profile,
+        notifier,
+        fetcher,
+        news_sentinel,
+        reddit_sentinel,
+        memory_sys,
+        brain,
+        exchange,
+        journal,
+        symbol_switches: SymbolSwitchStore::new(pool.clone()),
+        autopsy,
+        scanner,
+        pnl_monitor,
+        shadow_book,
+        price_cache,
+        book_cache,
+        universe_tx,
+        initial_capital,
+        max_drawdown,
+        inst_type,
+        is_spot,
+        max_leverage,
+    };
 
-        // [New] Dynamic Sleep Logic
-        // Base is 0.5% volatility (ATR). If vol is 1.0% (2x), sleep time halves.
-        // Min sleep is 60s to prevent API spam.
-        let dynamic_rest = if max_atr_pct > 0.0 {
-            let volatility_ratio = max_atr_pct / 0.5; // normalized to 0.5%
-            let adjusted_secs = (base_rest_interval.as_secs_f64() / volatility_ratio.max(0.5)).max(60.0);
-            Duration::from_secs(adjusted_secs as u64)
-        } else {
-            base_rest_interval
-        };
+    info!("✅ System initialized. Loop starting...");
 
-        info!("💤 Cycle done. Volatility: {:.2}%. Sleeping {}s...", max_atr_pct, dynamic_rest.as_secs());
-        sleep(dynamic_rest).await;
+    loop {
+        let outcome = cycle::run_cycle(&deps, &mut state).await;
+        sleep(outcome.rest()).await;
     }
 }
@@ -0,0 +1,166 @@
+// 文件名: close.rs
+// 平仓下单：平仓失败意味着持仓失去保护，重试次数/间隔与开仓分开配置 ([retry])，连续失败提前升级为紧急通知
+
+use serde_json::json;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use super::entry::OrderDeps;
+use super::event_log::EventKind;
+use super::exchange::Exchange;
+use super::executor::PositionSummary;
+use crate::modules::evolution::ExitReason;
+use crate::utils::notifier::Priority;
+
+/// 一笔信号平仓 (整仓，reduce-only 市价单)
+pub struct CloseOrder<'a> {
+    /// 与决策事件共用的 correlation_id
+    pub correlation_id: Uuid,
+    pub symbol: &'a str,
+    /// long / short
+    pub pos_side: &'a str,
+    pub qty: f64,
+    pub price: f64,
+    /// 通知中附带的决策理由
+    pub reason: &'a str,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum CloseOutcome {
+    Closed,
+    /// 重试耗尽仍未平掉
+    Failed,
+}
+
+/// 平仓并在成功后记录退出原因、事件流水并发送通知；失败时按 close_escalate_after 提前告警
+pub async fn close_position(deps: &OrderDeps<'_>, order: &CloseOrder<'_>) -> CloseOutcome {
+    let (symbol, pos_side, qty) = (order.symbol, order.pos_side, order.qty);
+    let (close_side, label, action) = if pos_side == "short" { ("buy", "平空", "CLOSE SHORT") } else { ("sell", "平多", "CLOSE LONG") };
+    let retry = deps.retry;
+    for attempt in 1..=retry.close_attempts {
+        match deps.exchange.execute_order(symbol, close_side, pos_side, qty, order.price, 0.0, 0.0, None, true).await {
+            Ok(_) => {
+                info!("{} Closed: {}", if pos_side == "short" { "Short" } else { "Long" }, symbol);
+                deps.journal.append_event(order.correlation_id, symbol, EventKind::Close, None, json!({
+                    "pos_side": pos_side, "qty": qty, "price": order.price, "attempt": attempt, "ok": true,
+                })).await;
+                if let Err(e) = deps.journal.mark_exit(symbol, pos_side, ExitReason::ManualClose).await {
+                    warn!("Failed to record exit reason for {}: {}", symbol, e);
+                }
+                deps.notifier.send_trade_signal(symbol, action, qty, order.price, order.reason, 0.0, 0.0, Priority::Critical).await;
+                return CloseOutcome::Closed;
+            }
+            Err(e) => {
+                warn!("❌ [{}] {} Failed (Attempt {}/{}): {}. Retrying...", symbol, action, attempt, retry.close_attempts, e);
+                if attempt == retry.close_attempts {
+                    notify_critical(deps, &format!("❌ [{}] {}失败 (重试 {} 次)，请人工检查持仓! 最后错误: {}", symbol, label, attempt, e)).await;
+                    deps.journal.append_event(order.correlation_id, symbol, EventKind::Close, None, json!({
+                        "pos_side": pos_side, "qty": qty, "attempt": attempt, "ok": false, "error": e.to_string(),
+                    })).await;
+                    return CloseOutcome::Failed;
+                }
+                if attempt == retry.close_escalate_after {
+                    notify_critical(deps, &format!("🚨 [{}] {}已连续失败 {} 次，持仓没有平掉，仍在重试: {}", symbol, label, attempt, e)).await;
+                }
+                sleep(retry.close_delay()).await;
+            }
+        }
+    }
+    CloseOutcome::Failed
+}
+
+async fn notify_critical(deps: &OrderDeps<'_>, msg: &str) {
+    error!("{}", msg);
+    deps.notifier.send_text(msg, Priority::Critical).await;
+}
+
+/// 紧急平掉全部持仓 (每个持仓只尝试一次 reduce-only 市价单)，返回未能平掉的持仓 "symbol side"
+pub async fn flatten_all(exchange: &dyn Exchange, positions: &[PositionSummary]) -> Vec<String> {
+    let mut failed = Vec::new();
+    for pos in positions.iter().filter(|p| p.size > 0.0) {
+        let close_side = if pos.side == "short" { "buy" } else { "sell" };
+        match exchange.execute_order(&pos.symbol, close_side, &pos.side, pos.size, pos.mark_px, 0.0, 0.0, None, true).await {
+            Ok(_) => warn!("🧯 [{}] {} {} flattened", pos.symbol, pos.side, pos.size),
+            Err(e) => {
+                error!("🧯 [{}] Flatten {} {} failed: {}", pos.symbol, pos.side, pos.size, e);
+                failed.push(format!("{} {}", pos.symbol, pos.side));
+            }
+        }
+    }
+    failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::risk_profile::{NotifyConfig, RetryConfig};
+    use crate::modules::action::exchange::mock::MockExchange;
+    use crate::modules::action::journal::mock::MemoryJournal;
+    use crate::utils::notifier::mock::RecordingNotifier;
+    use crate::utils::notifier::NotifierHub;
+
+    const SYMBOL: &str = "ETH-USDT-SWAP";
+
+    fn retry() -> RetryConfig {
+        RetryConfig { close_attempts: 3, close_delay_ms: 0, close_escalate_after: 2, ..RetryConfig::default() }
+    }
+
+    fn order() -> CloseOrder<'static> {
+        CloseOrder { correlation_id: Uuid::new_v4(), symbol: SYMBOL, pos_side: "short", qty: 4.0, price: 3000.0, reason: "target hit" }
+    }
+
+    #[tokio::test]
+    async fn close_is_reduce_only_and_records_exit() {
+        let (exchange, journal, backend) = (MockExchange::new(1000.0, 1000.0), MemoryJournal::default(), RecordingNotifier::default());
+        let notifier = NotifierHub::with_backends(vec![Box::new(backend.clone())], NotifyConfig::default());
+        let cfg = retry();
+        let deps = OrderDeps { exchange: &exchange, journal: &journal, notifier: &notifier, retry: &cfg };
+
+        assert_eq!(close_position(&deps, &order()).await, CloseOutcome::Closed);
+        let placed = exchange.placed_orders();
+        assert_eq!(placed.len(), 1);
+        assert_eq!((placed[0].side.as_str(), placed[0].pos_side.as_str(), placed[0].size), ("buy", "short", 4.0));
+        assert!(placed[0].reduce_only);
+        assert_eq!(journal.exits.lock().unwrap().clone(), vec![(SYMBOL.to_string(), "short".to_string(), "manual_close")]);
+        assert!(backend.messages()[0].contains("CLOSE SHORT"));
+    }
+
+    #[tokio::test]
+    async fn failing_close_escalates_before_giving_up() {
+        let exchange = MockExchange { fail_orders: true, ..MockExchange::new(1000.0, 1000.0) };
+        let (journal, backend) = (MemoryJournal::default(), RecordingNotifier::default());
+        let notifier = NotifierHub::with_backends(vec![Box::new(backend.clone())], NotifyConfig::default());
+        let cfg = retry();
+        let deps = OrderDeps { exchange: &exchange, journal: &journal, notifier: &notifier, retry: &cfg };
+
+        assert_eq!(close_position(&deps, &order()).await, CloseOutcome::Failed);
+        // 第 2 次失败时升级告警，第 3 次 (耗尽) 再通知一次
+        let sent = backend.messages();
+        assert_eq!(sent.len(), 2);
+        assert!(sent[0].contains("连续失败 2 次") && sent[1].contains("重试 3 次"));
+        assert!(journal.exits.lock().unwrap().is_empty());
+        let events = journal.events.lock().unwrap().clone();
+        assert_eq!((events.len(), events[0].payload["ok"].as_bool()), (1, Some(false)));
+    }
+
+    #[tokio::test]
+    async fn flatten_all_closes_every_open_position() {
+        let position = |symbol: &str, side: &str, size: f64| PositionSummary {
+            symbol: symbol.to_string(), size, upl: 0.0, side: side.to_string(), avg_px: 100.0,
+            mark_px: 100.0, leverage: 5, notional_usd: 100.0, margin_usd: 20.0,
+        };
+        let exchange = MockExchange::new(1000.0, 1000.0);
+        let positions = [position("BTC-USDT-SWAP", "long", 2.0), position(SYMBOL, "short", 3.0), position("SOL-USDT-SWAP", "long", 0.0)];
+        assert!(flatten_all(&exchange, &positions).await.is_empty());
+        let placed: Vec<(String, String, f64, bool)> = exchange.placed_orders().into_iter()
+            .map(|o| (o.symbol, o.side, o.size, o.reduce_only)).collect();
+        assert_eq!(placed, vec![
+            ("BTC-USDT-SWAP".to_string(), "sell".to_string(), 2.0, true),
+            (SYMBOL.to_string(), "buy".to_string(), 3.0, true),
+        ]);
+
+        let failing = MockExchange { fail_orders: true, ..MockExchange::new(1000.0, 1000.0) };
+        assert_eq!(flatten_all(&failing, &positions).await, vec!["BTC-USDT-SWAP long".to_string(), format!("{} short", SYMBOL)]);
+    }
+}
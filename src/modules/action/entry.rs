@@ -0,0 +1,219 @@
+// 文件名: entry.rs
+// 开仓下单：已通过全部风控、数量已确定的开仓在这里下单 (可重试错误按 [retry] 重试)，成交后落库并通知
+// 依赖全部通过 trait 注入，主循环使用 OKX / Postgres，测试使用 MockExchange / MemoryJournal
+
+use serde_json::json;
+use tokio::time::sleep;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use super::event_log::EventKind;
+use super::exchange::Exchange;
+use super::journal::TradeJournal;
+use super::snapshot::TradeRecord;
+use crate::config::risk_profile::{RetryConfig, TpRung};
+use crate::error::{self, TraderError};
+use crate::modules::brain::llm::AiDecision;
+use crate::modules::perception::MarketState;
+use crate::utils::notifier::{NotifierHub, Priority};
+
+/// 开仓/平仓下单所需的外部依赖
+pub struct OrderDeps<'a> {
+    pub exchange: &'a dyn Exchange,
+    pub journal: &'a dyn TradeJournal,
+    pub notifier: &'a NotifierHub,
+    pub retry: &'a RetryConfig,
+}
+
+/// 一笔已通过风控的开仓/加仓
+pub struct EntryOrder<'a> {
+    /// 与决策事件共用的 correlation_id
+    pub correlation_id: Uuid,
+    pub symbol: &'a str,
+    /// buy / sell
+    pub side: &'a str,
+    /// long / short
+    pub pos_side: &'a str,
+    pub qty: f64,
+    pub state: &'a MarketState,
+    pub decision: &'a AiDecision,
+    /// 新开仓为成交参考价，加仓时为加权后的持仓均价
+    pub entry_price: f64,
+    pub tp_ladder: &'a [TpRung],
+    /// 最优买卖价 (maker 挂单使用)
+    pub quote: Option<(f64, f64)>,
+    /// 通知中显示的动作 (加仓时带 "(ADD)")
+    pub signal_label: &'a str,
+}
+
+/// 开仓结果 (决定是否计入连续下单失败熔断)
+#[derive(Debug, PartialEq)]
+pub enum EntryOutcome {
+    /// 成交，携带订单号
+    Filled(String),
+    /// 风控主动拒绝，不计入熔断
+    Rejected,
+    /// 下单失败 (不可重试的业务错误或重试耗尽)，计入熔断
+    Failed,
+}
+
+/// 以开仓价计算的止盈/止损触发价
+pub fn tpsl_prices(pos_side: &str, price: f64, tp_pct: f64, sl_pct: f64) -> (f64, f64) {
+    if pos_side == "long" {
+        (price * (1.0 + tp_pct), price * (1.0 - sl_pct))
+    } else {
+        (price * (1.0 - tp_pct), price * (1.0 + sl_pct))
+    }
+}
+
+/// 下单并在成交后记录 TP/SL 价位、交易日志、事件流水并发送交易通知
+pub async fn place_entry(deps: &OrderDeps<'_>, order: &EntryOrder<'_>) -> EntryOutcome {
+    let (symbol, side, pos_side, qty) = (order.symbol, order.side, order.pos_side, order.qty);
+    let (state, decision) = (order.state, order.decision);
+    let attempts = deps.retry.open_attempts;
+    for attempt in 1..=attempts {
+        match deps.exchange.execute_entry(symbol, side, pos_side, qty, state.price, decision.tp_pct, decision.sl_pct, order.tp_ladder, Some(decision.leverage), order.quote).await {
+            Ok(res) => {
+                info!("✅ [{}] Order Sent: {}", symbol, res.order_id);
+                // maker 部分成交时按实际成交数量记录
+                let qty = res.filled_sz.unwrap_or(qty);
+                if qty < order.qty {
+                    warn!("⚠️ [{}] Entry partially filled: {} of {}", symbol, qty, order.qty);
+                }
+                if let Some(reason) = &res.unprotected {
+                    let msg = format!("🚨 [{}] {} 已开仓但止盈止损未挂上 (下一轮自动补挂): {}", symbol, pos_side, reason);
+                    deps.notifier.send_text(&msg, Priority::Critical).await;
+                }
+                deps.journal.append_event(order.correlation_id, symbol, EventKind::Entry, Some(&res.order_id), json!({
+                    "side": side, "pos_side": pos_side, "qty": qty, "price": state.price, "attempt": attempt, "ok": true,
+                })).await;
+                let face_val = deps.exchange.instrument_meta(symbol).await.map(|m| m.face_value).unwrap_or(0.0);
+                let initial_margin = (qty * state.price * face_val) / (decision.leverage as f64);
+                let (tp_price, sl_price) = tpsl_prices(pos_side, state.price, decision.tp_pct, decision.sl_pct);
+                if let Err(e) = deps.journal.record_entry(symbol, pos_side, state.price, qty, tp_price, sl_price).await {
+                    warn!("Failed to store TP/SL levels for {}: {}", symbol, e);
+                }
+                let _ = deps.journal.log_trade(&TradeRecord {
+                    symbol, direction: side, state, order_id: &res.order_id,
+                    initial_margin, entry_price: order.entry_price, tp_price, sl_price,
+                    reasoning: &decision.reasoning,
+                    strategy_version: &decision.strategy_version,
+                }).await;
+                deps.notifier.send_trade_signal(
+                    symbol, order.signal_label, qty, state.price,
+                    &decision.reason, decision.tp_pct, decision.sl_pct, Priority::Normal
+                ).await;
+                return EntryOutcome::Filled(res.order_id);
+            }
+            Err(e) => {
+                // 余额不足、参数错误等业务错误重试无意义，直接放弃
+                if !error::is_retryable(&e) {
+                    warn!("❌ [{}] Order Rejected (non-retryable): {}", symbol, e);
+                    let msg = if matches!(TraderError::classify(&e), Some(TraderError::Uncertain(_))) {
+                        format!("⚠️ [{}] {} 开仓状态未知 (订单可能已部分成交)，不再重试，请人工核对持仓: {}", symbol, side, e)
+                    } else {
+                        format!("❌ [{}] {} 开仓被拒: {}", symbol, side, e)
+                    };
+                    deps.notifier.send_text(&msg, Priority::Critical).await;
+                    deps.journal.append_event(order.correlation_id, symbol, EventKind::Entry, None, json!({
+                        "side": side, "pos_side": pos_side, "qty": qty, "attempt": attempt, "ok": false, "error": e.to_string(),
+                    })).await;
+                    return if matches!(TraderError::classify(&e), Some(TraderError::Rejected(_))) { EntryOutcome::Rejected } else { EntryOutcome::Failed };
+                }
+                warn!("❌ [{}] Order Failed (Attempt {}/{}): {}. Retrying in {}ms...", symbol, attempt, attempts, e, deps.retry.open_delay_ms);
+                if attempt == attempts {
+                    deps.notifier.send_text(&format!("❌ [{}] {} 开仓失败 (重试 {} 次): {}", symbol, side, attempts, e), Priority::Critical).await;
+                    deps.journal.append_event(order.correlation_id, symbol, EventKind::Entry, None, json!({
+                        "side": side, "pos_side": pos_side, "qty": qty, "attempt": attempt, "ok": false, "error": e.to_string(),
+                    })).await;
+                    return EntryOutcome::Failed;
+                }
+                sleep(deps.retry.open_delay()).await;
+            }
+        }
+    }
+    EntryOutcome::Failed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::risk_profile::{NotifyConfig, RiskProfile};
+    use crate::modules::action::exchange::mock::MockExchange;
+    use crate::modules::action::journal::mock::MemoryJournal;
+    use crate::modules::brain::llm::TradeAction;
+    use crate::modules::brain::llm::mock::decision;
+    use crate::modules::perception::fetcher::mock::market_state as mock_market_state;
+    use crate::utils::notifier::mock::RecordingNotifier;
+
+    const SYMBOL: &str = "BTC-USDT-SWAP";
+
+    fn market_state(price: f64, reddit_sentiment: String, news_sentiment: String) -> MarketState {
+        MarketState { reddit_sentiment, news_sentiment, ..mock_market_state(SYMBOL, price) }
+    }
+
+    /// 代替 DecisionMaker (LLM) 的固定决策
+    fn stub_decision() -> AiDecision {
+        decision(TradeAction::Buy)
+    }
+
+    fn order_deps_fixture() -> (RiskProfile, MarketState, AiDecision) {
+        let mut risk = RiskProfile::for_tests();
+        risk.retry.open_attempts = 3;
+        risk.retry.open_delay_ms = 0;
+        (risk, market_state(50_000.0, String::new(), String::new()), stub_decision())
+    }
+
+    #[tokio::test]
+    async fn uncertain_maker_entry_is_never_resent() {
+        let (risk, state, decision) = order_deps_fixture();
+        let exchange = MockExchange { uncertain_entries: true, ..MockExchange::new(10_000.0, 10_000.0) }.with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+        let journal = MemoryJournal::default();
+        let backend = RecordingNotifier::default();
+        let notifier = NotifierHub::with_backends(vec![Box::new(backend.clone())], NotifyConfig::default());
+        let deps = OrderDeps { exchange: &exchange, journal: &journal, notifier: &notifier, retry: &risk.retry };
+        let outcome = place_entry(&deps, &EntryOrder {
+            correlation_id: Uuid::new_v4(), symbol: SYMBOL, side: "buy", pos_side: "long", qty: 4.0,
+            state: &state, decision: &decision, entry_price: state.price,
+            tp_ladder: &[], quote: None, signal_label: "buy",
+        }).await;
+        assert_eq!(outcome, EntryOutcome::Failed);
+        assert_eq!(*exchange.entry_attempts.lock().unwrap(), 1);
+        assert!(backend.messages()[0].contains("状态未知"));
+    }
+
+    #[tokio::test]
+    async fn partial_fill_records_filled_quantity() {
+        let (risk, state, decision) = order_deps_fixture();
+        let exchange = MockExchange { partial_fill: Some(1.5), ..MockExchange::new(10_000.0, 10_000.0) }.with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+        let journal = MemoryJournal::default();
+        let notifier = NotifierHub::with_backends(vec![Box::new(RecordingNotifier::default())], NotifyConfig::default());
+        let deps = OrderDeps { exchange: &exchange, journal: &journal, notifier: &notifier, retry: &risk.retry };
+        let outcome = place_entry(&deps, &EntryOrder {
+            correlation_id: Uuid::new_v4(), symbol: SYMBOL, side: "buy", pos_side: "long", qty: 4.0,
+            state: &state, decision: &decision, entry_price: state.price,
+            tp_ladder: &[], quote: None, signal_label: "buy",
+        }).await;
+        assert_eq!(outcome, EntryOutcome::Filled("mock-1".to_string()));
+        assert_eq!(journal.entries.lock().unwrap().clone(), vec![(SYMBOL.to_string(), "long".to_string(), 1.5)]);
+    }
+
+    #[tokio::test]
+    async fn unprotected_fill_raises_an_alert() {
+        let (risk, state, decision) = order_deps_fixture();
+        let exchange = MockExchange { unprotected_entries: true, ..MockExchange::new(10_000.0, 10_000.0) }.with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+        let journal = MemoryJournal::default();
+        let backend = RecordingNotifier::default();
+        let notifier = NotifierHub::with_backends(vec![Box::new(backend.clone())], NotifyConfig::default());
+        let deps = OrderDeps { exchange: &exchange, journal: &journal, notifier: &notifier, retry: &risk.retry };
+        let outcome = place_entry(&deps, &EntryOrder {
+            correlation_id: Uuid::new_v4(), symbol: SYMBOL, side: "buy", pos_side: "long", qty: 4.0,
+            state: &state, decision: &decision, entry_price: state.price,
+            tp_ladder: &[], quote: None, signal_label: "buy",
+        }).await;
+        // 已成交的仓位照常记账，只额外告警
+        assert_eq!(outcome, EntryOutcome::Filled("mock-1".to_string()));
+        assert_eq!(journal.entries.lock().unwrap().len(), 1);
+        assert!(backend.messages().iter().any(|m| m.contains("止盈止损未挂上") && m.contains("mock OCO rejected")));
+    }
+}
@@ -0,0 +1,116 @@
+// 文件名: event_log.rs
+// 追加写入的事件流水：决策 -> 下单 -> 实现盈亏 通过 correlation_id 串联，用于回放任意一笔交易的完整时间线
+// 与 trade_logs (只记录开仓) 不同，这里记录每一次决策及其结果，写入失败只告警不影响交易
+
+use anyhow::Result;
+use serde_json::Value;
+use sqlx::{PgPool, Row};
+use tracing::warn;
+use uuid::Uuid;
+
+/// 事件类型 (event_log.kind)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// 大脑给出的决策 (已经过胜率下限与动作白名单过滤)
+    Decision,
+    /// 开仓/加仓订单结果
+    Entry,
+    /// 平仓订单结果
+    Close,
+    /// 账单回填的实现盈亏
+    RealizedPnl,
+}
+
+impl EventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EventKind::Decision => "decision",
+            EventKind::Entry => "entry",
+            EventKind::Close => "close",
+            EventKind::RealizedPnl => "realized_pnl",
+        }
+    }
+}
+
+/// 时间线上的一条事件 (CLI timeline 命令使用)
+#[derive(Debug, Clone)]
+pub struct EventRecord {
+    pub ts: String,
+    pub correlation_id: Option<Uuid>,
+    pub symbol: String,
+    pub kind: String,
+    pub order_id: Option<String>,
+    pub payload: Value,
+}
+
+#[derive(Clone)]
+pub struct EventLog {
+    pool: PgPool,
+}
+
+impl EventLog {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// 追加一条事件；失败只记录告警 (事件流水不能阻塞下单)
+    pub async fn append(&self, correlation_id: Uuid, symbol: &str, kind: EventKind, order_id: Option<&str>, payload: Value) {
+        let res = sqlx::query(
+            "INSERT INTO event_log (correlation_id, symbol, kind, order_id, payload) VALUES ($1, $2, $3, $4, $5)"
+        )
+        .bind(correlation_id)
+        .bind(symbol)
+        .bind(kind.as_str())
+        .bind(order_id)
+        .bind(payload)
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = res {
+            warn!("📜 [{}] Failed to append {} event: {}", symbol, kind.as_str(), e);
+        }
+    }
+
+    /// 实现盈亏事件：沿用该订单开仓事件的 correlation_id (早于事件流水的订单为 NULL)
+    pub async fn append_realized_pnl(&self, symbol: &str, order_id: &str, payload: Value) {
+        let res = sqlx::query(
+            "INSERT INTO event_log (correlation_id, symbol, kind, order_id, payload)
+             VALUES ((SELECT correlation_id FROM event_log WHERE order_id = $3 AND kind = $4 ORDER BY id LIMIT 1), $1, $2, $3, $5)"
+        )
+        .bind(symbol)
+        .bind(EventKind::RealizedPnl.as_str())
+        .bind(order_id)
+        .bind(EventKind::Entry.as_str())
+        .bind(payload)
+        .execute(&self.pool)
+        .await;
+        if let Err(e) = res {
+            warn!("📜 [{}] Failed to append realized_pnl event for order {}: {}", symbol, order_id, e);
+        }
+    }
+
+    /// 按订单号或 correlation_id 重建完整时间线 (按写入顺序)
+    pub async fn timeline(&self, id: &str) -> Result<Vec<EventRecord>> {
+        let rows = sqlx::query(
+            "SELECT created_at::TEXT AS ts, correlation_id, symbol, kind, order_id, payload
+             FROM event_log
+             WHERE correlation_id IN (
+                 SELECT correlation_id FROM event_log
+                 WHERE order_id = $1 OR correlation_id::TEXT = $1
+             )
+             OR (order_id = $1 AND correlation_id IS NULL)
+             ORDER BY id"
+        )
+        .bind(id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(|row| Ok(EventRecord {
+            ts: row.try_get("ts")?,
+            correlation_id: row.try_get("correlation_id")?,
+            symbol: row.try_get("symbol")?,
+            kind: row.try_get("kind")?,
+            order_id: row.try_get("order_id")?,
+            payload: row.try_get("payload")?,
+        })).collect()
+    }
+}
@@ -0,0 +1,395 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::config::risk_profile::TpRung;
+use super::executor::{AccountSnapshot, BalanceSummary, CancelledLimit, InstrumentMeta, OrderResult, PositionSummary, ProtectionRetry, TradeExecutor};
+
+/// 交易所抽象：主循环的下单、仓位计算与持仓维护只依赖此 trait，便于用 MockExchange 离线测试
+#[async_trait]
+pub trait Exchange: Send + Sync {
+    async fn fetch_account_summary(&self) -> Result<BalanceSummary>;
+
+    async fn fetch_positions(&self) -> Result<Vec<PositionSummary>>;
+
+    /// 余额与持仓一起获取 (默认并发调用上面两个接口)
+    async fn fetch_account_snapshot(&self) -> Result<AccountSnapshot> {
+        let (balance, positions) = tokio::join!(self.fetch_account_summary(), self.fetch_positions());
+        Ok(AccountSnapshot { balance: balance?, positions: positions? })
+    }
+
+    async fn instrument_meta(&self, symbol: &str) -> Option<InstrumentMeta>;
+
+    /// 普通市价单 (平仓等)，reduce_only = true 时保证只减仓不反向开仓
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_order(
+        &self,
+        symbol: &str,
+        side: &str,
+        pos_side: &str,
+        size: f64,
+        current_price: f64,
+        tp_pct: f64,
+        sl_pct: f64,
+        leverage: Option<u32>,
+        reduce_only: bool
+    ) -> Result<OrderResult>;
+
+    /// 开仓 (按配置选择市价或 maker 优先)
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_entry(
+        &self,
+        symbol: &str,
+        side: &str,
+        pos_side: &str,
+        size: f64,
+        current_price: f64,
+        tp_pct: f64,
+        sl_pct: f64,
+        tp_ladder: &[TpRung],
+        leverage: Option<u32>,
+        quote: Option<(f64, f64)>
+    ) -> Result<OrderResult>;
+
+    /// 正在进行的系统维护 (标题)，没有时返回 None
+    async fn fetch_active_maintenance(&self) -> Result<Option<String>>;
+
+    /// 当前最优买一/卖一
+    async fn fetch_best_quote(&self, symbol: &str) -> Result<(f64, f64)>;
+
+    /// 干跑模拟账本
+    fn is_paper(&self) -> bool;
+
+    /// 合约信息缓存中缺失的币种
+    async fn missing_instruments(&self, symbols: &[String]) -> Vec<String>;
+
+    /// 单独刷新一个币种的合约信息，返回是否找到
+    async fn refresh_instrument(&self, symbol: &str) -> Result<bool>;
+
+    async fn load_position_tiers(&self, symbols: &[String]);
+
+    // 以下调用会修改账户上的挂单 (影子模式下主循环不调用)
+    /// 移动交易所上的止损
+    async fn amend_stop(&self, symbol: &str, pos_side: &str, new_sl_price: f64) -> Result<()>;
+
+    /// 持仓数量变化后按新数量重挂 TP/SL
+    async fn resize_protection(&self, positions: &[PositionSummary]) -> Result<()>;
+
+    /// 撤掉超时未成交的限价挂单
+    async fn reconcile_pending_orders(&self) -> Result<Vec<CancelledLimit>>;
+
+    /// 补挂入场后未能挂上的 TP/SL
+    async fn retry_unprotected(&self, positions: &[PositionSummary]) -> Vec<ProtectionRetry>;
+
+    /// 清理已消失持仓的阶梯止盈、算法单与待补挂记录
+    async fn prune_protection(&self, positions: &[PositionSummary]);
+
+    /// 阶梯止盈第一档成交后返回入场价 (每个持仓只返回一次)
+    async fn ladder_first_tp_filled(&self, symbol: &str, pos_side: &str, current_size: f64) -> Option<f64>;
+}
+
+#[async_trait]
+impl Exchange for TradeExecutor {
+    async fn fetch_account_summary(&self) -> Result<BalanceSummary> {
+        TradeExecutor::fetch_account_summary(self).await
+    }
+
+    async fn fetch_positions(&self) -> Result<Vec<PositionSummary>> {
+        TradeExecutor::fetch_positions(self).await
+    }
+
+    async fn fetch_account_snapshot(&self) -> Result<AccountSnapshot> {
+        TradeExecutor::fetch_account_snapshot(self).await
+    }
+
+    async fn instrument_meta(&self, symbol: &str) -> Option<InstrumentMeta> {
+        self.get_instrument_meta(symbol).await
+    }
+
+    async fn execute_order(
+        &self,
+        symbol: &str,
+        side: &str,
+        pos_side: &str,
+        size: f64,
+        current_price: f64,
+        tp_pct: f64,
+        sl_pct: f64,
+        leverage: Option<u32>,
+        reduce_only: bool
+    ) -> Result<OrderResult> {
+        TradeExecutor::execute_order(self, symbol, side, pos_side, size, current_price, tp_pct, sl_pct, leverage, reduce_only).await
+    }
+
+    async fn execute_entry(
+        &self,
+        symbol: &str,
+        side: &str,
+        pos_side: &str,
+        size: f64,
+        current_price: f64,
+        tp_pct: f64,
+        sl_pct: f64,
+        tp_ladder: &[TpRung],
+        leverage: Option<u32>,
+        quote: Option<(f64, f64)>
+    ) -> Result<OrderResult> {
+        TradeExecutor::execute_entry(self, symbol, side, pos_side, size, current_price, tp_pct, sl_pct, tp_ladder, leverage, quote).await
+    }
+
+    async fn fetch_active_maintenance(&self) -> Result<Option<String>> {
+        TradeExecutor::fetch_active_maintenance(self).await
+    }
+
+    async fn fetch_best_quote(&self, symbol: &str) -> Result<(f64, f64)> {
+        TradeExecutor::fetch_best_quote(self, symbol).await
+    }
+
+    fn is_paper(&self) -> bool {
+        TradeExecutor::is_paper(self)
+    }
+
+    async fn missing_instruments(&self, symbols: &[String]) -> Vec<String> {
+        TradeExecutor::missing_instruments(self, symbols).await
+    }
+
+    async fn refresh_instrument(&self, symbol: &str) -> Result<bool> {
+        TradeExecutor::refresh_instrument(self, symbol).await
+    }
+
+    async fn load_position_tiers(&self, symbols: &[String]) {
+        TradeExecutor::load_position_tiers(self, symbols).await
+    }
+
+    async fn amend_stop(&self, symbol: &str, pos_side: &str, new_sl_price: f64) -> Result<()> {
+        TradeExecutor::amend_stop(self, symbol, pos_side, new_sl_price).await
+    }
+
+    async fn resize_protection(&self, positions: &[PositionSummary]) -> Result<()> {
+        TradeExecutor::resize_protection(self, positions).await
+    }
+
+    async fn reconcile_pending_orders(&self) -> Result<Vec<CancelledLimit>> {
+        TradeExecutor::reconcile_pending_orders(self).await
+    }
+
+    async fn retry_unprotected(&self, positions: &[PositionSummary]) -> Vec<ProtectionRetry> {
+        TradeExecutor::retry_unprotected(self, positions).await
+    }
+
+    async fn prune_protection(&self, positions: &[PositionSummary]) {
+        TradeExecutor::prune_protection(self, positions).await
+    }
+
+    async fn ladder_first_tp_filled(&self, symbol: &str, pos_side: &str, current_size: f64) -> Option<f64> {
+        TradeExecutor::ladder_first_tp_filled(self, symbol, pos_side, current_size).await
+    }
+}
+
+/// 测试用交易所：返回预设的余额/持仓/合约信息，并记录所有下单请求
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use anyhow::anyhow;
+    use crate::error::TraderError;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone)]
+    pub struct PlacedOrder {
+        pub symbol: String,
+        pub side: String,
+        pub pos_side: String,
+        pub size: f64,
+        pub leverage: Option<u32>,
+        pub reduce_only: bool,
+    }
+
+    #[derive(Default)]
+    pub struct MockExchange {
+        pub total_equity: f64,
+        pub available_balance: f64,
+        pub maintenance_margin: f64,
+        pub positions: Vec<PositionSummary>,
+        pub instruments: HashMap<String, InstrumentMeta>,
+        /// 为 true 时所有下单返回错误
+        pub fail_orders: bool,
+        /// 为 true 时开仓返回 "状态未知" 错误 (模拟 maker 挂单查询失败)
+        pub uncertain_entries: bool,
+        /// 开仓只成交该数量 (模拟 maker 部分成交)
+        pub partial_fill: Option<f64>,
+        /// 开仓成交但保护单未挂上 (模拟 OCO 挂单失败)
+        pub unprotected_entries: bool,
+        pub entry_attempts: Mutex<u32>,
+        pub placed: Mutex<Vec<PlacedOrder>>,
+        /// 下单之外修改账户挂单的调用 (改止损/重挂 TP/SL/撤单)
+        pub amendments: Mutex<Vec<String>>,
+    }
+
+    impl MockExchange {
+        pub fn new(total_equity: f64, available_balance: f64) -> Self {
+            Self { total_equity, available_balance, ..Default::default() }
+        }
+
+        pub fn with_instrument(mut self, symbol: &str, face_value: f64, min_sz: f64, lot_sz: f64) -> Self {
+            self.instruments.insert(symbol.to_string(), InstrumentMeta {
+                face_value,
+                tick_size: 0.1,
+                min_sz,
+                lot_sz,
+                max_mkt_sz: 0.0,
+                max_lmt_sz: 0.0,
+                inst_family: String::new(),
+                position_tiers: Vec::new(),
+            });
+            self
+        }
+
+        pub fn placed_orders(&self) -> Vec<PlacedOrder> {
+            self.placed.lock().unwrap().clone()
+        }
+
+        pub fn amendments(&self) -> Vec<String> {
+            self.amendments.lock().unwrap().clone()
+        }
+
+        fn record(&self, symbol: &str, side: &str, pos_side: &str, size: f64, leverage: Option<u32>, reduce_only: bool) -> Result<OrderResult> {
+            if self.fail_orders {
+                return Err(anyhow!("mock order rejected"));
+            }
+            let mut placed = self.placed.lock().unwrap();
+            placed.push(PlacedOrder {
+                symbol: symbol.to_string(),
+                side: side.to_string(),
+                pos_side: pos_side.to_string(),
+                size,
+                leverage,
+                reduce_only,
+            });
+            Ok(OrderResult { order_id: format!("mock-{}", placed.len()), response: "ok".to_string(), filled_sz: None, unprotected: None })
+        }
+    }
+
+    #[async_trait]
+    impl Exchange for MockExchange {
+        async fn fetch_account_summary(&self) -> Result<BalanceSummary> {
+            Ok(BalanceSummary { total_equity: self.total_equity, available_balance: self.available_balance, maintenance_margin: self.maintenance_margin })
+        }
+
+        async fn fetch_positions(&self) -> Result<Vec<PositionSummary>> {
+            Ok(self.positions.clone())
+        }
+
+        async fn instrument_meta(&self, symbol: &str) -> Option<InstrumentMeta> {
+            self.instruments.get(symbol).cloned()
+        }
+
+        async fn execute_order(
+            &self,
+            symbol: &str,
+            side: &str,
+            pos_side: &str,
+            size: f64,
+            _current_price: f64,
+            _tp_pct: f64,
+            _sl_pct: f64,
+            leverage: Option<u32>,
+            reduce_only: bool
+        ) -> Result<OrderResult> {
+            self.record(symbol, side, pos_side, size, leverage, reduce_only)
+        }
+
+        async fn execute_entry(
+            &self,
+            symbol: &str,
+            side: &str,
+            pos_side: &str,
+            size: f64,
+            _current_price: f64,
+            _tp_pct: f64,
+            _sl_pct: f64,
+            _tp_ladder: &[TpRung],
+            leverage: Option<u32>,
+            _quote: Option<(f64, f64)>
+        ) -> Result<OrderResult> {
+            *self.entry_attempts.lock().unwrap() += 1;
+            if self.uncertain_entries {
+                return Err(TraderError::Uncertain("mock maker order state unknown".to_string()).into());
+            }
+            let res = self.record(symbol, side, pos_side, self.partial_fill.unwrap_or(size), leverage, false)?;
+            let unprotected = self.unprotected_entries.then(|| "mock OCO rejected".to_string());
+            Ok(OrderResult { filled_sz: self.partial_fill, unprotected, ..res })
+        }
+
+        async fn fetch_active_maintenance(&self) -> Result<Option<String>> {
+            Ok(None)
+        }
+
+        async fn fetch_best_quote(&self, _symbol: &str) -> Result<(f64, f64)> {
+            Err(anyhow!("mock exchange has no order book"))
+        }
+
+        fn is_paper(&self) -> bool {
+            false
+        }
+
+        async fn missing_instruments(&self, symbols: &[String]) -> Vec<String> {
+            symbols.iter().filter(|s| !self.instruments.contains_key(s.as_str())).cloned().collect()
+        }
+
+        async fn refresh_instrument(&self, symbol: &str) -> Result<bool> {
+            Ok(self.instruments.contains_key(symbol))
+        }
+
+        async fn load_position_tiers(&self, _symbols: &[String]) {}
+
+        async fn amend_stop(&self, symbol: &str, pos_side: &str, new_sl_price: f64) -> Result<()> {
+            self.amendments.lock().unwrap().push(format!("amend_stop {} {} {}", symbol, pos_side, new_sl_price));
+            Ok(())
+        }
+
+        async fn resize_protection(&self, _positions: &[PositionSummary]) -> Result<()> {
+            self.amendments.lock().unwrap().push("resize_protection".to_string());
+            Ok(())
+        }
+
+        async fn reconcile_pending_orders(&self) -> Result<Vec<CancelledLimit>> {
+            self.amendments.lock().unwrap().push("reconcile_pending_orders".to_string());
+            Ok(Vec::new())
+        }
+
+        async fn retry_unprotected(&self, _positions: &[PositionSummary]) -> Vec<ProtectionRetry> {
+            self.amendments.lock().unwrap().push("retry_unprotected".to_string());
+            Vec::new()
+        }
+
+        async fn prune_protection(&self, _positions: &[PositionSummary]) {}
+
+        async fn ladder_first_tp_filled(&self, _symbol: &str, _pos_side: &str, _current_size: f64) -> Option<f64> {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockExchange;
+    use super::Exchange;
+
+    #[tokio::test]
+    async fn mock_records_orders_and_scripted_failures() {
+        let ex = MockExchange::new(1000.0, 1000.0);
+        let res = ex.execute_entry("ETH-USDT-SWAP", "buy", "long", 2.0, 3000.0, 0.04, 0.02, &[], Some(5), None).await.unwrap();
+        assert_eq!(res.order_id, "mock-1");
+
+        let placed = ex.placed_orders();
+        assert_eq!(placed.len(), 1);
+        assert_eq!(placed[0].symbol, "ETH-USDT-SWAP");
+        assert_eq!((placed[0].side.as_str(), placed[0].pos_side.as_str()), ("buy", "long"));
+        assert_eq!((placed[0].size, placed[0].leverage), (2.0, Some(5)));
+        assert!(!placed[0].reduce_only);
+
+        let failing = MockExchange { fail_orders: true, ..MockExchange::new(1000.0, 1000.0) };
+        assert!(failing.execute_order("ETH-USDT-SWAP", "sell", "long", 2.0, 3000.0, 0.0, 0.0, None, true).await.is_err());
+        assert!(failing.placed_orders().is_empty());
+    }
+}
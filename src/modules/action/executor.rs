@@ -6,11 +6,20 @@ use hmac::{Hmac, Mac};
 use sha2::Sha256;
 use base64::{Engine as _, engine::general_purpose};
 use serde_json::{json, Value};
-use tracing::{info, warn};
-use std::collections::HashMap;
+use tracing::{info, warn, error};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use tokio::sync::RwLock;
 use tokio::time::{sleep, Duration};
+use crate::config::risk_profile::{ExecutionConfig, EntryMode, PaperConfig, TpRung, TpSlMode, TradingMode};
+use crate::config::okx::{OkxEndpoints, SIMULATED_HEADER};
+use super::paper::PaperLedger;
+use crate::modules::risk::maintenance;
+use crate::error::TraderError;
+use crate::utils::money::{self, format_on_grid};
+use crate::utils::http_client::jittered;
 
 // ----------------------------------------------------------------------------
 // 数据结构定义
@@ -22,6 +31,8 @@ pub struct PositionSummary {
     pub size: f64,
     pub upl: f64,
     pub side: String,
+    pub avg_px: f64,       // 开仓均价
+    pub mark_px: f64,      // 标记价格
     // [新增] 满足通知需求的关键字段
     pub leverage: u32,
     pub notional_usd: f64, // 持仓名义价值
@@ -34,15 +45,58 @@ pub struct PnlRecord {
     pub symbol: String,
     pub pnl: f64,
     pub fee: f64,
+    /// 成交价
+    pub px: f64,
     pub ts: i64,
     pub type_name: String,
     pub ord_id: String,
+    /// 账单 ID，翻页游标
+    pub bill_id: String,
+    /// 余额变动 (资金费账单的金额在这里)
+    pub bal_chg: f64,
 }
 
+/// 历史成交明细 (来自 /api/v5/trade/fills-history，保留近 3 个月)
+#[derive(Debug, Clone)]
+pub struct HistoricalFill {
+    pub symbol: String,
+    /// buy / sell
+    pub side: String,
+    /// long / short (双向持仓) 或 net (单向持仓)
+    pub pos_side: String,
+    pub px: f64,
+    /// 成交张数
+    pub sz: f64,
+    /// 平仓收益 (开仓成交为 0)
+    pub pnl: f64,
+    /// 手续费 (扣费为负)
+    pub fee: f64,
+    pub ts: i64,
+    pub ord_id: String,
+    pub bill_id: String,
+}
+
+/// 成交账单类型
+pub const BILL_TYPE_TRADE: &str = "2";
+/// 资金费账单类型
+pub const BILL_TYPE_FUNDING: &str = "8";
+/// 入场后挂 OCO 保护单的尝试次数，仍失败则留到下一轮补挂
+const PROTECTION_ATTEMPTS: u32 = 3;
+/// 账单接口单页上限
+const BILLS_PAGE_LIMIT: usize = 100;
+/// 每次同步最多翻的页数 (账单接口只保留近 7 天，繁忙时也足够覆盖)
+const MAX_BILL_PAGES: usize = 10;
+/// 历史成交回放最多翻的页数 (每页 100 条)
+const MAX_FILL_PAGES: usize = 50;
+
 #[allow(dead_code)]
 pub struct OrderResult {
     pub order_id: String,
     pub response: String,
+    /// 实际成交数量 (maker 部分成交时小于请求数量)，None = 按请求数量
+    pub filled_sz: Option<f64>,
+    /// 成交后未能挂上止盈止损的原因 (已登记，下一轮主循环补挂)
+    pub unprotected: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -51,11 +105,320 @@ pub struct InstrumentMeta {
     pub tick_size: f64,  
     pub min_sz: f64,     
     pub lot_sz: f64,     
+    /// 单笔市价单/限价单最大张数 (maxMktSz / maxLmtSz)，0 = 未知
+    pub max_mkt_sz: f64,
+    pub max_lmt_sz: f64,
+    /// 合约品种族 (如 BTC-USDT)，查询阶梯持仓上限使用
+    pub inst_family: String,
+    /// 阶梯持仓上限 (/api/v5/public/position-tiers)，未加载时为空
+    pub position_tiers: Vec<PositionTier>,
+}
+
+/// 一档持仓上限：杠杆不超过 max_lever 时，持仓最多 max_sz 张
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionTier {
+    pub max_lever: f64,
+    pub max_sz: f64,
+}
+
+impl InstrumentMeta {
+    /// 以 leverage 开仓时交易所允许的最大张数：单笔下单上限 (市价/限价取较小者) 与该杠杆可用的最高档持仓上限取较小值
+    /// 均未知时返回 None；持仓上限不扣除已有持仓
+    pub fn max_contracts(&self, leverage: u32) -> Option<f64> {
+        let order_max = [self.max_mkt_sz, self.max_lmt_sz].into_iter().filter(|v| *v > 0.0).reduce(f64::min);
+        let tier_max = self.position_tiers.iter()
+            .filter(|t| t.max_lever >= f64::from(leverage.max(1)) && t.max_sz > 0.0)
+            .map(|t| t.max_sz)
+            .reduce(f64::max);
+        match (order_max, tier_max) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+/// 订单状态 (来自 /api/v5/trade/order)
+#[derive(Debug, Clone)]
+pub struct OrderStatus {
+    pub state: String,   // live / partially_filled / filled / canceled
+    pub filled_sz: f64,
+    pub avg_px: f64,
+}
+
+/// 阶梯止盈的挂单状态 (key: "symbol:posSide")
+#[derive(Debug, Clone)]
+struct LadderState {
+    /// 挂阶梯时的持仓数量，低于该值说明至少一档止盈已成交
+    initial_size: f64,
+    entry_px: f64,
+    tp_algo_ids: Vec<String>,
+    breakeven_done: bool,
+}
+
+/// 本程序挂出的 OCO / 止损算法单 (key: "symbol:posSide")
+#[derive(Debug, Clone)]
+struct TrackedAlgo {
+    algo_id: String,
+    /// 挂单时覆盖的持仓数量；按 closeFraction 全仓挂出时为 None (随持仓自动缩放)
+    size: Option<f64>,
+}
+
+/// 入场后保护单挂单失败的持仓，下一轮主循环按 OCO 补挂 (key: "symbol:posSide")
+#[derive(Debug, Clone)]
+struct UnprotectedPosition {
+    side: String,
+    tp_pct: f64,
+    sl_pct: f64,
+    attempts: u32,
+}
+
+/// 一次补挂保护单的结果
+#[derive(Debug, Clone)]
+pub struct ProtectionRetry {
+    pub symbol: String,
+    pub pos_side: String,
+    /// 含本次在内的补挂次数
+    pub attempts: u32,
+    /// None = 已挂上
+    pub error: Option<String>,
+}
+
+/// 挂单中的 TP/SL 算法单 (来自 orders-algo-pending)
+#[derive(Debug, Clone, PartialEq)]
+struct PendingBracket {
+    algo_id: String,
+    symbol: String,
+    pos_side: String,
+    /// closeFraction 全仓单为 None
+    size: Option<f64>,
+    tp_px: Option<String>,
+    sl_px: Option<String>,
+    created_ms: i64,
+    /// algoClOrdId (本程序挂出的带 BOT_CL_ORD_PREFIX 前缀)
+    cl_id: Option<String>,
+}
+
+/// 解析挂单中的算法单，只保留带 TP 或 SL 触发价的
+fn parse_pending_brackets(data: &Value) -> Vec<PendingBracket> {
+    let text = |v: &Value| v.as_str().filter(|s| !s.is_empty()).map(str::to_string);
+    data.as_array().into_iter().flatten().filter_map(|a| {
+        let (tp_px, sl_px) = (text(&a["tpTriggerPx"]), text(&a["slTriggerPx"]));
+        if tp_px.is_none() && sl_px.is_none() { return None; }
+        let size = if a["closeFraction"].as_str() == Some("1") { None } else { text(&a["sz"]).and_then(|s| s.parse().ok()) };
+        Some(PendingBracket {
+            algo_id: text(&a["algoId"])?,
+            symbol: text(&a["instId"])?,
+            pos_side: text(&a["posSide"]).unwrap_or_default(),
+            size,
+            tp_px,
+            sl_px,
+            created_ms: text(&a["cTime"]).and_then(|s| s.parse().ok()).unwrap_or(0),
+            cl_id: text(&a["algoClOrdId"]),
+        })
+    }).collect()
+}
+
+/// 某个持仓上属于本程序的保护单：本地跟踪的 algoId 或带本程序前缀的 algoClOrdId，
+/// 运营者手动挂的 TP/SL 既不合并也不撤销
+fn bot_brackets<'a>(pending: &'a [PendingBracket], symbol: &str, pos_side: &str, tracked_ids: &HashSet<String>) -> Vec<&'a PendingBracket> {
+    pending.iter()
+        .filter(|b| b.symbol == symbol && b.pos_side == pos_side)
+        .filter(|b| tracked_ids.contains(&b.algo_id) || is_bot_client_id(b.cl_id.as_deref()))
+        .collect()
+}
+
+/// 持仓数量变化后保护单应覆盖的新数量 (对齐到 lot_sz)：
+/// 只有一张且数量与持仓相差不足半个 lot 时返回 None；多张 (加仓附带的) 或数量不符时合并为一张覆盖整个持仓
+fn bracket_resize(protected: &[f64], position_size: f64, lot_sz: f64) -> Option<f64> {
+    if protected.is_empty() || position_size <= 0.0 { return None; }
+    let target = money::to_f64(money::snap_to_grid(money::dec(position_size), money::dec(lot_sz), true));
+    let covered = money::sum(protected.iter().copied());
+    let tolerance = if lot_sz > 0.0 { lot_sz / 2.0 } else { 1e-9 };
+    if protected.len() == 1 && (covered - target).abs() < tolerance { return None; }
+    (target > 0.0).then_some(target)
+}
+
+/// 本程序挂出的限价单 (key: ordId)，超时未成交时由 reconcile_pending_orders 撤单
+#[derive(Debug, Clone)]
+struct PendingLimit {
+    symbol: String,
+    side: String,
+    /// 挂单时间 (毫秒时间戳)
+    placed_at_ms: i64,
+}
+
+/// 超时撤单的结果 (用于通知)
+#[derive(Debug, Clone)]
+pub struct CancelledLimit {
+    pub symbol: String,
+    pub side: String,
+    pub order_id: String,
+    pub age_sec: i64,
+    /// 撤单前已成交的数量 (已成交部分保留为持仓)
+    pub filled_sz: f64,
+}
+
+/// 本程序挂出的限价单与 TP/SL 算法单的客户端订单号前缀：只认领、改挂、撤销带该前缀的挂单，
+/// 运营者手动挂的单永远不动
+pub const BOT_CL_ORD_PREFIX: &str = "rtbot";
+
+/// 生成客户端订单号：前缀 + 毫秒时间戳 + 序号 (OKX 要求字母开头、仅字母数字、最长 32 位)
+fn bot_client_id() -> String {
+    static SEQ: AtomicU32 = AtomicU32::new(0);
+    format!("{}{}{:03}", BOT_CL_ORD_PREFIX, Utc::now().timestamp_millis(), SEQ.fetch_add(1, Ordering::Relaxed) % 1000)
+}
+
+fn is_bot_client_id(id: Option<&str>) -> bool {
+    id.is_some_and(|id| id.starts_with(BOT_CL_ORD_PREFIX))
+}
+
+/// 按交易所的挂单列表同步本地跟踪的限价单：已结束的不再跟踪；
+/// 未跟踪的只认领带本程序前缀的开仓挂单 (重启前遗留)，手动挂单与 reduce-only 止盈单一律不碰
+fn sync_pending_limits(pending: &mut HashMap<String, PendingLimit>, live: &[Value], now_ms: i64) {
+    let live_ids: HashSet<&str> = live.iter().filter_map(|o| o["ordId"].as_str()).collect();
+    pending.retain(|id, _| live_ids.contains(id.as_str()));
+    for o in live {
+        let Some(id) = o["ordId"].as_str() else { continue };
+        if !is_bot_client_id(o["clOrdId"].as_str()) || o["reduceOnly"].as_str() == Some("true") {
+            continue;
+        }
+        pending.entry(id.to_string()).or_insert_with(|| PendingLimit {
+            symbol: o["instId"].as_str().unwrap_or_default().to_string(),
+            side: o["side"].as_str().unwrap_or_default().to_string(),
+            placed_at_ms: o["cTime"].as_str().and_then(|t| t.parse().ok()).unwrap_or(now_ms),
+        });
+    }
+}
+
+/// 挂单时间超过 timeout 的订单号 (按挂单时间排序)
+fn expired_limit_orders(pending: &HashMap<String, PendingLimit>, now_ms: i64, timeout_ms: i64) -> Vec<String> {
+    let mut expired: Vec<(&String, i64)> = pending.iter()
+        .filter(|(_, p)| now_ms - p.placed_at_ms >= timeout_ms)
+        .map(|(id, p)| (id, p.placed_at_ms))
+        .collect();
+    expired.sort_by_key(|(_, ts)| *ts);
+    expired.into_iter().map(|(id, _)| id.clone()).collect()
+}
+
+/// 永续下单带 posSide (long/short)，账户必须是双向持仓模式
+pub const REQUIRED_POS_MODE: &str = "long_short_mode";
+
+/// 启动时持仓模式检查的结论
+#[derive(Debug, PartialEq)]
+pub enum PosModeCheck {
+    Ok,
+    /// 模式不符且可以安全切换
+    Switch,
+    /// 模式不符但不能自动切换 (原因)
+    Mismatch(&'static str),
+}
+
+/// 持仓模式不符时：未开启 auto_fix 或账户有持仓 (切换会被拒，且不该在持仓时改变语义) 都只告警
+pub fn check_pos_mode(detected: &str, auto_fix: bool, open_positions: usize) -> PosModeCheck {
+    if detected == REQUIRED_POS_MODE {
+        PosModeCheck::Ok
+    } else if !auto_fix {
+        PosModeCheck::Mismatch("execution.auto_fix_pos_mode is disabled")
+    } else if open_positions > 0 {
+        PosModeCheck::Mismatch("positions are open, refusing to switch")
+    } else {
+        PosModeCheck::Switch
+    }
+}
+
+/// OKX: 有持仓/挂单/策略时无法调整杠杆
+const LEVERAGE_LOCKED_CODES: [&str; 2] = ["59000", "59107"];
+
+/// 每个币种最近一次成功设置的杠杆，相同时跳过 set-leverage 请求
+#[derive(Debug, Default)]
+struct LeverageCache {
+    levels: std::sync::Mutex<HashMap<String, u32>>,
+}
+
+impl LeverageCache {
+    fn is_current(&self, symbol: &str, leverage: u32) -> bool {
+        self.levels.lock().unwrap().get(symbol) == Some(&leverage)
+    }
+
+    fn record(&self, symbol: &str, leverage: u32) {
+        self.levels.lock().unwrap().insert(symbol.to_string(), leverage);
+    }
+
+    /// 设置失败后交易所上的实际杠杆未知，下次重新设置
+    fn invalidate(&self, symbol: &str) {
+        self.levels.lock().unwrap().remove(symbol);
+    }
+}
+
+/// /api/v5/trade/order 请求体参数
+struct OrderRequest<'a> {
+    symbol: &'a str,
+    td_mode: &'a str,
+    side: &'a str,
+    /// 双向持仓模式下必填，现货为 None
+    pos_side: Option<&'a str>,
+    sz: String,
+    /// Some 时为 post-only 限价单，否则市价单
+    px: Option<String>,
+    tgt_ccy: Option<&'a str>,
+    reduce_only: bool,
+    /// 附带 TP/SL 触发价 (tp, sl)
+    attach_tpsl: Option<(String, String)>,
+    /// 本程序的客户端订单号 (限价挂单带上，重启后据此认领)
+    cl_ord_id: Option<String>,
+}
+
+impl OrderRequest<'_> {
+    fn to_body(&self) -> Value {
+        let mut body = json!({
+            "instId": self.symbol,
+            "tdMode": self.td_mode,
+            "side": self.side,
+            "sz": self.sz,
+        });
+        if let Some(pos_side) = self.pos_side {
+            body["posSide"] = json!(pos_side);
+        }
+        match &self.px {
+            Some(px) => {
+                body["ordType"] = json!("post_only");
+                body["px"] = json!(px);
+            }
+            None => body["ordType"] = json!("market"),
+        }
+        if let Some(ccy) = self.tgt_ccy {
+            body["tgtCcy"] = json!(ccy);
+        }
+        if let Some(id) = &self.cl_ord_id {
+            body["clOrdId"] = json!(id);
+        }
+        if self.reduce_only {
+            body["reduceOnly"] = json!(true);
+        }
+        if let Some((tp, sl)) = &self.attach_tpsl {
+            body["attachAlgoOrds"] = json!([{
+                "attachAlgoClOrdId": bot_client_id(),
+                "tpTriggerPx": tp,
+                "tpOrdPx": "-1", 
+                "slTriggerPx": sl,
+                "slOrdPx": "-1"
+            }]);
+        }
+        body
+    }
 }
 
 pub struct BalanceSummary {
     pub total_equity: f64,
     pub available_balance: f64,
+    /// 全仓维持保证金 (现货/模拟账本为 0)
+    pub maintenance_margin: f64,
+}
+
+/// 同一时刻的账户快照 (余额 + 持仓)，每轮主循环只拉取一次，交易逻辑与报告共用
+pub struct AccountSnapshot {
+    pub balance: BalanceSummary,
+    pub positions: Vec<PositionSummary>,
 }
 
 pub struct TradeExecutor {
@@ -66,27 +429,109 @@ pub struct TradeExecutor {
     passphrase: String,
     is_simulated: bool,
     is_dry_run: bool,
+    exec_config: ExecutionConfig,
+    /// 保证金/计价币种 (USDT / USDC)
+    settle_ccy: String,
     
     instruments_cache: Arc<RwLock<HashMap<String, InstrumentMeta>>>,
+    // 由本程序下的 OCO / 止损算法单及其覆盖的数量 (key: "symbol:posSide")，持仓数量变化时据此重挂
+    algo_orders: Arc<RwLock<HashMap<String, TrackedAlgo>>>,
+    /// 干跑模式的模拟账本，余额与持仓从这里读取
+    paper: Option<Arc<RwLock<PaperLedger>>>,
+    leverage_cache: LeverageCache,
+    tp_ladders: Arc<RwLock<HashMap<String, LadderState>>>,
+    unprotected: Arc<RwLock<HashMap<String, UnprotectedPosition>>>,
+    pending_limits: Arc<RwLock<HashMap<String, PendingLimit>>>,
 }
 
 impl TradeExecutor {
-    pub fn new(client: Client) -> Self {
-        let is_sim = env::var("OKX_SIMULATED").unwrap_or("0".to_string()) == "1";
-        let is_dry = env::var("DRY_RUN").unwrap_or("0".to_string()) == "1";
+    pub fn new(client: Client, endpoints: &OkxEndpoints, exec_config: ExecutionConfig, settle_ccy: String, paper_config: PaperConfig) -> Self {
+        // 影子模式不访问账户：沿用干跑的模拟账本读取余额与持仓
+        let is_dry = env::var("DRY_RUN").unwrap_or("0".to_string()) == "1" || super::shadow::is_enabled();
+        let paper = is_dry.then(|| {
+            info!("🧪 [DRY RUN] Paper ledger enabled: starting equity {:.2} {}", paper_config.starting_equity, settle_ccy);
+            Arc::new(RwLock::new(PaperLedger::new(paper_config.starting_equity, paper_config.fee_rate)))
+        });
         
         Self {
             client,
-            base_url: env::var("OKX_BASE_URL").unwrap_or("https://www.okx.com".to_string()),
+            base_url: endpoints.rest_url.clone(),
             api_key: env::var("OKX_API_KEY").unwrap_or_default(),
             secret_key: env::var("OKX_SECRET_KEY").unwrap_or_default(),
             passphrase: env::var("OKX_PASSPHRASE").unwrap_or_default(),
-            is_simulated: is_sim,
+            is_simulated: endpoints.simulated,
             is_dry_run: is_dry,
+            exec_config,
+            settle_ccy,
             instruments_cache: Arc::new(RwLock::new(HashMap::new())),
+            algo_orders: Arc::new(RwLock::new(HashMap::new())),
+            paper,
+            leverage_cache: LeverageCache::default(),
+            tp_ladders: Arc::new(RwLock::new(HashMap::new())),
+            unprotected: Arc::new(RwLock::new(HashMap::new())),
+            pending_limits: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// 干跑模式下权益与持仓均为模拟值
+    pub fn is_paper(&self) -> bool {
+        self.paper.is_some()
+    }
+
+    /// 用最新盘口中间价刷新模拟持仓的浮动盈亏
+    async fn refresh_paper_marks(&self, ledger: &RwLock<PaperLedger>) {
+        let symbols = ledger.read().await.symbols();
+        for symbol in symbols {
+            match self.fetch_best_quote(&symbol).await {
+                Ok((bid, ask)) if bid > 0.0 && ask > 0.0 => ledger.write().await.mark(&symbol, (bid + ask) / 2.0),
+                Ok(_) => {}
+                Err(e) => warn!("🧪 [DRY RUN] Failed to mark {}: {}", symbol, e),
+            }
+        }
+    }
+
+    /// 设置杠杆；失败不阻断下单 (订单沿用交易所上的当前杠杆)，但要明确记录原因
+    async fn ensure_leverage(&self, symbol: &str, lev: u32) {
+        if self.leverage_cache.is_current(symbol, lev) { return; }
+
+        let lev_body = json!({
+            "instId": symbol,
+            "lever": lev.to_string(),
+            "mgnMode": "cross"
+        });
+        match self.send_signed_request(Method::POST, "/api/v5/account/set-leverage", &lev_body).await {
+            Ok(_) => self.leverage_cache.record(symbol, lev),
+            Err(e) => {
+                self.leverage_cache.invalidate(symbol);
+                match TraderError::classify(&e) {
+                    Some(TraderError::Exchange { code, msg }) if LEVERAGE_LOCKED_CODES.contains(&code.as_str()) => warn!(
+                        "⚠️ [{}] Cannot change leverage to {}x while positions/orders are open ({}: {}). Order will use the current leverage.",
+                        symbol, lev, code, msg
+                    ),
+                    _ => warn!("⚠️ [{}] Failed to set leverage to {}x: {}. Order will use the current leverage.", symbol, lev, e),
+                }
+            }
         }
     }
 
+    /// 现货模式：无杠杆、无 posSide，下单数量为币本位数量
+    /// 干跑与现货没有交易所条件单，TP/SL 由 TpSlMonitor 在客户端执行
+    pub fn client_side_tpsl(&self) -> bool {
+        self.is_dry_run || self.is_spot()
+    }
+
+    fn is_spot(&self) -> bool {
+        self.exec_config.trading_mode == TradingMode::Spot
+    }
+
+    fn inst_type(&self) -> &'static str {
+        if self.is_spot() { "SPOT" } else { "SWAP" }
+    }
+
+    fn td_mode(&self) -> &'static str {
+        if self.is_spot() { "cash" } else { "cross" }
+    }
+
     // ------------------------------------------------------------------------
     // 签名与请求辅助
     // ------------------------------------------------------------------------
@@ -108,14 +553,18 @@ impl TradeExecutor {
 
         for attempt in 1..=3 {
             let mut retry_req = self.client.request(method.clone(), &url)
-                .header("OK-ACCESS-KEY", &self.api_key)
-                .header("OK-ACCESS-SIGN", &sign)
-                .header("OK-ACCESS-TIMESTAMP", &timestamp)
-                .header("OK-ACCESS-PASSPHRASE", &self.passphrase)
                 .header("Content-Type", "application/json");
+            // 未配置 Key 时 (影子模式) 只能访问公共接口，不发送签名头
+            if !self.api_key.is_empty() {
+                retry_req = retry_req
+                    .header("OK-ACCESS-KEY", &self.api_key)
+                    .header("OK-ACCESS-SIGN", &sign)
+                    .header("OK-ACCESS-TIMESTAMP", &timestamp)
+                    .header("OK-ACCESS-PASSPHRASE", &self.passphrase);
+            }
             
             if self.is_simulated {
-                retry_req = retry_req.header("x-simulated-trading", "1");
+                retry_req = retry_req.header(SIMULATED_HEADER, "1");
             }
             if method != Method::GET {
                 retry_req = retry_req.json(body_json);
@@ -132,7 +581,10 @@ impl TradeExecutor {
                             return Ok(json_val);
                         } else {
                             warn!("❌ OKX Biz Error: {} | Msg: {} | Req Body: {}", json_val["code"], json_val["msg"], body_str);
-                            return Err(anyhow!("OKX Biz Error: {} | Msg: {}", json_val["code"], json_val["msg"]));
+                            return Err(TraderError::Exchange {
+                                code: json_val["code"].as_str().unwrap_or("unknown").to_string(),
+                                msg: json_val["msg"].as_str().unwrap_or_default().to_string(),
+                            }.into());
                         }
                     } else {
                         warn!("⚠️ OKX HTTP {} (Attempt {}/3): {}", status, attempt, text);
@@ -145,76 +597,189 @@ impl TradeExecutor {
             sleep(Duration::from_millis(500 * attempt as u64)).await;
         }
 
-        Err(anyhow!("OKX Request Failed after 3 attempts: {}", path))
+        Err(TraderError::Network(format!("OKX Request Failed after 3 attempts: {}", path)).into())
     }
 
     // ------------------------------------------------------------------------
     // 元数据管理
     // ------------------------------------------------------------------------
-    pub async fn init_instruments_cache(&self) -> Result<()> {
+    /// 加载全部合约元数据，并为 symbols (交易池) 加载阶梯持仓上限
+    pub async fn init_instruments_cache(&self, symbols: &[String]) -> Result<()> {
         info!("⏳ Fetching Instrument Metadata from OKX...");
         
-        let resp = self.send_signed_request(Method::GET, "/api/v5/public/instruments?instType=SWAP", &json!({})).await?;
+        let path = format!("/api/v5/public/instruments?instType={}", self.inst_type());
+        let resp = self.send_signed_request(Method::GET, &path, &json!({})).await?;
         
         let mut cache = self.instruments_cache.write().await;
         cache.clear();
 
         if let Some(data) = resp["data"].as_array() {
             for item in data {
-                let inst_id = item["instId"].as_str().unwrap_or_default().to_string();
-                if inst_id.is_empty() { continue; }
-
-                let face_val = item["ctVal"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
-                let tick_sz = item["tickSz"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
-                let min_sz = item["minSz"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
-                let lot_sz = item["lotSz"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
-
-                cache.insert(inst_id, InstrumentMeta {
-                    face_value: face_val,
-                    tick_size: tick_sz,
-                    min_sz,
-                    lot_sz,
-                });
+                if let Some((inst_id, meta)) = self.parse_instrument(item) {
+                    cache.insert(inst_id, meta);
+                }
             }
             info!("✅ Instruments Meta Cache Initialized: {} symbols loaded.", cache.len());
         }
+        drop(cache);
+        self.load_position_tiers(symbols).await;
         Ok(())
     }
 
-    pub async fn get_face_value(&self, symbol: &str) -> f64 {
+    /// 拉取 symbols 的阶梯持仓上限写入缓存 (按品种族去重，现货无阶梯)；失败只告警，仓位计算仍受单笔下单上限约束
+    pub async fn load_position_tiers(&self, symbols: &[String]) {
+        if self.is_spot() { return; }
+        let mut by_family: HashMap<String, Vec<String>> = HashMap::new();
+        {
+            let cache = self.instruments_cache.read().await;
+            for symbol in symbols {
+                if let Some(meta) = cache.get(symbol).filter(|m| !m.inst_family.is_empty()) {
+                    by_family.entry(meta.inst_family.clone()).or_default().push(symbol.clone());
+                }
+            }
+        }
+        for (family, members) in by_family {
+            let path = format!("/api/v5/public/position-tiers?instType={}&tdMode={}&instFamily={}", self.inst_type(), self.td_mode(), family);
+            let tiers = match self.send_signed_request(Method::GET, &path, &json!({})).await {
+                Ok(resp) => parse_position_tiers(&resp),
+                Err(e) => {
+                    warn!("🧱 Position tiers for {} unavailable: {}", family, e);
+                    continue;
+                }
+            };
+            let mut cache = self.instruments_cache.write().await;
+            for symbol in members {
+                if let Some(meta) = cache.get_mut(&symbol) {
+                    meta.position_tiers = tiers.clone();
+                }
+            }
+        }
+    }
+
+    fn parse_instrument(&self, item: &Value) -> Option<(String, InstrumentMeta)> {
+        let inst_id = item["instId"].as_str().unwrap_or_default().to_string();
+        if inst_id.is_empty() { return None; }
+
+        // 现货没有合约面值，数量单位即币本身，面值按 1 处理
+        let face_val = if self.is_spot() {
+            1.0
+        } else {
+            item["ctVal"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0)
+        };
+        let tick_sz = item["tickSz"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+        let min_sz = item["minSz"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+        let lot_sz = item["lotSz"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+        let max_mkt_sz = item["maxMktSz"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+        let max_lmt_sz = item["maxLmtSz"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+
+        Some((inst_id, InstrumentMeta {
+            face_value: face_val,
+            tick_size: tick_sz,
+            min_sz,
+            lot_sz,
+            max_mkt_sz,
+            max_lmt_sz,
+            inst_family: item["instFamily"].as_str().unwrap_or_default().to_string(),
+            position_tiers: Vec::new(),
+        }))
+    }
+
+    /// 单独拉取某个 instId 的元数据并写入缓存，返回是否拿到有效面值
+    pub async fn refresh_instrument(&self, symbol: &str) -> Result<bool> {
+        let path = format!("/api/v5/public/instruments?instType={}&instId={}", self.inst_type(), symbol);
+        let resp = self.send_signed_request(Method::GET, &path, &json!({})).await?;
+
+        let parsed = resp["data"].as_array()
+            .and_then(|data| data.iter().find_map(|item| self.parse_instrument(item)))
+            .filter(|(inst_id, _)| inst_id == symbol);
+        match parsed {
+            Some((inst_id, meta)) => {
+                let valid = meta.face_value > 0.0;
+                self.instruments_cache.write().await.insert(inst_id, meta);
+                self.load_position_tiers(&[symbol.to_string()]).await;
+                Ok(valid)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// 缓存中缺失或面值为 0 的币种 (这些币种的仓位计算恒为 0，永远不会下单)
+    pub async fn missing_instruments(&self, symbols: &[String]) -> Vec<String> {
         let cache = self.instruments_cache.read().await;
-        cache.get(symbol).map(|m| m.face_value).unwrap_or(0.0)
+        symbols.iter()
+            .filter(|s| cache.get(s.as_str()).is_none_or(|m| m.face_value <= 0.0))
+            .cloned()
+            .collect()
     }
 
-    pub async fn get_min_size(&self, symbol: &str) -> f64 {
+    pub async fn get_instrument_meta(&self, symbol: &str) -> Option<InstrumentMeta> {
         let cache = self.instruments_cache.read().await;
-        cache.get(symbol).map(|m| m.min_sz).unwrap_or(1.0)
+        cache.get(symbol).cloned()
     }
 
     async fn format_sz(&self, symbol: &str, size: f64) -> String {
         let cache = self.instruments_cache.read().await;
         if let Some(meta) = cache.get(symbol) {
             if meta.lot_sz > 0.0 {
-                let epsilon = 1e-9;
-                let steps = ((size + epsilon) / meta.lot_sz).floor();
-                let aligned = steps * meta.lot_sz;
-                
-                let decimals = if meta.lot_sz < 1.0 {
-                    meta.lot_sz.log10().abs().ceil() as usize
-                } else { 0 };
-                
-                return format!("{:.*}", decimals, aligned);
+                // 数量只能向下取整，避免超过可用保证金或持仓
+                return format_on_grid(size, meta.lot_sz, false);
             }
         }
         format!("{}", size)
     }
 
+    async fn get_tick_size(&self, symbol: &str) -> f64 {
+        let cache = self.instruments_cache.read().await;
+        cache.get(symbol).map(|m| m.tick_size).unwrap_or(0.0)
+    }
+
+    /// 确保触发价距离入场价至少 min_ticks 个 tick，返回 (调整后价格, 是否放宽)
+    /// above = true 表示触发价应在入场价上方 (多单 TP / 空单 SL)
+    fn enforce_tick_floor(entry: f64, trigger: f64, tick_size: f64, min_ticks: u32, above: bool) -> (f64, bool) {
+        if tick_size <= 0.0 || min_ticks == 0 { return (trigger, false); }
+
+        let min_distance = tick_size * min_ticks as f64;
+        if (trigger - entry).abs() >= min_distance && (trigger > entry) == above {
+            return (trigger, false);
+        }
+        let widened = if above { entry + min_distance } else { entry - min_distance };
+        (widened, true)
+    }
+
+    /// 按入场参考价计算 TP/SL 触发价 (已格式化)，价格无效时返回 None
+    async fn compute_tpsl_prices(&self, symbol: &str, pos_side: &str, ref_price: f64, tp_pct: f64, sl_pct: f64) -> Option<(String, String)> {
+        let (raw_tp, raw_sl) = if pos_side == "long" {
+            (ref_price * (1.0 + tp_pct), ref_price * (1.0 - sl_pct))
+        } else {
+            (ref_price * (1.0 - tp_pct), ref_price * (1.0 + sl_pct))
+        };
+
+        // [Fix] 低流动性品种 TP/SL 过于贴近入场价会被 OKX 拒单，按 tick 放宽
+        let tick_size = self.get_tick_size(symbol).await;
+        let min_ticks = self.exec_config.tpsl_min_ticks;
+        let is_long = pos_side == "long";
+        let (tp_price, tp_widened) = Self::enforce_tick_floor(ref_price, raw_tp, tick_size, min_ticks, is_long);
+        let (sl_price, sl_widened) = Self::enforce_tick_floor(ref_price, raw_sl, tick_size, min_ticks, !is_long);
+        if tp_widened {
+            warn!("📏 [{}] TP too close to entry {} (< {} ticks of {}). Widened {} -> {}", symbol, ref_price, min_ticks, tick_size, raw_tp, tp_price);
+        }
+        if sl_widened {
+            warn!("📏 [{}] SL too close to entry {} (< {} ticks of {}). Widened {} -> {}", symbol, ref_price, min_ticks, tick_size, raw_sl, sl_price);
+        }
+
+        if tp_price > 0.0 && sl_price > 0.0 {
+            Some((self.format_price_dynamic(symbol, tp_price).await, self.format_price_dynamic(symbol, sl_price).await))
+        } else {
+            warn!("⚠️ TPSL Skipped: Calculated prices invalid. TP: {}, SL: {}", tp_price, sl_price);
+            None
+        }
+    }
+
     async fn format_price_dynamic(&self, symbol: &str, price: f64) -> String {
         let cache = self.instruments_cache.read().await;
         if let Some(meta) = cache.get(symbol) {
             if meta.tick_size > 0.0 {
-                let decimals = meta.tick_size.log10().abs().ceil() as usize;
-                return format!("{:.*}", decimals, price);
+                return format_on_grid(price, meta.tick_size, true);
             }
         }
         let decimals = if price < 0.01 { 6 } else if price < 1.0 { 4 } else if price < 10.0 { 3 } else { 2 };
@@ -226,19 +791,108 @@ impl TradeExecutor {
     // ------------------------------------------------------------------------
     
     pub async fn fetch_account_summary(&self) -> Result<BalanceSummary> {
-        let resp = self.send_signed_request(Method::GET, "/api/v5/account/balance?ccy=USDT", &json!({})).await?;
+        if let Some(ledger) = &self.paper {
+            self.refresh_paper_marks(ledger).await;
+            return Ok(ledger.read().await.balance());
+        }
+        if self.is_spot() {
+            let resp = self.send_signed_request(Method::GET, "/api/v5/account/balance", &json!({})).await?;
+            return Self::parse_spot_balance(&resp, &self.settle_ccy);
+        }
+        let path = format!("/api/v5/account/balance?ccy={}", self.settle_ccy);
+        let resp = self.send_signed_request(Method::GET, &path, &json!({})).await?;
         
-        let details = &resp["data"][0]["details"][0];
+        let details = resp["data"][0]["details"].as_array()
+            .and_then(|list| list.iter().find(|d| d["ccy"].as_str() == Some(self.settle_ccy.as_str())))
+            .unwrap_or(&Value::Null);
         let equity = details["eq"].as_str().unwrap_or("0").parse::<f64>()?;
         let avail = details["availEq"].as_str().unwrap_or("0").parse::<f64>()?; 
+        let maintenance = details["mmr"].as_str().filter(|s| !s.is_empty()).unwrap_or("0").parse::<f64>().unwrap_or(0.0);
         
         Ok(BalanceSummary {
             total_equity: equity,
             available_balance: avail,
+            maintenance_margin: maintenance,
+        })
+    }
+
+    /// 现货模式：总权益含持有币种市值，可用余额为计价币 (USDT/USDC) 可用
+    fn parse_spot_balance(resp: &Value, quote_ccy: &str) -> Result<BalanceSummary> {
+        let account = &resp["data"][0];
+        let equity = account["totalEq"].as_str().unwrap_or("0").parse::<f64>()?;
+        let avail = account["details"].as_array()
+            .and_then(|list| list.iter().find(|d| d["ccy"].as_str() == Some(quote_ccy)))
+            .and_then(|d| d["availBal"].as_str())
+            .unwrap_or("0")
+            .parse::<f64>()?;
+
+        Ok(BalanceSummary {
+            total_equity: equity,
+            available_balance: avail,
+            maintenance_margin: 0.0,
         })
     }
 
+    /// 现货模式：把持有的币种余额视为多头 "持仓" (只统计已缓存交易对且超过最小下单量的币种)
+    async fn parse_spot_positions(&self, resp: &Value) -> Vec<PositionSummary> {
+        let cache = self.instruments_cache.read().await;
+
+        let mut list = Vec::new();
+        if let Some(details) = resp["data"][0]["details"].as_array() {
+            for item in details {
+                let ccy = item["ccy"].as_str().unwrap_or("");
+                if ccy.is_empty() || ccy == self.settle_ccy { continue; }
+
+                let symbol = format!("{}-{}", ccy, self.settle_ccy);
+                let Some(meta) = cache.get(&symbol) else { continue; };
+                let sz = item["availBal"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+                if sz <= 0.0 || sz < meta.min_sz { continue; }
+
+                let eq_usd = item["eqUsd"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+                let eq = item["eq"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+                list.push(PositionSummary {
+                    symbol,
+                    size: sz,
+                    upl: item["spotUpl"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0),
+                    side: "long".to_string(),
+                    avg_px: item["accAvgPx"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0),
+                    mark_px: if eq > 0.0 { eq_usd / eq } else { 0.0 },
+                    leverage: 1,
+                    notional_usd: eq_usd,
+                    margin_usd: eq_usd,
+                });
+            }
+        }
+        list
+    }
+
+    /// 余额 + 持仓：现货模式一次 balance 请求同时解析两者，合约模式并发请求
+    pub async fn fetch_account_snapshot(&self) -> Result<AccountSnapshot> {
+        if let Some(ledger) = &self.paper {
+            self.refresh_paper_marks(ledger).await;
+            let ledger = ledger.read().await;
+            return Ok(AccountSnapshot { balance: ledger.balance(), positions: ledger.positions() });
+        }
+        if self.is_spot() {
+            let resp = self.send_signed_request(Method::GET, "/api/v5/account/balance", &json!({})).await?;
+            return Ok(AccountSnapshot {
+                balance: Self::parse_spot_balance(&resp, &self.settle_ccy)?,
+                positions: self.parse_spot_positions(&resp).await,
+            });
+        }
+        let (balance, positions) = tokio::join!(self.fetch_account_summary(), self.fetch_positions());
+        Ok(AccountSnapshot { balance: balance?, positions: positions? })
+    }
+
     pub async fn fetch_positions(&self) -> Result<Vec<PositionSummary>> {
+        if let Some(ledger) = &self.paper {
+            self.refresh_paper_marks(ledger).await;
+            return Ok(ledger.read().await.positions());
+        }
+        if self.is_spot() {
+            let resp = self.send_signed_request(Method::GET, "/api/v5/account/balance", &json!({})).await?;
+            return Ok(self.parse_spot_positions(&resp).await);
+        }
         let resp = self.send_signed_request(Method::GET, "/api/v5/account/positions?instType=SWAP", &json!({})).await?;
         
         let mut list = Vec::new();
@@ -252,6 +906,8 @@ impl TradeExecutor {
                     size: sz,
                     upl: item["upl"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0),
                     side: item["posSide"].as_str().unwrap_or("net").to_string(),
+                    avg_px: item["avgPx"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0),
+                    mark_px: item["markPx"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0),
                     // [新增] 提取更多字段用于通知
                     leverage: item["lever"].as_str().unwrap_or("1").parse::<u32>().unwrap_or(1),
                     notional_usd: item["notionalUsd"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0),
@@ -262,6 +918,7 @@ impl TradeExecutor {
         Ok(list)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn execute_order(
         &self, 
         symbol: &str, 
@@ -271,83 +928,1176 @@ impl TradeExecutor {
         current_price: f64,
         tp_pct: f64,
         sl_pct: f64,
-        leverage: Option<u32>
+        leverage: Option<u32>,
+        reduce_only: bool
     ) -> Result<OrderResult> {
-        if let Some(lev) = leverage {
-            let lev_body = json!({
-                "instId": symbol,
-                "lever": lev.to_string(),
-                "mgnMode": "cross"
+        let res = self.place_order(symbol, side, pos_side, size, current_price, tp_pct, sl_pct, leverage, None, reduce_only).await?;
+        if !reduce_only {
+            self.verify_fill(symbol, side, pos_side, &res.order_id, current_price, tp_pct, sl_pct).await?;
+        }
+        let unprotected = self.protect_if_oco(symbol, side, pos_side, &res.order_id, tp_pct, sl_pct).await;
+        Ok(OrderResult { unprotected, ..res })
+    }
+
+    /// 开仓入口：按 entry_mode 选择市价或 maker 优先
+    /// quote: WS 盘口 (bid, ask)，为空或过期时从 REST 拉取
+    /// tp_ladder 非空时入场单不附带 TP/SL，成交后单独挂止损与分批止盈
+    #[allow(clippy::too_many_arguments)]
+    pub async fn execute_entry(
+        &self,
+        symbol: &str,
+        side: &str,
+        pos_side: &str,
+        size: f64,
+        current_price: f64,
+        tp_pct: f64,
+        sl_pct: f64,
+        tp_ladder: &[TpRung],
+        leverage: Option<u32>,
+        quote: Option<(f64, f64)>
+    ) -> Result<OrderResult> {
+        let laddered = !tp_ladder.is_empty() && sl_pct > 0.0 && !self.client_side_tpsl();
+        let (entry_tp, entry_sl) = if laddered { (0.0, 0.0) } else { (tp_pct, sl_pct) };
+
+        let res = if self.exec_config.entry_mode != EntryMode::MakerFirst || self.is_dry_run {
+            self.execute_order(symbol, side, pos_side, size, current_price, entry_tp, entry_sl, leverage, false).await?
+        } else {
+            // maker 挂单可能已部分成交，任何错误都不能由外层重试重新下一遍整单
+            let res = self.execute_maker_entry(symbol, side, pos_side, size, current_price, entry_tp, entry_sl, leverage, quote).await
+                .map_err(|e| e.context(TraderError::Uncertain(format!("maker entry {} {}", symbol, pos_side))))?;
+            self.verify_fill(symbol, side, pos_side, &res.order_id, current_price, entry_tp, entry_sl).await?;
+            let unprotected = self.protect_if_oco(symbol, side, pos_side, &res.order_id, entry_tp, entry_sl).await;
+            OrderResult { unprotected, ..res }
+        };
+
+        if laddered {
+            if let Err(e) = self.place_ladder_after_fill(symbol, side, pos_side, &res.order_id, sl_pct, tp_ladder).await {
+                // 阶梯挂不上时退回整仓 OCO，由下一轮补挂
+                let unprotected = Some(self.defer_protection(symbol, side, pos_side, tp_pct, sl_pct, &e.context("TP ladder placement failed")).await);
+                return Ok(OrderResult { unprotected, ..res });
+            }
+        }
+        Ok(res)
+    }
+
+    /// 等待订单完全成交，超时返回错误
+    async fn wait_for_fill(&self, symbol: &str, order_id: &str) -> Result<OrderStatus> {
+        for _ in 0..10 {
+            let status = self.fetch_order_status(symbol, order_id).await?;
+            if status.state == "filled" {
+                return Ok(status);
+            }
+            sleep(Duration::from_millis(500)).await;
+        }
+        Err(anyhow!("entry order {} not filled in time", order_id))
+    }
+
+    /// 成交后核对均价：不利滑点超过 max_slippage_pct 时立即减仓平掉这笔成交并拒绝；
+    /// 否则在附带 TP/SL 模式下按实际均价重设止盈止损 (OCO 模式本身以持仓均价挂单)
+    #[allow(clippy::too_many_arguments)]
+    async fn verify_fill(&self, symbol: &str, side: &str, pos_side: &str, order_id: &str, expected_px: f64, tp_pct: f64, sl_pct: f64) -> Result<()> {
+        if self.is_dry_run || expected_px <= 0.0 {
+            return Ok(());
+        }
+        let status = match self.wait_for_fill(symbol, order_id).await {
+            Ok(s) if s.avg_px > 0.0 => s,
+            Ok(_) => return Ok(()),
+            Err(e) => {
+                warn!("⚠️ [{}] Could not confirm fill price for {}: {}", symbol, order_id, e);
+                return Ok(());
+            }
+        };
+
+        let slippage = adverse_slippage(side, expected_px, status.avg_px);
+        let max_slippage = self.exec_config.max_slippage_pct;
+        if max_slippage > 0.0 && slippage > max_slippage {
+            error!("🔥 [{}] Fill slippage {:.3}% > {:.3}% (expected {}, filled {}). Closing {} immediately.",
+                symbol, slippage * 100.0, max_slippage * 100.0, expected_px, status.avg_px, status.filled_sz);
+            let close_side = if side == "buy" { "sell" } else { "buy" };
+            self.place_order(symbol, close_side, pos_side, status.filled_sz, status.avg_px, 0.0, 0.0, None, None, true).await?;
+            return Err(TraderError::Rejected(format!(
+                "fill slippage {:.3}% exceeded {:.3}% (expected {}, filled {})",
+                slippage * 100.0, max_slippage * 100.0, expected_px, status.avg_px
+            )).into());
+        }
+
+        if self.exec_config.tpsl_mode == TpSlMode::Attached && !self.client_side_tpsl() && tp_pct > 0.0 && sl_pct > 0.0 && status.avg_px != expected_px {
+            if let Some((tp_str, sl_str)) = self.compute_tpsl_prices(symbol, pos_side, status.avg_px, tp_pct, sl_pct).await {
+                match self.amend_tpsl(symbol, pos_side, &tp_str, &sl_str).await {
+                    Ok(_) => info!("🛡️ [{}] TP/SL re-based on fill {} (expected {}): TP {} / SL {}", symbol, status.avg_px, expected_px, tp_str, sl_str),
+                    Err(e) => warn!("⚠️ [{}] Re-basing TP/SL on fill price failed: {}", symbol, e),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    async fn execute_maker_entry(
+        &self,
+        symbol: &str,
+        side: &str,
+        pos_side: &str,
+        size: f64,
+        current_price: f64,
+        tp_pct: f64,
+        sl_pct: f64,
+        leverage: Option<u32>,
+        quote: Option<(f64, f64)>
+    ) -> Result<OrderResult> {
+        let mut leverage = leverage;
+        let mut remaining = size;
+        let mut last_result: Option<OrderResult> = None;
+        let wait = Duration::from_secs(self.exec_config.maker_timeout_sec);
+
+        for round in 0..=self.exec_config.maker_reprice_count {
+            let (bid, ask) = match (round, quote) {
+                (0, Some(q)) => q,
+                // 此时没有挂着的 maker 单，拉不到盘口直接走市价兜底
+                _ => match self.fetch_best_quote(symbol).await {
+                    Ok(q) => q,
+                    Err(e) => {
+                        warn!("⚠️ [{}] Quote fetch failed (round {}): {}. Falling back to market.", symbol, round + 1, e);
+                        break;
+                    }
+                },
+            };
+            let limit_px = if side == "buy" { bid } else { ask };
+            if limit_px <= 0.0 { break; }
+
+            let res = match self.place_order(symbol, side, pos_side, remaining, current_price, tp_pct, sl_pct, leverage.take(), Some(limit_px), false).await {
+                Ok(r) => r,
+                Err(e) => {
+                    // post-only 穿价会被拒，直接走市价兜底
+                    warn!("⚠️ [{}] Maker order rejected (round {}): {}. Falling back to market.", symbol, round + 1, e);
+                    break;
+                }
+            };
+            self.pending_limits.write().await.insert(res.order_id.clone(), PendingLimit {
+                symbol: symbol.to_string(), side: side.to_string(), placed_at_ms: Utc::now().timestamp_millis(),
             });
-            let _ = self.send_signed_request(Method::POST, "/api/v5/account/set-leverage", &lev_body).await;
+            info!("📌 [{}] Maker order {} @ {} resting for {}s (round {}/{})", 
+                symbol, res.order_id, limit_px, wait.as_secs(), round + 1, self.exec_config.maker_reprice_count + 1);
+            sleep(wait).await;
+
+            let final_status = match self.settle_maker_order(symbol, &res.order_id).await {
+                Ok(s) => s,
+                Err(e) => {
+                    // 无法确认这张挂单的最终成交量：已尝试撤单，不再补单，由外层按状态未知处理
+                    let filled = size - remaining;
+                    error!("🔥 [{}] Maker order {} state unknown after cancel attempt: {}. Confirmed filled {} of {}.", symbol, res.order_id, e, filled, size);
+                    return Err(TraderError::Uncertain(format!(
+                        "maker order {} state unknown ({}), confirmed filled {} of {}", res.order_id, e, filled, size
+                    )).into());
+                }
+            };
+            if matches!(final_status.state.as_str(), "filled" | "canceled") {
+                self.pending_limits.write().await.remove(&res.order_id);
+            }
+            remaining -= final_status.filled_sz;
+
+            if self.format_sz(symbol, remaining).await.parse::<f64>().unwrap_or(0.0) <= 0.0 {
+                info!("✅ [{}] Maker entry fully filled (avg {})", symbol, final_status.avg_px);
+                return Ok(OrderResult { filled_sz: Some(size), ..res });
+            }
+            last_result = Some(res);
+            info!("⏳ [{}] Maker unfilled remainder: {} contracts", symbol, remaining);
         }
 
-        let sz_str = self.format_sz(symbol, size).await;
-        if sz_str.parse::<f64>().unwrap_or(0.0) == 0.0 {
-            return Err(anyhow!("Order size {} too small after formatting (sz_str: {})", size, sz_str));
+        info!("🏃 [{}] Market-filling remaining {} contracts", symbol, remaining);
+        match self.place_order(symbol, side, pos_side, remaining, current_price, tp_pct, sl_pct, leverage.take(), None, false).await {
+            Ok(r) => Ok(OrderResult { filled_sz: Some(size), ..r }),
+            Err(e) => match last_result {
+                // maker 已部分成交，市价补单失败时仍返回已成交的数量
+                Some(r) if remaining < size => {
+                    warn!("⚠️ [{}] Market remainder failed: {}. Keeping partial maker fill ({} of {}).", symbol, e, size - remaining, size);
+                    Ok(OrderResult { filled_sz: Some(size - remaining), ..r })
+                }
+                _ => Err(e),
+            },
         }
+    }
+
+    /// maker 挂单到期：未完全成交则撤单，返回撤单后的最终状态 (撤单期间可能继续成交)
+    /// 查询出错时也先撤单，再查一次最终状态，确保返回时不会留下挂着的单
+    async fn settle_maker_order(&self, symbol: &str, order_id: &str) -> Result<OrderStatus> {
+        match self.fetch_order_status(symbol, order_id).await {
+            Ok(status) if status.state == "filled" => return Ok(status),
+            Ok(_) => {}
+            Err(e) => warn!("⚠️ [{}] Maker order {} status check failed: {}. Cancelling.", symbol, order_id, e),
+        }
+        if let Err(e) = self.cancel_order(symbol, order_id).await {
+            warn!("⚠️ [{}] Cancel maker order {} failed: {}", symbol, order_id, e);
+        }
+        self.fetch_order_status(symbol, order_id).await
+    }
+
+    /// OCO 模式：确认入场成交后，按整个持仓数量挂独立的 TP/SL (one-cancels-other) 算法单；
+    /// 重试 PROTECTION_ATTEMPTS 次仍失败时登记到下一轮补挂，并返回失败原因
+    async fn protect_if_oco(&self, symbol: &str, side: &str, pos_side: &str, order_id: &str, tp_pct: f64, sl_pct: f64) -> Option<String> {
+        if self.exec_config.tpsl_mode != TpSlMode::Oco || self.client_side_tpsl() || tp_pct <= 0.0 || sl_pct <= 0.0 {
+            return None;
+        }
+        let mut result = self.wait_for_fill(symbol, order_id).await.map(|_| ());
+        if result.is_ok() {
+            for attempt in 1..=PROTECTION_ATTEMPTS {
+                result = self.place_oco(symbol, side, pos_side, tp_pct, sl_pct).await;
+                match &result {
+                    Ok(()) => return None,
+                    Err(e) if attempt < PROTECTION_ATTEMPTS => {
+                        warn!("⚠️ [{}] OCO placement failed (attempt {}/{}): {}", symbol, attempt, PROTECTION_ATTEMPTS, e);
+                        sleep(jittered(Duration::from_secs(1))).await;
+                    }
+                    Err(_) => {}
+                }
+            }
+        }
+        let e = result.err()?;
+        Some(self.defer_protection(symbol, side, pos_side, tp_pct, sl_pct, &e).await)
+    }
+
+    /// 登记无保护的持仓，下一轮由 retry_unprotected 补挂
+    async fn defer_protection(&self, symbol: &str, side: &str, pos_side: &str, tp_pct: f64, sl_pct: f64, e: &anyhow::Error) -> String {
+        error!("🔥 [{}] Position {} is UNPROTECTED, retrying next cycle: {:#}", symbol, pos_side, e);
+        self.unprotected.write().await.insert(format!("{}:{}", symbol, pos_side), UnprotectedPosition {
+            side: side.to_string(), tp_pct, sl_pct, attempts: 0,
+        });
+        format!("{:#}", e)
+    }
+
+    /// 补挂之前未能挂上的保护单 (整仓 OCO)；持仓已平掉的记录直接丢弃
+    pub async fn retry_unprotected(&self, positions: &[PositionSummary]) -> Vec<ProtectionRetry> {
+        let pending: Vec<(String, UnprotectedPosition)> = self.unprotected.read().await.iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        let mut results = Vec::new();
+        for (key, mut entry) in pending {
+            let Some(pos) = positions.iter().find(|p| p.size > 0.0 && format!("{}:{}", p.symbol, p.side) == key) else {
+                self.unprotected.write().await.remove(&key);
+                continue;
+            };
+            entry.attempts += 1;
+            let error = match self.place_oco(&pos.symbol, &entry.side, &pos.side, entry.tp_pct, entry.sl_pct).await {
+                Ok(()) => {
+                    self.unprotected.write().await.remove(&key);
+                    None
+                }
+                Err(e) => {
+                    self.unprotected.write().await.insert(key, entry.clone());
+                    Some(format!("{:#}", e))
+                }
+            };
+            results.push(ProtectionRetry { symbol: pos.symbol.clone(), pos_side: pos.side.clone(), attempts: entry.attempts, error });
+        }
+        results
+    }
+
+    async fn place_oco(&self, symbol: &str, side: &str, pos_side: &str, tp_pct: f64, sl_pct: f64) -> Result<()> {
+        // 以持仓均价和总数量为准 (加仓后覆盖旧的 OCO)
+        let pos = self.fetch_positions().await?
+            .into_iter()
+            .find(|p| p.symbol == symbol && p.side == pos_side)
+            .ok_or_else(|| anyhow!("position not found after fill"))?;
+
+        let (tp_str, sl_str) = self.compute_tpsl_prices(symbol, pos_side, pos.avg_px, tp_pct, sl_pct).await
+            .ok_or_else(|| anyhow!("invalid TP/SL prices"))?;
+
+        let key = format!("{}:{}", symbol, pos_side);
+        self.cancel_protection(symbol, &key).await;
+
+        let close_side = if side == "buy" { "sell" } else { "buy" };
+        let mut body = json!({
+            "instId": symbol,
+            "tdMode": self.td_mode(),
+            "side": close_side,
+            "ordType": "oco",
+            "sz": self.format_sz(symbol, pos.size).await,
+            "tpTriggerPx": tp_str,
+            "tpOrdPx": "-1",
+            "slTriggerPx": sl_str,
+            "slOrdPx": "-1",
+            "algoClOrdId": bot_client_id()
+        });
+        if !self.is_spot() {
+            body["posSide"] = json!(pos_side);
+        }
+        let resp = self.send_signed_request(Method::POST, "/api/v5/trade/order-algo", &body).await?;
+        let algo_id = resp["data"][0]["algoId"].as_str().unwrap_or_default().to_string();
+        info!("🛡️ [{}] OCO placed: TP {} / SL {} sz {} (algoId {})", symbol, tp_str, sl_str, pos.size, algo_id);
+        self.algo_orders.write().await.insert(key, TrackedAlgo { algo_id, size: Some(pos.size) });
+        Ok(())
+    }
 
-        let mut body_map = serde_json::Map::new();
-        body_map.insert("instId".to_string(), json!(symbol));
-        body_map.insert("tdMode".to_string(), json!("cross"));
-        body_map.insert("side".to_string(), json!(side));
-        body_map.insert("posSide".to_string(), json!(pos_side));
-        body_map.insert("ordType".to_string(), json!("market"));
-        body_map.insert("sz".to_string(), json!(sz_str));
+    /// 撤销本程序为该持仓挂的 OCO / 止损 / 阶梯止盈 (加仓后按新的持仓重挂)
+    async fn cancel_protection(&self, symbol: &str, key: &str) {
+        let mut algo_ids: Vec<String> = self.algo_orders.write().await.remove(key).map(|t| t.algo_id).into_iter().collect();
+        if let Some(ladder) = self.tp_ladders.write().await.remove(key) {
+            algo_ids.extend(ladder.tp_algo_ids);
+        }
+        for algo_id in algo_ids {
+            if let Err(e) = self.cancel_algo(symbol, &algo_id).await {
+                warn!("⚠️ [{}] Cancel previous algo {} failed: {}", symbol, algo_id, e);
+            }
+        }
+    }
 
-        if tp_pct > 0.0 && sl_pct > 0.0 {
-            let (tp_price, sl_price) = if pos_side == "long" {
-                (current_price * (1.0 + tp_pct), current_price * (1.0 - sl_pct))
+    /// 阶梯止盈：确认成交后按持仓均价挂一张全仓止损 + 每档一张 reduce-only 止盈
+    async fn place_ladder_after_fill(&self, symbol: &str, side: &str, pos_side: &str, order_id: &str, sl_pct: f64, ladder: &[TpRung]) -> Result<()> {
+        self.wait_for_fill(symbol, order_id).await?;
+        let pos = self.fetch_positions().await?
+            .into_iter()
+            .find(|p| p.symbol == symbol && p.side == pos_side)
+            .ok_or_else(|| anyhow!("position not found after fill"))?;
+
+        let key = format!("{}:{}", symbol, pos_side);
+        self.cancel_protection(symbol, &key).await;
+
+        let close_side = if side == "buy" { "sell" } else { "buy" };
+        let conditional = |extra: Value| {
+            let mut body = json!({
+                "instId": symbol,
+                "tdMode": self.td_mode(),
+                "side": close_side,
+                "ordType": "conditional",
+                "algoClOrdId": bot_client_id(),
+            });
+            if !self.is_spot() {
+                body["posSide"] = json!(pos_side);
+                body["reduceOnly"] = json!(true);
+            }
+            for (k, v) in extra.as_object().into_iter().flatten() {
+                body[k] = v.clone();
+            }
+            body
+        };
+
+        // 1. 止损覆盖整个持仓，部分止盈后剩余仓位仍受保护；记录到 algo_orders 以便 amend_stop 移动
+        let (_, sl_str) = self.compute_tpsl_prices(symbol, pos_side, pos.avg_px, ladder[0].r_multiple * sl_pct, sl_pct).await
+            .ok_or_else(|| anyhow!("invalid SL price"))?;
+        let mut sl_extra = json!({ "slTriggerPx": sl_str, "slOrdPx": "-1" });
+        let sl_size = if self.is_spot() {
+            sl_extra["sz"] = json!(self.format_sz(symbol, pos.size).await);
+            Some(pos.size)
+        } else {
+            sl_extra["closeFraction"] = json!("1");
+            None
+        };
+        let sl_body = conditional(sl_extra);
+        let resp = self.send_signed_request(Method::POST, "/api/v5/trade/order-algo", &sl_body).await?;
+        let sl_algo = resp["data"][0]["algoId"].as_str().unwrap_or_default().to_string();
+        self.algo_orders.write().await.insert(key.clone(), TrackedAlgo { algo_id: sl_algo, size: sl_size });
+
+        // 2. 分批止盈
+        let (lot_sz, min_sz) = self.get_instrument_meta(symbol).await.map(|m| (m.lot_sz, m.min_sz)).unwrap_or((0.0, 0.0));
+        let fractions: Vec<f64> = ladder.iter().map(|r| r.fraction).collect();
+        let sizes = Self::ladder_sizes(pos.size, &fractions, lot_sz, min_sz);
+        let mut tp_algo_ids = Vec::new();
+        let mut placed = Vec::new();
+        for (rung, sz) in ladder.iter().zip(sizes) {
+            if sz <= 0.0 { continue; }
+            let Some((tp_str, _)) = self.compute_tpsl_prices(symbol, pos_side, pos.avg_px, rung.r_multiple * sl_pct, sl_pct).await else { continue };
+            let body = conditional(json!({
+                "sz": self.format_sz(symbol, sz).await,
+                "tpTriggerPx": tp_str,
+                "tpOrdPx": "-1",
+            }));
+            match self.send_signed_request(Method::POST, "/api/v5/trade/order-algo", &body).await {
+                Ok(resp) => {
+                    tp_algo_ids.push(resp["data"][0]["algoId"].as_str().unwrap_or_default().to_string());
+                    placed.push(format!("{}R {} x{}", rung.r_multiple, tp_str, sz));
+                }
+                Err(e) => warn!("⚠️ [{}] TP rung {}R placement failed: {}", symbol, rung.r_multiple, e),
+            }
+        }
+        info!("🪜 [{}] TP ladder placed on {} {} @ {}: SL {} | {}", symbol, pos_side, pos.size, pos.avg_px, sl_str, placed.join(" | "));
+
+        self.tp_ladders.write().await.insert(key, LadderState {
+            initial_size: pos.size,
+            entry_px: pos.avg_px,
+            tp_algo_ids,
+            breakeven_done: false,
+        });
+        Ok(())
+    }
+
+    /// 按比例把持仓数量拆给各档止盈，每档向下对齐到 lot_sz；
+    /// 比例合计为 1 时最后一档取余数，保证全部平掉；不足 min_sz 的档并入下一档 (最后一档并入前一档)
+    fn ladder_sizes(total: f64, fractions: &[f64], lot_sz: f64, min_sz: f64) -> Vec<f64> {
+        let align = |x: f64| if lot_sz > 0.0 { ((x + 1e-9) / lot_sz).floor() * lot_sz } else { x };
+        let n = fractions.len();
+        let mut raw: Vec<f64> = fractions.iter().map(|f| align(total * f)).collect();
+        if n > 0 && fractions.iter().sum::<f64>() >= 1.0 - 1e-9 {
+            let rest = total - raw[..n - 1].iter().sum::<f64>();
+            raw[n - 1] = if lot_sz > 0.0 { (rest / lot_sz).round() * lot_sz } else { rest };
+        }
+
+        let mut sizes = vec![0.0; n];
+        let mut carry = 0.0;
+        for (i, sz) in raw.into_iter().enumerate() {
+            let sz = sz + carry;
+            if sz < min_sz && i + 1 < n {
+                carry = sz;
             } else {
-                (current_price * (1.0 - tp_pct), current_price * (1.0 + sl_pct))
+                sizes[i] = sz;
+                carry = 0.0;
+            }
+        }
+        if let Some(last) = sizes.last().copied().filter(|s| *s > 0.0 && *s < min_sz) {
+            if let Some(prev) = sizes[..n - 1].iter_mut().rev().find(|s| **s > 0.0) {
+                *prev += last;
+                sizes[n - 1] = 0.0;
+            }
+        }
+        sizes
+    }
+
+    /// 阶梯的第一档止盈是否刚成交 (持仓数量低于挂单时的数量)，是则返回入场均价 (每个阶梯只触发一次)
+    pub async fn ladder_first_tp_filled(&self, symbol: &str, pos_side: &str, current_size: f64) -> Option<f64> {
+        let key = format!("{}:{}", symbol, pos_side);
+        let mut ladders = self.tp_ladders.write().await;
+        let state = ladders.get_mut(&key)?;
+        if state.breakeven_done || current_size >= state.initial_size - 1e-9 {
+            return None;
+        }
+        state.breakeven_done = true;
+        Some(state.entry_px)
+    }
+
+    /// 持仓已平掉后其阶梯状态、算法单记录 (剩余挂单随 reduce-only 失效) 与待补挂记录不再跟踪
+    pub async fn prune_protection(&self, positions: &[PositionSummary]) {
+        let open: HashSet<String> = positions.iter().map(|p| format!("{}:{}", p.symbol, p.side)).collect();
+        self.tp_ladders.write().await.retain(|key, _| open.contains(key));
+        self.algo_orders.write().await.retain(|key, _| open.contains(key));
+        self.unprotected.write().await.retain(|key, _| open.contains(key));
+    }
+
+    /// 部分平仓/加仓后持仓数量与 TP/SL 算法单不一致 (旧单按原数量挂出，之后触发会平错数量)：
+    /// 按最近一次的触发价挂一张覆盖整个持仓的新单，再撤掉旧单。阶梯止盈与 closeFraction 全仓单不处理；
+    /// 只合并本程序的保护单 (见 bot_brackets)，手动挂的 TP/SL 原样保留
+    pub async fn resize_protection(&self, positions: &[PositionSummary]) -> Result<()> {
+        if self.client_side_tpsl() {
+            return Ok(());
+        }
+        let (ladders, mut tracked_ids): (HashSet<String>, HashSet<String>) = {
+            let ladders = self.tp_ladders.read().await;
+            (ladders.keys().cloned().collect(), ladders.values().flat_map(|l| l.tp_algo_ids.iter().cloned()).collect())
+        };
+        let tracked = self.algo_orders.read().await.clone();
+        tracked_ids.extend(tracked.values().map(|t| t.algo_id.clone()));
+        let mut pending: Option<Vec<PendingBracket>> = None;
+
+        for pos in positions.iter().filter(|p| p.size > 0.0) {
+            let key = format!("{}:{}", pos.symbol, pos.side);
+            if ladders.contains(&key) { continue; }
+            let lot_sz = self.get_instrument_meta(&pos.symbol).await.map_or(0.0, |m| m.lot_sz);
+            // 本地记录的数量与持仓一致时不查询挂单
+            if let Some(t) = tracked.get(&key) {
+                if t.size.is_none_or(|size| bracket_resize(&[size], pos.size, lot_sz).is_none()) { continue; }
+            }
+
+            if pending.is_none() {
+                let path = format!("/api/v5/trade/orders-algo-pending?instType={}&ordType=conditional,oco", self.inst_type());
+                let resp = self.send_signed_request(Method::GET, &path, &json!({})).await?;
+                pending = Some(parse_pending_brackets(&resp["data"]));
+            }
+            let mut brackets = bot_brackets(pending.as_deref().unwrap_or_default(), &pos.symbol, &pos.side, &tracked_ids);
+            if brackets.is_empty() || brackets.iter().any(|b| b.size.is_none()) { continue; }
+            let sizes: Vec<f64> = brackets.iter().filter_map(|b| b.size).collect();
+            let Some(new_size) = bracket_resize(&sizes, pos.size, lot_sz) else {
+                // 入场附带的单张保护单 (attachAlgoClOrdId 带前缀) 数量正确：纳入跟踪，之后按本地记录比对
+                self.algo_orders.write().await.insert(key, TrackedAlgo { algo_id: brackets[0].algo_id.clone(), size: sizes.first().copied() });
+                continue;
             };
 
-            if tp_price > 0.0 && sl_price > 0.0 {
-                let tp_str = self.format_price_dynamic(symbol, tp_price).await;
-                let sl_str = self.format_price_dynamic(symbol, sl_price).await;
-                
+            // 最近挂出的触发价反映最新的决策 (加仓时的 TP/SL)
+            brackets.sort_by_key(|b| std::cmp::Reverse(b.created_ms));
+            let tp_px = brackets.iter().find_map(|b| b.tp_px.clone());
+            let sl_px = brackets.iter().find_map(|b| b.sl_px.clone());
+            let mut body = json!({
+                "instId": pos.symbol,
+                "tdMode": self.td_mode(),
+                "side": if pos.side == "short" { "buy" } else { "sell" },
+                "posSide": pos.side,
+                "sz": self.format_sz(&pos.symbol, new_size).await,
+                "reduceOnly": true,
+                "ordType": if tp_px.is_some() && sl_px.is_some() { "oco" } else { "conditional" },
+                "algoClOrdId": bot_client_id(),
+            });
+            if let Some(tp) = &tp_px {
+                body["tpTriggerPx"] = json!(tp);
+                body["tpOrdPx"] = json!("-1");
+            }
+            if let Some(sl) = &sl_px {
+                body["slTriggerPx"] = json!(sl);
+                body["slOrdPx"] = json!("-1");
+            }
+
+            // 先挂新单再撤旧单，避免持仓出现无保护的间隙
+            let algo_id = match self.send_signed_request(Method::POST, "/api/v5/trade/order-algo", &body).await {
+                Ok(resp) => resp["data"][0]["algoId"].as_str().unwrap_or_default().to_string(),
+                Err(e) => {
+                    warn!("⚠️ [{}] Resizing {} TP/SL to {} failed, keeping old protection: {}", pos.symbol, pos.side, new_size, e);
+                    continue;
+                }
+            };
+            for old in &brackets {
+                if let Err(e) = self.cancel_algo(&pos.symbol, &old.algo_id).await {
+                    warn!("⚠️ [{}] Cancel resized algo {} failed: {}", pos.symbol, old.algo_id, e);
+                }
+            }
+            info!("🛡️ [{}] TP/SL resized for {} {}: {:?} -> {} (TP {:?} / SL {:?}, algoId {})",
+                pos.symbol, pos.side, pos.size, sizes, new_size, tp_px, sl_px, algo_id);
+            self.algo_orders.write().await.insert(key, TrackedAlgo { algo_id, size: Some(new_size) });
+        }
+        Ok(())
+    }
+
+    async fn cancel_algo(&self, symbol: &str, algo_id: &str) -> Result<()> {
+        let body = json!([{ "algoId": algo_id, "instId": symbol }]);
+        self.send_signed_request(Method::POST, "/api/v5/trade/cancel-algos", &body).await?;
+        Ok(())
+    }
+
+    /// 修改持仓止损所在的算法单：优先使用本程序记录的 OCO / 止损单，
+    /// 修改失败 (已触发、被手动撤掉或被替换) 时丢弃该记录，改用挂单查询到的止损单再试一次
+    async fn amend_stop_algo(&self, symbol: &str, pos_side: &str, changes: Value) -> Result<String> {
+        let key = format!("{}:{}", symbol, pos_side);
+        let tracked = self.algo_orders.read().await.get(&key).map(|t| t.algo_id.clone());
+        if let Some(algo_id) = tracked {
+            match self.send_amend_algo(symbol, &algo_id, &changes).await {
+                Ok(()) => return Ok(algo_id),
+                Err(e) => {
+                    warn!("⚠️ [{}] Amending tracked algo {} failed, falling back to pending orders: {}", symbol, algo_id, e);
+                    self.algo_orders.write().await.remove(&key);
+                }
+            }
+        }
+        let algo_id = self.find_pending_stop_algo_id(symbol, pos_side).await?;
+        self.send_amend_algo(symbol, &algo_id, &changes).await?;
+        Ok(algo_id)
+    }
+
+    async fn send_amend_algo(&self, symbol: &str, algo_id: &str, changes: &Value) -> Result<()> {
+        let mut body = json!({ "instId": symbol, "algoId": algo_id });
+        if let (Some(b), Some(c)) = (body.as_object_mut(), changes.as_object()) {
+            b.extend(c.clone());
+        }
+        self.send_signed_request(Method::POST, "/api/v5/trade/amend-algos", &body).await?;
+        Ok(())
+    }
+
+    /// 查询挂单中带止损的算法单 (OCO 或附带止损)
+    async fn find_pending_stop_algo_id(&self, symbol: &str, pos_side: &str) -> Result<String> {
+        let path = format!("/api/v5/trade/orders-algo-pending?instType={}&instId={}&ordType=conditional,oco", self.inst_type(), symbol);
+        let resp = self.send_signed_request(Method::GET, &path, &json!({})).await?;
+        // 现货算法单没有 posSide，只按是否带止损匹配
+        let is_spot = self.is_spot();
+        resp["data"].as_array()
+            .and_then(|list| list.iter().find(|a| {
+                (is_spot || a["posSide"].as_str() == Some(pos_side)) && !a["slTriggerPx"].as_str().unwrap_or("").is_empty()
+            }))
+            .and_then(|a| a["algoId"].as_str())
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow!("no pending stop-loss algo for {} {}", symbol, pos_side))
+    }
+
+    /// 修改持仓止损触发价 (用于保本 / 手动移动止损)
+    pub async fn amend_stop(&self, symbol: &str, pos_side: &str, new_sl_price: f64) -> Result<()> {
+        if self.client_side_tpsl() {
+            info!("🧪 [Client TP/SL] Amend SL: {} {} -> {}", symbol, pos_side, new_sl_price);
+            return Ok(());
+        }
+        let sl_str = self.format_price_dynamic(symbol, new_sl_price).await;
+        let algo_id = self.amend_stop_algo(symbol, pos_side, json!({
+            "newSlTriggerPx": sl_str,
+            "newSlOrdPx": "-1"
+        })).await?;
+        info!("🛡️ [{}] Stop amended: {} SL -> {} (algoId {})", symbol, pos_side, sl_str, algo_id);
+        Ok(())
+    }
+
+    /// 同时修改持仓的止盈与止损触发价
+    async fn amend_tpsl(&self, symbol: &str, pos_side: &str, tp_str: &str, sl_str: &str) -> Result<()> {
+        self.amend_stop_algo(symbol, pos_side, json!({
+            "newTpTriggerPx": tp_str,
+            "newTpOrdPx": "-1",
+            "newSlTriggerPx": sl_str,
+            "newSlOrdPx": "-1"
+        })).await?;
+        Ok(())
+    }
+
+    /// 获取盘口一档 (bid, ask)
+    pub async fn fetch_best_quote(&self, symbol: &str) -> Result<(f64, f64)> {
+        let path = format!("/api/v5/market/ticker?instId={}", symbol);
+        let resp = self.send_signed_request(Method::GET, &path, &json!({})).await?;
+        let item = &resp["data"][0];
+        let bid = item["bidPx"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+        let ask = item["askPx"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+        Ok((bid, ask))
+    }
+
+    pub async fn fetch_order_status(&self, symbol: &str, order_id: &str) -> Result<OrderStatus> {
+        let path = format!("/api/v5/trade/order?instId={}&ordId={}", symbol, order_id);
+        let resp = self.send_signed_request(Method::GET, &path, &json!({})).await?;
+        let item = &resp["data"][0];
+        Ok(OrderStatus {
+            state: item["state"].as_str().unwrap_or("").to_string(),
+            filled_sz: item["accFillSz"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0),
+            avg_px: item["avgPx"].as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0),
+        })
+    }
+
+    /// 对账限价挂单：补登本程序重启前遗留的挂单 (按 clOrdId 前缀识别)，清理已结束的记录，
+    /// 撤掉超过 pending_order_timeout_sec 仍未完全成交的挂单。撤单后不重挂：入场信号已过时，由下一轮分析重新决定
+    pub async fn reconcile_pending_orders(&self) -> Result<Vec<CancelledLimit>> {
+        let timeout_sec = self.exec_config.pending_order_timeout_sec;
+        if self.is_dry_run || timeout_sec == 0 {
+            return Ok(Vec::new());
+        }
+
+        // 本程序只挂 post_only 限价单
+        let resp = self.send_signed_request(Method::GET, &format!("/api/v5/trade/orders-pending?instType={}&ordType=post_only", self.inst_type()), &json!({})).await?;
+        let live = resp["data"].as_array().cloned().unwrap_or_default();
+
+        let expired = {
+            let mut pending = self.pending_limits.write().await;
+            sync_pending_limits(&mut pending, &live, Utc::now().timestamp_millis());
+            let expired = expired_limit_orders(&pending, Utc::now().timestamp_millis(), timeout_sec as i64 * 1000);
+            expired.into_iter().filter_map(|id| pending.remove(&id).map(|p| (id, p))).collect::<Vec<_>>()
+        };
+
+        let mut cancelled = Vec::new();
+        for (order_id, order) in expired {
+            if let Err(e) = self.cancel_order(&order.symbol, &order_id).await {
+                // 撤单失败 (可能刚好成交)，下一轮对账时重新处理
+                warn!("⚠️ [{}] Auto-cancel of stale limit {} failed: {}", order.symbol, order_id, e);
+                continue;
+            }
+            let filled_sz = self.fetch_order_status(&order.symbol, &order_id).await.map(|s| s.filled_sz).unwrap_or(0.0);
+            cancelled.push(CancelledLimit {
+                age_sec: (Utc::now().timestamp_millis() - order.placed_at_ms) / 1000,
+                symbol: order.symbol, side: order.side, order_id, filled_sz,
+            });
+        }
+        Ok(cancelled)
+    }
+
+    pub async fn cancel_order(&self, symbol: &str, order_id: &str) -> Result<()> {
+        let body = json!({ "instId": symbol, "ordId": order_id });
+        self.send_signed_request(Method::POST, "/api/v5/trade/cancel-order", &body).await?;
+        Ok(())
+    }
+
+    /// limit_px 为 Some 时下 post-only 限价单，否则市价单
+    /// reduce_only 为 true 时只允许减仓 (平仓单)，永远不会反向开仓
+    #[allow(clippy::too_many_arguments)]
+    async fn place_order(
+        &self, 
+        symbol: &str, 
+        side: &str, 
+        pos_side: &str, 
+        size: f64, 
+        current_price: f64,
+        tp_pct: f64,
+        sl_pct: f64,
+        leverage: Option<u32>,
+        limit_px: Option<f64>,
+        reduce_only: bool
+    ) -> Result<OrderResult> {
+        // 现货无杠杆；与上次设置相同时不重复请求
+        if let Some(lev) = leverage.filter(|_| !self.is_spot()) {
+            self.ensure_leverage(symbol, lev).await;
+        }
+
+        let sz_str = self.format_sz(symbol, size).await;
+        if sz_str.parse::<f64>().unwrap_or(0.0) == 0.0 {
+            return Err(anyhow!("Order size {} too small after formatting (sz_str: {})", size, sz_str));
+        }
+
+        let px = match limit_px {
+            Some(px) => Some(self.format_price_dynamic(symbol, px).await),
+            None => None,
+        };
+        let mut attach_tpsl = None;
+        if tp_pct > 0.0 && sl_pct > 0.0 && self.exec_config.tpsl_mode == TpSlMode::Attached && !self.client_side_tpsl() {
+            if let Some((tp_str, sl_str)) = self.compute_tpsl_prices(symbol, pos_side, current_price, tp_pct, sl_pct).await {
                 info!("🛡️ Attaching Algo: TP {} ({}%) / SL {} ({}%)", tp_str, tp_pct*100.0, sl_str, sl_pct*100.0);
-                
-                body_map.insert("attachAlgoOrds".to_string(), json!([{
-                    "tpTriggerPx": tp_str,
-                    "tpOrdPx": "-1", 
-                    "slTriggerPx": sl_str,
-                    "slOrdPx": "-1"
-                }]));
-            } else {
-                warn!("⚠️ TPSL Skipped: Calculated prices invalid. TP: {}, SL: {}", tp_price, sl_price);
+                attach_tpsl = Some((tp_str, sl_str));
             }
         }
 
+        let body = OrderRequest {
+            symbol,
+            td_mode: self.td_mode(),
+            side,
+            pos_side: if self.is_spot() { None } else { Some(pos_side) },
+            sz: sz_str.clone(),
+            px,
+            // 现货市价买单默认按计价币数量成交，统一指定为币本位数量
+            tgt_ccy: if self.is_spot() && limit_px.is_none() { Some("base_ccy") } else { None },
+            // 现货不支持 reduceOnly (卖出数量本身受持币余额限制)
+            reduce_only: reduce_only && !self.is_spot(),
+            attach_tpsl,
+            cl_ord_id: limit_px.map(|_| bot_client_id()),
+        }.to_body();
+
         if self.is_dry_run {
-            info!("🧪 [DRY RUN] Order: {} {} {} sz={}", side, pos_side, symbol, sz_str);
-            return Ok(OrderResult { order_id: "dry-run".to_string(), response: "ok".to_string() });
+            info!("🧪 [DRY RUN] Order: {} {} {} sz={} reduceOnly={}", side, pos_side, symbol, sz_str, reduce_only);
+            if let Some(ledger) = &self.paper {
+                // 按分析价 (或限价) 模拟全部成交
+                let fill_px = limit_px.unwrap_or(current_price);
+                let face_value = if self.is_spot() { 1.0 } else {
+                    self.get_instrument_meta(symbol).await.map(|m| m.face_value).unwrap_or(1.0)
+                };
+                let sz = sz_str.parse::<f64>().unwrap_or(0.0);
+                let pos_side = if self.is_spot() { "long" } else { pos_side };
+                ledger.write().await.apply_fill(symbol, side, pos_side, sz, fill_px, face_value, leverage.unwrap_or(1));
+            }
+            return Ok(OrderResult { order_id: "dry-run".to_string(), response: "ok".to_string(), filled_sz: None, unprotected: None });
         }
 
         info!("🚀 Placing Atomic Order for {} (sz: {})...", symbol, sz_str);
-        let res = self.send_signed_request(Method::POST, "/api/v5/trade/order", &Value::Object(body_map)).await?;
+        let res = self.send_signed_request(Method::POST, "/api/v5/trade/order", &body).await?;
         
         let ord_id = res["data"][0]["ordId"].as_str().unwrap_or("unknown").to_string();
         info!("✅ OKX Order Success: ID {}", ord_id);
-        Ok(OrderResult { order_id: ord_id, response: res.to_string() })
+        Ok(OrderResult { order_id: ord_id, response: res.to_string(), filled_sz: None, unprotected: None })
     }
 
-    pub async fn fetch_recent_pnl(&self) -> Result<Vec<PnlRecord>> {
-        let resp = self.send_signed_request(Method::GET, "/api/v5/account/bills?instType=SWAP&type=2", &json!({})).await?;
-        
+    /// 账户持仓模式：long_short_mode (双向) / net_mode (单向)
+    pub async fn fetch_position_mode(&self) -> Result<String> {
+        let res = self.send_signed_request(Method::GET, "/api/v5/account/config", &json!({})).await?;
+        res["data"][0]["posMode"].as_str()
+            .map(|s| s.to_string())
+            .ok_or_else(|| anyhow!("posMode missing in account config: {}", res))
+    }
+
+    pub async fn set_position_mode(&self, pos_mode: &str) -> Result<()> {
+        self.send_signed_request(Method::POST, "/api/v5/account/set-position-mode", &json!({ "posMode": pos_mode })).await?;
+        Ok(())
+    }
+
+    /// 查询 OKX 系统状态 (公共接口)，返回正在进行中的维护标题
+    pub async fn fetch_active_maintenance(&self) -> Result<Option<String>> {
+        let url = format!("{}/api/v5/system/status", self.base_url);
+        let resp: Value = self.client.get(&url)
+            .query(&[("state", "ongoing")])
+            .send()
+            .await?
+            .json()
+            .await?;
+        Ok(maintenance::active_maintenance(&resp))
+    }
+
+    /// 拉取成交与资金费账单，向更早的账单翻页直到 unsynced 中的订单全部出现 (或达到页数上限)
+    pub async fn fetch_recent_pnl(&self, unsynced: &HashSet<String>) -> Result<Vec<PnlRecord>> {
+        let bills = paginate_bills(|after| self.fetch_bill_page(after), unsynced, MAX_BILL_PAGES).await?;
+        Ok(bills.into_iter()
+            .filter(|b| b.type_name == BILL_TYPE_TRADE || b.type_name == BILL_TYPE_FUNDING)
+            .collect())
+    }
+
+    /// 拉取 since_ms 之后的全部历史成交 (按时间倒序翻页)，用于把历史交易回放进记忆
+    pub async fn fetch_fills_since(&self, since_ms: i64) -> Result<Vec<HistoricalFill>> {
+        let mut fills: Vec<HistoricalFill> = Vec::new();
+        let mut cursor: Option<String> = None;
+        for page_no in 1..=MAX_FILL_PAGES {
+            let mut path = format!("/api/v5/trade/fills-history?instType={}&limit={}&begin={}", self.inst_type(), BILLS_PAGE_LIMIT, since_ms);
+            if let Some(bill_id) = cursor.take() {
+                path.push_str(&format!("&after={}", bill_id));
+            }
+            let resp = self.send_signed_request(Method::GET, &path, &json!({})).await?;
+
+            let parse = |v: &Value| v.as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+            let page: Vec<HistoricalFill> = resp["data"].as_array().map(|data| data.iter().map(|item| HistoricalFill {
+                symbol: item["instId"].as_str().unwrap_or("").to_string(),
+                side: item["side"].as_str().unwrap_or("").to_string(),
+                pos_side: item["posSide"].as_str().unwrap_or("net").to_string(),
+                px: parse(&item["fillPx"]),
+                sz: parse(&item["fillSz"]),
+                pnl: parse(&item["fillPnl"]),
+                fee: parse(&item["fee"]),
+                ts: item["ts"].as_str().unwrap_or("0").parse().unwrap_or(0),
+                ord_id: item["ordId"].as_str().unwrap_or("").to_string(),
+                bill_id: item["billId"].as_str().unwrap_or("").to_string(),
+            }).collect()).unwrap_or_default();
+
+            let full_page = page.len() >= BILLS_PAGE_LIMIT;
+            cursor = page.last().map(|f| f.bill_id.clone()).filter(|id| !id.is_empty());
+            let reached_since = page.last().is_some_and(|f| f.ts < since_ms);
+            fills.extend(page.into_iter().filter(|f| f.ts >= since_ms));
+
+            if !full_page || reached_since || cursor.is_none() {
+                break;
+            }
+            if page_no == MAX_FILL_PAGES {
+                warn!("📥 Fill history pagination stopped at {} pages, older fills are skipped", MAX_FILL_PAGES);
+            }
+        }
+        Ok(fills)
+    }
+
+    /// 单页账单 (按时间倒序)，after = 上一页最后一条的 billId
+    async fn fetch_bill_page(&self, after: Option<String>) -> Result<Vec<PnlRecord>> {
+        let mut path = format!("/api/v5/account/bills?instType={}&limit={}", self.inst_type(), BILLS_PAGE_LIMIT);
+        if let Some(bill_id) = after {
+            path.push_str(&format!("&after={}", bill_id));
+        }
+        let resp = self.send_signed_request(Method::GET, &path, &json!({})).await?;
+
+        let parse = |v: &Value| v.as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
         let mut list = Vec::new();
         if let Some(data) = resp["data"].as_array() {
             for item in data {
                 list.push(PnlRecord {
                     symbol: item["instId"].as_str().unwrap_or("").to_string(),
-                    pnl: item["pnl"].as_str().unwrap_or("0").parse().unwrap_or(0.0),
-                    fee: item["fee"].as_str().unwrap_or("0").parse().unwrap_or(0.0),
+                    pnl: parse(&item["pnl"]),
+                    fee: parse(&item["fee"]),
+                    px: parse(&item["px"]),
                     ts: item["ts"].as_str().unwrap_or("0").parse().unwrap_or(0),
                     type_name: item["type"].as_str().unwrap_or("").to_string(),
                     ord_id: item["ordId"].as_str().unwrap_or("").to_string(),
+                    bill_id: item["billId"].as_str().unwrap_or("").to_string(),
+                    bal_chg: parse(&item["balChg"]),
                 });
             }
         }
         Ok(list)
     }
-}
\ No newline at end of file
+}
+
+/// 解析阶梯持仓上限 (maxLever / maxSz 为字符串)
+pub fn parse_position_tiers(resp: &Value) -> Vec<PositionTier> {
+    let parse = |v: &Value| v.as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+    resp["data"].as_array()
+        .map(|data| data.iter()
+            .map(|t| PositionTier { max_lever: parse(&t["maxLever"]), max_sz: parse(&t["maxSz"]) })
+            .filter(|t| t.max_lever > 0.0 && t.max_sz > 0.0)
+            .collect())
+        .unwrap_or_default()
+}
+
+/// 账单翻页：以每页最后一条的 billId 作为 after 游标向更早翻页
+/// 遇到空页/不满一页、wanted 中的订单已全部出现或达到 max_pages 时停止；wanted 为空时只取第一页
+pub async fn paginate_bills<F, Fut>(mut fetch_page: F, wanted: &HashSet<String>, max_pages: usize) -> Result<Vec<PnlRecord>>
+where
+    F: FnMut(Option<String>) -> Fut,
+    Fut: Future<Output = Result<Vec<PnlRecord>>>,
+{
+    let mut bills: Vec<PnlRecord> = Vec::new();
+    let mut missing: HashSet<&str> = wanted.iter().map(|s| s.as_str()).collect();
+    let mut cursor = None;
+    for page_no in 1..=max_pages {
+        let page = fetch_page(cursor.take()).await?;
+        let full_page = page.len() >= BILLS_PAGE_LIMIT;
+        cursor = page.last().map(|b| b.bill_id.clone()).filter(|id| !id.is_empty());
+        for bill in &page {
+            missing.remove(bill.ord_id.as_str());
+        }
+        bills.extend(page);
+
+        if missing.is_empty() || !full_page || cursor.is_none() {
+            break;
+        }
+        if page_no == max_pages {
+            warn!("📥 Bill pagination stopped at {} pages, {} unsynced orders not found (older than the bills window?)", max_pages, missing.len());
+        }
+    }
+    Ok(bills)
+}
+
+/// 成交价相对预期价的不利滑点比例 (买入成交更高 / 卖出成交更低为正，有利成交为负)
+pub fn adverse_slippage(side: &str, expected_px: f64, fill_px: f64) -> f64 {
+    if expected_px <= 0.0 { return 0.0; }
+    let diff = (fill_px - expected_px) / expected_px;
+    if side == "buy" { diff } else { -diff }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::money::grid_decimals;
+
+    #[test]
+    fn pos_mode_switches_only_when_flat_and_enabled() {
+        assert_eq!(check_pos_mode("long_short_mode", false, 3), PosModeCheck::Ok);
+        assert_eq!(check_pos_mode("net_mode", true, 0), PosModeCheck::Switch);
+        assert!(matches!(check_pos_mode("net_mode", false, 0), PosModeCheck::Mismatch(_)));
+        assert!(matches!(check_pos_mode("net_mode", true, 1), PosModeCheck::Mismatch(_)));
+    }
+
+    fn close_request(reduce_only: bool) -> OrderRequest<'static> {
+        OrderRequest {
+            symbol: "BTC-USDT-SWAP",
+            td_mode: "cross",
+            side: "sell",
+            pos_side: Some("long"),
+            sz: "3".to_string(),
+            px: None,
+            tgt_ccy: None,
+            reduce_only,
+            attach_tpsl: None,
+            cl_ord_id: None,
+        }
+    }
+
+    #[test]
+    fn reduce_only_flag_in_close_body() {
+        let body = close_request(true).to_body();
+        assert_eq!(body["reduceOnly"], json!(true));
+        assert_eq!(body["ordType"], json!("market"));
+        assert_eq!(body["posSide"], json!("long"));
+        assert!(body.get("attachAlgoOrds").is_none());
+    }
+
+    #[test]
+    fn entry_body_omits_reduce_only() {
+        let mut req = close_request(false);
+        req.attach_tpsl = Some(("110".to_string(), "95".to_string()));
+        let body = req.to_body();
+        assert!(body.get("reduceOnly").is_none());
+        assert_eq!(body["attachAlgoOrds"][0]["tpTriggerPx"], json!("110"));
+        assert!(is_bot_client_id(body["attachAlgoOrds"][0]["attachAlgoClOrdId"].as_str()));
+    }
+
+    #[test]
+    fn only_limits_past_the_timeout_expire() {
+        let limit = |symbol: &str, placed_at_ms: i64| PendingLimit { symbol: symbol.to_string(), side: "buy".to_string(), placed_at_ms };
+        let pending: HashMap<String, PendingLimit> = [
+            ("1".to_string(), limit("BTC-USDT-SWAP", 0)),
+            ("2".to_string(), limit("ETH-USDT-SWAP", 50_000)),
+            ("3".to_string(), limit("SOL-USDT-SWAP", 100_000)),
+        ].into_iter().collect();
+        // now = 120s，超时 60s：0s 与 50s 挂出的订单过期 (按挂单先后)，100s 的还在等待
+        assert_eq!(expired_limit_orders(&pending, 120_000, 60_000), ["1", "2"]);
+        assert!(expired_limit_orders(&pending, 30_000, 60_000).is_empty());
+    }
+
+    #[test]
+    fn only_bot_tagged_limits_are_adopted() {
+        let order = |id: &str, cl: &str, reduce_only: &str| json!({
+            "ordId": id, "clOrdId": cl, "instId": "BTC-USDT-SWAP", "side": "buy", "cTime": "0", "reduceOnly": reduce_only,
+        });
+        let bot_id = bot_client_id();
+        assert!(bot_id.len() <= 32 && bot_id.chars().all(|c| c.is_ascii_alphanumeric()));
+        let live = vec![
+            order("1", &bot_id, "false"),
+            // 手动挂的开仓单与止盈单
+            order("2", "", "false"),
+            order("3", "manualtp", "true"),
+            // 带前缀但是 reduce-only 的单也不认领
+            order("4", &bot_id, "true"),
+            // 已跟踪的订单按订单号保留
+            order("5", "", "false"),
+        ];
+        let mut pending: HashMap<String, PendingLimit> = [
+            ("5".to_string(), PendingLimit { symbol: "ETH-USDT-SWAP".to_string(), side: "sell".to_string(), placed_at_ms: 0 }),
+            ("gone".to_string(), PendingLimit { symbol: "ETH-USDT-SWAP".to_string(), side: "sell".to_string(), placed_at_ms: 0 }),
+        ].into_iter().collect();
+        sync_pending_limits(&mut pending, &live, 1_000_000);
+
+        let mut ids: Vec<&str> = pending.keys().map(|k| k.as_str()).collect();
+        ids.sort();
+        assert_eq!(ids, ["1", "5"]);
+        // 手动挂单不在跟踪列表中，永远不会过期撤单
+        let mut expired = expired_limit_orders(&pending, 1_000_000, 120_000);
+        expired.sort();
+        assert_eq!(expired, ["1", "5"]);
+    }
+
+    #[test]
+    fn limit_orders_carry_the_bot_client_id() {
+        let mut req = close_request(false);
+        req.px = Some("100".to_string());
+        req.cl_ord_id = Some(bot_client_id());
+        assert!(is_bot_client_id(req.to_body()["clOrdId"].as_str()));
+        assert!(close_request(true).to_body().get("clOrdId").is_none());
+    }
+
+    #[test]
+    fn leverage_is_only_reset_when_it_changes() {
+        let cache = LeverageCache::default();
+        assert!(!cache.is_current("BTC-USDT-SWAP", 5));
+
+        cache.record("BTC-USDT-SWAP", 5);
+        assert!(cache.is_current("BTC-USDT-SWAP", 5));
+        assert!(!cache.is_current("BTC-USDT-SWAP", 10));
+        assert!(!cache.is_current("ETH-USDT-SWAP", 5));
+
+        cache.invalidate("BTC-USDT-SWAP");
+        assert!(!cache.is_current("BTC-USDT-SWAP", 5));
+    }
+
+    #[test]
+    fn ladder_sizes_follow_lot_and_min_size() {
+        assert_eq!(TradeExecutor::ladder_sizes(10.0, &[0.5, 0.5], 1.0, 1.0), vec![5.0, 5.0]);
+        // 最后一档吃掉对齐余数
+        assert_eq!(TradeExecutor::ladder_sizes(3.0, &[0.5, 0.5], 1.0, 1.0), vec![1.0, 2.0]);
+        // 比例合计不足 1 时剩余仓位留给止损
+        assert_eq!(TradeExecutor::ladder_sizes(10.0, &[0.3, 0.3], 1.0, 1.0), vec![3.0, 3.0]);
+        // 第一档不足最小下单量 -> 并入下一档
+        assert_eq!(TradeExecutor::ladder_sizes(1.0, &[0.5, 0.5], 1.0, 1.0), vec![0.0, 1.0]);
+        // 最后一档不足最小下单量 -> 并入前一档
+        assert_eq!(TradeExecutor::ladder_sizes(5.0, &[0.8, 0.2], 1.0, 2.0), vec![5.0, 0.0]);
+    }
+
+    #[test]
+    fn bracket_resize_after_partial_close_and_add() {
+        // 数量一致 (含浮点尾差) -> 不动
+        assert_eq!(bracket_resize(&[10.0], 10.0, 1.0), None);
+        assert_eq!(bracket_resize(&[0.3], 0.1 + 0.2, 0.1), None);
+        // 部分平仓 10 -> 6：缩小到剩余持仓
+        assert_eq!(bracket_resize(&[10.0], 6.0, 1.0), Some(6.0));
+        // 加仓后两张附带单 (10 + 5)：合并为一张 15
+        assert_eq!(bracket_resize(&[10.0, 5.0], 15.0, 1.0), Some(15.0));
+        // 加仓 0.1 + 0.2 张，持仓按 lot 0.1 对齐为 0.3
+        assert_eq!(bracket_resize(&[0.1], 0.1 + 0.2, 0.1), Some(0.3));
+        // 无保护单或已平仓
+        assert_eq!(bracket_resize(&[], 5.0, 1.0), None);
+        assert_eq!(bracket_resize(&[5.0], 0.0, 1.0), None);
+    }
+
+    #[test]
+    fn pending_brackets_skip_plain_orders_and_mark_full_position_stops() {
+        let data = json!([
+            { "algoId": "1", "instId": "BTC-USDT-SWAP", "posSide": "long", "sz": "10", "tpTriggerPx": "52000", "slTriggerPx": "49000", "cTime": "100" },
+            { "algoId": "2", "instId": "BTC-USDT-SWAP", "posSide": "long", "sz": "", "closeFraction": "1", "tpTriggerPx": "", "slTriggerPx": "49500", "cTime": "200" },
+            { "algoId": "3", "instId": "ETH-USDT-SWAP", "posSide": "short", "sz": "4", "tpTriggerPx": "", "slTriggerPx": "", "cTime": "300" },
+        ]);
+        let brackets = parse_pending_brackets(&data);
+        assert_eq!(brackets.len(), 2);
+        assert_eq!((brackets[0].size, brackets[0].tp_px.as_deref(), brackets[0].created_ms), (Some(10.0), Some("52000"), 100));
+        assert_eq!((brackets[1].size, brackets[1].tp_px.as_deref(), brackets[1].sl_px.as_deref()), (None, None, Some("49500")));
+    }
+
+    #[test]
+    fn foreign_brackets_are_neither_merged_nor_cancelled() {
+        let data = json!([
+            { "algoId": "1", "instId": "BTC-USDT-SWAP", "posSide": "long", "sz": "6", "slTriggerPx": "49000", "cTime": "100", "algoClOrdId": "rtbot1700000000000001" },
+            { "algoId": "2", "instId": "BTC-USDT-SWAP", "posSide": "long", "sz": "4", "slTriggerPx": "49500", "cTime": "200", "algoClOrdId": "" },
+            { "algoId": "3", "instId": "BTC-USDT-SWAP", "posSide": "long", "sz": "4", "tpTriggerPx": "53000", "cTime": "300", "algoClOrdId": "manual42" },
+            { "algoId": "4", "instId": "BTC-USDT-SWAP", "posSide": "long", "sz": "4", "slTriggerPx": "48000", "cTime": "400" },
+        ]);
+        let pending = parse_pending_brackets(&data);
+        let tracked: HashSet<String> = ["4".to_string()].into();
+        let ids: Vec<&str> = bot_brackets(&pending, "BTC-USDT-SWAP", "long", &tracked).iter().map(|b| b.algo_id.as_str()).collect();
+        // 手动挂的 2、3 不参与合并，也就不会被撤
+        assert_eq!(ids, vec!["1", "4"]);
+        assert!(bot_brackets(&pending, "BTC-USDT-SWAP", "short", &tracked).is_empty());
+    }
+
+    fn bill(bill_id: usize, ord_id: &str) -> PnlRecord {
+        PnlRecord {
+            symbol: "BTC-USDT-SWAP".to_string(), pnl: 0.0, fee: 0.0, px: 0.0, ts: 0,
+            type_name: BILL_TYPE_TRADE.to_string(), ord_id: ord_id.to_string(), bill_id: bill_id.to_string(), bal_chg: 0.0,
+        }
+    }
+
+    /// 模拟账单接口：共 pages 页，每页 BILLS_PAGE_LIMIT 条，billId 递减，ordId = "o{billId}"
+    fn mocked_pages(pages: usize) -> impl Fn(Option<String>) -> std::future::Ready<Result<Vec<PnlRecord>>> {
+        let total = pages * BILLS_PAGE_LIMIT;
+        move |after: Option<String>| {
+            let start = after.as_deref().map_or(total, |id| id.parse::<usize>().unwrap() - 1);
+            let page = (0..BILLS_PAGE_LIMIT.min(start)).map(|i| bill(start - i, &format!("o{}", start - i))).collect();
+            std::future::ready(Ok(page))
+        }
+    }
+
+    #[tokio::test]
+    async fn bill_pagination_stops_when_orders_are_found() {
+        let fetch = mocked_pages(5);
+        // o450 在第一页 (500..401)，o250 在第三页 (300..201)
+        let wanted: HashSet<String> = ["o450".to_string(), "o250".to_string()].into();
+        let bills = paginate_bills(&fetch, &wanted, 10).await.unwrap();
+        assert_eq!(bills.len(), 3 * BILLS_PAGE_LIMIT);
+
+        // 没有待同步订单时只取第一页
+        assert_eq!(paginate_bills(&fetch, &HashSet::new(), 10).await.unwrap().len(), BILLS_PAGE_LIMIT);
+        // 找不到的订单：翻到最后一页 (不满一页或空页) 为止
+        let missing: HashSet<String> = ["gone".to_string()].into();
+        assert_eq!(paginate_bills(&fetch, &missing, 10).await.unwrap().len(), 5 * BILLS_PAGE_LIMIT);
+        // 页数上限
+        assert_eq!(paginate_bills(&fetch, &missing, 2).await.unwrap().len(), 2 * BILLS_PAGE_LIMIT);
+    }
+
+    fn position(symbol: &str, side: &str, size: f64) -> PositionSummary {
+        PositionSummary {
+            symbol: symbol.to_string(), size, upl: 0.0, side: side.to_string(), avg_px: 100.0, mark_px: 100.0,
+            leverage: 5, notional_usd: 0.0, margin_usd: 0.0,
+        }
+    }
+
+    #[tokio::test]
+    async fn unprotected_positions_are_retried_until_closed() {
+        let okx = OkxEndpoints { rest_url: "http://127.0.0.1:1".to_string(), ..OkxEndpoints::default() };
+        let ex = TradeExecutor::new(Client::new(), &okx, ExecutionConfig::default(), "USDT".to_string(), PaperConfig::default());
+        let e = anyhow!("algo rejected");
+        ex.defer_protection("BTC-USDT-SWAP", "buy", "long", 0.04, 0.02, &e).await;
+        ex.defer_protection("ETH-USDT-SWAP", "sell", "short", 0.04, 0.02, &e).await;
+        ex.algo_orders.write().await.insert("ETH-USDT-SWAP:short".to_string(), TrackedAlgo { algo_id: "1".to_string(), size: None });
+
+        // 交易所不可达：补挂失败并累计次数，已平掉的 ETH 空单不再补挂
+        let open = vec![position("BTC-USDT-SWAP", "long", 2.0)];
+        for attempt in 1..=2 {
+            let results = ex.retry_unprotected(&open).await;
+            assert_eq!(results.len(), 1);
+            assert_eq!((results[0].symbol.as_str(), results[0].attempts), ("BTC-USDT-SWAP", attempt));
+            assert!(results[0].error.is_some());
+        }
+        assert!(!ex.unprotected.read().await.contains_key("ETH-USDT-SWAP:short"));
+
+        // 平仓后算法单记录与待补挂记录一起清理
+        ex.prune_protection(&[]).await;
+        assert!(ex.algo_orders.read().await.is_empty());
+        assert!(ex.unprotected.read().await.is_empty());
+        assert!(ex.retry_unprotected(&open).await.is_empty());
+    }
+
+    #[test]
+    fn prices_snap_to_tick_grid() {
+        assert_eq!(grid_decimals(0.5), 1);
+        assert_eq!(grid_decimals(2.5), 1);
+        assert_eq!(grid_decimals(0.0001), 4);
+        assert_eq!(grid_decimals(10.0), 0);
+        assert_eq!(grid_decimals(0.00000001), 8);
+
+        assert_eq!(format_on_grid(100.26, 0.5, true), "100.5");
+        assert_eq!(format_on_grid(100.24, 0.5, true), "100.0");
+        assert_eq!(format_on_grid(101.3, 2.5, true), "102.5");
+        assert_eq!(format_on_grid(100.9, 2.5, true), "100.0");
+        assert_eq!(format_on_grid(0.123456, 0.0001, true), "0.1235");
+        assert_eq!(format_on_grid(64_123.0, 10.0, true), "64120");
+    }
+
+    #[test]
+    fn sizes_floor_to_lot_grid() {
+        assert_eq!(format_on_grid(2.99, 0.5, false), "2.5");
+        assert_eq!(format_on_grid(0.3, 0.1, false), "0.3");
+        assert_eq!(format_on_grid(7.4, 2.5, false), "5.0");
+        assert_eq!(format_on_grid(0.00019, 0.0001, false), "0.0001");
+    }
+
+    #[test]
+    fn slippage_is_signed_by_side() {
+        assert!((adverse_slippage("buy", 100.0, 100.6) - 0.006).abs() < 1e-12);
+        assert!((adverse_slippage("sell", 100.0, 99.4) - 0.006).abs() < 1e-12);
+        // 有利成交为负，不会触发拒绝
+        assert!(adverse_slippage("buy", 100.0, 99.0) < 0.0);
+        assert_eq!(adverse_slippage("sell", 0.0, 99.0), 0.0);
+    }
+
+    #[test]
+    fn position_tiers_parse_and_skip_empty_rows() {
+        let resp = json!({"code": "0", "data": [
+            {"tier": "1", "maxLever": "50", "maxSz": "1000"},
+            {"tier": "2", "maxLever": "20", "maxSz": "5000"},
+            {"tier": "3", "maxLever": "", "maxSz": "9000"},
+        ]});
+        let tiers = parse_position_tiers(&resp);
+        assert_eq!(tiers, vec![
+            PositionTier { max_lever: 50.0, max_sz: 1000.0 },
+            PositionTier { max_lever: 20.0, max_sz: 5000.0 },
+        ]);
+    }
+}
@@ -0,0 +1,256 @@
+// 文件名: journal.rs
+// 落库抽象：成交后的 positions / trade_logs / event_log 写入，以及主循环对这三张表的读取只依赖此 trait，便于用内存实现离线测试
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use uuid::Uuid;
+
+use super::event_log::{EventKind, EventLog};
+use super::executor::PositionSummary;
+use super::positions::{PositionEntry, PositionStore};
+use super::snapshot::{LogManager, TradeLevels, TradeRecord};
+use crate::modules::evolution::ExitReason;
+use crate::modules::evolution::stats::{PerformanceReport, VersionStats, WindowSummary};
+
+#[async_trait]
+pub trait TradeJournal: Send + Sync {
+    /// 开仓成交后记录 TP/SL 触发价与开仓时间 (positions)
+    async fn record_entry(&self, symbol: &str, side: &str, entry_price: f64, size: f64, tp_price: f64, sl_price: f64) -> Result<()>;
+
+    /// 开仓/加仓记录 (trade_logs)
+    async fn log_trade(&self, record: &TradeRecord<'_>) -> Result<()>;
+
+    /// 平仓后记录退出原因 (trade_logs)
+    async fn mark_exit(&self, symbol: &str, pos_side: &str, reason: ExitReason) -> Result<()>;
+
+    /// 事件流水，失败只告警
+    async fn append_event(&self, correlation_id: Uuid, symbol: &str, kind: EventKind, order_id: Option<&str>, payload: Value);
+
+    /// 按交易所持仓清理本地持仓缓存 (positions)
+    async fn reconcile_positions(&self, positions: &[PositionSummary]) -> Result<()>;
+
+    /// 本地记录的入场信息 (positions)
+    async fn get_entry(&self, symbol: &str, side: &str) -> Result<Option<PositionEntry>>;
+
+    async fn stop_price(&self, symbol: &str, side: &str) -> Result<Option<f64>>;
+
+    /// 止损移动后同步本地持仓缓存 (positions)
+    async fn set_stop(&self, symbol: &str, side: &str, sl_price: f64) -> Result<()>;
+
+    /// 本次持仓累计支付的资金费 (funding_bills)
+    async fn funding_paid(&self, symbol: &str, side: &str) -> Result<f64>;
+
+    /// 未平仓交易的入场价与止损 (trade_logs，direction 为 buy / sell)
+    async fn fetch_open_trade_levels(&self, symbol: &str, direction: &str) -> Result<Option<TradeLevels>>;
+
+    /// 止损移动后同步交易记录 (trade_logs)
+    async fn update_stop_price(&self, id: Uuid, sl_price: f64) -> Result<()>;
+
+    /// 累计盈利平仓笔数 (杠杆爬坡使用)
+    async fn count_profitable_trades(&self) -> Result<u64>;
+
+    // 以下用于定时状态报告
+    async fn fetch_closed_summary(&self, window_secs: u64) -> Result<WindowSummary>;
+
+    async fn fetch_performance_stats(&self, window_days: i32) -> Result<PerformanceReport>;
+
+    async fn fetch_version_stats(&self, window_days: i32) -> Result<Vec<VersionStats>>;
+}
+
+/// Postgres 实现：组合现有的 LogManager / PositionStore / EventLog
+pub struct PgJournal {
+    logger: Arc<LogManager>,
+    positions: PositionStore,
+    events: EventLog,
+}
+
+impl PgJournal {
+    pub fn new(logger: Arc<LogManager>, positions: PositionStore, events: EventLog) -> Self {
+        Self { logger, positions, events }
+    }
+}
+
+#[async_trait]
+impl TradeJournal for PgJournal {
+    async fn record_entry(&self, symbol: &str, side: &str, entry_price: f64, size: f64, tp_price: f64, sl_price: f64) -> Result<()> {
+        self.positions.record_entry(symbol, side, entry_price, size, tp_price, sl_price).await
+    }
+
+    async fn log_trade(&self, record: &TradeRecord<'_>) -> Result<()> {
+        self.logger.log_trade(record).await
+    }
+
+    async fn mark_exit(&self, symbol: &str, pos_side: &str, reason: ExitReason) -> Result<()> {
+        // trade_logs.direction 记录的是开仓方向 buy / sell
+        let direction = if pos_side == "short" { "sell" } else { "buy" };
+        self.logger.mark_exit_reason(symbol, direction, reason).await
+    }
+
+    async fn append_event(&self, correlation_id: Uuid, symbol: &str, kind: EventKind, order_id: Option<&str>, payload: Value) {
+        self.events.append(correlation_id, symbol, kind, order_id, payload).await
+    }
+
+    async fn reconcile_positions(&self, positions: &[PositionSummary]) -> Result<()> {
+        self.positions.reconcile(positions).await
+    }
+
+    async fn get_entry(&self, symbol: &str, side: &str) -> Result<Option<PositionEntry>> {
+        self.positions.get_entry(symbol, side).await
+    }
+
+    async fn stop_price(&self, symbol: &str, side: &str) -> Result<Option<f64>> {
+        self.positions.stop_price(symbol, side).await
+    }
+
+    async fn set_stop(&self, symbol: &str, side: &str, sl_price: f64) -> Result<()> {
+        self.positions.set_stop(symbol, side, sl_price).await
+    }
+
+    async fn funding_paid(&self, symbol: &str, side: &str) -> Result<f64> {
+        self.positions.funding_paid(symbol, side).await
+    }
+
+    async fn fetch_open_trade_levels(&self, symbol: &str, direction: &str) -> Result<Option<TradeLevels>> {
+        self.logger.fetch_open_trade_levels(symbol, direction).await
+    }
+
+    async fn update_stop_price(&self, id: Uuid, sl_price: f64) -> Result<()> {
+        self.logger.update_stop_price(id, sl_price).await
+    }
+
+    async fn count_profitable_trades(&self) -> Result<u64> {
+        self.logger.count_profitable_trades().await
+    }
+
+    async fn fetch_closed_summary(&self, window_secs: u64) -> Result<WindowSummary> {
+        self.logger.fetch_closed_summary(window_secs).await
+    }
+
+    async fn fetch_performance_stats(&self, window_days: i32) -> Result<PerformanceReport> {
+        self.logger.fetch_performance_stats(window_days).await
+    }
+
+    async fn fetch_version_stats(&self, window_days: i32) -> Result<Vec<VersionStats>> {
+        self.logger.fetch_version_stats(window_days).await
+    }
+}
+
+/// 测试用流水：所有写入保存在内存中，读取只返回写入过的内容
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Debug, Clone)]
+    pub struct LoggedTrade {
+        pub symbol: String,
+        pub direction: String,
+        pub order_id: String,
+        pub initial_margin: f64,
+        pub entry_price: f64,
+        pub tp_price: f64,
+        pub sl_price: f64,
+        pub strategy_version: String,
+    }
+
+    #[derive(Debug, Clone)]
+    pub struct LoggedEvent {
+        pub correlation_id: Uuid,
+        pub kind: EventKind,
+        pub order_id: Option<String>,
+        pub payload: Value,
+    }
+
+    #[derive(Default)]
+    pub struct MemoryJournal {
+        /// (symbol, side, size)
+        pub entries: Mutex<Vec<(String, String, f64)>>,
+        pub trades: Mutex<Vec<LoggedTrade>>,
+        /// (symbol, pos_side, 退出原因)
+        pub exits: Mutex<Vec<(String, String, &'static str)>>,
+        pub events: Mutex<Vec<LoggedEvent>>,
+        /// set_stop 写入的 (symbol, side, 止损价)
+        pub stops: Mutex<Vec<(String, String, f64)>>,
+        /// funding_paid 对任意持仓返回的累计资金费
+        pub funding: Mutex<f64>,
+    }
+
+    #[async_trait]
+    impl TradeJournal for MemoryJournal {
+        async fn record_entry(&self, symbol: &str, side: &str, _entry_price: f64, size: f64, _tp_price: f64, _sl_price: f64) -> Result<()> {
+            self.entries.lock().unwrap().push((symbol.to_string(), side.to_string(), size));
+            Ok(())
+        }
+
+        async fn log_trade(&self, record: &TradeRecord<'_>) -> Result<()> {
+            self.trades.lock().unwrap().push(LoggedTrade {
+                symbol: record.symbol.to_string(),
+                direction: record.direction.to_string(),
+                order_id: record.order_id.to_string(),
+                initial_margin: record.initial_margin,
+                entry_price: record.entry_price,
+                tp_price: record.tp_price,
+                sl_price: record.sl_price,
+                strategy_version: record.strategy_version.to_string(),
+            });
+            Ok(())
+        }
+
+        async fn mark_exit(&self, symbol: &str, pos_side: &str, reason: ExitReason) -> Result<()> {
+            self.exits.lock().unwrap().push((symbol.to_string(), pos_side.to_string(), reason.as_str()));
+            Ok(())
+        }
+
+        async fn append_event(&self, correlation_id: Uuid, _symbol: &str, kind: EventKind, order_id: Option<&str>, payload: Value) {
+            self.events.lock().unwrap().push(LoggedEvent { correlation_id, kind, order_id: order_id.map(str::to_string), payload });
+        }
+
+        async fn reconcile_positions(&self, _positions: &[PositionSummary]) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_entry(&self, _symbol: &str, _side: &str) -> Result<Option<PositionEntry>> {
+            Ok(None)
+        }
+
+        async fn stop_price(&self, symbol: &str, side: &str) -> Result<Option<f64>> {
+            Ok(self.stops.lock().unwrap().iter().rev().find(|(s, d, _)| s == symbol && d == side).map(|(_, _, sl)| *sl))
+        }
+
+        async fn set_stop(&self, symbol: &str, side: &str, sl_price: f64) -> Result<()> {
+            self.stops.lock().unwrap().push((symbol.to_string(), side.to_string(), sl_price));
+            Ok(())
+        }
+
+        async fn funding_paid(&self, _symbol: &str, _side: &str) -> Result<f64> {
+            Ok(*self.funding.lock().unwrap())
+        }
+
+        async fn fetch_open_trade_levels(&self, _symbol: &str, _direction: &str) -> Result<Option<TradeLevels>> {
+            Ok(None)
+        }
+
+        async fn update_stop_price(&self, _id: Uuid, _sl_price: f64) -> Result<()> {
+            Ok(())
+        }
+
+        async fn count_profitable_trades(&self) -> Result<u64> {
+            Ok(0)
+        }
+
+        async fn fetch_closed_summary(&self, window_secs: u64) -> Result<WindowSummary> {
+            Ok(WindowSummary::compute(&[], window_secs))
+        }
+
+        async fn fetch_performance_stats(&self, window_days: i32) -> Result<PerformanceReport> {
+            Ok(PerformanceReport::compute(&[], window_days))
+        }
+
+        async fn fetch_version_stats(&self, _window_days: i32) -> Result<Vec<VersionStats>> {
+            Ok(Vec::new())
+        }
+    }
+}
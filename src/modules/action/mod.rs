@@ -1,5 +1,20 @@
 pub mod executor;
 pub mod snapshot;
+pub mod exchange;
+pub mod sizing;
+pub mod positions;
+pub mod paper;
+pub mod shadow;
+pub mod tpsl_monitor;
+pub mod event_log;
+pub mod journal;
+pub mod entry;
+pub mod close;
+pub mod symbol_switch;
 
 pub use executor::TradeExecutor;
-pub use snapshot::LogManager;
\ No newline at end of file
+pub use exchange::Exchange;
+pub use snapshot::LogManager;
+pub use positions::PositionStore;
+pub use event_log::{EventKind, EventLog};
+pub use journal::PgJournal;
@@ -0,0 +1,156 @@
+// 文件名: paper.rs
+// 干跑 (DRY_RUN=1) 模式下的模拟账本：从配置的初始资金开始，按模拟成交与手续费记账
+// 仓位计算与回撤熔断读取这里的权益，而不是真实账户余额
+
+use std::collections::HashMap;
+
+use rust_decimal::Decimal;
+
+use super::executor::{BalanceSummary, PositionSummary};
+use crate::utils::money::{dec, to_f64};
+
+#[derive(Debug, Clone)]
+struct PaperPosition {
+    /// 持仓张数 (现货为币数量)
+    size: f64,
+    avg_px: f64,
+    mark_px: f64,
+    /// 每张合约对应的币数量
+    face_value: f64,
+    leverage: u32,
+}
+
+impl PaperPosition {
+    fn direction(side: &str) -> f64 {
+        if side == "long" { 1.0 } else { -1.0 }
+    }
+
+    fn notional(&self) -> f64 {
+        self.size * self.face_value * self.mark_px
+    }
+
+    fn upl(&self, side: &str) -> f64 {
+        (self.mark_px - self.avg_px) * self.size * self.face_value * Self::direction(side)
+    }
+}
+
+pub struct PaperLedger {
+    /// 已实现盈亏与手续费结算后的现金余额 (十进制累加，长时间运行不漂移)
+    cash: Decimal,
+    fee_rate: f64,
+    /// key: (symbol, long/short)
+    positions: HashMap<(String, String), PaperPosition>,
+}
+
+impl PaperLedger {
+    pub fn new(starting_equity: f64, fee_rate: f64) -> Self {
+        Self { cash: dec(starting_equity), fee_rate, positions: HashMap::new() }
+    }
+
+    /// 记录一笔模拟成交：买入 long / 卖出 short 为开仓，反向为平仓 (平仓数量不超过持仓)
+    #[allow(clippy::too_many_arguments)]
+    pub fn apply_fill(&mut self, symbol: &str, side: &str, pos_side: &str, size: f64, px: f64, face_value: f64, leverage: u32) {
+        if size <= 0.0 || px <= 0.0 { return; }
+        let face_value = if face_value > 0.0 { face_value } else { 1.0 };
+        let is_open = (side == "buy") == (pos_side == "long");
+        let key = (symbol.to_string(), pos_side.to_string());
+
+        let filled = if is_open {
+            let pos = self.positions.entry(key).or_insert(PaperPosition {
+                size: 0.0, avg_px: px, mark_px: px, face_value, leverage: leverage.max(1),
+            });
+            pos.avg_px = (pos.avg_px * pos.size + px * size) / (pos.size + size);
+            pos.size += size;
+            pos.mark_px = px;
+            size
+        } else {
+            let Some(pos) = self.positions.get_mut(&key) else { return; };
+            let closed = size.min(pos.size);
+            self.cash += (dec(px) - dec(pos.avg_px)) * dec(closed) * dec(face_value) * dec(PaperPosition::direction(pos_side));
+            pos.size -= closed;
+            pos.mark_px = px;
+            if pos.size <= 1e-12 {
+                self.positions.remove(&key);
+            }
+            closed
+        };
+        self.cash -= dec(filled) * dec(face_value) * dec(px) * dec(self.fee_rate);
+    }
+
+    /// 用最新价格更新该币种所有模拟持仓的浮动盈亏
+    pub fn mark(&mut self, symbol: &str, px: f64) {
+        if px <= 0.0 { return; }
+        for ((sym, _), pos) in self.positions.iter_mut() {
+            if sym == symbol { pos.mark_px = px; }
+        }
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        let mut list: Vec<String> = self.positions.keys().map(|(s, _)| s.clone()).collect();
+        list.sort();
+        list.dedup();
+        list
+    }
+
+    /// 权益 = 现金 + 浮动盈亏；可用 = 权益 - 保证金占用
+    pub fn balance(&self) -> BalanceSummary {
+        let upl: f64 = self.positions.iter().map(|((_, side), p)| p.upl(side)).sum();
+        let margin: f64 = self.positions.values().map(|p| p.notional() / p.leverage as f64).sum();
+        let equity = to_f64(self.cash) + upl;
+        BalanceSummary { total_equity: equity, available_balance: (equity - margin).max(0.0), maintenance_margin: 0.0 }
+    }
+
+    pub fn positions(&self) -> Vec<PositionSummary> {
+        self.positions.iter().map(|((symbol, side), p)| PositionSummary {
+            symbol: symbol.clone(),
+            size: p.size,
+            upl: p.upl(side),
+            side: side.clone(),
+            avg_px: p.avg_px,
+            mark_px: p.mark_px,
+            leverage: p.leverage,
+            notional_usd: p.notional(),
+            margin_usd: p.notional() / p.leverage as f64,
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn round_trip_realizes_pnl_minus_fees() {
+        let mut ledger = PaperLedger::new(10_000.0, 0.0005);
+        // 10 张 × 0.01 BTC @ 50000 = 5000 名义，开仓手续费 2.5
+        ledger.apply_fill("BTC-USDT-SWAP", "buy", "long", 10.0, 50_000.0, 0.01, 5);
+        let bal = ledger.balance();
+        assert_close(bal.total_equity, 9_997.5);
+        assert_close(bal.available_balance, 9_997.5 - 1_000.0);
+
+        ledger.mark("BTC-USDT-SWAP", 51_000.0);
+        assert_close(ledger.positions()[0].upl, 100.0);
+
+        // 平仓 @ 51000：+100 盈利，手续费 2.55
+        ledger.apply_fill("BTC-USDT-SWAP", "sell", "long", 10.0, 51_000.0, 0.01, 5);
+        assert!(ledger.positions().is_empty());
+        assert_close(ledger.balance().total_equity, 10_000.0 - 2.5 + 100.0 - 2.55);
+    }
+
+    #[test]
+    fn short_close_is_capped_at_position_size() {
+        let mut ledger = PaperLedger::new(1_000.0, 0.0);
+        ledger.apply_fill("ETH-USDT-SWAP", "sell", "short", 2.0, 3_000.0, 0.1, 1);
+        ledger.apply_fill("ETH-USDT-SWAP", "buy", "short", 5.0, 2_900.0, 0.1, 1);
+        assert!(ledger.positions().is_empty());
+        assert_close(ledger.balance().total_equity, 1_020.0);
+
+        // 无持仓时的平仓单不影响账本
+        ledger.apply_fill("ETH-USDT-SWAP", "buy", "short", 1.0, 2_900.0, 0.1, 1);
+        assert_close(ledger.balance().total_equity, 1_020.0);
+    }
+}
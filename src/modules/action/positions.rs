@@ -0,0 +1,212 @@
+use sqlx::{PgPool, Row};
+use anyhow::Result;
+use std::time::Duration;
+use super::executor::PositionSummary;
+
+/// 本地记录的持仓入场信息 (重启后仍可用)
+#[derive(Debug, Clone)]
+#[allow(dead_code)]
+pub struct PositionEntry {
+    pub entry_price: f64,
+    pub size: f64,
+    pub opened_at: String,
+    /// 距开仓的秒数
+    pub age_sec: f64,
+    /// 距最近一次开仓/加仓的秒数 (旧记录没有时为 None)
+    pub since_entry_sec: Option<f64>,
+}
+
+impl PositionEntry {
+    pub fn age(&self) -> Duration {
+        Duration::from_secs_f64(self.age_sec.max(0.0))
+    }
+
+    pub fn since_entry(&self) -> Option<Duration> {
+        self.since_entry_sec.map(|s| Duration::from_secs_f64(s.max(0.0)))
+    }
+}
+
+/// 客户端执行的 TP/SL 触发价 (0 = 未设置)
+#[derive(Debug, Clone)]
+pub struct PositionLevels {
+    pub symbol: String,
+    pub side: String,
+    pub size: f64,
+    pub tp_price: f64,
+    pub sl_price: f64,
+}
+
+/// 持仓入场价/开仓时间的持久化缓存，每轮与 OKX 持仓对账
+/// OKX 持仓接口在重启后无法提供可靠的开仓时间，持仓时长、R 倍数等逻辑以此表为准
+pub struct PositionStore {
+    pool: PgPool,
+}
+
+impl PositionStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// 与 OKX 持仓对账：新持仓记录开仓时间，已有持仓更新均价与数量，已消失的持仓删除
+    /// 注意：只能传入成功拉取的持仓列表，否则会误删全部记录
+    pub async fn reconcile(&self, positions: &[PositionSummary]) -> Result<()> {
+        let mut keys = Vec::with_capacity(positions.len());
+        for pos in positions.iter().filter(|p| p.size > 0.0) {
+            sqlx::query(
+                "INSERT INTO positions (symbol, side, entry_price, size)
+                 VALUES ($1, $2, NULLIF($3, 0), $4)
+                 ON CONFLICT (symbol, side) DO UPDATE SET
+                    entry_price = COALESCE(EXCLUDED.entry_price, positions.entry_price),
+                    size = EXCLUDED.size,
+                    updated_at = CURRENT_TIMESTAMP"
+            )
+            .bind(&pos.symbol)
+            .bind(&pos.side)
+            .bind(pos.avg_px)
+            .bind(pos.size)
+            .execute(&self.pool)
+            .await?;
+            keys.push(format!("{}:{}", pos.symbol, pos.side));
+        }
+
+        sqlx::query("DELETE FROM positions WHERE NOT (symbol || ':' || side = ANY($1))")
+            .bind(&keys)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_entry(&self, symbol: &str, side: &str) -> Result<Option<PositionEntry>> {
+        let row = sqlx::query(
+            "SELECT entry_price::FLOAT8 AS entry_price, size::FLOAT8 AS size, opened_at::TEXT AS opened_at,
+                    EXTRACT(EPOCH FROM (NOW() - opened_at))::FLOAT8 AS age_sec,
+                    EXTRACT(EPOCH FROM (NOW() - last_entry_at))::FLOAT8 AS since_entry_sec
+             FROM positions
+             WHERE symbol = $1 AND side = $2"
+        )
+        .bind(symbol)
+        .bind(side)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(r) => Ok(Some(PositionEntry {
+                entry_price: r.try_get::<Option<f64>, _>("entry_price")?.unwrap_or(0.0),
+                size: r.try_get("size")?,
+                opened_at: r.try_get("opened_at")?,
+                age_sec: r.try_get("age_sec")?,
+                since_entry_sec: r.try_get("since_entry_sec")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// 开仓成交后记录 TP/SL 触发价与开仓时间 (持仓行尚未对账时先插入，加仓时覆盖为新的触发价)
+    pub async fn record_entry(&self, symbol: &str, side: &str, entry_price: f64, size: f64, tp_price: f64, sl_price: f64) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO positions (symbol, side, entry_price, size, tp_price, sl_price)
+             VALUES ($1, $2, NULLIF($3, 0), $4, NULLIF($5, 0), NULLIF($6, 0))
+             ON CONFLICT (symbol, side) DO UPDATE SET
+                tp_price = EXCLUDED.tp_price,
+                sl_price = EXCLUDED.sl_price,
+                last_entry_at = CURRENT_TIMESTAMP,
+                updated_at = CURRENT_TIMESTAMP"
+        )
+        .bind(symbol)
+        .bind(side)
+        .bind(entry_price)
+        .bind(size)
+        .bind(tp_price)
+        .bind(sl_price)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 移动止损 (保本等) 后同步客户端止损价
+    pub async fn set_stop(&self, symbol: &str, side: &str, sl_price: f64) -> Result<()> {
+        sqlx::query("UPDATE positions SET sl_price = $3, updated_at = CURRENT_TIMESTAMP WHERE symbol = $1 AND side = $2")
+            .bind(symbol)
+            .bind(side)
+            .bind(sl_price)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 本地记录的止损价，未记录 (功能上线前的旧持仓) 时为 None
+    pub async fn stop_price(&self, symbol: &str, side: &str) -> Result<Option<f64>> {
+        let row = sqlx::query("SELECT sl_price::FLOAT8 AS sl_price FROM positions WHERE symbol = $1 AND side = $2")
+            .bind(symbol)
+            .bind(side)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(r) => Ok(r.try_get::<Option<f64>, _>("sl_price")?),
+            None => Ok(None),
+        }
+    }
+
+    /// 开仓以来该币种净支付的资金费 (收取为负)，来自已同步的资金费账单
+    /// 账单不区分持仓方向，同一币种同时持有多空时结果是两边的合计
+    pub async fn funding_paid(&self, symbol: &str, side: &str) -> Result<f64> {
+        let paid: Option<f64> = sqlx::query_scalar(
+            "SELECT -SUM(f.amount)::FLOAT8 FROM funding_bills f
+             JOIN positions p ON p.symbol = f.symbol
+             WHERE p.symbol = $1 AND p.side = $2 AND f.ts >= p.opened_at"
+        )
+        .bind(symbol)
+        .bind(side)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(paid.unwrap_or(0.0))
+    }
+
+    /// 所有设置了 TP 或 SL 的持仓
+    pub async fn levels(&self) -> Result<Vec<PositionLevels>> {
+        let rows = sqlx::query(
+            "SELECT symbol, side, size::FLOAT8 AS size, tp_price::FLOAT8 AS tp_price, sl_price::FLOAT8 AS sl_price
+             FROM positions
+             WHERE size > 0 AND (tp_price IS NOT NULL OR sl_price IS NOT NULL)"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut list = Vec::with_capacity(rows.len());
+        for r in rows {
+            list.push(PositionLevels {
+                symbol: r.try_get("symbol")?,
+                side: r.try_get("side")?,
+                size: r.try_get("size")?,
+                tp_price: r.try_get::<Option<f64>, _>("tp_price")?.unwrap_or(0.0),
+                sl_price: r.try_get::<Option<f64>, _>("sl_price")?.unwrap_or(0.0),
+            });
+        }
+        Ok(list)
+    }
+
+    /// 触发平仓前原子地清空触发价，返回是否由本次调用抢到 (已被清空说明平仓已在进行)
+    pub async fn claim_exit(&self, symbol: &str, side: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE positions SET tp_price = NULL, sl_price = NULL
+             WHERE symbol = $1 AND side = $2 AND (tp_price IS NOT NULL OR sl_price IS NOT NULL)"
+        )
+        .bind(symbol)
+        .bind(side)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 平仓下单失败时恢复触发价，下一轮继续监控
+    pub async fn restore_levels(&self, levels: &PositionLevels) -> Result<()> {
+        sqlx::query("UPDATE positions SET tp_price = NULLIF($3, 0), sl_price = NULLIF($4, 0) WHERE symbol = $1 AND side = $2")
+            .bind(&levels.symbol)
+            .bind(&levels.side)
+            .bind(levels.tp_price)
+            .bind(levels.sl_price)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,234 @@
+// 文件名: shadow.rs
+// 影子模式 (SHADOW_MODE=1)：完整运行真实行情 + 真实大脑，但只记录"本来会做的交易"
+// 不访问账户、不需要交易 API Key；按实时价格跟踪假设盈亏，用于入金前评估策略
+// 与 DRY_RUN 的区别：不模拟下单与资金，只按价格百分比 (含杠杆) 记录每个信号的结果
+
+use std::env;
+use std::fmt;
+
+use anyhow::Result;
+use sqlx::{PgPool, Row};
+
+use super::tpsl_monitor::level_breach;
+use crate::modules::evolution::ExitReason;
+
+pub fn is_enabled() -> bool {
+    env::var("SHADOW_MODE").unwrap_or_default() == "1"
+}
+
+/// 一笔未平仓的影子交易
+#[derive(Debug, Clone)]
+pub struct ShadowTrade {
+    pub id: uuid::Uuid,
+    pub symbol: String,
+    /// long / short
+    pub side: String,
+    pub entry_price: f64,
+    pub tp_price: f64,
+    pub sl_price: f64,
+    pub leverage: u32,
+}
+
+/// 影子交易开仓参数
+pub struct ShadowEntry<'a> {
+    pub symbol: &'a str,
+    pub side: &'a str,
+    pub price: f64,
+    pub tp_pct: f64,
+    pub sl_pct: f64,
+    pub leverage: u32,
+    pub strategy_version: &'a str,
+    pub reason: &'a str,
+}
+
+/// 按当前价判断是否触及 TP/SL (影子模式下没有真实的条件单)
+pub fn shadow_exit(trade: &ShadowTrade, price: f64) -> Option<ExitReason> {
+    level_breach(&trade.side, trade.tp_price, trade.sl_price, price)
+}
+
+/// 假设收益率 (保证金口径，含杠杆)
+pub fn shadow_return(side: &str, entry: f64, exit: f64, leverage: u32) -> f64 {
+    if entry <= 0.0 { return 0.0; }
+    let move_pct = if side == "short" { (entry - exit) / entry } else { (exit - entry) / entry };
+    move_pct * leverage.max(1) as f64
+}
+
+/// 影子模式绩效汇总
+#[derive(Debug, Clone)]
+pub struct ShadowReport {
+    pub window_days: i32,
+    pub closed: usize,
+    pub wins: usize,
+    /// 单笔平均收益率 (保证金口径)
+    pub avg_return: f64,
+    pub best_return: f64,
+    pub worst_return: f64,
+    pub open: usize,
+}
+
+impl ShadowReport {
+    pub fn compute(returns: &[f64], open: usize, window_days: i32) -> Self {
+        let closed = returns.len();
+        Self {
+            window_days,
+            closed,
+            wins: returns.iter().filter(|r| **r > 0.0).count(),
+            avg_return: if closed == 0 { 0.0 } else { returns.iter().sum::<f64>() / closed as f64 },
+            best_return: returns.iter().copied().fold(f64::NAN, f64::max),
+            worst_return: returns.iter().copied().fold(f64::NAN, f64::min),
+            open,
+        }
+    }
+}
+
+impl fmt::Display for ShadowReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.closed == 0 {
+            return write!(f, "Last {}d | No closed shadow trades yet | Open: {}", self.window_days, self.open);
+        }
+        write!(f,
+            "Last {}d | Closed: {} | Win Rate: {:.1}% | Avg Return: {:+.2}% | Best: {:+.2}% | Worst: {:+.2}% | Open: {}",
+            self.window_days, self.closed, self.wins as f64 / self.closed as f64 * 100.0,
+            self.avg_return * 100.0, self.best_return * 100.0, self.worst_return * 100.0, self.open
+        )
+    }
+}
+
+/// 影子交易账本 (shadow_trades 表)
+pub struct ShadowBook {
+    pool: PgPool,
+}
+
+impl ShadowBook {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// 记录一个开仓信号；同方向已有未平仓影子交易时视为加仓信号，不重复记录
+    pub async fn open(&self, entry: &ShadowEntry<'_>) -> Result<bool> {
+        let (tp_price, sl_price) = if entry.side == "short" {
+            (entry.price * (1.0 - entry.tp_pct), entry.price * (1.0 + entry.sl_pct))
+        } else {
+            (entry.price * (1.0 + entry.tp_pct), entry.price * (1.0 - entry.sl_pct))
+        };
+        let result = sqlx::query(
+            "INSERT INTO shadow_trades (symbol, side, entry_price, tp_price, sl_price, leverage, strategy_version, reason)
+             SELECT $1, $2, $3, $4, $5, $6, $7, $8
+             WHERE NOT EXISTS (SELECT 1 FROM shadow_trades WHERE symbol = $1 AND side = $2 AND closed_at IS NULL)"
+        )
+        .bind(entry.symbol)
+        .bind(entry.side)
+        .bind(entry.price)
+        .bind(tp_price)
+        .bind(sl_price)
+        .bind(entry.leverage as i32)
+        .bind(entry.strategy_version)
+        .bind(entry.reason)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn open_trades(&self, symbol: &str) -> Result<Vec<ShadowTrade>> {
+        let rows = sqlx::query(
+            "SELECT id, symbol, side, entry_price::FLOAT8 AS entry_price, tp_price::FLOAT8 AS tp_price,
+                    sl_price::FLOAT8 AS sl_price, leverage
+             FROM shadow_trades
+             WHERE symbol = $1 AND closed_at IS NULL"
+        )
+        .bind(symbol)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut trades = Vec::with_capacity(rows.len());
+        for r in rows {
+            trades.push(ShadowTrade {
+                id: r.try_get("id")?,
+                symbol: r.try_get("symbol")?,
+                side: r.try_get("side")?,
+                entry_price: r.try_get("entry_price")?,
+                tp_price: r.try_get::<Option<f64>, _>("tp_price")?.unwrap_or(0.0),
+                sl_price: r.try_get::<Option<f64>, _>("sl_price")?.unwrap_or(0.0),
+                leverage: r.try_get::<i32, _>("leverage")?.max(1) as u32,
+            });
+        }
+        Ok(trades)
+    }
+
+    pub async fn close(&self, trade: &ShadowTrade, price: f64, reason: ExitReason) -> Result<f64> {
+        let ret = shadow_return(&trade.side, trade.entry_price, price, trade.leverage);
+        sqlx::query(
+            "UPDATE shadow_trades SET exit_price = $1, return_pct = $2, exit_reason = $3, closed_at = NOW()
+             WHERE id = $4 AND closed_at IS NULL"
+        )
+        .bind(price)
+        .bind(ret)
+        .bind(reason.as_str())
+        .bind(trade.id)
+        .execute(&self.pool)
+        .await?;
+        Ok(ret)
+    }
+
+    /// 用最新价格检查该币种未平仓影子交易的 TP/SL，返回本次平仓的 (交易, 原因, 收益率)
+    pub async fn mark(&self, symbol: &str, price: f64) -> Result<Vec<(ShadowTrade, ExitReason, f64)>> {
+        let mut closed = Vec::new();
+        for trade in self.open_trades(symbol).await? {
+            if let Some(reason) = shadow_exit(&trade, price) {
+                let ret = self.close(&trade, price, reason).await?;
+                closed.push((trade, reason, ret));
+            }
+        }
+        Ok(closed)
+    }
+
+    pub async fn report(&self, window_days: i32) -> Result<ShadowReport> {
+        let returns: Vec<f64> = sqlx::query_scalar(
+            "SELECT return_pct::FLOAT8 FROM shadow_trades
+             WHERE closed_at IS NOT NULL AND closed_at > NOW() - make_interval(days => $1)
+             ORDER BY closed_at ASC"
+        )
+        .bind(window_days)
+        .fetch_all(&self.pool)
+        .await?;
+        let open: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM shadow_trades WHERE closed_at IS NULL")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(ShadowReport::compute(&returns, open.max(0) as usize, window_days))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(side: &str) -> ShadowTrade {
+        let (tp_price, sl_price) = if side == "long" { (110.0, 95.0) } else { (90.0, 105.0) };
+        ShadowTrade {
+            id: uuid::Uuid::nil(), symbol: "BTC-USDT-SWAP".to_string(), side: side.to_string(),
+            entry_price: 100.0, tp_price, sl_price, leverage: 5,
+        }
+    }
+
+    #[test]
+    fn exits_and_returns_follow_side() {
+        assert_eq!(shadow_exit(&trade("long"), 111.0), Some(ExitReason::TpHit));
+        assert_eq!(shadow_exit(&trade("long"), 94.0), Some(ExitReason::SlHit));
+        assert_eq!(shadow_exit(&trade("long"), 100.0), None);
+        assert_eq!(shadow_exit(&trade("short"), 89.0), Some(ExitReason::TpHit));
+        assert_eq!(shadow_exit(&trade("short"), 106.0), Some(ExitReason::SlHit));
+
+        // 5x 杠杆：价格 +10% -> +50%，空单 -5% 价格 -> +25%
+        assert!((shadow_return("long", 100.0, 110.0, 5) - 0.5).abs() < 1e-9);
+        assert!((shadow_return("short", 100.0, 95.0, 5) - 0.25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn report_summarizes_returns() {
+        let report = ShadowReport::compute(&[0.5, -0.2, 0.1], 2, 30);
+        assert_eq!((report.closed, report.wins, report.open), (3, 2, 2));
+        assert!((report.avg_return - 0.4 / 3.0).abs() < 1e-9);
+        assert_eq!((report.best_return, report.worst_return), (0.5, -0.2));
+        assert!(ShadowReport::compute(&[], 0, 30).to_string().contains("No closed shadow trades"));
+    }
+}
@@ -0,0 +1,465 @@
+use rust_decimal::Decimal;
+use tracing::{info, warn};
+
+use super::exchange::Exchange;
+use crate::utils::money;
+use crate::config::risk_profile::{AvailableFallback, DrawdownCurve, DrawdownScalingConfig, RiskProfile, TradingMode};
+
+/// 单次仓位计算的输入
+pub struct SizingRequest<'a> {
+    pub symbol: &'a str,
+    pub equity: f64,
+    pub available_equity: f64,
+    pub kelly_fraction: f64,
+    pub leverage: u32,
+    pub price: f64,
+    /// 止损距离 (占价格比例，0.02 = 2%)，0 = 未知，不做单笔亏损限制
+    pub sl_pct: f64,
+    /// 当前回撤 (相对启动资金基准，0.05 = 5%) 与最大回撤上限，用于回撤降仓
+    pub drawdown: f64,
+    pub max_drawdown: f64,
+}
+
+/// 凯利公式: f = p - (1 - p) / b，赔率非正时返回 0
+pub fn kelly_fraction(win_rate: f64, risk_reward_ratio: f64) -> f64 {
+    if risk_reward_ratio > 0.0 { win_rate - ((1.0 - win_rate) / risk_reward_ratio) } else { 0.0 }
+}
+
+/// 实际投入保证金占权益比例：凯利值 × 乘数，限制在 [1%, max_pct]
+pub fn kelly_margin_pct(kelly_fraction: f64, multiplier: f64, max_pct: f64) -> f64 {
+    let safe_kelly = kelly_fraction * multiplier;
+    if safe_kelly > max_pct { max_pct } else if safe_kelly < 0.01 { 0.01 } else { safe_kelly }
+}
+
+/// 回撤降仓系数 (0, 1]：回撤为 0 时为 1，达到 max_drawdown 时为 min_scale，超出上限后保持 min_scale
+pub fn drawdown_scale(cfg: &DrawdownScalingConfig, drawdown: f64, max_drawdown: f64) -> f64 {
+    if !cfg.enabled || max_drawdown <= 0.0 || drawdown <= 0.0 { return 1.0; }
+    let progress = (drawdown / max_drawdown).min(1.0);
+    match cfg.curve {
+        DrawdownCurve::Linear => 1.0 - (1.0 - cfg.min_scale) * progress,
+        DrawdownCurve::Exponential => cfg.min_scale.powf(progress),
+    }
+}
+
+/// 单笔亏损上限对应的最大数量 = 最大亏损 / (单位名义价值 × 止损距离)
+/// 未配置或止损距离未知时返回 None
+pub fn loss_cap_units(max_loss_usd: Option<f64>, unit_notional: Decimal, sl_pct: f64) -> Option<Decimal> {
+    let max_loss = max_loss_usd.filter(|u| *u > 0.0)?;
+    let unit_loss = unit_notional * money::dec(sl_pct);
+    (sl_pct > 0.0 && unit_loss > Decimal::ZERO).then(|| money::dec(max_loss) / unit_loss)
+}
+
+/// fallback = Grab 时使用的可用余额比例
+const GRAB_RATIO: f64 = 0.95;
+
+/// 目标保证金 (或现货下单金额，已受 max_order_size_pct 限制)，超过可用余额时按 fallback 缩减
+/// MaxOrderPct 截到可用余额，目标与可用余额相差一分钱时下单量也只差一分钱 (没有断崖)
+pub fn margin_within_available(target: f64, available: f64, fallback: AvailableFallback) -> f64 {
+    if target <= available { return target; }
+    match fallback {
+        AvailableFallback::MaxOrderPct => available,
+        AvailableFallback::Grab => money::to_f64(money::dec(available) * money::dec(GRAB_RATIO)),
+    }
+}
+
+/// 扣除预留资金后可用于开仓的余额 = max(可用余额 - 权益 × reserve_pct, 0)
+/// 预留部分按总权益计算：持仓越多可用余额越少，但预留额不变，始终保留同样的缓冲
+pub fn deployable_available(equity: f64, available: f64, reserve_pct: f64) -> f64 {
+    let reserve = money::dec(equity.max(0.0)) * money::dec(reserve_pct);
+    money::to_f64((money::dec(available) - reserve).max(Decimal::ZERO))
+}
+
+/// 凯利仓位计算，返回合约张数 (现货模式为币本位数量，0 表示不开仓)
+pub async fn calculate_position_size_kelly(req: &SizingRequest<'_>, risk: &RiskProfile, exchange: &dyn Exchange) -> f64 {
+    let req = &SizingRequest {
+        available_equity: deployable_available(req.equity, req.available_equity, risk.kelly.reserve_pct),
+        ..*req
+    };
+    // 回撤降仓：缩小单笔上限 (降仓后的上限低于 1% 下限时以上限为准)
+    let scale = drawdown_scale(&risk.drawdown_scaling, req.drawdown, req.max_drawdown);
+    let max_pct = risk.max_order_size_pct * scale;
+    if scale < 1.0 {
+        info!("📉 [{}] Drawdown {:.2}% / {:.2}%: max order size scaled x{:.2} -> {:.2}%",
+            req.symbol, req.drawdown * 100.0, req.max_drawdown * 100.0, scale, max_pct * 100.0);
+    }
+    let actual_pct = kelly_margin_pct(req.kelly_fraction, risk.kelly.multiplier, max_pct).min(max_pct);
+    // 记录最终决定仓位大小的约束，便于排查 "为什么只开了这么点"
+    let binding = if actual_pct < req.kelly_fraction * risk.kelly.multiplier { "max_order_size_pct" } else { "kelly" };
+    if risk.execution.trading_mode == TradingMode::Spot {
+        return spot_quantity(req, actual_pct, binding, risk, exchange).await;
+    }
+    let (symbol, price, leverage, available_equity) = (req.symbol, req.price, req.leverage, req.available_equity);
+    
+    let meta = exchange.instrument_meta(symbol).await;
+    let face_val = meta.as_ref().map(|m| m.face_value).unwrap_or(0.0);
+    let min_sz = meta.as_ref().map(|m| m.min_sz).unwrap_or(1.0);
+
+    if price * face_val == 0.0 {
+        warn!("⚠️ [{}] Cannot size order: {} (price {}, face value {}). Skipped.",
+            symbol, if meta.is_none() { "instrument meta missing" } else { "zero face value" }, price, face_val);
+        return 0.0;
+    }
+
+    // 张数与保证金在十进制中计算，避免 lotSz 网格边界上的浮点尾差
+    let lev = Decimal::from(leverage.max(1));
+    let unit_notional = money::dec(price) * money::dec(face_val);
+    let (min_contracts, available) = (money::dec(min_sz), money::dec(available_equity));
+    let min_cost_margin = unit_notional * min_contracts / lev;
+    
+    if available < min_cost_margin {
+        warn!("💰 资金不足: {} 最小 {}张合约需 ${:.2} (杠杆{}x)，但可用余额仅 ${:.2}。跳过。", 
+            symbol, min_sz, min_cost_margin, leverage, available_equity);
+        return 0.0; 
+    }
+
+    let target_margin = money::to_f64(money::dec(req.equity) * money::dec(actual_pct));
+    let margin = margin_within_available(target_margin, available_equity, risk.kelly.available_fallback);
+    let mut binding = if margin < target_margin { "available balance" } else { binding };
+
+    let mut contracts = money::dec(margin) * lev / unit_notional;
+    if contracts < min_contracts {
+        contracts = min_contracts;
+        binding = "min order size";
+    }
+
+    // 单笔亏损上限：止损触发时的亏损 = 张数 × 面值 × 价格 × 止损距离
+    let lot_sz = meta.as_ref().map_or(0.0, |m| m.lot_sz);
+    if let Some(cap) = loss_cap_units(risk.max_loss_per_trade_usd, unit_notional, req.sl_pct) {
+        let cap = money::snap_to_grid(cap, money::dec(lot_sz), false);
+        if contracts > cap {
+            if cap < min_contracts {
+                warn!("💵 [{}] Minimum order size {} would lose more than ${} at a {:.2}% stop. Skipped.",
+                    symbol, min_sz, risk.max_loss_per_trade_usd.unwrap_or_default(), req.sl_pct * 100.0);
+                return 0.0;
+            }
+            contracts = cap;
+            binding = "max_loss_per_trade_usd";
+        }
+    }
+
+    // 交易所单笔下单上限与该杠杆档位的持仓上限，超出的部分会被 OKX 直接拒单
+    if let Some(max_contracts) = meta.as_ref().and_then(|m| m.max_contracts(leverage)) {
+        let cap = money::snap_to_grid(money::dec(max_contracts), money::dec(lot_sz), false);
+        if contracts > cap {
+            if cap < min_contracts {
+                warn!("🧱 [{}] OKX max size {} is below the minimum order size {}. Skipped.", symbol, max_contracts, min_sz);
+                return 0.0;
+            }
+            info!("🧱 [{}] OKX max size is the binding constraint: {} -> {} contracts ({}x)", symbol, contracts, cap, leverage);
+            contracts = cap;
+            binding = "OKX max size";
+        }
+    }
+    
+    let final_cost = contracts * unit_notional / lev;
+    if final_cost > available {
+        return 0.0;
+    }
+    
+    info!("📐 [{}] Sized {} contracts (margin ${:.2}), bound by {}", symbol, contracts, final_cost, binding);
+    money::to_f64(contracts)
+}
+
+/// 现货：按计价币 (USDT/USDC) 金额下注，无杠杆，换算为币本位数量
+async fn spot_quantity(req: &SizingRequest<'_>, pct: f64, binding: &str, risk: &RiskProfile, exchange: &dyn Exchange) -> f64 {
+    let (symbol, price, available_quote) = (req.symbol, req.price, req.available_equity);
+    let (min_sz, lot_sz) = match exchange.instrument_meta(symbol).await {
+        Some(m) => (m.min_sz, m.lot_sz),
+        None => return 0.0,
+    };
+    if price <= 0.0 { return 0.0; }
+
+    let (px, min_qty, available) = (money::dec(price), money::dec(min_sz), money::dec(available_quote));
+    let min_cost = px * min_qty;
+    if available < min_cost {
+        warn!("💰 资金不足: {} 最小下单量 {} 需 ${:.2}，但可用余额仅 ${:.2}。跳过。", symbol, min_sz, min_cost, available_quote);
+        return 0.0;
+    }
+
+    let target = money::to_f64(money::dec(req.equity) * money::dec(pct));
+    let quote_amount = margin_within_available(target, available_quote, risk.kelly.available_fallback);
+    let mut binding = if quote_amount < target { "available balance" } else { binding };
+
+    let mut qty = money::dec(quote_amount) / px;
+    if qty < min_qty {
+        qty = min_qty;
+        binding = "min order size";
+    }
+    if let Some(cap) = loss_cap_units(risk.max_loss_per_trade_usd, px, req.sl_pct) {
+        let cap = money::snap_to_grid(cap, money::dec(lot_sz), false);
+        if qty > cap {
+            if cap < min_qty {
+                warn!("💵 [{}] Minimum order size {} would lose more than ${} at a {:.2}% stop. Skipped.",
+                    symbol, min_sz, risk.max_loss_per_trade_usd.unwrap_or_default(), req.sl_pct * 100.0);
+                return 0.0;
+            }
+            qty = cap;
+            binding = "max_loss_per_trade_usd";
+        }
+    }
+    if qty * px > available { return 0.0; }
+    info!("📐 [{}] Sized {} (${:.2}), bound by {}", symbol, qty, qty * px, binding);
+    money::to_f64(qty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::modules::action::exchange::mock::MockExchange;
+    use crate::modules::action::executor::PositionTier;
+
+    const SYMBOL: &str = "BTC-USDT-SWAP";
+
+    fn exchange() -> MockExchange {
+        // BTC 永续: 面值 0.01 BTC，最小 1 张
+        MockExchange::new(10_000.0, 10_000.0).with_instrument(SYMBOL, 0.01, 1.0, 1.0)
+    }
+
+    fn request(equity: f64, available_equity: f64, kelly_fraction: f64) -> SizingRequest<'static> {
+        SizingRequest { symbol: SYMBOL, equity, available_equity, kelly_fraction, leverage: 10, price: 50_000.0, sl_pct: 0.02, drawdown: 0.0, max_drawdown: 0.10 }
+    }
+
+    fn profile(max_pct: f64) -> RiskProfile {
+        let mut p = RiskProfile::for_tests();
+        p.max_order_size_pct = max_pct;
+        p
+    }
+
+    #[test]
+    fn kelly_fraction_formula() {
+        assert!((kelly_fraction(0.6, 2.0) - 0.4).abs() < 1e-9);
+        assert!(kelly_fraction(0.3, 1.0) < 0.0);
+        assert_eq!(kelly_fraction(0.9, 0.0), 0.0);
+    }
+
+    #[test]
+    fn margin_pct_clamp_boundaries() {
+        // 上限截断
+        assert_eq!(kelly_margin_pct(0.8, 0.5, 0.1), 0.1);
+        // 正好等于上限不截断
+        assert_eq!(kelly_margin_pct(0.2, 0.5, 0.1), 0.1);
+        // 区间内按乘数缩放
+        assert!((kelly_margin_pct(0.1, 0.5, 0.1) - 0.05).abs() < 1e-12);
+        assert!((kelly_margin_pct(0.1, 1.0, 0.2) - 0.1).abs() < 1e-12);
+        // 下限 1%
+        assert_eq!(kelly_margin_pct(0.01, 0.5, 0.1), 0.01);
+        assert_eq!(kelly_margin_pct(0.0, 0.5, 0.1), 0.01);
+    }
+
+    #[tokio::test]
+    async fn sizes_by_half_kelly() {
+        let ex = exchange();
+        // kelly 0.1 => half 0.05 => margin $500 × 10x = $5000 notional / ($50000 × 0.01) = 10 张
+        let qty = calculate_position_size_kelly(&request(10_000.0, 10_000.0, 0.1), &profile(0.2), &ex).await;
+        assert!((qty - 10.0).abs() < 1e-9, "got {}", qty);
+    }
+
+    #[tokio::test]
+    async fn multiplier_is_configurable() {
+        let ex = exchange();
+        let mut risk = profile(0.2);
+        risk.kelly.multiplier = 1.0;
+        // full kelly 0.1 => margin $1000 × 10x / $500 = 20 张
+        let qty = calculate_position_size_kelly(&request(10_000.0, 10_000.0, 0.1), &risk, &ex).await;
+        assert!((qty - 20.0).abs() < 1e-9, "got {}", qty);
+    }
+
+    #[tokio::test]
+    async fn clamps_to_max_order_pct() {
+        let ex = exchange();
+        // kelly 0.8 => half 0.4，被 max 0.1 截断 => margin $1000 × 10x / $500 = 20 张
+        let qty = calculate_position_size_kelly(&request(10_000.0, 10_000.0, 0.8), &profile(0.1), &ex).await;
+        assert!((qty - 20.0).abs() < 1e-9, "got {}", qty);
+    }
+
+    #[tokio::test]
+    async fn rounds_up_to_min_size() {
+        let ex = MockExchange::new(100.0, 100.0).with_instrument(SYMBOL, 0.01, 1.0, 1.0);
+        // margin $1 × 10x = $10 notional => 0.02 张，低于最小 1 张 => 按 1 张 ($50 保证金) 下单
+        let qty = calculate_position_size_kelly(&request(100.0, 100.0, 0.02), &profile(0.1), &ex).await;
+        assert!((qty - 1.0).abs() < 1e-9, "got {}", qty);
+    }
+
+    #[test]
+    fn drawdown_scale_curves() {
+        let mut cfg = DrawdownScalingConfig { enabled: true, ..DrawdownScalingConfig::default() }; // min_scale 0.1
+        // 上限 10%：回撤 0 / 2.5% / 5% / 10% / 15%
+        let linear: Vec<f64> = [0.0, 0.025, 0.05, 0.10, 0.15].iter().map(|d| drawdown_scale(&cfg, *d, 0.10)).collect();
+        for (got, want) in linear.iter().zip([1.0, 0.775, 0.55, 0.1, 0.1]) {
+            assert!((got - want).abs() < 1e-9, "linear: got {}, want {}", got, want);
+        }
+
+        cfg.curve = DrawdownCurve::Exponential;
+        let exp: Vec<f64> = [0.0, 0.025, 0.05, 0.10].iter().map(|d| drawdown_scale(&cfg, *d, 0.10)).collect();
+        for (got, want) in exp.iter().zip([1.0, 0.1f64.powf(0.25), 0.1f64.sqrt(), 0.1]) {
+            assert!((got - want).abs() < 1e-9, "exponential: got {}, want {}", got, want);
+        }
+        // 指数曲线前半段降得更快
+        assert!(exp[2] < linear[2]);
+
+        // 关闭、无回撤 (盈利) 或未知上限时不缩放
+        assert_eq!(drawdown_scale(&cfg, -0.05, 0.10), 1.0);
+        assert_eq!(drawdown_scale(&cfg, 0.05, 0.0), 1.0);
+        cfg.enabled = false;
+        assert_eq!(drawdown_scale(&cfg, 0.05, 0.10), 1.0);
+    }
+
+    #[tokio::test]
+    async fn drawdown_shrinks_order_size() {
+        let ex = exchange();
+        let mut risk = profile(0.1);
+        risk.drawdown_scaling.enabled = true;
+        // kelly 0.8 => 被 max 0.1 截断 => 20 张；回撤 5% / 10% 时上限线性降为 5.5% => 11 张
+        let mut req = request(10_000.0, 10_000.0, 0.8);
+        assert!((calculate_position_size_kelly(&req, &risk, &ex).await - 20.0).abs() < 1e-9);
+        req.drawdown = 0.05;
+        let qty = calculate_position_size_kelly(&req, &risk, &ex).await;
+        assert!((qty - 11.0).abs() < 1e-9, "got {}", qty);
+        // 到达上限：1% 的上限 => 2 张
+        req.drawdown = 0.10;
+        let qty = calculate_position_size_kelly(&req, &risk, &ex).await;
+        assert!((qty - 2.0).abs() < 1e-9, "got {}", qty);
+    }
+
+    #[test]
+    fn reserve_is_a_fraction_of_equity() {
+        assert_eq!(deployable_available(10_000.0, 8_000.0, 0.0), 8_000.0);
+        // 预留 20% × 10000 = 2000
+        assert_eq!(deployable_available(10_000.0, 8_000.0, 0.2), 6_000.0);
+        // 可用余额已低于预留额：一分都不能动
+        assert_eq!(deployable_available(10_000.0, 1_500.0, 0.2), 0.0);
+    }
+
+    #[tokio::test]
+    async fn reserve_limits_sizing() {
+        let ex = exchange();
+        let mut risk = profile(0.5);
+        risk.kelly.multiplier = 1.0;
+        // 目标保证金 0.4 × 10000 = $4000，可用 $5000 足够 => 80 张
+        let qty = calculate_position_size_kelly(&request(10_000.0, 5_000.0, 0.4), &risk, &ex).await;
+        assert!((qty - 80.0).abs() < 1e-9, "got {}", qty);
+
+        // 预留 30% ($3000) 后只剩 $2000，目标截到 $2000 => 40 张
+        risk.kelly.reserve_pct = 0.3;
+        let qty = calculate_position_size_kelly(&request(10_000.0, 5_000.0, 0.4), &risk, &ex).await;
+        assert!((qty - 40.0).abs() < 1e-9, "got {}", qty);
+
+        // 可用余额全部是预留资金：不开仓
+        let qty = calculate_position_size_kelly(&request(10_000.0, 3_000.0, 0.4), &risk, &ex).await;
+        assert_eq!(qty, 0.0);
+    }
+
+    #[tokio::test]
+    async fn clamps_to_exchange_max_size() {
+        let mut ex = exchange();
+        let meta = ex.instruments.get_mut(SYMBOL).unwrap();
+        meta.max_mkt_sz = 15.0;
+        meta.max_lmt_sz = 100.0;
+        // 凯利算出 20 张，单笔市价上限 15 张
+        let qty = calculate_position_size_kelly(&request(10_000.0, 10_000.0, 0.8), &profile(0.1), &ex).await;
+        assert!((qty - 15.0).abs() < 1e-9, "got {}", qty);
+
+        // 10x 只能用到第一档 (最多 12 张)，更高档位只允许 5x
+        ex.instruments.get_mut(SYMBOL).unwrap().position_tiers = vec![
+            PositionTier { max_lever: 20.0, max_sz: 12.0 },
+            PositionTier { max_lever: 5.0, max_sz: 50.0 },
+        ];
+        let qty = calculate_position_size_kelly(&request(10_000.0, 10_000.0, 0.8), &profile(0.1), &ex).await;
+        assert!((qty - 12.0).abs() < 1e-9, "got {}", qty);
+    }
+
+    #[tokio::test]
+    async fn insufficient_funds_returns_zero() {
+        let ex = exchange();
+        // 1 张需 $50 保证金，可用仅 $40
+        let qty = calculate_position_size_kelly(&request(10_000.0, 40.0, 0.1), &profile(0.1), &ex).await;
+        assert_eq!(qty, 0.0);
+    }
+
+    #[tokio::test]
+    async fn unknown_instrument_returns_zero() {
+        let ex = MockExchange::new(10_000.0, 10_000.0);
+        let qty = calculate_position_size_kelly(&request(10_000.0, 10_000.0, 0.1), &profile(0.1), &ex).await;
+        assert_eq!(qty, 0.0);
+    }
+
+    #[tokio::test]
+    async fn spot_sizes_in_quote_currency_without_leverage() {
+        let ex = MockExchange::new(10_000.0, 10_000.0).with_instrument("BTC-USDT", 1.0, 0.0001, 0.00000001);
+        let mut risk = profile(0.2);
+        risk.execution.trading_mode = TradingMode::Spot;
+        let req = SizingRequest { symbol: "BTC-USDT", equity: 10_000.0, available_equity: 10_000.0, kelly_fraction: 0.1, leverage: 10, price: 50_000.0, sl_pct: 0.02, drawdown: 0.0, max_drawdown: 0.10 };
+        // half kelly 0.05 => $500 USDT / $50000 = 0.01 BTC (杠杆被忽略)
+        let qty = calculate_position_size_kelly(&req, &risk, &ex).await;
+        assert!((qty - 0.01).abs() < 1e-12, "got {}", qty);
+    }
+
+    #[tokio::test]
+    async fn available_equity_binds_margin() {
+        let ex = exchange();
+        let mut risk = profile(0.1);
+        risk.kelly.available_fallback = AvailableFallback::Grab;
+        // 目标保证金 $1000 > 可用 $600 => 使用 $570 (95%) × 10x / $500 = 11.4 张
+        let qty = calculate_position_size_kelly(&request(10_000.0, 600.0, 0.4), &risk, &ex).await;
+        assert!((qty - 11.4).abs() < 1e-9, "got {}", qty);
+    }
+
+    #[tokio::test]
+    async fn available_fallback_caps_at_available_by_default() {
+        let ex = exchange();
+        // 默认: 目标 $1000 截到可用 $600 × 10x / $500 = 12 张，而 grab 为 11.4 张
+        let qty = calculate_position_size_kelly(&request(10_000.0, 600.0, 0.4), &profile(0.1), &ex).await;
+        assert!((qty - 12.0).abs() < 1e-9, "got {}", qty);
+
+        // 可用余额略低于目标时只少下相应的差额，不会断崖式缩小
+        assert_eq!(margin_within_available(100.0, 100.0, AvailableFallback::MaxOrderPct), 100.0);
+        assert_eq!(margin_within_available(100.0, 99.0, AvailableFallback::MaxOrderPct), 99.0);
+        // 可用余额充足时两种方式一致
+        assert_eq!(margin_within_available(500.0, 600.0, AvailableFallback::MaxOrderPct), 500.0);
+        assert_eq!(margin_within_available(500.0, 600.0, AvailableFallback::Grab), 500.0);
+    }
+
+    #[tokio::test]
+    async fn contract_math_has_no_float_tail() {
+        let ex = exchange();
+        // 601.1 × 10 / (50000 × 0.01) 在十进制下恰为 12.022 张，可直接做相等比较
+        let qty = calculate_position_size_kelly(&request(10_000.0, 601.1, 0.4), &profile(0.1), &ex).await;
+        assert_eq!(qty, 12.022);
+        assert_eq!(money::format_on_grid(qty, 0.001, false), "12.022");
+    }
+
+    #[tokio::test]
+    async fn absolute_loss_cap_and_percent_cap_take_the_smaller() {
+        let ex = exchange();
+        // 每张 $500 名义价值，止损 2% => 每张亏 $10；kelly 0.8 被 max 10% 截断 => 20 张 (止损亏 $200)
+        let req = request(10_000.0, 10_000.0, 0.8);
+        let mut risk = profile(0.1);
+        risk.max_loss_per_trade_usd = Some(100.0);
+        assert_eq!(calculate_position_size_kelly(&req, &risk, &ex).await, 10.0);
+
+        // 亏损上限宽松时仍由百分比上限决定
+        risk.max_loss_per_trade_usd = Some(1_000.0);
+        assert_eq!(calculate_position_size_kelly(&req, &risk, &ex).await, 20.0);
+
+        // 止损更宽 (5%) 时同样的 $100 只能开 4 张
+        risk.max_loss_per_trade_usd = Some(100.0);
+        let wide = SizingRequest { sl_pct: 0.05, ..request(10_000.0, 10_000.0, 0.8) };
+        assert_eq!(calculate_position_size_kelly(&wide, &risk, &ex).await, 4.0);
+
+        // 止损距离未知时不做亏损限制
+        let unknown = SizingRequest { sl_pct: 0.0, ..request(10_000.0, 10_000.0, 0.8) };
+        assert_eq!(calculate_position_size_kelly(&unknown, &risk, &ex).await, 20.0);
+    }
+
+    #[tokio::test]
+    async fn loss_cap_below_min_size_skips_the_trade() {
+        let ex = exchange();
+        let mut risk = profile(0.1);
+        // 最小 1 张在 2% 止损时亏 $10，上限 $5 放不下
+        risk.max_loss_per_trade_usd = Some(5.0);
+        assert_eq!(calculate_position_size_kelly(&request(10_000.0, 10_000.0, 0.8), &risk, &ex).await, 0.0);
+
+        assert_eq!(loss_cap_units(None, Decimal::from(500), 0.02), None);
+        assert_eq!(loss_cap_units(Some(50.0), Decimal::from(500), 0.02), Some(Decimal::from(5)));
+    }
+}
@@ -1,34 +1,229 @@
-use sqlx::PgPool;
+use sqlx::{PgPool, Row};
 use anyhow::Result;
 use serde_json::json;
 use crate::modules::perception::MarketState;
-use std::env;
+use crate::modules::evolution::stats::{ClosedTrade, ClosedTradeRecord, PerformanceReport, VersionStats, WindowSummary};
+use crate::modules::evolution::ExitReason;
 
 pub struct LogManager {
     pool: PgPool,
 }
 
+/// 一笔开仓/加仓记录
+pub struct TradeRecord<'a> {
+    pub symbol: &'a str,
+    pub direction: &'a str,
+    pub state: &'a MarketState,
+    pub order_id: &'a str,
+    pub initial_margin: f64,
+    /// 新开仓为成交参考价，加仓时为加权后的持仓均价
+    pub entry_price: f64,
+    pub tp_price: f64,
+    pub sl_price: f64,
+    /// LLM 推理过程 (已截断)
+    pub reasoning: &'a str,
+    /// 产生该决策的策略版本 (AiDecision.strategy_version)
+    pub strategy_version: &'a str,
+}
+
+/// 某笔订单的决策记录 (CLI reasoning 命令使用)
+#[derive(Debug, Clone)]
+pub struct ReasoningRecord {
+    pub symbol: String,
+    pub direction: String,
+    pub strategy_version: Option<String>,
+    pub created_at: String,
+    pub reasoning: Option<String>,
+}
+
+/// 未平仓交易的关键价位 (用于保本止损等)
+#[derive(Debug, Clone)]
+pub struct TradeLevels {
+    pub id: uuid::Uuid,
+    pub entry_price: f64,
+    pub sl_price: f64,
+}
+
 impl LogManager {
     pub fn new(pool: PgPool) -> Self {
         Self { pool }
     }
 
     // [修改] 接收 initial_margin 参数
-    pub async fn log_trade(&self, symbol: &str, direction: &str, state: &MarketState, order_id: &str, initial_margin: f64) -> Result<()> {
-        let strategy_ver = env::var("STRATEGY_VERSION").unwrap_or("unknown".to_string());
+    pub async fn log_trade(&self, record: &TradeRecord<'_>) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO trade_logs (symbol, direction, context_snapshot, okx_order_id, strategy_version, initial_margin, entry_price, tp_price, sl_price, reasoning) 
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)"
+        )
+        .bind(record.symbol)
+        .bind(record.direction)
+        .bind(json!(record.state))
+        .bind(record.order_id) 
+        .bind(record.strategy_version)
+        .bind(record.initial_margin) // 记录初始投入
+        .bind(record.entry_price)
+        .bind(record.tp_price)
+        .bind(record.sl_price)
+        .bind(record.reasoning)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 获取某方向最近一笔未结算交易的入场价与止损价
+    pub async fn fetch_open_trade_levels(&self, symbol: &str, direction: &str) -> Result<Option<TradeLevels>> {
+        let row = sqlx::query(
+            "SELECT id, entry_price::FLOAT8 AS entry_price, sl_price::FLOAT8 AS sl_price
+             FROM trade_logs
+             WHERE symbol = $1 AND direction = $2 AND realized_pnl IS NULL
+             AND entry_price IS NOT NULL AND sl_price IS NOT NULL
+             ORDER BY created_at DESC
+             LIMIT 1"
+        )
+        .bind(symbol)
+        .bind(direction)
+        .fetch_optional(&self.pool)
+        .await?;
 
+        match row {
+            Some(r) => Ok(Some(TradeLevels {
+                id: r.try_get("id")?,
+                entry_price: r.try_get("entry_price")?,
+                sl_price: r.try_get("sl_price")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// 机器人主动平仓后标记该方向所有未结算记录的平仓原因
+    /// (realized_pnl 只在平仓成交对账后回填，平仓当下这些记录仍未结算；对账时保留这里写入的原因)
+    pub async fn mark_exit_reason(&self, symbol: &str, direction: &str, reason: ExitReason) -> Result<()> {
         sqlx::query(
-            "INSERT INTO trade_logs (symbol, direction, context_snapshot, okx_order_id, strategy_version, initial_margin) VALUES ($1, $2, $3, $4, $5, $6)"
+            "UPDATE trade_logs SET exit_reason = $1
+             WHERE symbol = $2 AND direction = $3 AND realized_pnl IS NULL AND exit_reason IS NULL"
         )
+        .bind(reason.as_str())
         .bind(symbol)
         .bind(direction)
-        .bind(json!(state))
-        .bind(order_id) 
-        .bind(strategy_ver)
-        .bind(initial_margin) // 记录初始投入
         .execute(&self.pool)
         .await?;
+        Ok(())
+    }
 
+    pub async fn update_stop_price(&self, id: uuid::Uuid, sl_price: f64) -> Result<()> {
+        sqlx::query("UPDATE trade_logs SET sl_price = $1 WHERE id = $2")
+            .bind(sl_price)
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// 按 OKX 订单号查询决策推理记录
+    pub async fn fetch_reasoning(&self, order_id: &str) -> Result<Option<ReasoningRecord>> {
+        let row = sqlx::query(
+            "SELECT symbol, direction, strategy_version, created_at::TEXT AS created_at, reasoning
+             FROM trade_logs
+             WHERE okx_order_id = $1
+             ORDER BY created_at DESC
+             LIMIT 1"
+        )
+        .bind(order_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(r) => Ok(Some(ReasoningRecord {
+                symbol: r.try_get("symbol")?,
+                direction: r.try_get("direction")?,
+                strategy_version: r.try_get("strategy_version")?,
+                created_at: r.try_get("created_at")?,
+                reasoning: r.try_get("reasoning")?,
+            })),
+            None => Ok(None),
+        }
+    }
+
+    /// 累计盈利平仓笔数 (杠杆爬坡使用)
+    pub async fn count_profitable_trades(&self) -> Result<u64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM trade_logs WHERE realized_pnl > 0")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count.max(0) as u64)
+    }
+
+    /// 最近 window_secs 秒内平仓 (realized_pnl 已回填) 的交易汇总
+    pub async fn fetch_closed_summary(&self, window_secs: u64) -> Result<WindowSummary> {
+        let rows = sqlx::query(
+            "SELECT symbol, direction, realized_pnl::FLOAT8 AS pnl
+             FROM trade_logs
+             WHERE realized_pnl IS NOT NULL
+             AND closed_at > NOW() - make_interval(secs => $1)
+             ORDER BY closed_at ASC"
+        )
+        .bind(window_secs as f64)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut trades = Vec::with_capacity(rows.len());
+        for row in rows {
+            trades.push(ClosedTradeRecord {
+                symbol: row.try_get::<Option<String>, _>("symbol")?.unwrap_or_default(),
+                direction: row.try_get::<Option<String>, _>("direction")?.unwrap_or_default(),
+                pnl: row.try_get("pnl")?,
+            });
+        }
+
+        Ok(WindowSummary::compute(&trades, window_secs))
+    }
+
+    /// 统计窗口内已回填 realized_pnl 的平仓交易绩效
+    pub async fn fetch_performance_stats(&self, window_days: i32) -> Result<PerformanceReport> {
+        let rows = sqlx::query(
+            "SELECT realized_pnl::FLOAT8 AS pnl, COALESCE(initial_margin, 0)::FLOAT8 AS margin
+             FROM trade_logs
+             WHERE realized_pnl IS NOT NULL
+             AND created_at > NOW() - make_interval(days => $1)
+             ORDER BY created_at ASC"
+        )
+        .bind(window_days)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut trades = Vec::with_capacity(rows.len());
+        for row in rows {
+            trades.push(ClosedTrade {
+                pnl: row.try_get("pnl")?,
+                initial_margin: row.try_get("margin")?,
+            });
+        }
+
+        Ok(PerformanceReport::compute(&trades, window_days))
+    }
+
+    /// 按 strategy_version 分组统计窗口内已平仓交易 (按笔数降序)，未记录版本的归为 unknown
+    pub async fn fetch_version_stats(&self, window_days: i32) -> Result<Vec<VersionStats>> {
+        let rows = sqlx::query(
+            "SELECT COALESCE(strategy_version, 'unknown') AS version,
+                    COUNT(*) AS trades,
+                    COUNT(*) FILTER (WHERE realized_pnl > 0) AS wins,
+                    SUM(realized_pnl)::FLOAT8 AS net_pnl
+             FROM trade_logs
+             WHERE realized_pnl IS NOT NULL
+             AND created_at > NOW() - make_interval(days => $1)
+             GROUP BY 1
+             ORDER BY trades DESC, version"
+        )
+        .bind(window_days)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(|row| Ok(VersionStats {
+            version: row.try_get("version")?,
+            closed_trades: row.try_get::<i64, _>("trades")? as usize,
+            wins: row.try_get::<i64, _>("wins")? as usize,
+            net_pnl: row.try_get("net_pnl")?,
+        })).collect()
+    }
+}
@@ -0,0 +1,150 @@
+// 文件名: symbol_switch.rs
+// 单币种开关：临时停止某个币种的新开仓 (如稳定币脱锚恐慌)，行情订阅、持仓管理、止盈止损照常
+// 配置 [[symbol_overrides]] enabled = false 需要重启生效；运行时开关写入 symbol_switches 表，下一轮立即生效，优先于配置
+
+use std::collections::HashMap;
+
+use anyhow::Result;
+use sqlx::{PgPool, Row};
+
+use crate::config::risk_profile::RiskProfile;
+
+/// 运行时开关 (命令行 enable / disable 写入)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeSwitch {
+    pub enabled: bool,
+    pub reason: String,
+}
+
+pub struct SymbolSwitchStore {
+    pool: PgPool,
+}
+
+impl SymbolSwitchStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn set(&self, symbol: &str, enabled: bool, reason: &str) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO symbol_switches (symbol, enabled, reason) VALUES ($1, $2, $3)
+             ON CONFLICT (symbol) DO UPDATE SET enabled = EXCLUDED.enabled, reason = EXCLUDED.reason, updated_at = CURRENT_TIMESTAMP"
+        )
+        .bind(symbol)
+        .bind(enabled)
+        .bind(reason)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn load(&self) -> Result<HashMap<String, RuntimeSwitch>> {
+        let rows = sqlx::query("SELECT symbol, enabled, reason FROM symbol_switches")
+            .fetch_all(&self.pool)
+            .await?;
+        let mut map = HashMap::with_capacity(rows.len());
+        for r in rows {
+            map.insert(r.try_get("symbol")?, RuntimeSwitch {
+                enabled: r.try_get("enabled")?,
+                reason: r.try_get::<Option<String>, _>("reason")?.unwrap_or_default(),
+            });
+        }
+        Ok(map)
+    }
+}
+
+/// 开关状态变化 (用于日志与通知)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwitchChange {
+    pub symbol: String,
+    pub enabled: bool,
+    pub reason: String,
+}
+
+/// 当前被禁止开仓的币种 -> 原因，每轮按配置与运行时开关刷新
+#[derive(Debug, Default)]
+pub struct SymbolGate {
+    disabled: HashMap<String, String>,
+}
+
+impl SymbolGate {
+    /// 重新计算 symbols 的开关状态，返回与上一轮相比发生变化的币种
+    pub fn update(&mut self, symbols: &[String], risk: &RiskProfile, runtime: &HashMap<String, RuntimeSwitch>) -> Vec<SwitchChange> {
+        let mut next = HashMap::new();
+        for symbol in symbols {
+            let (enabled, reason) = match runtime.get(symbol) {
+                Some(sw) => (sw.enabled, if sw.reason.is_empty() { "runtime switch".to_string() } else { sw.reason.clone() }),
+                None => (risk.symbol_enabled(symbol), "disabled in symbol_overrides".to_string()),
+            };
+            if !enabled {
+                next.insert(symbol.clone(), reason);
+            }
+        }
+
+        let mut changes: Vec<SwitchChange> = next.iter()
+            .filter(|(s, _)| !self.disabled.contains_key(*s))
+            .map(|(s, reason)| SwitchChange { symbol: s.clone(), enabled: false, reason: reason.clone() })
+            .collect();
+        changes.extend(self.disabled.keys()
+            .filter(|s| !next.contains_key(*s) && symbols.contains(s))
+            .map(|s| SwitchChange {
+                symbol: s.clone(),
+                enabled: true,
+                reason: runtime.get(s).map(|sw| sw.reason.clone()).unwrap_or_default(),
+            }));
+        changes.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        self.disabled = next;
+        changes
+    }
+
+    /// 被禁止开仓时返回原因
+    pub fn blocked(&self, symbol: &str) -> Option<&str> {
+        self.disabled.get(symbol).map(|s| s.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::risk_profile::SymbolOverride;
+
+    fn profile() -> RiskProfile {
+        let mut p = RiskProfile::for_tests();
+        p.symbol_overrides.push(SymbolOverride {
+            symbol: "USDC-USDT-SWAP".to_string(), max_spread_bps: None, allowed_actions: None, min_tp_pct: None,
+            max_tp_pct: None, min_sl_pct: None, max_sl_pct: None, max_leverage: None, enabled: Some(false),
+        });
+        p
+    }
+
+    fn symbols() -> Vec<String> {
+        vec!["BTC-USDT-SWAP".to_string(), "USDC-USDT-SWAP".to_string()]
+    }
+
+    #[test]
+    fn config_and_runtime_switches() {
+        let risk = profile();
+        let mut gate = SymbolGate::default();
+
+        // 首轮：配置中禁用的币种报告一次
+        let changes = gate.update(&symbols(), &risk, &HashMap::new());
+        assert_eq!(changes.len(), 1);
+        assert!(!changes[0].enabled && changes[0].symbol == "USDC-USDT-SWAP");
+        assert!(gate.blocked("USDC-USDT-SWAP").is_some());
+        assert!(gate.blocked("BTC-USDT-SWAP").is_none());
+        assert!(gate.update(&symbols(), &risk, &HashMap::new()).is_empty());
+
+        // 运行时开关优先于配置：禁用 BTC、重新启用 USDC
+        let runtime = HashMap::from([
+            ("BTC-USDT-SWAP".to_string(), RuntimeSwitch { enabled: false, reason: "depeg scare".to_string() }),
+            ("USDC-USDT-SWAP".to_string(), RuntimeSwitch { enabled: true, reason: String::new() }),
+        ]);
+        let changes = gate.update(&symbols(), &risk, &runtime);
+        assert_eq!(changes, vec![
+            SwitchChange { symbol: "BTC-USDT-SWAP".to_string(), enabled: false, reason: "depeg scare".to_string() },
+            SwitchChange { symbol: "USDC-USDT-SWAP".to_string(), enabled: true, reason: String::new() },
+        ]);
+        assert_eq!(gate.blocked("BTC-USDT-SWAP"), Some("depeg scare"));
+        assert!(gate.blocked("USDC-USDT-SWAP").is_none());
+    }
+}
@@ -0,0 +1,105 @@
+// 文件名: tpsl_monitor.rs
+// 客户端 TP/SL：干跑与现货模式下没有交易所条件单，按 WS 价格检查持仓缓存中的触发价并市价平仓
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use tokio::time::sleep;
+use tracing::{error, info, warn};
+
+use super::executor::TradeExecutor;
+use super::positions::{PositionLevels, PositionStore};
+use crate::modules::evolution::ExitReason;
+use crate::modules::perception::ws_client::PriceCache;
+use crate::utils::notifier::{NotifierHub, Priority};
+
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// WS 价格超过该时长未更新时不做判断 (断线期间避免按旧价格平仓)
+const MAX_PRICE_AGE: Duration = Duration::from_secs(30);
+
+/// 按当前价判断是否触及 TP/SL (0 = 未设置)；价格跳空越过触发价同样视为触发
+/// 同时满足时按止损处理 (保守)
+pub fn level_breach(side: &str, tp_price: f64, sl_price: f64, price: f64) -> Option<ExitReason> {
+    if price <= 0.0 { return None; }
+    let long = side != "short";
+    let hit_tp = tp_price > 0.0 && if long { price >= tp_price } else { price <= tp_price };
+    let hit_sl = sl_price > 0.0 && if long { price <= sl_price } else { price >= sl_price };
+    if hit_sl { Some(ExitReason::SlHit) } else if hit_tp { Some(ExitReason::TpHit) } else { None }
+}
+
+pub struct TpSlMonitor {
+    store: PositionStore,
+    executor: Arc<TradeExecutor>,
+    prices: PriceCache,
+    notifier: Arc<NotifierHub>,
+}
+
+impl TpSlMonitor {
+    pub fn new(store: PositionStore, executor: Arc<TradeExecutor>, prices: PriceCache, notifier: Arc<NotifierHub>) -> Self {
+        Self { store, executor, prices, notifier }
+    }
+
+    pub async fn run(self) {
+        info!("🛡️ Client-side TP/SL monitor started (checks every {:?})", CHECK_INTERVAL);
+        loop {
+            if let Err(e) = self.check().await {
+                warn!("⚠️ TP/SL monitor check failed: {}", e);
+            }
+            sleep(CHECK_INTERVAL).await;
+        }
+    }
+
+    async fn check(&self) -> Result<()> {
+        for levels in self.store.levels().await? {
+            let price = match self.prices.get(&levels.symbol) {
+                Some(entry) if entry.value().1.elapsed() <= MAX_PRICE_AGE => entry.value().0,
+                _ => continue,
+            };
+            let Some(reason) = level_breach(&levels.side, levels.tp_price, levels.sl_price, price) else { continue };
+            // 先清空触发价再下单：下一轮检查与其它路径都不会重复平仓
+            if !self.store.claim_exit(&levels.symbol, &levels.side).await? { continue; }
+            self.close(&levels, reason, price).await;
+        }
+        Ok(())
+    }
+
+    async fn close(&self, levels: &PositionLevels, reason: ExitReason, price: f64) {
+        let trigger = if reason == ExitReason::TpHit { levels.tp_price } else { levels.sl_price };
+        let close_side = if levels.side == "short" { "buy" } else { "sell" };
+        match self.executor.execute_order(&levels.symbol, close_side, &levels.side, levels.size, price, 0.0, 0.0, None, true).await {
+            Ok(_) => {
+                let gap = (price - trigger).abs() / trigger * 100.0;
+                let msg = format!("🛡️ 客户端 {} 触发: {} {} | 触发价 {:.4} | 成交参考价 {:.4} (跳空 {:.2}%) | 数量 {}",
+                    if reason == ExitReason::TpHit { "止盈" } else { "止损" }, levels.symbol, levels.side, trigger, price, gap, levels.size);
+                info!("{}", msg);
+                self.notifier.send_text(&msg, Priority::Normal).await;
+            }
+            Err(e) => {
+                error!("🔥 [{}] Client-side {} close failed: {}. Levels restored for retry.", levels.symbol, reason.as_str(), e);
+                if let Err(e) = self.store.restore_levels(levels).await {
+                    error!("🔥 [{}] Restoring TP/SL levels failed, position {} is UNPROTECTED: {}", levels.symbol, levels.side, e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn breach_handles_gaps_and_sides() {
+        assert_eq!(level_breach("long", 110.0, 95.0, 110.0), Some(ExitReason::TpHit));
+        // 跳空越过触发价
+        assert_eq!(level_breach("long", 110.0, 95.0, 120.0), Some(ExitReason::TpHit));
+        assert_eq!(level_breach("long", 110.0, 95.0, 80.0), Some(ExitReason::SlHit));
+        assert_eq!(level_breach("long", 110.0, 95.0, 100.0), None);
+        assert_eq!(level_breach("short", 90.0, 105.0, 85.0), Some(ExitReason::TpHit));
+        assert_eq!(level_breach("short", 90.0, 105.0, 106.0), Some(ExitReason::SlHit));
+        // 未设置的触发价不参与判断
+        assert_eq!(level_breach("long", 0.0, 95.0, 200.0), None);
+        assert_eq!(level_breach("long", 110.0, 95.0, 0.0), None);
+    }
+}
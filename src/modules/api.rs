@@ -0,0 +1,190 @@
+// 文件名: api.rs
+// 调试用 HTTP 接口：查看当前持仓、试跑一次大脑决策 (只分析，不下单)
+// 由 DEBUG_API=1 开启，所有请求必须携带 Authorization: Bearer <DEBUG_API_TOKEN>
+
+use std::env;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use anyhow::{anyhow, Context, Result};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tracing::{info, warn};
+
+use crate::config::risk_profile::MemoryConfig;
+use crate::modules::action::Exchange;
+use crate::modules::brain::budget::LlmBudget;
+use crate::modules::brain::{rag, DecisionMaker, MemorySystem};
+use crate::modules::perception::{MarketDataFetcher, NewsSentinel, RedditSentinel};
+
+/// 接口共享的模块句柄 (与主循环共用同一批实例)
+pub struct ApiState {
+    pub exchange: Arc<dyn Exchange>,
+    pub fetcher: Arc<MarketDataFetcher>,
+    pub brain: Arc<DecisionMaker>,
+    pub memory: Arc<MemorySystem>,
+    pub news: Arc<NewsSentinel>,
+    pub reddit: Arc<RedditSentinel>,
+    pub allowed_symbols: Vec<String>,
+    pub max_leverage: f64,
+    /// 记忆召回的查询文本与主循环一致 (超长时按 embed_strategy 摘要或截断)
+    pub memory_config: MemoryConfig,
+    /// 与主循环共用的 LLM 调用预算
+    pub llm_budget: Arc<Mutex<LlmBudget>>,
+}
+
+struct AppState {
+    inner: ApiState,
+    token: String,
+}
+
+type ApiError = (StatusCode, Json<Value>);
+
+fn api_error(status: StatusCode, msg: impl ToString) -> ApiError {
+    (status, Json(json!({ "error": msg.to_string() })))
+}
+
+#[derive(Deserialize)]
+struct DecideRequest {
+    symbol: String,
+}
+
+/// DEBUG_API=1 时返回监听地址与 token；开启但未配置 token 时报错，拒绝裸奔
+pub fn config_from_env() -> Result<Option<(SocketAddr, String)>> {
+    if env::var("DEBUG_API").unwrap_or_default() != "1" {
+        return Ok(None);
+    }
+    let token = env::var("DEBUG_API_TOKEN").unwrap_or_default();
+    if token.trim().is_empty() {
+        return Err(anyhow!("DEBUG_API=1 but DEBUG_API_TOKEN is not set"));
+    }
+    let addr = env::var("DEBUG_API_ADDR").unwrap_or("127.0.0.1:8088".to_string());
+    let addr = addr.parse().with_context(|| format!("Invalid DEBUG_API_ADDR: {}", addr))?;
+    Ok(Some((addr, token)))
+}
+
+pub async fn serve(addr: SocketAddr, token: String, state: ApiState) -> Result<()> {
+    let app = Router::new()
+        .route("/positions", get(positions))
+        .route("/decide", post(decide))
+        .with_state(Arc::new(AppState { inner: state, token }));
+
+    info!("🔧 Debug API listening on http://{}", addr);
+    axum::Server::bind(&addr)
+        .serve(app.into_make_service())
+        .await
+        .context("Debug API server stopped")
+}
+
+/// 校验 Bearer token
+fn authorize(headers: &HeaderMap, token: &str) -> Result<(), ApiError> {
+    let provided = headers.get("authorization")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+    if provided.is_some_and(|p| constant_time_eq(p.as_bytes(), token.as_bytes())) {
+        Ok(())
+    } else {
+        Err(api_error(StatusCode::UNAUTHORIZED, "invalid or missing bearer token"))
+    }
+}
+
+/// 长度相同时逐字节比较全部内容，避免按耗时猜测 token
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn positions(State(app): State<Arc<AppState>>, headers: HeaderMap) -> Result<Json<Value>, ApiError> {
+    authorize(&headers, &app.token)?;
+    let list = app.inner.exchange.fetch_positions().await
+        .map_err(|e| api_error(StatusCode::BAD_GATEWAY, e))?;
+
+    let positions: Vec<Value> = list.iter().map(|p| json!({
+        "symbol": p.symbol,
+        "side": p.side,
+        "size": p.size,
+        "avg_px": p.avg_px,
+        "mark_px": p.mark_px,
+        "upl": p.upl,
+        "leverage": p.leverage,
+        "notional_usd": p.notional_usd,
+        "margin_usd": p.margin_usd,
+    })).collect();
+    Ok(Json(json!({ "positions": positions })))
+}
+
+/// 按当前行情、记忆与持仓跑一次决策，只返回结果，不执行
+async fn decide(State(app): State<Arc<AppState>>, headers: HeaderMap, Json(req): Json<DecideRequest>) -> Result<Json<Value>, ApiError> {
+    authorize(&headers, &app.token)?;
+    let s = &app.inner;
+    if !s.allowed_symbols.contains(&req.symbol) {
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("{} is not in allowed_symbols", req.symbol)));
+    }
+    info!("🔧 [Debug API] Dry decision requested for {}", req.symbol);
+    if !s.llm_budget.lock().unwrap_or_else(|e| e.into_inner()).try_acquire(Instant::now()) {
+        return Err(api_error(StatusCode::TOO_MANY_REQUESTS, "LLM call budget exhausted, try again later"));
+    }
+
+    let (reddit, news) = tokio::join!(s.reddit.analyze_sentiment(), s.news.fetch_raw_headlines("GLOBAL"));
+    let reddit = reddit.unwrap_or_else(|e| format!("Error fetching Reddit: {}", e));
+    let news = news.unwrap_or_else(|e| format!("Error fetching News: {}", e));
+
+    let state = s.fetcher.snapshot(&req.symbol, reddit, news).await
+        .map_err(|e| api_error(StatusCode::BAD_GATEWAY, e))?;
+    let ctx_str = rag::embedding_context(&state, s.brain.as_ref(), &s.memory_config).await;
+    let memories = s.memory.recall_memories(&ctx_str).await.unwrap_or_default();
+
+    let positions = s.exchange.fetch_positions().await
+        .map_err(|e| api_error(StatusCode::BAD_GATEWAY, e))?;
+    let described: Vec<String> = positions.iter()
+        .filter(|p| p.symbol == req.symbol && p.size > 0.0)
+        .map(|p| format!("{}: {} @ avg {} (PnL ${})", if p.side == "long" { "Long" } else { "Short" }, p.size, p.avg_px, p.upl))
+        .collect();
+    let pos_info = if described.is_empty() { "No active positions".to_string() } else { described.join(", ") };
+
+    let decision = s.brain.analyze(&state, &memories, &pos_info, s.max_leverage).await
+        .map_err(|e| {
+            warn!("🔧 [Debug API] Decision failed for {}: {}", req.symbol, e);
+            api_error(StatusCode::BAD_GATEWAY, e)
+        })?;
+
+    Ok(Json(json!({
+        "symbol": req.symbol,
+        "price": state.price,
+        "position": pos_info,
+        "memories": memories.len(),
+        "executed": false,
+        "decision": {
+            "action": format!("{:?}", decision.action),
+            "reason": decision.reason,
+            "tp_pct": decision.tp_pct,
+            "sl_pct": decision.sl_pct,
+            "leverage": decision.leverage,
+            "win_rate": decision.win_rate,
+            "risk_reward_ratio": decision.risk_reward_ratio,
+            "kelly_fraction": decision.kelly_fraction,
+            "strategy_version": decision.strategy_version,
+        },
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bearer_token_is_required() {
+        let mut headers = HeaderMap::new();
+        assert!(authorize(&headers, "secret").is_err());
+
+        headers.insert("authorization", "Bearer wrong".parse().unwrap());
+        assert!(authorize(&headers, "secret").is_err());
+
+        headers.insert("authorization", "Bearer secret".parse().unwrap());
+        assert!(authorize(&headers, "secret").is_ok());
+    }
+}
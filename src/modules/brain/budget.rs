@@ -0,0 +1,98 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::config::risk_profile::LlmConfig;
+
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// LLM analyze 调用预算：每轮上限 + 滚动一小时上限 (0 = 不限制)
+#[derive(Debug)]
+pub struct LlmBudget {
+    per_cycle: u32,
+    per_hour: u32,
+    cycle_calls: u32,
+    /// 最近一小时内的调用时间
+    recent: VecDeque<Instant>,
+}
+
+impl LlmBudget {
+    pub fn new(cfg: &LlmConfig) -> Self {
+        Self { per_cycle: cfg.max_calls_per_cycle, per_hour: cfg.max_calls_per_hour, cycle_calls: 0, recent: VecDeque::new() }
+    }
+
+    /// 新一轮循环开始时重置每轮计数
+    pub fn start_cycle(&mut self) {
+        self.cycle_calls = 0;
+    }
+
+    /// 预算充足时记一次调用并返回 true
+    pub fn try_acquire(&mut self, now: Instant) -> bool {
+        while self.recent.front().is_some_and(|t| now.duration_since(*t) >= HOUR) {
+            self.recent.pop_front();
+        }
+        if self.per_cycle > 0 && self.cycle_calls >= self.per_cycle { return false; }
+        if self.per_hour > 0 && self.recent.len() >= self.per_hour as usize { return false; }
+
+        self.cycle_calls += 1;
+        self.recent.push_back(now);
+        true
+    }
+}
+
+/// 预算有限时的分析顺序：
+/// 1. 有持仓的币种 (需要及时平仓/管理风险)
+/// 2. 上一轮 ATR% 较高的币种 (波动大，机会与风险都更集中)，没有数据的排在最后
+/// 3. 其余保持交易池 (allowed_symbols 或自动选币结果) 中的原顺序
+pub fn prioritize_symbols(symbols: &[String], open_symbols: &[&str], volatility: &HashMap<String, f64>) -> Vec<String> {
+    let mut ordered = symbols.to_vec();
+    // sort_by 是稳定排序，同优先级保持原顺序
+    ordered.sort_by(|a, b| {
+        let held = |s: &String| open_symbols.contains(&s.as_str());
+        let vol = |s: &String| volatility.get(s).copied().unwrap_or(f64::NEG_INFINITY);
+        held(b).cmp(&held(a)).then(vol(b).total_cmp(&vol(a)))
+    });
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(per_cycle: u32, per_hour: u32) -> LlmBudget {
+        LlmBudget::new(&LlmConfig { max_calls_per_cycle: per_cycle, max_calls_per_hour: per_hour, ..LlmConfig::default() })
+    }
+
+    #[test]
+    fn cycle_and_hourly_limits() {
+        let t0 = Instant::now();
+        let mut b = budget(2, 3);
+        assert!(b.try_acquire(t0));
+        assert!(b.try_acquire(t0));
+        assert!(!b.try_acquire(t0));
+
+        // 新一轮只剩小时额度 1 次
+        b.start_cycle();
+        assert!(b.try_acquire(t0));
+        assert!(!b.try_acquire(t0));
+
+        // 一小时后额度恢复
+        b.start_cycle();
+        assert!(b.try_acquire(t0 + HOUR));
+
+        let mut unlimited = budget(0, 0);
+        assert!((0..100).all(|_| unlimited.try_acquire(t0)));
+    }
+
+    #[test]
+    fn open_positions_first_then_volatility() {
+        let symbols: Vec<String> = ["BTC", "ETH", "SOL", "DOGE", "XRP"].iter().map(|s| s.to_string()).collect();
+        let volatility = HashMap::from([
+            ("BTC".to_string(), 0.8),
+            ("SOL".to_string(), 2.5),
+            ("DOGE".to_string(), 3.1),
+            ("XRP".to_string(), 1.0),
+        ]);
+        let ordered = prioritize_symbols(&symbols, &["XRP", "BTC"], &volatility);
+        assert_eq!(ordered, ["XRP", "BTC", "DOGE", "SOL", "ETH"]);
+    }
+}
@@ -0,0 +1,175 @@
+use anyhow::{Result, anyhow};
+use serde_json::{json, Value};
+use std::env;
+use crate::config::risk_profile::{EmbeddingProviderKind, MemoryConfig};
+
+/// 豆包 Embedding 的向量维度
+const VOLC_DIM: u64 = 2560;
+/// 单次请求最多嵌入的文本条数
+const VOLC_MAX_BATCH: usize = 16;
+const OPENAI_MAX_BATCH: usize = 64;
+
+/// Embedding 服务商：负责请求体构造与响应解析，HTTP 重试与熔断由 MemorySystem 统一处理
+pub trait EmbeddingProvider: Send + Sync {
+    /// 日志与报错中使用的名称
+    fn name(&self) -> &'static str;
+    fn model(&self) -> &str;
+    /// 返回向量的维度，Qdrant 集合按此创建并校验
+    fn dimension(&self) -> u64;
+    /// Key 与模型是否都已配置
+    fn is_configured(&self) -> bool;
+    /// 配置错误时提示检查的环境变量
+    fn env_hint(&self) -> &'static str;
+    fn api_key(&self) -> &str;
+    fn url(&self) -> String;
+    fn max_batch(&self) -> usize;
+    fn request_body(&self, inputs: &[&str]) -> Value;
+    /// 按输入顺序返回向量，条数与 expected 不一致时报错
+    fn parse_response(&self, resp: &Value, expected: usize) -> Result<Vec<Vec<f32>>> {
+        parse_data_array(resp, expected)
+    }
+}
+
+/// 按配置选择服务商
+pub fn from_config(config: &MemoryConfig) -> Box<dyn EmbeddingProvider> {
+    match config.embedding_provider {
+        EmbeddingProviderKind::Volcengine => Box::new(VolcengineProvider::from_env(config.embedding_dim)),
+        EmbeddingProviderKind::Openai => Box::new(OpenAiProvider::from_env(&config.openai_model, config.embedding_dim)),
+    }
+}
+
+/// 火山引擎 (豆包 Embedding)，模型 ID 为控制台中的接入点
+pub struct VolcengineProvider {
+    api_key: String,
+    api_base: String,
+    model: String,
+    dim: u64,
+}
+
+impl VolcengineProvider {
+    pub fn from_env(dim: Option<u64>) -> Self {
+        Self {
+            api_key: env::var("VOLC_API_KEY").unwrap_or_default(),
+            api_base: env::var("VOLC_ENDPOINT").unwrap_or("https://ark.cn-beijing.volces.com/api/v3".to_string()),
+            model: env::var("VOLC_MODEL").unwrap_or_default(),
+            dim: dim.unwrap_or(VOLC_DIM),
+        }
+    }
+}
+
+impl EmbeddingProvider for VolcengineProvider {
+    fn name(&self) -> &'static str { "Volcengine" }
+    fn model(&self) -> &str { &self.model }
+    fn dimension(&self) -> u64 { self.dim }
+    fn is_configured(&self) -> bool { !self.api_key.is_empty() && !self.model.is_empty() }
+    fn env_hint(&self) -> &'static str { "VOLC_API_KEY / VOLC_MODEL / VOLC_ENDPOINT" }
+    fn api_key(&self) -> &str { &self.api_key }
+    fn url(&self) -> String { format!("{}/embeddings", self.api_base.trim_end_matches('/')) }
+    fn max_batch(&self) -> usize { VOLC_MAX_BATCH }
+
+    fn request_body(&self, inputs: &[&str]) -> Value {
+        json!({
+            "model": self.model,
+            "input": inputs,
+            "encoding_format": "float"
+        })
+    }
+}
+
+/// OpenAI 兼容接口 (text-embedding-3-small / -large 等)
+pub struct OpenAiProvider {
+    api_key: String,
+    api_base: String,
+    model: String,
+    /// 配置了 embedding_dim 时通过 dimensions 参数缩短向量 (仅 text-embedding-3 系列支持)
+    dimensions: Option<u64>,
+}
+
+impl OpenAiProvider {
+    pub fn from_env(model: &str, dimensions: Option<u64>) -> Self {
+        Self {
+            api_key: env::var("OPENAI_API_KEY").unwrap_or_default(),
+            api_base: env::var("OPENAI_EMBEDDING_BASE_URL").unwrap_or("https://api.openai.com/v1".to_string()),
+            model: model.to_string(),
+            dimensions,
+        }
+    }
+}
+
+/// OpenAI 各模型的默认维度
+fn openai_default_dim(model: &str) -> u64 {
+    if model.contains("3-large") { 3072 } else { 1536 }
+}
+
+impl EmbeddingProvider for OpenAiProvider {
+    fn name(&self) -> &'static str { "OpenAI" }
+    fn model(&self) -> &str { &self.model }
+    fn dimension(&self) -> u64 { self.dimensions.unwrap_or_else(|| openai_default_dim(&self.model)) }
+    fn is_configured(&self) -> bool { !self.api_key.is_empty() && !self.model.is_empty() }
+    fn env_hint(&self) -> &'static str { "OPENAI_API_KEY / OPENAI_EMBEDDING_BASE_URL / memory.openai_model" }
+    fn api_key(&self) -> &str { &self.api_key }
+    fn url(&self) -> String { format!("{}/embeddings", self.api_base.trim_end_matches('/')) }
+    fn max_batch(&self) -> usize { OPENAI_MAX_BATCH }
+
+    fn request_body(&self, inputs: &[&str]) -> Value {
+        let mut body = json!({
+            "model": self.model,
+            "input": inputs,
+            "encoding_format": "float"
+        });
+        if let Some(dim) = self.dimensions {
+            body["dimensions"] = json!(dim);
+        }
+        body
+    }
+}
+
+/// 两家共用的响应格式：data[].embedding，按 data[].index 还原输入顺序
+fn parse_data_array(resp: &Value, expected: usize) -> Result<Vec<Vec<f32>>> {
+    let data = resp["data"].as_array().ok_or_else(|| anyhow!("Invalid JSON format: missing data array"))?;
+    let mut rows: Vec<(u64, Vec<f32>)> = data.iter().enumerate()
+        .map(|(i, item)| {
+            let vector = item["embedding"].as_array()
+                .ok_or_else(|| anyhow!("Invalid JSON format: missing embedding"))?
+                .iter()
+                .map(|v| v.as_f64().unwrap_or(0.0) as f32)
+                .collect();
+            Ok((item["index"].as_u64().unwrap_or(i as u64), vector))
+        })
+        .collect::<Result<_>>()?;
+    if rows.len() != expected {
+        return Err(anyhow!("Expected {} embeddings, got {}", expected, rows.len()));
+    }
+    rows.sort_by_key(|(i, _)| *i);
+    Ok(rows.into_iter().map(|(_, v)| v).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn openai_dimension_follows_model_and_override() {
+        let small = OpenAiProvider { api_key: "k".into(), api_base: "https://api.openai.com/v1/".into(), model: "text-embedding-3-small".into(), dimensions: None };
+        assert_eq!(small.dimension(), 1536);
+        assert_eq!(small.url(), "https://api.openai.com/v1/embeddings");
+        assert!(small.request_body(&["a"]).get("dimensions").is_none());
+
+        let large = OpenAiProvider { model: "text-embedding-3-large".into(), dimensions: Some(1024), ..small };
+        assert_eq!(large.dimension(), 1024);
+        assert_eq!(large.request_body(&["a", "b"])["dimensions"], 1024);
+    }
+
+    #[test]
+    fn response_is_reordered_by_index() {
+        let resp = json!({"data": [
+            {"index": 1, "embedding": [0.3, 0.4]},
+            {"index": 0, "embedding": [0.1, 0.2]},
+        ]});
+        let vectors = parse_data_array(&resp, 2).unwrap();
+        assert_eq!(vectors, vec![vec![0.1, 0.2], vec![0.3, 0.4]]);
+
+        assert!(parse_data_array(&resp, 3).is_err());
+        assert!(parse_data_array(&json!({"error": "bad"}), 1).is_err());
+    }
+}
@@ -1,11 +1,16 @@
 use reqwest::Client;
 use anyhow::{Result, anyhow, Context};
+use async_trait::async_trait;
 use serde_json::{json, Value};
 use std::env;
 use std::time::Duration;
 use tokio::time::sleep;
 // 引用路径改为 utils，确保文件结构正确
 use crate::modules::perception::structs::MarketState;
+use crate::modules::action::sizing::kelly_fraction;
+use crate::config::risk_profile::{is_valid_ladder, AllowedAction, LeverageMode, LeveragePoint, LeverageScalingConfig, LlmConfig, ResponseFormatMode, TpRung, TpSlClampConfig, TpSlClamps};
+use super::prompt;
+use crate::utils::http_client::{host_limiter, jittered};
 
 use tracing::{info, warn};
 
@@ -14,6 +19,13 @@ pub struct DecisionMaker {
     ds_key: String,
     ds_url: String,
     strategy_version: String,
+    system_prompt_template: String,
+    llm_config: LlmConfig,
+    /// 各币种的 TP/SL 允许范围
+    tpsl_clamps: TpSlClamps,
+    leverage_scaling: LeverageScalingConfig,
+    /// 已按端点解析后的结构化输出方式 (不会是 Auto)
+    response_format: ResponseFormatMode,
 }
 
 // [关键修改] 添加 PartialEq, Clone 以支持主程序中的比较逻辑
@@ -26,21 +38,92 @@ pub enum TradeAction {
     Hold,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct AiDecision {
     pub action: TradeAction,
     pub reason: String,
     pub tp_pct: f64, 
     pub sl_pct: f64,
+    /// 模型给出的阶梯止盈 (可选)，为空时使用 execution.tp_ladder 或单一 TP
+    pub tp_ladder: Vec<TpRung>,
     pub leverage: u32,
     pub win_rate: f64,       
     pub kelly_fraction: f64, 
     pub risk_reward_ratio: f64,
     pub strategy_version: String,
+    /// 模型的原始推理过程 (R1 的 reasoning_content 或 <think> 块)，已截断，用于审计与复盘
+    pub reasoning: String,
+}
+
+/// 预筛提示词：只判断是否值得完整分析，不做交易决策
+const PRESCREEN_PROMPT: &str = r#"You are a fast pre-screener for a crypto trading desk. Decide ONLY whether the setup below deserves a full analysis by the senior analyst.
+Answer "consider" if there is a clear trend, breakout, reversal signal, unusual volatility/volume, or an open position that may need management.
+Answer "skip" if the market is quiet, range-bound and no position needs attention.
+Reply with JSON ONLY: {"verdict": "consider" | "skip", "reason": "short reason"}"#;
+
+/// 舆情压缩提示词：Embedding 输入超长时使用，只保留对行情检索有用的语义
+const SUMMARY_PROMPT: &str = r#"You compress crypto market sentiment for a similarity-search index. Keep the assets, events, direction (bullish/bearish), magnitude and any numbers. Drop boilerplate, links, usernames and duplicates.
+Reply with JSON ONLY: {"news": "compressed news headlines", "social": "compressed social discussion"}"#;
+
+/// 推理过程入库上限 (字符)，超出时保留开头与结尾 (结论通常在末尾)
+const MAX_REASONING_CHARS: usize = 16000;
+
+/// LLM 返回的正文与推理过程
+struct LlmReply {
+    content: String,
+    reasoning: String,
 }
 
 impl AiDecision {
+    /// 不经完整分析直接 HOLD (预筛 skip)
+    pub fn hold(reason: String, strategy_version: String) -> Self {
+        Self {
+            action: TradeAction::Hold,
+            reason,
+            tp_pct: 0.0,
+            sl_pct: 0.0,
+            tp_ladder: Vec::new(),
+            leverage: 1,
+            win_rate: 0.0,
+            kelly_fraction: 0.0,
+            risk_reward_ratio: 0.0,
+            strategy_version,
+            reasoning: String::new(),
+        }
+    }
+
+    /// 胜率软顶：超过 cap 时截断并重算凯利值，返回是否发生截断
+    pub fn apply_win_rate_cap(&mut self, cap: f64) -> bool {
+        if self.win_rate <= cap { return false; }
+        self.win_rate = cap;
+        self.kelly_fraction = kelly_fraction(self.win_rate, self.risk_reward_ratio).max(0.0);
+        true
+    }
+
+    /// 胜率下限：开仓信号的胜率低于 floor 时强制 HOLD，返回是否被否决
+    /// 需在 apply_win_rate_cap 之前调用，按模型给出的原始胜率判断
+    pub fn enforce_min_win_rate(&mut self, floor: f64) -> bool {
+        let is_entry = matches!(self.action, TradeAction::Buy | TradeAction::Sell);
+        if !is_entry || self.win_rate >= floor { return false; }
+        self.action = TradeAction::Hold;
+        true
+    }
+
+    /// 动作白名单：决策动作不在 allowed 中时强制 HOLD，返回是否被过滤
+    pub fn restrict_actions(&mut self, allowed: &[AllowedAction]) -> bool {
+        let kind = match self.action {
+            TradeAction::Buy => AllowedAction::Buy,
+            TradeAction::Sell => AllowedAction::Sell,
+            TradeAction::CloseLong => AllowedAction::CloseLong,
+            TradeAction::CloseShort => AllowedAction::CloseShort,
+            TradeAction::Hold => return false,
+        };
+        if allowed.contains(&kind) { return false; }
+        self.action = TradeAction::Hold;
+        true
+    }
+
     #[allow(dead_code)]
     pub fn action_name(&self) -> String {
         match self.action {
@@ -54,15 +137,34 @@ impl AiDecision {
 }
 
 impl DecisionMaker {
-    pub fn new(client: Client) -> Self {
+    pub fn new(client: Client, llm_config: LlmConfig) -> Self {
+        let ds_url = env::var("DEEPSEEK_BASE_URL").unwrap_or("https://api.deepseek.com".to_string());
+        let response_format = resolve_response_format(llm_config.response_format, &ds_url);
         Self { 
             client, 
             ds_key: env::var("DEEPSEEK_API_KEY").unwrap_or_default(),
-            ds_url: env::var("DEEPSEEK_BASE_URL").unwrap_or("https://api.deepseek.com".to_string()),
+            ds_url,
             strategy_version: env::var("STRATEGY_VERSION").unwrap_or("v6.0-Deep-Reasoning".to_string()),
+            system_prompt_template: prompt::load_system_prompt(),
+            llm_config,
+            tpsl_clamps: TpSlClamps::default(),
+            leverage_scaling: LeverageScalingConfig::default(),
+            response_format,
         }
     }
 
+    /// 使用配置的 TP/SL 范围 (默认为 [tpsl_clamp] 的默认值)
+    pub fn with_tpsl_clamps(mut self, clamps: TpSlClamps) -> Self {
+        self.tpsl_clamps = clamps;
+        self
+    }
+
+    /// 杠杆来源 (模型给出 / 按置信度推导)
+    pub fn with_leverage_scaling(mut self, scaling: LeverageScalingConfig) -> Self {
+        self.leverage_scaling = scaling;
+        self
+    }
+
     pub async fn analyze(&self, state: &MarketState, memories: &[String], position_info: &str, max_leverage: f64) -> Result<AiDecision> {
         if self.ds_key.is_empty() {
             return Err(anyhow!("DeepSeek API Key missing. Check .env"));
@@ -87,38 +189,27 @@ impl DecisionMaker {
             0.0
         };
 
-        info!("🧠 [DeepSeek Reasoner] Ingesting Full Context (ATR: {:.2}%)...", atr_pct);
+        if self.llm_config.prescreen {
+            match self.prescreen(state, &position_state_str, atr_pct).await {
+                Ok((false, reason)) => {
+                    info!("⏭️ [{}] Prescreen ({}) skipped full analysis: {}", state.symbol, self.llm_config.prescreen_model, reason);
+                    return Ok(AiDecision::hold(format!("Prescreen skip: {}", reason), self.strategy_version.clone()));
+                }
+                Ok((true, reason)) => info!("🔎 [{}] Prescreen: consider ({})", state.symbol, reason),
+                // 预筛失败时不影响正常分析
+                Err(e) => warn!("⚠️ [{}] Prescreen failed, running full analysis: {}", state.symbol, e),
+            }
+        }
+
+        let model = if self.llm_config.reasoning { "deepseek-reasoner" } else { "deepseek-chat" };
+        info!("🧠 [DeepSeek {}] Ingesting Full Context (ATR: {:.2}%)...", model, atr_pct);
 
         // [UPGRADE] System Prompt: CIO Edition (No Bias, Friction Aware, ATR Driven)
-        let system_prompt = r#"You are a seasoned Crypto Hedge Fund CIO powered by DeepSeek-R1. 
-Your goal is to maximize Alpha while strictly managing Risk of Ruin.
-
-### CORE PHILOSOPHY:
-1. **Trend Follower**: We trade with the trend (EMA20/50), not against it.
-2. **Friction Averse**: Trading costs money (Fees + Slippage). DO NOT flip positions (Close -> Open) unless the signal reversal is STRONG.
-3. **Data-Driven**: Your feelings don't matter. Only Price, Volume, and Volatility (ATR) matter.
-4. **History Rhymes**: Use the RAG Memory. If a setup failed before ("PAST MISTAKE"), DO NOT repeat it.
-
-### TASK:
-Analyze the provided Market Snapshot, Position, and Memories. Output a JSON decision.
-
-### RISK MANAGEMENT RULES (STRICT):
-- **Stop Loss (SL)**: MUST be calculated based on volatility. Typically 1.5x - 3.0x ATR. 
-  - If Volatility is HIGH, widen SL to avoid noise.
-  - If Volatility is LOW, tighten SL.
-- **Take Profit (TP)**: Aim for >1.5 Risk-Reward Ratio.
-- **Confidence**: If the signal is weak, output action: "HOLD".
-
-### OUTPUT FORMAT (JSON ONLY - NO COMMENTARY OUTSIDE JSON):
-{
-  "action": "BUY" | "SELL" | "CLOSE_LONG" | "CLOSE_SHORT" | "HOLD",
-  "reason": "Concise reasoning citing specific indicators (e.g. 'RSI div', 'Price > EMA20')...",
-  "tp": 0.0, // Target Profit (Decimal, e.g. 0.06 for 6%)
-  "sl": 0.0, // Stop Loss (Decimal, e.g. 0.02 for 2%)
-  "leverage": 1, // Integer, max constraint applies
-  "win_rate": 0.0, // Estimated probability (0.0-1.0) based on signal quality & memory match
-  "risk_reward_ratio": 0.0 // Expected Payoff (e.g. 2.5)
-}"#;
+        // 模板来自 SYSTEM_PROMPT_PATH，未配置时使用内置的 prompts/system_prompt.md
+        let system_prompt = prompt::render(&self.system_prompt_template, &[
+            ("max_leverage", (max_leverage as u32).to_string()),
+            ("strategy_version", self.strategy_version.clone()),
+        ]);
 
         // [UPGRADE] User Prompt: Injected ATR Context
         let user_prompt = format!(
@@ -145,10 +236,60 @@ Max Leverage: {}x
         // 打印 Prompt 供调试
         info!("\n================ [DEBUG] LLM FULL PROMPT START ================\n{}\n\n[USER MESSAGE]:\n{}\n================ [DEBUG] LLM FULL PROMPT END ================", system_prompt, user_prompt);
 
-        let response = self.call_llm("deepseek-reasoner", &self.ds_url, &self.ds_key, system_prompt, &user_prompt, 0.1).await
+        let reply = self.call_llm(model, &self.ds_url, &self.ds_key, &system_prompt, &user_prompt, ("trade_decision", decision_schema())).await
             .context("DeepSeek Analysis Failed")?;
         
-        self.parse_decision(&response, max_leverage)
+        let mut decision = self.parse_decision(&reply.content, max_leverage, atr_pct / 100.0, self.tpsl_clamps.for_symbol(&state.symbol))?;
+        decision.reasoning = truncate_reasoning(&reply.reasoning, MAX_REASONING_CHARS);
+        Ok(decision)
+    }
+
+    /// 两阶段决策的第一阶段：快速模型判断是否值得完整分析，返回 (是否继续, 原因)
+    pub async fn prescreen(&self, state: &MarketState, position_state: &str, atr_pct: f64) -> Result<(bool, String)> {
+        let user_prompt = format!("{}\n\nATR (1H): {:.2}% of price\nPosition: {}", state, atr_pct, position_state);
+        let reply = self.call_llm(&self.llm_config.prescreen_model, &self.ds_url, &self.ds_key, PRESCREEN_PROMPT, &user_prompt, ("prescreen_verdict", prescreen_schema())).await
+            .context("Prescreen failed")?;
+        self.parse_prescreen(&reply.content)
+            .ok_or_else(|| anyhow!("unrecognized prescreen reply: {}", reply.content.chars().take(200).collect::<String>()))
+    }
+
+    /// 压缩新闻与社媒两段舆情，使两段合计不超过 max_chars 字符，返回 (news, social)
+    pub async fn summarize_sentiment(&self, model: &str, news: &str, social: &str, max_chars: usize) -> Result<(String, String)> {
+        let user_prompt = format!(
+            "Compress both sections to at most {} characters IN TOTAL.\n\n[News Headlines]:\n{}\n\n[Social Discussion]:\n{}",
+            max_chars, news, social
+        );
+        let reply = self.call_llm(model, &self.ds_url, &self.ds_key, SUMMARY_PROMPT, &user_prompt, ("sentiment_summary", summary_schema())).await
+            .context("Sentiment summarization failed")?;
+        let v = self.extract_json(&reply.content)?;
+        let field = |key: &str| v[key].as_str().unwrap_or("").trim().to_string();
+        Ok((field("news"), field("social")))
+    }
+
+    /// 解析预筛结果；JSON 缺失时按正文中的 consider / skip 关键字判断
+    fn parse_prescreen(&self, content: &str) -> Option<(bool, String)> {
+        if let Ok(v) = self.extract_json(content) {
+            let reason = v["reason"].as_str().unwrap_or("").to_string();
+            match v["verdict"].as_str().map(|s| s.trim().to_lowercase()).as_deref() {
+                Some("consider") => return Some((true, reason)),
+                Some("skip") => return Some((false, reason)),
+                _ => {}
+            }
+        }
+        let lower = self.clean_reasoning_content(content).to_lowercase();
+        match (lower.contains("consider"), lower.contains("skip")) {
+            (true, false) => Some((true, String::new())),
+            (false, true) => Some((false, String::new())),
+            _ => None,
+        }
+    }
+
+    /// 提取内容中的 <think>...</think> 推理块 (部分模型把推理混在正文中返回)
+    fn extract_think_block(&self, raw: &str) -> Option<String> {
+        let start = raw.find("<think>")?;
+        let end = raw.find("</think>")?;
+        if end <= start { return None; }
+        Some(raw[start + 7..end].trim().to_string())
     }
 
     fn clean_reasoning_content(&self, raw: &str) -> String {
@@ -189,18 +330,37 @@ Max Leverage: {}x
         Err(anyhow!("Failed to extract JSON from response"))
     }
 
-    async fn call_llm(&self, model: &str, base_url: &str, key: &str, sys_prompt: &str, user_prompt: &str, temp: f64) -> Result<String> {
-        let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
-        let body = json!({
+    /// 构造 chat/completions 请求体，采样参数取自 [llm] 配置；schema = (名称, JSON Schema)，按 response_format 决定是否发送
+    fn build_request_body(&self, model: &str, sys_prompt: &str, user_prompt: &str, schema: (&str, Value)) -> Value {
+        let mut body = json!({
             "model": model,
             "messages": [
                 {"role": "system", "content": sys_prompt}, 
                 {"role": "user", "content": user_prompt}
             ],
-            "temperature": temp, 
+            "temperature": self.llm_config.temperature, 
         });
+        if let Some(max_tokens) = self.llm_config.max_tokens {
+            body["max_tokens"] = json!(max_tokens);
+        }
+        let (name, schema) = schema;
+        match self.response_format {
+            ResponseFormatMode::JsonObject => body["response_format"] = json!({"type": "json_object"}),
+            ResponseFormatMode::JsonSchema => body["response_format"] = json!({
+                "type": "json_schema",
+                "json_schema": {"name": name, "schema": schema},
+            }),
+            ResponseFormatMode::Auto | ResponseFormatMode::Off => {}
+        }
+        body
+    }
+
+    async fn call_llm(&self, model: &str, base_url: &str, key: &str, sys_prompt: &str, user_prompt: &str, schema: (&str, Value)) -> Result<LlmReply> {
+        let url = format!("{}/chat/completions", base_url.trim_end_matches('/'));
+        let mut body = self.build_request_body(model, sys_prompt, user_prompt, schema);
 
         for _attempt in 1..=3 {
+            let permit = host_limiter().acquire(&url).await;
             let resp_result = self.client.post(&url)
                 .header("Authorization", format!("Bearer {}", key))
                 .json(&body)
@@ -212,26 +372,42 @@ Max Leverage: {}x
                     if !r.status().is_success() {
                         let err = r.text().await.unwrap_or_default();
                         warn!("⚠️ {} API Error: {}", model, err);
-                        sleep(Duration::from_secs(3)).await;
+                        // 端点不支持结构化输出：去掉 response_format 重试，回到文本提取
+                        if err.contains("response_format") {
+                            if let Some(obj) = body.as_object_mut() {
+                                if obj.remove("response_format").is_some() {
+                                    warn!("⚠️ {} rejected response_format, retrying without it", model);
+                                }
+                            }
+                        }
+                        drop(permit);
+                        sleep(jittered(Duration::from_secs(3))).await;
                         continue;
                     }
                     let content_str = r.text().await.unwrap_or_default();
                     if let Ok(json_res) = serde_json::from_str::<Value>(&content_str) {
-                        if let Some(content) = json_res["choices"][0]["message"]["content"].as_str() {
-                            return Ok(content.to_string());
+                        let message = &json_res["choices"][0]["message"];
+                        if let Some(content) = message["content"].as_str() {
+                            // deepseek-reasoner 通过 reasoning_content 单独返回推理过程
+                            let reasoning = message["reasoning_content"].as_str()
+                                .map(|r| r.trim().to_string())
+                                .or_else(|| self.extract_think_block(content))
+                                .unwrap_or_default();
+                            return Ok(LlmReply { content: content.to_string(), reasoning });
                         }
                     }
                 },
                 Err(e) => {
                     warn!("⚠️ {} Network Error: {}", model, e);
-                    sleep(Duration::from_secs(3)).await;
+                    drop(permit);
+                    sleep(jittered(Duration::from_secs(3))).await;
                 }
             }
         }
         Err(anyhow!("{} Failed after 3 attempts", model))
     }
 
-    fn parse_decision(&self, content: &str, max_leverage: f64) -> Result<AiDecision> {
+    fn parse_decision(&self, content: &str, max_leverage: f64, atr_frac: f64, clamp: &TpSlClampConfig) -> Result<AiDecision> {
         let decision_json = self.extract_json(content)?;
         let action_str = decision_json["action"].as_str().unwrap_or("HOLD").to_uppercase();
         let action = match action_str.as_str() {
@@ -242,29 +418,44 @@ Max Leverage: {}x
             _ => TradeAction::Hold,
         };
 
-        let mut tp_pct = decision_json["tp"].as_f64().unwrap_or(0.04);
-        let mut sl_pct = decision_json["sl"].as_f64().unwrap_or(0.02);
-        
-        // [单位换算] 唯一的容错逻辑：防止 AI 把 5% 写成 5.0
-        if tp_pct > 1.0 { tp_pct /= 100.0; }
-        if sl_pct > 1.0 { sl_pct /= 100.0; }
+        // [单位换算] 唯一的容错逻辑：防止 AI 把 5% 写成 5.0 (先于兜底逻辑，只作用于模型给出的值)
+        let to_frac = |v: f64| if v > 1.0 { v / 100.0 } else { v };
+        let raw_tp = decision_json["tp"].as_f64().filter(|v| *v > 0.0).map(to_frac);
+        let raw_sl = decision_json["sl"].as_f64().filter(|v| *v > 0.0).map(to_frac);
+        if raw_tp.is_none() || raw_sl.is_none() {
+            warn!("⚠️ LLM omitted tp/sl (tp={:?}, sl={:?}). Using ATR-based fallback (ATR {:.2}%).", raw_tp, raw_sl, atr_frac * 100.0);
+        }
+        let (tp_pct, sl_pct) = fallback_tpsl(raw_tp, raw_sl, atr_frac, &self.llm_config);
+        let (mut tp_pct, sl_pct) = clamp_tpsl(tp_pct, sl_pct, clamp);
         
         // 兜底极小值 (防止 API 报错说价格太近)
         if (matches!(action, TradeAction::Buy) || matches!(action, TradeAction::Sell)) && tp_pct < 0.005 {
             tp_pct = 0.008; 
         }
 
+        let tp_ladder = parse_tp_ladder(&decision_json["tp_ladder"]);
+
         let raw_leverage = decision_json["leverage"].as_u64().unwrap_or(1) as u32;
-        let leverage = if raw_leverage > max_leverage as u32 { max_leverage as u32 } else if raw_leverage < 1 { 1 } else { raw_leverage };
 
         let p = decision_json["win_rate"].as_f64().unwrap_or(0.5);
         let b = decision_json["risk_reward_ratio"].as_f64().unwrap_or(1.5);
-        let kelly_fraction = if b > 0.0 { p - ((1.0 - p) / b) } else { 0.0 };
-        let (final_action, final_kelly) = if kelly_fraction <= 0.0 && (matches!(action, TradeAction::Buy) || matches!(action, TradeAction::Sell)) {
-            warn!("⚠️ Kelly negative ({:.2}). Force HOLD. (WinRate={:.2}, Odds={:.2})", kelly_fraction, p, b);
+        let kelly = kelly_fraction(p, b);
+        let (final_action, final_kelly) = if kelly <= 0.0 && (matches!(action, TradeAction::Buy) || matches!(action, TradeAction::Sell)) {
+            warn!("⚠️ Kelly negative ({:.2}). Force HOLD. (WinRate={:.2}, Odds={:.2})", kelly, p, b);
             (TradeAction::Hold, 0.0)
         } else {
-            (action, kelly_fraction.max(0.0))
+            (action, kelly.max(0.0))
+        };
+
+        let leverage = match self.leverage_scaling.mode {
+            LeverageMode::Model => raw_leverage.clamp(1, (max_leverage as u32).max(1)),
+            LeverageMode::Confidence => {
+                let scaled = confidence_leverage(final_kelly, max_leverage, &self.leverage_scaling.curve);
+                if scaled != raw_leverage {
+                    info!("🎚️ Confidence leverage: kelly {:.3} -> {}x (model suggested {}x, cap {:.0}x)", final_kelly, scaled, raw_leverage, max_leverage);
+                }
+                scaled
+            }
         };
 
         Ok(AiDecision {
@@ -272,11 +463,408 @@ Max Leverage: {}x
             reason: decision_json["reason"].as_str().unwrap_or("No reason").to_string(),
             tp_pct,
             sl_pct,
+            tp_ladder,
             leverage,
             win_rate: p,
             risk_reward_ratio: b,
             kelly_fraction: final_kelly,
             strategy_version: self.strategy_version.clone(),
+            reasoning: String::new(),
         })
     }
 }
+
+/// Auto 模式按端点判断结构化输出能力：OpenAI 支持 json_schema，DeepSeek 只支持 json_object
+fn resolve_response_format(mode: ResponseFormatMode, base_url: &str) -> ResponseFormatMode {
+    if mode != ResponseFormatMode::Auto { return mode; }
+    let url = base_url.to_lowercase();
+    if url.contains("api.openai.com") {
+        ResponseFormatMode::JsonSchema
+    } else if url.contains("deepseek.com") {
+        ResponseFormatMode::JsonObject
+    } else {
+        ResponseFormatMode::Off
+    }
+}
+
+/// 与 AiDecision / 系统提示词输出格式一致的 JSON Schema (tp_ladder 可选)
+fn decision_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "action": {"type": "string", "enum": ["BUY", "SELL", "CLOSE_LONG", "CLOSE_SHORT", "HOLD"]},
+            "reason": {"type": "string"},
+            "tp": {"type": "number"},
+            "sl": {"type": "number"},
+            "tp_ladder": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {"r": {"type": "number"}, "fraction": {"type": "number"}},
+                    "required": ["r", "fraction"],
+                    "additionalProperties": false,
+                },
+            },
+            "leverage": {"type": "integer", "minimum": 1},
+            "win_rate": {"type": "number", "minimum": 0, "maximum": 1},
+            "risk_reward_ratio": {"type": "number"},
+        },
+        "required": ["action", "reason", "tp", "sl", "leverage", "win_rate", "risk_reward_ratio"],
+        "additionalProperties": false,
+    })
+}
+
+fn prescreen_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "verdict": {"type": "string", "enum": ["consider", "skip"]},
+            "reason": {"type": "string"},
+        },
+        "required": ["verdict", "reason"],
+        "additionalProperties": false,
+    })
+}
+
+fn summary_schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {"news": {"type": "string"}, "social": {"type": "string"}},
+        "required": ["news", "social"],
+        "additionalProperties": false,
+    })
+}
+
+/// 置信度杠杆：凯利值在折线上线性插值得到上限的比例，四舍五入后限制在 [1, max_leverage]
+fn confidence_leverage(kelly: f64, max_leverage: f64, curve: &[LeveragePoint]) -> u32 {
+    let cap = (max_leverage as u32).max(1);
+    let fraction = match (curve.first(), curve.last()) {
+        (Some(first), _) if kelly <= first.kelly => first.fraction,
+        (_, Some(last)) if kelly >= last.kelly => last.fraction,
+        (Some(_), Some(_)) => curve.windows(2)
+            .find(|w| kelly <= w[1].kelly)
+            .map(|w| w[0].fraction + (w[1].fraction - w[0].fraction) * (kelly - w[0].kelly) / (w[1].kelly - w[0].kelly))
+            .unwrap_or(1.0),
+        _ => 1.0,
+    };
+    ((fraction * f64::from(cap)).round() as u32).clamp(1, cap)
+}
+
+/// 补全模型漏填的 TP/SL：SL = k × ATR%，TP = SL × 目标盈亏比；ATR 不可用时退回固定 4% / 2%
+fn fallback_tpsl(tp: Option<f64>, sl: Option<f64>, atr_frac: f64, cfg: &LlmConfig) -> (f64, f64) {
+    let has_atr = atr_frac > 0.0;
+    let sl = sl.unwrap_or(if has_atr { atr_frac * cfg.fallback_sl_atr_mult } else { 0.02 });
+    let tp = tp.unwrap_or(if has_atr { sl * cfg.fallback_target_rr } else { 0.04 });
+    (tp, sl)
+}
+
+/// 把 TP/SL 夹紧到允许范围内，发生调整时记录原值与调整幅度
+fn clamp_tpsl(tp: f64, sl: f64, clamp: &TpSlClampConfig) -> (f64, f64) {
+    let clamp_one = |name: &str, value: f64, min: f64, max: f64| {
+        let clamped = value.clamp(min, max);
+        if clamped != value {
+            warn!("✂️ LLM {} {:.2}% clamped to {:.2}% ({:+.2}pp, allowed {:.2}%-{:.2}%)",
+                name, value * 100.0, clamped * 100.0, (clamped - value) * 100.0, min * 100.0, max * 100.0);
+        }
+        clamped
+    };
+    (clamp_one("TP", tp, clamp.min_tp_pct, clamp.max_tp_pct), clamp_one("SL", sl, clamp.min_sl_pct, clamp.max_sl_pct))
+}
+
+/// 解析模型输出的 tp_ladder: [{"r": 1.5, "fraction": 0.5}, ...]，缺失或不合法时返回空 (使用配置的阶梯)
+fn parse_tp_ladder(value: &Value) -> Vec<TpRung> {
+    if value.is_null() { return Vec::new(); }
+    match serde_json::from_value::<Vec<TpRung>>(value.clone()) {
+        Ok(rungs) if is_valid_ladder(&rungs) => rungs,
+        _ => {
+            warn!("⚠️ LLM returned invalid tp_ladder {}. Ignored.", value);
+            Vec::new()
+        }
+    }
+}
+
+/// 决策抽象：主循环只依赖此 trait，便于用固定决策离线测试
+#[async_trait]
+pub trait DecisionEngine: Send + Sync {
+    async fn analyze(&self, state: &MarketState, memories: &[String], position_info: &str, max_leverage: f64) -> Result<AiDecision>;
+
+    /// 压缩新闻与社媒两段舆情 (Embedding 输入超长时使用)，返回 (news, social)
+    async fn summarize_sentiment(&self, model: &str, news: &str, social: &str, max_chars: usize) -> Result<(String, String)>;
+}
+
+#[async_trait]
+impl DecisionEngine for DecisionMaker {
+    async fn analyze(&self, state: &MarketState, memories: &[String], position_info: &str, max_leverage: f64) -> Result<AiDecision> {
+        DecisionMaker::analyze(self, state, memories, position_info, max_leverage).await
+    }
+
+    async fn summarize_sentiment(&self, model: &str, news: &str, social: &str, max_chars: usize) -> Result<(String, String)> {
+        DecisionMaker::summarize_sentiment(self, model, news, social, max_chars).await
+    }
+}
+
+/// 测试用大脑：每次分析都返回同一个预设决策，并记录收到的持仓描述
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use std::sync::Mutex;
+
+    pub struct ScriptedBrain {
+        pub decision: AiDecision,
+        pub positions_seen: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedBrain {
+        pub fn new(decision: AiDecision) -> Self {
+            Self { decision, positions_seen: Mutex::new(Vec::new()) }
+        }
+    }
+
+    /// 胜率 0.6、盈亏比 2 的固定决策
+    pub fn decision(action: TradeAction) -> AiDecision {
+        AiDecision {
+            action,
+            reason: "Breakout above EMA20 with rising OBV".to_string(),
+            tp_pct: 0.04,
+            sl_pct: 0.02,
+            tp_ladder: Vec::new(),
+            leverage: 5,
+            win_rate: 0.6,
+            kelly_fraction: kelly_fraction(0.6, 2.0),
+            risk_reward_ratio: 2.0,
+            strategy_version: "test-v1".to_string(),
+            reasoning: "stub".to_string(),
+        }
+    }
+
+    #[async_trait]
+    impl DecisionEngine for ScriptedBrain {
+        async fn analyze(&self, _state: &MarketState, _memories: &[String], position_info: &str, _max_leverage: f64) -> Result<AiDecision> {
+            self.positions_seen.lock().unwrap().push(position_info.to_string());
+            Ok(self.decision.clone())
+        }
+
+        async fn summarize_sentiment(&self, _model: &str, _news: &str, _social: &str, _max_chars: usize) -> Result<(String, String)> {
+            Err(anyhow!("mock brain does not summarize"))
+        }
+    }
+}
+
+/// 超长时保留首尾各一半，中间标注被省略的字符数
+fn truncate_reasoning(reasoning: &str, max_chars: usize) -> String {
+    let total = reasoning.chars().count();
+    if total <= max_chars { return reasoning.to_string(); }
+
+    let half = max_chars / 2;
+    let head: String = reasoning.chars().take(half).collect();
+    let tail: String = reasoning.chars().skip(total - half).collect();
+    format!("{}\n\n...[truncated {} chars]...\n\n{}", head, total - 2 * half, tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decision(win_rate: f64, rr: f64) -> AiDecision {
+        AiDecision {
+            action: TradeAction::Buy,
+            reason: String::new(),
+            tp_pct: 0.04,
+            sl_pct: 0.02,
+            tp_ladder: Vec::new(),
+            leverage: 1,
+            win_rate,
+            kelly_fraction: kelly_fraction(win_rate, rr),
+            risk_reward_ratio: rr,
+            strategy_version: "test".to_string(),
+            reasoning: String::new(),
+        }
+    }
+
+    #[test]
+    fn win_rate_at_cap_is_untouched() {
+        let mut d = decision(0.75, 2.0);
+        assert!(!d.apply_win_rate_cap(0.75));
+        assert_eq!(d.win_rate, 0.75);
+    }
+
+    #[test]
+    fn win_rate_above_cap_recomputes_kelly() {
+        let mut d = decision(0.9, 2.0);
+        assert!(d.apply_win_rate_cap(0.75));
+        assert_eq!(d.win_rate, 0.75);
+        // 0.75 - 0.25 / 2 = 0.625
+        assert!((d.kelly_fraction - 0.625).abs() < 1e-9);
+    }
+
+    #[test]
+    fn win_rate_below_floor_forces_hold() {
+        let mut d = decision(0.5, 3.0);
+        assert!(d.enforce_min_win_rate(0.55));
+        assert_eq!(d.action, TradeAction::Hold);
+    }
+
+    #[test]
+    fn win_rate_at_floor_passes() {
+        let mut d = decision(0.55, 3.0);
+        assert!(!d.enforce_min_win_rate(0.55));
+        assert_eq!(d.action, TradeAction::Buy);
+    }
+
+    #[test]
+    fn floor_ignores_close_actions() {
+        let mut d = decision(0.1, 1.0);
+        d.action = TradeAction::CloseLong;
+        assert!(!d.enforce_min_win_rate(0.55));
+        assert_eq!(d.action, TradeAction::CloseLong);
+    }
+
+    #[test]
+    fn request_body_uses_configured_sampling() {
+        let cfg = LlmConfig { temperature: 0.7, max_tokens: Some(4096), reasoning: false, ..LlmConfig::default() };
+        let body = DecisionMaker::new(Client::new(), cfg).build_request_body("deepseek-chat", "sys", "user", ("trade_decision", decision_schema()));
+        assert_eq!(body["temperature"], json!(0.7));
+        assert_eq!(body["max_tokens"], json!(4096));
+
+        let body = DecisionMaker::new(Client::new(), LlmConfig::default()).build_request_body("deepseek-reasoner", "sys", "user", ("trade_decision", decision_schema()));
+        assert!(body.get("max_tokens").is_none());
+    }
+
+    #[test]
+    fn response_format_follows_provider() {
+        assert_eq!(resolve_response_format(ResponseFormatMode::Auto, "https://api.deepseek.com/v1"), ResponseFormatMode::JsonObject);
+        assert_eq!(resolve_response_format(ResponseFormatMode::Auto, "https://api.openai.com/v1"), ResponseFormatMode::JsonSchema);
+        assert_eq!(resolve_response_format(ResponseFormatMode::Auto, "http://localhost:11434/v1"), ResponseFormatMode::Off);
+        assert_eq!(resolve_response_format(ResponseFormatMode::Off, "https://api.deepseek.com"), ResponseFormatMode::Off);
+
+        let cfg = LlmConfig { response_format: ResponseFormatMode::JsonSchema, ..LlmConfig::default() };
+        let body = DecisionMaker::new(Client::new(), cfg).build_request_body("m", "sys", "user", ("trade_decision", decision_schema()));
+        assert_eq!(body["response_format"]["type"], json!("json_schema"));
+        assert_eq!(body["response_format"]["json_schema"]["name"], json!("trade_decision"));
+        let required = body["response_format"]["json_schema"]["schema"]["required"].as_array().unwrap();
+        assert!(required.contains(&json!("win_rate")) && !required.contains(&json!("tp_ladder")));
+
+        let cfg = LlmConfig { response_format: ResponseFormatMode::Off, ..LlmConfig::default() };
+        let body = DecisionMaker::new(Client::new(), cfg).build_request_body("m", "sys", "user", ("trade_decision", decision_schema()));
+        assert!(body.get("response_format").is_none());
+    }
+
+    #[test]
+    fn missing_tpsl_falls_back_to_atr() {
+        let cfg = LlmConfig::default(); // 2 × ATR, RR 2
+        let (tp, sl) = fallback_tpsl(None, None, 0.01, &cfg);
+        assert!((sl - 0.02).abs() < 1e-12 && (tp - 0.04).abs() < 1e-12);
+
+        // 只缺 tp：按模型给出的 sl 推算
+        let (tp, sl) = fallback_tpsl(None, Some(0.015), 0.01, &cfg);
+        assert!((sl - 0.015).abs() < 1e-12 && (tp - 0.03).abs() < 1e-12);
+
+        // ATR 不可用时退回固定值
+        assert_eq!(fallback_tpsl(None, None, 0.0, &cfg), (0.04, 0.02));
+    }
+
+    #[test]
+    fn percent_units_are_converted_before_fallback() {
+        let dm = DecisionMaker::new(Client::new(), LlmConfig::default());
+        let d = dm.parse_decision(r#"{"action":"BUY","tp":5.0,"win_rate":0.6,"risk_reward_ratio":2.0}"#, 10.0, 0.01, &TpSlClampConfig::default()).unwrap();
+        assert!((d.tp_pct - 0.05).abs() < 1e-12);
+        assert!((d.sl_pct - 0.02).abs() < 1e-12);
+    }
+
+    #[test]
+    fn tpsl_clamped_to_configured_range() {
+        let clamp = TpSlClampConfig { min_tp_pct: 0.01, max_tp_pct: 0.10, min_sl_pct: 0.005, max_sl_pct: 0.05 };
+        // 超过上限
+        assert_eq!(clamp_tpsl(0.50, 0.08, &clamp), (0.10, 0.05));
+        // 低于下限
+        assert_eq!(clamp_tpsl(0.002, 0.001, &clamp), (0.01, 0.005));
+        // 范围内不变
+        assert_eq!(clamp_tpsl(0.04, 0.02, &clamp), (0.04, 0.02));
+
+        // 在单位换算之后生效：50 -> 50% -> 10%
+        let dm = DecisionMaker::new(Client::new(), LlmConfig::default());
+        let d = dm.parse_decision(r#"{"action":"BUY","tp":50,"sl":0.001,"win_rate":0.6,"risk_reward_ratio":2.0}"#, 10.0, 0.01, &clamp).unwrap();
+        assert!((d.tp_pct - 0.10).abs() < 1e-12);
+        assert!((d.sl_pct - 0.005).abs() < 1e-12);
+    }
+
+    #[test]
+    fn confidence_leverage_follows_the_curve() {
+        let curve = LeverageScalingConfig::default().curve; // (0.05, 20%) (0.15, 50%) (0.30, 100%)
+        let cases = [
+            (0.0, 2),    // 低于第一个点：20% × 10 = 2
+            (0.05, 2),
+            (0.10, 4),   // 20% 与 50% 之间插值 -> 35% -> 3.5 四舍五入为 4
+            (0.15, 5),
+            (0.225, 8),  // 75% -> 7.5 -> 8
+            (0.30, 10),
+            (0.90, 10),  // 超过最后一个点：不超过上限
+        ];
+        for (kelly, expected) in cases {
+            assert_eq!(confidence_leverage(kelly, 10.0, &curve), expected, "kelly {}", kelly);
+        }
+        // 上限很低时至少 1 倍
+        assert_eq!(confidence_leverage(0.0, 2.0, &curve), 1);
+        assert_eq!(confidence_leverage(0.3, 1.0, &curve), 1);
+    }
+
+    #[test]
+    fn leverage_mode_decides_the_source() {
+        let content = r#"{"action":"BUY","tp":0.06,"sl":0.02,"leverage":20,"win_rate":0.6,"risk_reward_ratio":2.0}"#;
+        // 模型模式：20x 被夹到上限 8x
+        let dm = DecisionMaker::new(Client::new(), LlmConfig::default());
+        assert_eq!(dm.parse_decision(content, 8.0, 0.01, &TpSlClampConfig::default()).unwrap().leverage, 8);
+
+        // 置信度模式：kelly = 0.6 - 0.4 / 2 = 0.4，超过曲线末端 -> 上限 8x；弱信号 (kelly 0.07) 降到 2x
+        let dm = DecisionMaker::new(Client::new(), LlmConfig::default())
+            .with_leverage_scaling(LeverageScalingConfig { mode: LeverageMode::Confidence, ..LeverageScalingConfig::default() });
+        assert_eq!(dm.parse_decision(content, 8.0, 0.01, &TpSlClampConfig::default()).unwrap().leverage, 8);
+        let weak = r#"{"action":"BUY","tp":0.06,"sl":0.02,"leverage":20,"win_rate":0.38,"risk_reward_ratio":2.0}"#;
+        assert_eq!(dm.parse_decision(weak, 8.0, 0.01, &TpSlClampConfig::default()).unwrap().leverage, 2);
+    }
+
+    #[test]
+    fn tp_ladder_is_optional_and_validated() {
+        let dm = DecisionMaker::new(Client::new(), LlmConfig::default());
+        let d = dm.parse_decision(
+            r#"{"action":"BUY","tp":0.06,"sl":0.02,"tp_ladder":[{"r":1.5,"fraction":0.5},{"r":3,"fraction":0.5}],"win_rate":0.6,"risk_reward_ratio":2.0}"#,
+            10.0, 0.01, &TpSlClampConfig::default(),
+        ).unwrap();
+        assert_eq!(d.tp_ladder, vec![
+            TpRung { r_multiple: 1.5, fraction: 0.5 },
+            TpRung { r_multiple: 3.0, fraction: 0.5 },
+        ]);
+
+        // 比例合计超过 1 -> 忽略
+        let d = dm.parse_decision(
+            r#"{"action":"BUY","tp":0.06,"sl":0.02,"tp_ladder":[{"r":1.5,"fraction":0.8},{"r":3,"fraction":0.5}],"win_rate":0.6,"risk_reward_ratio":2.0}"#,
+            10.0, 0.01, &TpSlClampConfig::default(),
+        ).unwrap();
+        assert!(d.tp_ladder.is_empty());
+    }
+
+    #[test]
+    fn prescreen_verdicts() {
+        let dm = DecisionMaker::new(Client::new(), LlmConfig::default());
+        assert_eq!(dm.parse_prescreen(r#"{"verdict":"skip","reason":"flat range"}"#), Some((false, "flat range".to_string())));
+        assert_eq!(dm.parse_prescreen(r#"```json
+{"verdict": "Consider", "reason": "breakout"}
+```"#), Some((true, "breakout".to_string())));
+        assert_eq!(dm.parse_prescreen("skip"), Some((false, String::new())));
+        // 含糊的回复交给完整分析处理
+        assert_eq!(dm.parse_prescreen("not sure"), None);
+    }
+
+    #[test]
+    fn disallowed_actions_become_hold() {
+        let longs_only = [AllowedAction::Buy, AllowedAction::CloseLong];
+        let mut d = decision(0.6, 2.0);
+        d.action = TradeAction::Sell;
+        assert!(d.restrict_actions(&longs_only));
+        assert_eq!(d.action, TradeAction::Hold);
+
+        let mut d = decision(0.6, 2.0);
+        assert!(!d.restrict_actions(&longs_only));
+        assert_eq!(d.action, TradeAction::Buy);
+    }
+}
@@ -1,5 +1,8 @@
 pub mod rag;
 pub mod llm;
+pub mod prompt;
+pub mod budget;
+pub mod embedding;
 
 pub use rag::MemorySystem;
 pub use llm::DecisionMaker;
\ No newline at end of file
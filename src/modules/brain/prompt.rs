@@ -0,0 +1,70 @@
+use std::env;
+use std::fs;
+use tracing::{info, warn, error};
+
+/// 内置的 CIO System Prompt，未设置 SYSTEM_PROMPT_PATH 时使用
+const DEFAULT_SYSTEM_PROMPT: &str = include_str!("../../../prompts/system_prompt.md");
+
+/// 模板必须包含的占位符，缺失时启动告警
+const REQUIRED_PLACEHOLDERS: &[&str] = &["max_leverage"];
+/// 所有支持的占位符
+const KNOWN_PLACEHOLDERS: &[&str] = &["max_leverage", "strategy_version"];
+
+/// 读取 System Prompt 模板：优先 SYSTEM_PROMPT_PATH 指向的文件，读取失败时回退到内置版本
+pub fn load_system_prompt() -> String {
+    let template = match env::var("SYSTEM_PROMPT_PATH") {
+        Ok(path) if !path.trim().is_empty() => match fs::read_to_string(&path) {
+            Ok(content) => {
+                info!("📝 Loaded system prompt template from {}", path);
+                content
+            }
+            Err(e) => {
+                error!("❌ Failed to read SYSTEM_PROMPT_PATH ({}): {}. Using built-in prompt.", path, e);
+                DEFAULT_SYSTEM_PROMPT.to_string()
+            }
+        },
+        _ => DEFAULT_SYSTEM_PROMPT.to_string(),
+    };
+    let template = template.trim().to_string();
+    validate(&template);
+    template
+}
+
+/// 检查必需占位符是否存在，并提示无法识别的占位符 (大概率是拼写错误)
+fn validate(template: &str) {
+    for name in REQUIRED_PLACEHOLDERS {
+        if !template.contains(&format!("{{{}}}", name)) {
+            warn!("⚠️ System prompt template is missing required placeholder {{{}}}", name);
+        }
+    }
+    for name in placeholders(template) {
+        if !KNOWN_PLACEHOLDERS.contains(&name) {
+            warn!("⚠️ System prompt template has unknown placeholder {{{}}} (left as-is)", name);
+        }
+    }
+}
+
+/// 提取 `{identifier}` 形式的占位符 (JSON 示例中的花括号不会匹配)
+fn placeholders(template: &str) -> Vec<&str> {
+    let mut found = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        if let Some(end) = rest.find('}') {
+            let name = &rest[..end];
+            if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                found.push(name);
+            }
+        }
+    }
+    found
+}
+
+/// 简单的 `{name}` 占位符替换
+pub fn render(template: &str, vars: &[(&str, String)]) -> String {
+    let mut out = template.to_string();
+    for (name, value) in vars {
+        out = out.replace(&format!("{{{}}}", name), value);
+    }
+    out
+}
@@ -1,50 +1,207 @@
 use reqwest::Client;
 use anyhow::{Result, anyhow};
 use serde_json::json;
-use std::env;
-use tracing::{info, error, warn};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, error, warn, debug};
 use qdrant_client::{
     Qdrant, 
     Payload, 
     qdrant::{
-        vectors_config::Config, CreateCollection, Distance, PointStruct, VectorParams, VectorsConfig,
+        vectors_config::Config, CollectionInfo, CreateCollection, Distance, GetCollectionInfoRequest, PointStruct, VectorParams, VectorsConfig,
         Filter, Condition, CountPoints, SearchPoints, UpsertPoints
     }
 };
 use uuid::Uuid;
+use crate::config::risk_profile::{EmbedStrategy, MemoryConfig};
+use crate::modules::perception::MarketState;
+use super::llm::DecisionEngine;
+use super::embedding::{self, EmbeddingProvider};
+use crate::error::TraderError;
+use crate::utils::http_client::{host_limiter, jittered};
 
 const COLLECTION_NAME: &str = "memory_vectors";
-const VECTOR_SIZE: u64 = 2560; 
+/// Qdrant 不可用时的重连探测间隔
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(60);
+/// 交易循环中 Embedding 可恢复错误的最大尝试次数
+const EMBED_MAX_ATTEMPTS: u32 = 10;
+/// 启动自检的最大尝试次数
+const STARTUP_CHECK_ATTEMPTS: u32 = 2;
+/// 启动自检耗时超过该值时提示链路过慢 (每轮召回都要付出这段延迟)
+pub const SLOW_EMBED_LATENCY: Duration = Duration::from_secs(5);
+
+/// Qdrant 可用性变化，由主循环取出后发送通知
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoryHealthEvent {
+    /// 连接丢失 (携带错误原因)，进入无记忆模式
+    Lost(String),
+    /// 重新连接成功
+    Restored,
+}
+
+/// Qdrant 连接状态：不可用时召回直接返回空、写入直接失败，不再逐次报错
+struct QdrantHealth {
+    available: bool,
+    last_probe: Option<Instant>,
+    events: Vec<MemoryHealthEvent>,
+}
+
+/// Embedding 接口错误分类：只有限频、服务端错误与网络错误值得重试
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EmbedErrorClass {
+    /// 400/401/403/404 等：Key、模型 ID 或参数错误，重试无意义
+    Permanent,
+    /// 429
+    RateLimited,
+    /// 5xx
+    Server,
+    /// 连接/超时/响应解析失败
+    Network,
+}
+
+impl EmbedErrorClass {
+    fn from_status(status: u16) -> Self {
+        match status {
+            429 => Self::RateLimited,
+            500..=599 => Self::Server,
+            _ => Self::Permanent,
+        }
+    }
+}
+
+/// Embedding 熔断：连续失败达到阈值后在冷却期内直接拒绝请求，避免每轮循环都卡在重试上
+struct EmbedBreaker {
+    max_failures: u32,
+    cooldown: Duration,
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+impl EmbedBreaker {
+    fn new(config: &MemoryConfig) -> Self {
+        Self {
+            max_failures: config.embed_max_failures,
+            cooldown: Duration::from_secs(config.embed_cooldown_sec),
+            consecutive_failures: 0,
+            open_until: None,
+        }
+    }
+
+    /// 熔断中返回剩余时间
+    fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.open_until.filter(|t| *t > now).map(|t| t - now)
+    }
+
+    fn record_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.open_until = None;
+    }
+
+    /// 记录一次失败，返回是否刚刚触发熔断
+    fn record_failure(&mut self, now: Instant) -> bool {
+        self.consecutive_failures += 1;
+        if self.max_failures == 0 || self.consecutive_failures < self.max_failures {
+            return false;
+        }
+        self.consecutive_failures = 0;
+        self.open_until = Some(now + self.cooldown);
+        true
+    }
+}
 
 pub struct MemorySystem {
     qdrant: Qdrant,
     client: Client, 
-    api_key: String,
-    api_base: String,
-    model_endpoint_id: String,
+    provider: Box<dyn EmbeddingProvider>,
+    config: MemoryConfig,
+    health: Mutex<QdrantHealth>,
+    embed_breaker: Mutex<EmbedBreaker>,
 }
 
 impl MemorySystem {
-    pub fn new(qdrant_url: String, client: Client) -> Result<Self> {
+    pub fn new(qdrant_url: String, client: Client, config: MemoryConfig) -> Result<Self> {
         let qdrant = Qdrant::from_url(&qdrant_url).build()?;
 
         Ok(Self { 
             qdrant, 
             client, 
-            api_key: env::var("VOLC_API_KEY").unwrap_or_default(),
-            api_base: env::var("VOLC_ENDPOINT").unwrap_or("https://ark.cn-beijing.volces.com/api/v3".to_string()),
-            model_endpoint_id: env::var("VOLC_MODEL").unwrap_or_default(),
+            provider: embedding::from_config(&config),
+            embed_breaker: Mutex::new(EmbedBreaker::new(&config)),
+            config,
+            health: Mutex::new(QdrantHealth { available: true, last_probe: None, events: Vec::new() }),
         })
     }
 
+    /// 确保集合存在；失败时进入无记忆模式，之后由 ensure_available 定期重连
     pub async fn init(&self) -> Result<()> {
-        if !self.qdrant.collection_exists(COLLECTION_NAME).await? {
-            info!("📦 Creating Qdrant collection '{}' with dim {}...", COLLECTION_NAME, VECTOR_SIZE);
+        let res = self.ensure_collection().await;
+        if let Err(e) = &res {
+            self.mark_unavailable(e);
+        }
+        res
+    }
+
+    /// 取出尚未通知的连接状态变化
+    pub fn take_health_events(&self) -> Vec<MemoryHealthEvent> {
+        std::mem::take(&mut self.health.lock().unwrap().events)
+    }
+
+    fn mark_unavailable(&self, err: &dyn std::fmt::Display) {
+        let mut health = self.health.lock().unwrap();
+        if health.available {
+            warn!("🧠 Qdrant unavailable ({}). Running without memory until it comes back.", err);
+            health.available = false;
+            health.last_probe = Some(Instant::now());
+            health.events.push(MemoryHealthEvent::Lost(err.to_string()));
+        }
+    }
+
+    /// Qdrant 可用时直接返回 true；不可用时每 RECONNECT_INTERVAL 探测一次
+    async fn ensure_available(&self) -> bool {
+        {
+            let mut health = self.health.lock().unwrap();
+            if health.available { return true; }
+            if health.last_probe.is_some_and(|t| t.elapsed() < RECONNECT_INTERVAL) { return false; }
+            health.last_probe = Some(Instant::now());
+        }
+
+        match self.ensure_collection().await {
+            Ok(()) => {
+                info!("🧠 Qdrant reconnected. Memory recall restored.");
+                let mut health = self.health.lock().unwrap();
+                health.available = true;
+                health.events.push(MemoryHealthEvent::Restored);
+                true
+            }
+            Err(e) => {
+                debug!("Qdrant still unavailable: {}", e);
+                false
+            }
+        }
+    }
+
+    /// 集合不存在时按服务商的维度创建；已存在时校验维度一致，不一致时拒绝使用 (写入和召回都会失败)
+    async fn ensure_collection(&self) -> Result<()> {
+        let dim = self.provider.dimension();
+        if self.qdrant.collection_exists(COLLECTION_NAME).await? {
+            let info = self.qdrant.collection_info(GetCollectionInfoRequest {
+                collection_name: COLLECTION_NAME.into(),
+            }).await?;
+            if let Some(existing) = info.result.as_ref().and_then(collection_dim) {
+                if existing != dim {
+                    return Err(TraderError::Config(format!(
+                        "Qdrant collection '{}' has dim {}, but embedding provider {} ({}) returns dim {}. Switch back or drop the collection to rebuild it",
+                        COLLECTION_NAME, existing, self.provider.name(), self.provider.model(), dim
+                    )).into());
+                }
+            }
+        } else {
+            info!("📦 Creating Qdrant collection '{}' with dim {} ({})...", COLLECTION_NAME, dim, self.provider.name());
             self.qdrant.create_collection(CreateCollection {
                 collection_name: COLLECTION_NAME.into(),
                 vectors_config: Some(VectorsConfig {
                     config: Some(Config::Params(VectorParams {
-                        size: VECTOR_SIZE,
+                        size: dim,
                         distance: Distance::Cosine.into(),
                         ..Default::default()
                     })),
@@ -57,77 +214,135 @@ impl MemorySystem {
     }
 
     async fn get_embedding(&self, text: &str) -> Result<Vec<f32>> {
-        if self.api_key.is_empty() || self.model_endpoint_id.is_empty() {
-            error!("Missing VOLC_API_KEY or VOLC_MODEL in .env");
-            return Ok(vec![0.0; VECTOR_SIZE as usize]); 
+        if !self.provider.is_configured() {
+            error!("Missing {} embedding config ({})", self.provider.name(), self.provider.env_hint());
+            return Ok(vec![0.0; self.provider.dimension() as usize]); 
         }
 
-        // [关键修复 1] 严格遵守豆包 API 4096 Token 限制
-        let safe_text = if text.len() > 8000 {
-            text.chars().take(8000).collect::<String>()
-        } else {
-            text.to_string()
-        };
+        if let Some(left) = self.embed_breaker.lock().unwrap().remaining(Instant::now()) {
+            return Err(anyhow!("Embedding circuit open ({}s left), skipping request", left.as_secs()));
+        }
 
-        let clean_base = self.api_base.trim_end_matches('/');
-        let url = format!("{}/embeddings", clean_base);
-        
-        let body_json = json!({
-            "model": self.model_endpoint_id, 
-            "input": safe_text,
-            "encoding_format": "float"
-        });
+        let res = self.request_embedding(text, EMBED_MAX_ATTEMPTS).await;
+        let mut breaker = self.embed_breaker.lock().unwrap();
+        match &res {
+            Ok(_) => breaker.record_success(),
+            Err(e) => {
+                if breaker.record_failure(Instant::now()) {
+                    error!("🔌 Embedding circuit opened for {}s after repeated failures. Last error: {}", self.config.embed_cooldown_sec, e);
+                }
+            }
+        }
+        res
+    }
+
+    /// 启动自检：嵌入一条短文本，校验向量维度与集合一致，返回耗时
+    /// 只尝试 STARTUP_CHECK_ATTEMPTS 次，避免网络不通时启动长时间卡住
+    pub async fn self_test(&self) -> Result<Duration> {
+        if !self.provider.is_configured() {
+            return Err(TraderError::Config(format!("{} embedding is not configured ({})", self.provider.name(), self.provider.env_hint())).into());
+        }
+        let start = Instant::now();
+        let vector = self.request_embedding("Rust Trader embedding self-test", STARTUP_CHECK_ATTEMPTS).await?;
+        let latency = start.elapsed();
+        if vector.len() != self.provider.dimension() as usize {
+            return Err(TraderError::Config(format!(
+                "Embedding dimension mismatch: model {} returned {} dims, collection expects {}",
+                self.provider.model(), vector.len(), self.provider.dimension()
+            )).into());
+        }
+        Ok(latency)
+    }
+
+    /// 嵌入单条文本
+    async fn request_embedding(&self, text: &str, max_attempts: u32) -> Result<Vec<f32>> {
+        self.request_embeddings(&[text], max_attempts).await?
+            .pop()
+            .ok_or_else(|| anyhow!("{} returned no embedding", self.provider.name()))
+    }
+
+    /// 调用 Embedding 接口：按服务商的批量上限分批请求，返回顺序与输入一致
+    /// 每批限频/5xx/网络错误最多尝试 max_attempts 次，Key/模型/参数错误立即返回配置错误
+    async fn request_embeddings(&self, texts: &[&str], max_attempts: u32) -> Result<Vec<Vec<f32>>> {
+        // [关键修复 1] 严格遵守模型上下文限制 (按字符截断，保证不切断 UTF-8)
+        let safe_texts: Vec<&str> = texts.iter().map(|text| {
+            let safe_text = truncate_chars(text, self.config.max_embed_chars);
+            if safe_text.len() < text.len() {
+                let total = text.chars().count();
+                warn!("✂️ Embedding input truncated: {} -> {} chars ({} dropped from tail)",
+                    total, self.config.max_embed_chars, total - self.config.max_embed_chars);
+            }
+            safe_text
+        }).collect();
+
+        let mut vectors = Vec::with_capacity(texts.len());
+        for batch in safe_texts.chunks(self.provider.max_batch().max(1)) {
+            vectors.extend(self.request_batch(batch, max_attempts).await?);
+        }
+        Ok(vectors)
+    }
 
+    async fn request_batch(&self, batch: &[&str], max_attempts: u32) -> Result<Vec<Vec<f32>>> {
+        let provider = self.provider.as_ref();
+        let url = provider.url();
         // [关键修复 2] 手动转 String 确保 Content-Length 头正确
-        let body_str = body_json.to_string();
+        let body_str = provider.request_body(batch).to_string();
         let mut last_error = anyhow!("Unknown error");
 
-        // [关键修复 3] 10次死磕重试
-        for attempt in 1..=10 {
-            match self.client.post(&url)
-                .header("Authorization", format!("Bearer {}", self.api_key))
+        // [关键修复 3] 可恢复错误最多重试 max_attempts 次
+        for attempt in 1..=max_attempts {
+            let permit = host_limiter().acquire(&url).await;
+            let result = self.client.post(&url)
+                .header("Authorization", format!("Bearer {}", provider.api_key()))
                 .header("Content-Type", "application/json")
                 .body(body_str.clone()) 
                 .send()
-                .await 
-            {
+                .await;
+            match result {
                 Ok(resp) => {
                     if resp.status().is_success() {
                         match resp.json::<serde_json::Value>().await {
-                            Ok(resp_json) => {
-                                if let Some(data) = resp_json["data"][0]["embedding"].as_array() {
-                                    let embedding_vec: Vec<f32> = data.iter()
-                                        .map(|v| v.as_f64().unwrap_or(0.0) as f32)
-                                        .collect();
-                                    
+                            Ok(resp_json) => match provider.parse_response(&resp_json, batch.len()) {
+                                Ok(vectors) => {
                                     if attempt > 1 {
                                         info!("✅ Embedding recovered on attempt {}", attempt);
                                     }
-                                    return Ok(embedding_vec);
+                                    return Ok(vectors);
                                 }
-                                last_error = anyhow!("Invalid JSON format from Volcengine");
+                                Err(e) => last_error = anyhow!("{} response: {}", provider.name(), e),
                             },
                             Err(e) => last_error = anyhow!("Failed to parse JSON: {}", e),
                         }
+                        warn!("⚠️ Embedding {:?} error (Attempt {}/{}): {}", EmbedErrorClass::Network, attempt, max_attempts, last_error);
                     } else {
                         // [编译错误修复点] 先把 status 存下来
                         let status_code = resp.status(); 
                         // 然后再消费 resp 获取 text
                         let err_text = resp.text().await.unwrap_or_default();
-                        
-                        last_error = anyhow!("Volcengine API Error [{}]: {}", status_code, err_text);
-                        warn!("⚠️ Embedding API Error (Attempt {}): {}", attempt, last_error);
+                        let class = EmbedErrorClass::from_status(status_code.as_u16());
+
+                        if class == EmbedErrorClass::Permanent {
+                            error!("❌ Embedding {:?} error [{}]: {}. Not retrying.", class, status_code, err_text);
+                            return Err(TraderError::Config(format!(
+                                "{} embedding rejected [{}]: {} (check {})",
+                                provider.name(), status_code, err_text, provider.env_hint()
+                            )).into());
+                        }
+                        last_error = anyhow!("{} API Error [{}]: {}", provider.name(), status_code, err_text);
+                        warn!("⚠️ Embedding {:?} error (Attempt {}/{}): {}", class, attempt, max_attempts, last_error);
                     }
                 },
                 Err(e) => {
                     last_error = anyhow!("Network Error: {}", e);
-                    warn!("⚠️ Embedding Network Error (Attempt {}/10): {}", attempt, e);
+                    warn!("⚠️ Embedding {:?} error (Attempt {}/{}): {}", EmbedErrorClass::Network, attempt, max_attempts, e);
                 }
             }
 
-            if attempt < 10 {
+            // 等待重试期间不占用并发名额
+            drop(permit);
+            if attempt < max_attempts {
                 let delay_sec = if attempt < 3 { 2 * attempt } else { 5 };
-                tokio::time::sleep(std::time::Duration::from_secs(delay_sec as u64)).await;
+                tokio::time::sleep(jittered(std::time::Duration::from_secs(delay_sec as u64))).await;
             }
         }
 
@@ -135,10 +350,13 @@ impl MemorySystem {
     }
 
     pub async fn recall_memories(&self, context_text: &str) -> Result<Vec<String>> {
+        // 无记忆模式：跳过 Embedding 调用，直接返回空
+        if !self.ensure_available().await { return Ok(vec![]); }
+
         let embedding = match self.get_embedding(context_text).await {
             Ok(v) => v,
             Err(e) => {
-                error!("❌ CRITICAL: RAG Failed after 10 attempts. Cause: {}", e);
+                error!("❌ CRITICAL: RAG embedding failed, recalling nothing this round. Cause: {}", e);
                 return Ok(vec![]);
             }
         };
@@ -152,16 +370,24 @@ impl MemorySystem {
             ..Default::default()
         };
 
-        let mistakes = self.qdrant.search_points(SearchPoints {
+        // 低于 min_score 的结果由 Qdrant 直接过滤
+        let score_threshold = if self.config.min_score > 0.0 { Some(self.config.min_score) } else { None };
+
+        let mistakes = match self.qdrant.search_points(SearchPoints {
             collection_name: COLLECTION_NAME.into(),
             vector: embedding.clone(),
             filter: Some(mistake_filter),
-            limit: 2,
+            limit: self.config.mistake_limit,
+            score_threshold,
             with_payload: Some(true.into()),
             ..Default::default()
-        }).await?;
+        }).await {
+            Ok(r) => r,
+            Err(e) => { self.mark_unavailable(&e); return Ok(vec![]); }
+        };
 
         for point in mistakes.result {
+            debug!("🔎 Recalled mistake memory (score {:.4})", point.score);
             if let Some(payload) = point.payload.get("content") {
                 if let Some(text) = payload.as_str() {
                     memories.push(format!("🚨 [CRITICAL WARNING] PAST MISTAKE: {}", text));
@@ -174,16 +400,21 @@ impl MemorySystem {
             ..Default::default()
         };
 
-        let missed = self.qdrant.search_points(SearchPoints {
+        let missed = match self.qdrant.search_points(SearchPoints {
             collection_name: COLLECTION_NAME.into(),
-            vector: embedding, 
+            vector: embedding.clone(),
             filter: Some(missed_filter),
-            limit: 2,
+            limit: self.config.missed_limit,
+            score_threshold,
             with_payload: Some(true.into()),
             ..Default::default()
-        }).await?;
+        }).await {
+            Ok(r) => r,
+            Err(e) => { self.mark_unavailable(&e); return Ok(memories); }
+        };
 
         for point in missed.result {
+            debug!("🔎 Recalled missed-opportunity memory (score {:.4})", point.score);
             if let Some(payload) = point.payload.get("content") {
                 if let Some(text) = payload.as_str() {
                     memories.push(format!("💡 [REFERENCE] MISSED OPPORTUNITY: {}", text));
@@ -191,10 +422,44 @@ impl MemorySystem {
             }
         }
 
+        // 成功交易 (backfill 回放的盈利平仓)，limit = 0 时跳过
+        if self.config.success_limit == 0 { return Ok(memories); }
+        let success_filter = Filter {
+            must: vec![Condition::matches("memory_type", "success".to_string())],
+            ..Default::default()
+        };
+
+        let successes = match self.qdrant.search_points(SearchPoints {
+            collection_name: COLLECTION_NAME.into(),
+            vector: embedding,
+            filter: Some(success_filter),
+            limit: self.config.success_limit,
+            score_threshold,
+            with_payload: Some(true.into()),
+            ..Default::default()
+        }).await {
+            Ok(r) => r,
+            Err(e) => { self.mark_unavailable(&e); return Ok(memories); }
+        };
+
+        for point in successes.result {
+            debug!("🔎 Recalled success memory (score {:.4})", point.score);
+            if let Some(payload) = point.payload.get("content") {
+                if let Some(text) = payload.as_str() {
+                    memories.push(format!("✅ [REFERENCE] PAST SUCCESS: {}", text));
+                }
+            }
+        }
+
         Ok(memories)
     }
 
+    /// 写入记忆；Qdrant 不可用时直接返回错误，调用方不会标记已处理，恢复后自然重试
     pub async fn store_memory(&self, memory_type: &str, content: &str) -> Result<()> {
+        if !self.ensure_available().await {
+            return Err(anyhow!("Qdrant unavailable, '{}' memory deferred", memory_type));
+        }
+
         let embedding = self.get_embedding(content).await?;
         
         if embedding.iter().all(|&x| x == 0.0) { return Ok(()); }
@@ -217,7 +482,10 @@ impl MemorySystem {
             ..Default::default()
         };
         
-        self.qdrant.upsert_points(request).await?;
+        if let Err(e) = self.qdrant.upsert_points(request).await {
+            self.mark_unavailable(&e);
+            return Err(e.into());
+        }
         Ok(())
     }
 
@@ -229,4 +497,130 @@ impl MemorySystem {
         }).await?;
         Ok(format!("Total Memories: {}", count_info.result.map(|r| r.count).unwrap_or(0)))
     }
-}
\ No newline at end of file
+}
+
+/// 压缩后舆情之外预留的余量 (字符)，模型输出长度并不精确
+const SUMMARY_MARGIN_CHARS: usize = 200;
+/// 留给舆情的空间少于该值时压缩已无意义，直接截断
+const MIN_SUMMARY_CHARS: usize = 300;
+
+/// 压缩后两段舆情可用的字符数；指标部分本身已接近上限时返回 None
+fn sentiment_budget(base_chars: usize, max_chars: usize) -> Option<usize> {
+    max_chars.checked_sub(base_chars + SUMMARY_MARGIN_CHARS).filter(|b| *b >= MIN_SUMMARY_CHARS)
+}
+
+/// 组装用于召回的 Embedding 文本：未超长时原样返回；超长时按 embed_strategy 截断 (在 request_embedding 中) 或先压缩舆情
+pub async fn embedding_context(state: &MarketState, brain: &dyn DecisionEngine, config: &MemoryConfig) -> String {
+    let full = state.to_context_string();
+    let full_chars = full.chars().count();
+    if full_chars <= config.max_embed_chars {
+        return full;
+    }
+    if config.embed_strategy == EmbedStrategy::Truncate {
+        info!("🧩 [{}] Embedding strategy: truncate ({} -> {} chars)", state.symbol, full_chars, config.max_embed_chars);
+        return full;
+    }
+
+    let mut compact = state.clone();
+    compact.news_sentiment.clear();
+    compact.reddit_sentiment.clear();
+    let base_chars = compact.to_context_string().chars().count();
+    let Some(budget) = sentiment_budget(base_chars, config.max_embed_chars) else {
+        warn!("🧩 [{}] Indicators alone use {} of {} chars, falling back to truncation", state.symbol, base_chars, config.max_embed_chars);
+        return full;
+    };
+
+    match brain.summarize_sentiment(&config.summary_model, &state.news_sentiment, &state.reddit_sentiment, budget).await {
+        Ok((news, social)) if !news.is_empty() || !social.is_empty() => {
+            compact.news_sentiment = news;
+            compact.reddit_sentiment = social;
+            let summarized = compact.to_context_string();
+            info!("🧩 [{}] Embedding strategy: summarize ({} -> {} chars, limit {})",
+                state.symbol, full_chars, summarized.chars().count(), config.max_embed_chars);
+            summarized
+        }
+        Ok(_) => {
+            warn!("🧩 [{}] Sentiment summary came back empty, falling back to truncation", state.symbol);
+            full
+        }
+        Err(e) => {
+            warn!("🧩 [{}] Sentiment summarization failed, falling back to truncation: {:#}", state.symbol, e);
+            full
+        }
+    }
+}
+
+/// 已有集合的向量维度 (单向量配置)；命名多向量或信息缺失时返回 None，不做校验
+fn collection_dim(info: &CollectionInfo) -> Option<u64> {
+    let vectors = info.config.as_ref()?.params.as_ref()?.vectors_config.as_ref()?;
+    match vectors.config.as_ref()? {
+        Config::Params(params) => Some(params.size),
+        _ => None,
+    }
+}
+
+/// 按字符数截断 (非字节)，未超长时原样返回
+fn truncate_chars(text: &str, max_chars: usize) -> &str {
+    match text.char_indices().nth(max_chars) {
+        Some((byte_idx, _)) => &text[..byte_idx],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_rate_limit_and_server_errors_are_transient() {
+        assert_eq!(EmbedErrorClass::from_status(401), EmbedErrorClass::Permanent);
+        assert_eq!(EmbedErrorClass::from_status(404), EmbedErrorClass::Permanent);
+        assert_eq!(EmbedErrorClass::from_status(400), EmbedErrorClass::Permanent);
+        assert_eq!(EmbedErrorClass::from_status(429), EmbedErrorClass::RateLimited);
+        assert_eq!(EmbedErrorClass::from_status(503), EmbedErrorClass::Server);
+    }
+
+    #[test]
+    fn sentiment_budget_leaves_margin_for_indicators() {
+        assert_eq!(sentiment_budget(1500, 8000), Some(8000 - 1500 - SUMMARY_MARGIN_CHARS));
+        // 指标部分已占满，压缩也放不下
+        assert_eq!(sentiment_budget(7700, 8000), None);
+        assert_eq!(sentiment_budget(9000, 8000), None);
+    }
+
+    #[test]
+    fn collection_dim_reads_single_vector_params() {
+        use qdrant_client::qdrant::{CollectionConfig, CollectionParams};
+        let info = |size| CollectionInfo {
+            config: Some(CollectionConfig {
+                params: Some(CollectionParams {
+                    vectors_config: Some(VectorsConfig {
+                        config: Some(Config::Params(VectorParams { size, ..Default::default() })),
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        assert_eq!(collection_dim(&info(2560)), Some(2560));
+        assert_eq!(collection_dim(&CollectionInfo::default()), None);
+    }
+
+    #[test]
+    fn breaker_opens_after_threshold_and_cools_down() {
+        let config = MemoryConfig { embed_max_failures: 2, embed_cooldown_sec: 60, ..MemoryConfig::default() };
+        let mut breaker = EmbedBreaker::new(&config);
+        let t0 = Instant::now();
+
+        assert!(!breaker.record_failure(t0));
+        assert!(breaker.remaining(t0).is_none());
+        assert!(breaker.record_failure(t0));
+        assert!(breaker.remaining(t0 + Duration::from_secs(30)).is_some());
+        assert!(breaker.remaining(t0 + Duration::from_secs(61)).is_none());
+
+        // 成功后计数清零
+        breaker.record_success();
+        assert!(!breaker.record_failure(t0));
+    }
+}
@@ -27,17 +27,16 @@ impl AutopsyDoctor {
 
         // [Fix] SQL 逻辑增强：
         // 1. ROE < 阈值 (大亏)
-        // 2. OR exit_reason 包含 'SL' (任何止损触发的交易，无论亏损大小)
-        // 注意：这需要数据库 trade_logs 表有 exit_reason 字段。如果暂时没有，我们先依赖 ROE。
-        // 目前数据库 schema 未知，假设我们先用 ROE 兜底，后续建议在 schema.sql 添加 exit_reason。
-        
-        // 这里的查询逻辑改为了更宽泛的捕获
+        // 2. OR exit_reason = 'sl_hit' (任何止损触发的交易，无论亏损大小)
         let rows = sqlx::query(
-            "SELECT id, context_snapshot, symbol, realized_pnl, initial_margin, direction 
+            "SELECT id, context_snapshot, symbol, realized_pnl::FLOAT8 AS realized_pnl,
+                    initial_margin::FLOAT8 AS initial_margin, direction, reasoning, exit_reason
              FROM trade_logs 
              WHERE (
                 (realized_pnl / NULLIF(initial_margin, 0)) < $1
+                OR exit_reason = 'sl_hit'
              )
+             AND realized_pnl IS NOT NULL
              AND is_reviewed = FALSE 
              AND created_at > NOW() - INTERVAL '24 hours'"
         )
@@ -52,20 +51,34 @@ impl AutopsyDoctor {
             let pnl: f64 = row.try_get("realized_pnl")?; 
             let margin: f64 = row.try_get("initial_margin")?;
             let direction: String = row.try_get("direction")?;
+            let reasoning: Option<String> = row.try_get("reasoning")?;
+            let exit_reason: Option<String> = row.try_get("exit_reason")?;
+            let sl_hit = exit_reason.as_deref() == Some("sl_hit");
 
             let roe = if margin != 0.0 { pnl / margin } else { 0.0 };
 
             let context_str = serde_json::to_string(&snapshot_val).unwrap_or_default();
             
+            // 附上当时推理的结尾部分 (结论所在)，让大脑看到具体是哪条逻辑出错
+            let reasoning_excerpt = reasoning
+                .filter(|r| !r.is_empty())
+                .map(|r| {
+                    let total = r.chars().count();
+                    let tail: String = r.chars().skip(total.saturating_sub(1000)).collect();
+                    format!("\nORIGINAL REASONING (excerpt): ...{}\n", tail)
+                })
+                .unwrap_or_default();
+
             // [Fix] 增强 Lesson 描述，增加摩擦提醒
+            let outcome = if sl_hit { "Stop Loss hit" } else { "Setup failed" };
             let lesson = format!(
                 "📚 LESSON: Trade {} on {} ended in LOSS (ROE: {:.2}%, PnL: {:.2} USDT). \
-                Setup failed or Stop Loss hit. \
+                {}.{}\
                 REVIEW CONTEXT & AVOID SIMILAR SETUPS:\n{}",
-                direction, symbol, roe * 100.0, pnl, context_str
+                direction, symbol, roe * 100.0, pnl, outcome, reasoning_excerpt, context_str
             );
 
-            info!("💀 Autopsy Generated Mistake Memory for {} (ROE: {:.2}%)", symbol, roe * 100.0);
+            info!("💀 Autopsy Generated Mistake Memory for {} (ROE: {:.2}%, exit: {})", symbol, roe * 100.0, exit_reason.as_deref().unwrap_or("unknown"));
             self.memory.store_memory("mistake", &lesson).await?;
 
             sqlx::query("UPDATE trade_logs SET is_reviewed = TRUE WHERE id = $1")
@@ -0,0 +1,303 @@
+// 文件名: backfill.rs
+// 历史交易回放：把 OKX 历史成交还原成完整的开平仓，按入场时的 K 线重建行情，盈利写入成功记忆、亏损写入错误记忆
+// 让新部署 (或记忆库被清空) 的机器人不必从零开始积累经验
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use tokio::time::sleep;
+use tracing::{info, warn};
+
+use crate::modules::action::executor::HistoricalFill;
+use crate::modules::action::TradeExecutor;
+use crate::modules::brain::MemorySystem;
+use crate::modules::perception::MarketDataFetcher;
+
+/// 每条记忆之间的间隔 (历史 K 线与 Embedding 接口都有限频)
+const REPLAY_PACING: Duration = Duration::from_millis(300);
+/// 数量精度容差：剩余张数低于此值视为已平完
+pub(crate) const SIZE_EPSILON: f64 = 1e-9;
+
+/// 还原后的一笔平仓 (同一平仓订单的多笔成交合并)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayedTrade {
+    pub symbol: String,
+    /// long / short
+    pub direction: &'static str,
+    /// 该仓位第一笔开仓成交时间 (毫秒)
+    pub entry_ts: i64,
+    /// 平仓时的持仓均价
+    pub entry_px: f64,
+    pub exit_ts: i64,
+    /// 平仓成交均价
+    pub exit_px: f64,
+    pub close_ord_id: String,
+    /// 平仓收益 + 平仓手续费
+    pub net_pnl: f64,
+}
+
+impl ReplayedTrade {
+    pub fn memory_type(&self) -> &'static str {
+        if self.net_pnl > 0.0 { "success" } else { "mistake" }
+    }
+}
+
+/// 未平的持仓腿
+struct OpenLeg {
+    sz: f64,
+    avg_px: f64,
+    first_ts: i64,
+}
+
+/// 成交所属的持仓方向 (long / short) 与是否为开仓
+/// - 双向持仓按 posSide 区分多空，buy long / sell short 为开仓
+/// - 单向持仓 (net) 有反向持仓时视为平仓，否则为开仓
+pub fn fill_leg(fill: &HistoricalFill, has_open_leg: impl Fn(&'static str) -> bool) -> (&'static str, bool) {
+    let side_dir = if fill.side == "buy" { "long" } else { "short" };
+    match fill.pos_side.as_str() {
+        "long" => ("long", fill.side == "buy"),
+        "short" => ("short", fill.side == "sell"),
+        _ => {
+            let opposite = if side_dir == "long" { "short" } else { "long" };
+            if has_open_leg(opposite) { (opposite, false) } else { (side_dir, true) }
+        }
+    }
+}
+
+/// 成交 → 平仓列表 (开平仓判断见 fill_leg)；找不到对应开仓的平仓 (开仓早于回看窗口) 直接跳过
+pub fn reconstruct_trades(mut fills: Vec<HistoricalFill>) -> Vec<ReplayedTrade> {
+    fills.sort_by(|a, b| a.ts.cmp(&b.ts).then_with(|| a.bill_id.cmp(&b.bill_id)));
+
+    let mut legs: HashMap<(String, &'static str), OpenLeg> = HashMap::new();
+    let mut trades: Vec<ReplayedTrade> = Vec::new();
+    // 平仓订单号 -> trades 下标，(合并分笔成交, 平仓张数)
+    let mut by_order: HashMap<String, (usize, f64)> = HashMap::new();
+
+    for fill in fills {
+        if fill.sz <= 0.0 || fill.px <= 0.0 {
+            continue;
+        }
+        let (direction, is_open) = fill_leg(&fill, |dir| legs.contains_key(&(fill.symbol.clone(), dir)));
+        let key = (fill.symbol.clone(), direction);
+
+        if is_open {
+            let leg = legs.entry(key).or_insert(OpenLeg { sz: 0.0, avg_px: 0.0, first_ts: fill.ts });
+            leg.avg_px = (leg.avg_px * leg.sz + fill.px * fill.sz) / (leg.sz + fill.sz);
+            leg.sz += fill.sz;
+            continue;
+        }
+
+        let net_pnl = fill.pnl + fill.fee;
+        if let Some((idx, closed_sz)) = by_order.get_mut(&fill.ord_id) {
+            let trade = &mut trades[*idx];
+            trade.exit_px = (trade.exit_px * *closed_sz + fill.px * fill.sz) / (*closed_sz + fill.sz);
+            trade.exit_ts = trade.exit_ts.max(fill.ts);
+            trade.net_pnl += net_pnl;
+            *closed_sz += fill.sz;
+        } else {
+            let Some(leg) = legs.get(&key) else { continue };
+            by_order.insert(fill.ord_id.clone(), (trades.len(), fill.sz));
+            trades.push(ReplayedTrade {
+                symbol: fill.symbol.clone(),
+                direction,
+                entry_ts: leg.first_ts,
+                entry_px: leg.avg_px,
+                exit_ts: fill.ts,
+                exit_px: fill.px,
+                close_ord_id: fill.ord_id.clone(),
+                net_pnl,
+            });
+        }
+
+        if let Some(leg) = legs.get_mut(&key) {
+            leg.sz -= fill.sz;
+            if leg.sz <= SIZE_EPSILON {
+                legs.remove(&key);
+            }
+        }
+    }
+    trades
+}
+
+/// 记忆正文：结果 + 入场时的行情叙事，与 autopsy 的 LESSON 同一格式，召回时可直接对照
+pub fn lesson_text(trade: &ReplayedTrade, entry_context: &str) -> String {
+    let held_hours = (trade.exit_ts - trade.entry_ts) as f64 / 3_600_000.0;
+    let closed_at = DateTime::<Utc>::from_timestamp_millis(trade.exit_ts)
+        .map(|t| t.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    let (header, outcome, advice) = if trade.net_pnl > 0.0 {
+        ("🏆 SUCCESS", "PROFIT", "SIMILAR SETUPS WORKED")
+    } else {
+        ("📚 LESSON", "LOSS", "REVIEW CONTEXT & AVOID SIMILAR SETUPS")
+    };
+    format!(
+        "{} (backfilled, closed {}): Trade {} on {} ended in {} (PnL: {:.2} USDT, entry {} -> exit {}, held {:.1}h). {}:\n{}",
+        header, closed_at, trade.direction, trade.symbol, outcome, trade.net_pnl,
+        trade.entry_px, trade.exit_px, held_hours, advice, entry_context
+    )
+}
+
+/// 回放结果统计
+#[derive(Debug, Default)]
+pub struct BackfillReport {
+    pub fills: usize,
+    pub trades: usize,
+    pub already_stored: usize,
+    pub successes: usize,
+    pub mistakes: usize,
+    pub failed: usize,
+}
+
+impl fmt::Display for BackfillReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} fills -> {} closed trades | {} already stored | stored {} success + {} mistake | {} failed",
+            self.fills, self.trades, self.already_stored, self.successes, self.mistakes, self.failed
+        )
+    }
+}
+
+/// 拉取近 lookback_days 天的成交并写入记忆；已写过的平仓订单 (memory_backfill) 跳过，可重复执行
+pub async fn run(
+    pool: &PgPool,
+    executor: &TradeExecutor,
+    fetcher: &MarketDataFetcher,
+    memory: &MemorySystem,
+    lookback_days: u32,
+    max_trades: usize,
+) -> Result<BackfillReport> {
+    let since_ms = Utc::now().timestamp_millis() - i64::from(lookback_days) * 86_400_000;
+    let fills = executor.fetch_fills_since(since_ms).await?;
+    let mut report = BackfillReport { fills: fills.len(), ..Default::default() };
+
+    let trades = reconstruct_trades(fills);
+    report.trades = trades.len();
+
+    let order_ids: Vec<String> = trades.iter().map(|t| t.close_ord_id.clone()).collect();
+    let stored: HashSet<String> = sqlx::query_scalar("SELECT close_ord_id FROM memory_backfill WHERE close_ord_id = ANY($1)")
+        .bind(&order_ids)
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .collect();
+    report.already_stored = stored.len();
+
+    // 超出上限时保留最近的交易
+    let pending: Vec<&ReplayedTrade> = trades.iter().filter(|t| !stored.contains(&t.close_ord_id)).collect();
+    let skip = pending.len().saturating_sub(max_trades);
+    if skip > 0 {
+        warn!("📼 Backfill capped at {} trades, {} older trades skipped", max_trades, skip);
+    }
+
+    for trade in pending.into_iter().skip(skip) {
+        let state = match fetcher.snapshot_at(&trade.symbol, trade.entry_ts).await {
+            Ok(s) => s,
+            Err(e) => {
+                warn!("📼 Backfill: no historical context for {} at {}: {}", trade.symbol, trade.entry_ts, e);
+                report.failed += 1;
+                continue;
+            }
+        };
+        let memory_type = trade.memory_type();
+        if let Err(e) = memory.store_memory(memory_type, &lesson_text(trade, &state.to_context_string())).await {
+            warn!("📼 Backfill: failed to store memory for order {}: {}", trade.close_ord_id, e);
+            report.failed += 1;
+            continue;
+        }
+
+        sqlx::query(
+            "INSERT INTO memory_backfill (close_ord_id, symbol, memory_type, net_pnl) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (close_ord_id) DO NOTHING"
+        )
+        .bind(&trade.close_ord_id)
+        .bind(&trade.symbol)
+        .bind(memory_type)
+        .bind(trade.net_pnl)
+        .execute(pool)
+        .await?;
+
+        if memory_type == "success" { report.successes += 1 } else { report.mistakes += 1 }
+        info!("📼 Backfilled {} memory: {} {} PnL {:.2} (order {})", memory_type, trade.direction, trade.symbol, trade.net_pnl, trade.close_ord_id);
+        sleep(REPLAY_PACING).await;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fill(ts: i64, side: &str, pos_side: &str, px: f64, sz: f64, pnl: f64, ord_id: &str) -> HistoricalFill {
+        HistoricalFill {
+            symbol: "BTC-USDT-SWAP".to_string(),
+            side: side.to_string(),
+            pos_side: pos_side.to_string(),
+            px,
+            sz,
+            pnl,
+            fee: -0.1,
+            ts,
+            ord_id: ord_id.to_string(),
+            bill_id: ts.to_string(),
+        }
+    }
+
+    #[test]
+    fn long_round_trip_uses_average_entry() {
+        // 倒序输入 (接口返回顺序)，两次开仓后一次平仓
+        let fills = vec![
+            fill(3, "sell", "long", 110.0, 2.0, 15.0, "c1"),
+            fill(2, "buy", "long", 105.0, 1.0, 0.0, "o2"),
+            fill(1, "buy", "long", 100.0, 1.0, 0.0, "o1"),
+        ];
+        let trades = reconstruct_trades(fills);
+        assert_eq!(trades.len(), 1);
+        let t = &trades[0];
+        assert_eq!((t.direction, t.entry_ts, t.exit_ts), ("long", 1, 3));
+        assert!((t.entry_px - 102.5).abs() < 1e-9);
+        assert!((t.net_pnl - 14.9).abs() < 1e-9);
+        assert_eq!(t.memory_type(), "success");
+    }
+
+    #[test]
+    fn partial_fills_of_one_close_order_are_merged() {
+        let fills = vec![
+            fill(1, "sell", "short", 100.0, 2.0, 0.0, "o1"),
+            fill(2, "buy", "short", 104.0, 1.0, -4.0, "c1"),
+            fill(3, "buy", "short", 106.0, 1.0, -6.0, "c1"),
+        ];
+        let trades = reconstruct_trades(fills);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(trades[0].direction, "short");
+        assert!((trades[0].exit_px - 105.0).abs() < 1e-9);
+        assert!((trades[0].net_pnl - (-10.2)).abs() < 1e-9);
+        assert_eq!(trades[0].memory_type(), "mistake");
+    }
+
+    #[test]
+    fn closes_without_known_entry_are_skipped() {
+        // 开仓早于回看窗口，只看到平仓
+        let fills = vec![fill(5, "sell", "long", 100.0, 1.0, 3.0, "c1")];
+        assert!(reconstruct_trades(fills).is_empty());
+    }
+
+    #[test]
+    fn net_mode_infers_close_from_opposite_position() {
+        let fills = vec![
+            fill(1, "sell", "net", 100.0, 1.0, 0.0, "o1"),
+            fill(2, "buy", "net", 95.0, 1.0, 5.0, "c1"),
+            // 平完后再次买入是新的多头开仓
+            fill(3, "buy", "net", 96.0, 1.0, 0.0, "o2"),
+            fill(4, "sell", "net", 97.0, 1.0, 1.0, "c2"),
+        ];
+        let trades = reconstruct_trades(fills);
+        let summary: Vec<(&str, i64, &str)> = trades.iter().map(|t| (t.direction, t.entry_ts, t.close_ord_id.as_str())).collect();
+        assert_eq!(summary, vec![("short", 1, "c1"), ("long", 3, "c2")]);
+    }
+}
@@ -1,7 +1,9 @@
 pub mod autopsy;
 pub mod scanner;
 pub mod pnl_monitor; // 新增
+pub mod stats;
+pub mod backfill;
 
 pub use autopsy::AutopsyDoctor;
 pub use scanner::OpportunityScanner;
-pub use pnl_monitor::PnlMonitor; // 导出
\ No newline at end of file
+pub use pnl_monitor::{ExitReason, PnlMonitor}; // 导出
\ No newline at end of file
@@ -1,57 +1,402 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
+use chrono::Utc;
 use sqlx::PgPool;
 use anyhow::Result;
-use crate::modules::action::TradeExecutor;
+use crate::modules::action::{EventLog, TradeExecutor};
+use crate::modules::action::executor::{HistoricalFill, BILL_TYPE_FUNDING};
+use crate::modules::evolution::backfill::{fill_leg, SIZE_EPSILON};
+use crate::modules::risk::circuit_breaker::consecutive_losses;
+use sqlx::Row;
 use tracing::{info, warn};
 
+/// 触发价与成交价的容差 (市价触发单存在少量滑点)
+const TRIGGER_TOLERANCE: f64 = 0.002;
+/// 统计连亏时最多回看的平仓笔数
+const LOSS_STREAK_LOOKBACK: i64 = 200;
+/// 成交明细接口只保留近 3 个月，更早开仓的持仓无法按成交还原
+const FILL_HISTORY_MS: i64 = 90 * 86_400_000;
+/// 记录写入时间晚于开仓成交，回看起点向前放宽
+const FILL_LOOKBACK_SLACK_MS: i64 = 10 * 60_000;
+
+/// 平仓原因 (trade_logs.exit_reason)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    TpHit,
+    SlHit,
+    /// 非 TP/SL 触发的平仓 (LLM 决策平仓或人工平仓)
+    ManualClose,
+    /// 预留: 持仓超时平仓
+    #[allow(dead_code)]
+    TimeStop,
+}
+
+impl ExitReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExitReason::TpHit => "tp_hit",
+            ExitReason::SlHit => "sl_hit",
+            ExitReason::ManualClose => "manual_close",
+            ExitReason::TimeStop => "time_stop",
+        }
+    }
+}
+
+/// 比较成交价与记录的 TP/SL 价格，推断平仓原因
+/// 止损单常以更差的价格成交，因此 SL 判断只向不利方向放宽
+pub fn classify_exit(direction: &str, fill_px: f64, tp_price: Option<f64>, sl_price: Option<f64>) -> ExitReason {
+    if fill_px <= 0.0 {
+        return ExitReason::ManualClose;
+    }
+    let is_long = direction == "long";
+    let hit_tp = tp_price.filter(|tp| *tp > 0.0).is_some_and(|tp| {
+        if is_long { fill_px >= tp * (1.0 - TRIGGER_TOLERANCE) } else { fill_px <= tp * (1.0 + TRIGGER_TOLERANCE) }
+    });
+    let hit_sl = sl_price.filter(|sl| *sl > 0.0).is_some_and(|sl| {
+        if is_long { fill_px <= sl * (1.0 + TRIGGER_TOLERANCE) } else { fill_px >= sl * (1.0 - TRIGGER_TOLERANCE) }
+    });
+    if hit_sl {
+        ExitReason::SlHit
+    } else if hit_tp {
+        ExitReason::TpHit
+    } else {
+        ExitReason::ManualClose
+    }
+}
+
+/// 一次完整的持仓 (开仓到数量归零)，由成交明细还原
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClosedPosition {
+    pub symbol: String,
+    /// long / short
+    pub direction: &'static str,
+    /// 开仓/加仓订单号 -> (成交张数, 开仓手续费)
+    pub entries: HashMap<String, (f64, f64)>,
+    /// 第一笔开仓成交时间 (毫秒)
+    pub opened_ts: i64,
+    /// 持仓归零的那笔平仓成交时间 (毫秒)
+    pub closed_ts: i64,
+    /// 最后一笔平仓成交价 (推断平仓原因)
+    pub exit_px: f64,
+    pub close_ord_id: String,
+    /// 全部平仓成交的收益 (fillPnl) + 平仓手续费
+    pub close_pnl: f64,
+}
+
+impl ClosedPosition {
+    /// 各开仓订单的净盈亏：自身开仓手续费 + 按开仓张数分摊的平仓盈亏与持仓期间的资金费
+    pub fn settle(&self, funding: f64) -> HashMap<String, f64> {
+        let total_sz: f64 = self.entries.values().map(|(sz, _)| sz).sum();
+        self.entries.iter()
+            .map(|(ord_id, (sz, fee))| (ord_id.clone(), fee + (self.close_pnl + funding) * sz / total_sz))
+            .collect()
+    }
+}
+
+/// 成交明细 → 已平完的持仓；部分平仓累计到同一持仓，数量归零才算平仓，
+/// 开仓早于回看窗口 (找不到开仓成交) 的平仓跳过
+pub fn closed_positions(mut fills: Vec<HistoricalFill>) -> Vec<ClosedPosition> {
+    fills.sort_by(|a, b| a.ts.cmp(&b.ts).then_with(|| a.bill_id.cmp(&b.bill_id)));
+
+    // (币种, 方向) -> (剩余张数, 持仓)
+    let mut open: HashMap<(String, &'static str), (f64, ClosedPosition)> = HashMap::new();
+    let mut closed = Vec::new();
+    for fill in fills {
+        if fill.sz <= 0.0 {
+            continue;
+        }
+        let (direction, is_open) = fill_leg(&fill, |dir| open.contains_key(&(fill.symbol.clone(), dir)));
+        let key = (fill.symbol.clone(), direction);
+
+        if is_open {
+            let (sz, pos) = open.entry(key).or_insert_with(|| (0.0, ClosedPosition {
+                symbol: fill.symbol.clone(),
+                direction,
+                entries: HashMap::new(),
+                opened_ts: fill.ts,
+                closed_ts: 0,
+                exit_px: 0.0,
+                close_ord_id: String::new(),
+                close_pnl: 0.0,
+            }));
+            *sz += fill.sz;
+            let entry = pos.entries.entry(fill.ord_id).or_insert((0.0, 0.0));
+            entry.0 += fill.sz;
+            entry.1 += fill.fee;
+            continue;
+        }
+
+        let Some((sz, pos)) = open.get_mut(&key) else { continue };
+        pos.close_pnl += fill.pnl + fill.fee;
+        pos.exit_px = fill.px;
+        pos.closed_ts = fill.ts;
+        pos.close_ord_id = fill.ord_id;
+        *sz -= fill.sz;
+        if *sz <= SIZE_EPSILON {
+            closed.extend(open.remove(&key).map(|(_, pos)| pos));
+        }
+    }
+    closed
+}
+
 pub struct PnlMonitor {
     pool: PgPool,
     executor: Arc<TradeExecutor>,
+    events: EventLog,
+    /// 最近一次同步后的连续亏损平仓笔数
+    loss_streak: AtomicU32,
 }
 
 impl PnlMonitor {
     pub fn new(pool: PgPool, executor: Arc<TradeExecutor>) -> Self {
-        Self { pool, executor }
+        Self { events: EventLog::new(pool.clone()), pool, executor, loss_streak: AtomicU32::new(0) }
     }
 
+    /// 最近一次同步后的连续亏损平仓笔数 (连亏暂停使用)
+    pub fn loss_streak(&self) -> u32 {
+        self.loss_streak.load(Ordering::Relaxed)
+    }
+
+    /// 回填已实现盈亏，然后按最新的平仓结果重算连亏笔数：
+    /// 只统计按平仓成交回填的记录 (close_ord_id 非空)，同一持仓的开仓/加仓记录合并为一笔
     pub async fn sync_realized_pnl(&self) -> Result<()> {
-        // [修复] 添加显式类型注解，解决编译器无法推断 bills 类型的问题
-        let bills: Vec<crate::modules::action::executor::PnlRecord> = match self.executor.fetch_recent_pnl().await {
+        self.backfill_realized_pnl().await?;
+        let pnls: Vec<f64> = sqlx::query_scalar(
+            "SELECT SUM(realized_pnl)::FLOAT8 FROM trade_logs
+             WHERE realized_pnl IS NOT NULL AND close_ord_id IS NOT NULL
+             GROUP BY close_ord_id
+             ORDER BY MAX(closed_at) DESC
+             LIMIT $1"
+        )
+        .bind(LOSS_STREAK_LOOKBACK)
+        .fetch_all(&self.pool)
+        .await?;
+        let streak = consecutive_losses(&pnls);
+        if streak != self.loss_streak.swap(streak, Ordering::Relaxed) {
+            info!("📉 Consecutive losing trades: {}", streak);
+        }
+        Ok(())
+    }
+
+    /// 未结算的开仓记录按持仓归零的平仓成交回填：净盈亏 = 开仓手续费 + 分摊的平仓盈亏 (fillPnl) + 持仓期间的资金费，
+    /// 平仓原因按最后一笔平仓成交价推断，closed_at 为该成交时间
+    async fn backfill_realized_pnl(&self) -> Result<()> {
+        let unsynced: HashSet<String> = sqlx::query_scalar(
+            "SELECT okx_order_id FROM trade_logs WHERE realized_pnl IS NULL AND okx_order_id IS NOT NULL AND okx_order_id <> 'dry-run'"
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .collect();
+        if unsynced.is_empty() {
+            return Ok(());
+        }
+
+        // 账单翻页直到这些订单全部出现，期间的资金费先入库，平仓回填时按持仓期间汇总
+        let bills = match self.executor.fetch_recent_pnl(&unsynced).await {
             Ok(b) => b,
             Err(e) => {
                 warn!("Failed to fetch bills from OKX: {}", e);
                 return Ok(());
             }
         };
-
-        if bills.is_empty() {
-            return Ok(());
+        for bill in bills.iter().filter(|b| b.type_name == BILL_TYPE_FUNDING && !b.bill_id.is_empty()) {
+            sqlx::query(
+                "INSERT INTO funding_bills (bill_id, symbol, amount, ts)
+                 VALUES ($1, $2, $3, to_timestamp($4::FLOAT8 / 1000.0))
+                 ON CONFLICT (bill_id) DO NOTHING"
+            )
+            .bind(&bill.bill_id)
+            .bind(&bill.symbol)
+            .bind(bill.bal_chg)
+            .bind(bill.ts as f64)
+            .execute(&self.pool)
+            .await?;
         }
 
-        info!("📥 Synced {} pnl records. Updating DB...", bills.len());
+        // 从最早的未结算记录开始拉成交明细，还原出已平完的持仓
+        let oldest_ms: Option<f64> = sqlx::query_scalar(
+            "SELECT (EXTRACT(EPOCH FROM MIN(created_at)) * 1000)::FLOAT8 FROM trade_logs
+             WHERE realized_pnl IS NULL AND okx_order_id IS NOT NULL AND okx_order_id <> 'dry-run'"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        let now_ms = Utc::now().timestamp_millis();
+        let since_ms = oldest_ms.map_or(now_ms, |ms| ms as i64 - FILL_LOOKBACK_SLACK_MS).max(now_ms - FILL_HISTORY_MS);
+        let fills = match self.executor.fetch_fills_since(since_ms).await {
+            Ok(f) => f,
+            Err(e) => {
+                warn!("Failed to fetch fills from OKX: {}", e);
+                return Ok(());
+            }
+        };
 
-        for bill in bills {
-            let net_pnl = bill.pnl + bill.fee;
+        let positions = closed_positions(fills);
+        info!("📥 Synced {} closed positions. Updating DB...", positions.len());
 
-            if bill.ord_id.is_empty() {
+        for pos in positions {
+            if !pos.entries.keys().any(|id| unsynced.contains(id)) {
                 continue;
             }
-
-            let result = sqlx::query(
-                "UPDATE trade_logs 
-                 SET realized_pnl = $1 
-                 WHERE okx_order_id = $2 AND realized_pnl IS NULL"
+            // 资金费账单不区分多空，按币种汇总 [第一笔开仓, 持仓归零] 期间的资金费
+            let funding: f64 = sqlx::query_scalar(
+                "SELECT COALESCE(SUM(amount), 0)::FLOAT8 FROM funding_bills
+                 WHERE symbol = $1 AND ts BETWEEN to_timestamp($2::FLOAT8 / 1000.0) AND to_timestamp($3::FLOAT8 / 1000.0)"
             )
-            .bind(net_pnl)
-            .bind(&bill.ord_id)
-            .execute(&self.pool)
+            .bind(&pos.symbol)
+            .bind(pos.opened_ts as f64)
+            .bind(pos.closed_ts as f64)
+            .fetch_one(&self.pool)
             .await?;
 
-            if result.rows_affected() > 0 {
-                info!("💰 PnL Updated for Order {}: ${:.2}", bill.ord_id, net_pnl);
+            for (ord_id, net_pnl) in pos.settle(funding) {
+                if !unsynced.contains(&ord_id) {
+                    continue;
+                }
+                let row = sqlx::query(
+                    "SELECT tp_price::FLOAT8 AS tp_price, sl_price::FLOAT8 AS sl_price
+                     FROM trade_logs
+                     WHERE okx_order_id = $1 AND realized_pnl IS NULL
+                     LIMIT 1"
+                )
+                .bind(&ord_id)
+                .fetch_optional(&self.pool)
+                .await?;
+                let Some(row) = row else { continue };
+                let reason = classify_exit(pos.direction, pos.exit_px, row.try_get("tp_price")?, row.try_get("sl_price")?);
+
+                // 机器人主动平仓时已写入 exit_reason，这里不覆盖
+                let result = sqlx::query(
+                    "UPDATE trade_logs
+                     SET realized_pnl = $1,
+                         exit_reason = COALESCE(exit_reason, $2),
+                         closed_at = to_timestamp($3::FLOAT8 / 1000.0),
+                         close_ord_id = $4
+                     WHERE okx_order_id = $5 AND realized_pnl IS NULL"
+                )
+                .bind(net_pnl)
+                .bind(reason.as_str())
+                .bind(pos.closed_ts as f64)
+                .bind(&pos.close_ord_id)
+                .bind(&ord_id)
+                .execute(&self.pool)
+                .await?;
+
+                if result.rows_affected() > 0 {
+                    info!("💰 PnL Updated for Order {}: ${:.2} ({}, closed by {})", ord_id, net_pnl, reason.as_str(), pos.close_ord_id);
+                    self.events.append_realized_pnl(&pos.symbol, &ord_id, serde_json::json!({
+                        "net_pnl": net_pnl, "fill_px": pos.exit_px, "exit_reason": reason.as_str(), "close_ord_id": pos.close_ord_id, "funding": funding,
+                    })).await;
+                }
             }
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_long_exits() {
+        let (tp, sl) = (Some(110.0), Some(95.0));
+        assert_eq!(classify_exit("long", 110.1, tp, sl), ExitReason::TpHit);
+        // 止损滑点成交
+        assert_eq!(classify_exit("long", 94.2, tp, sl), ExitReason::SlHit);
+        assert_eq!(classify_exit("long", 102.0, tp, sl), ExitReason::ManualClose);
+    }
+
+    #[test]
+    fn classifies_short_exits() {
+        let (tp, sl) = (Some(90.0), Some(105.0));
+        assert_eq!(classify_exit("short", 89.9, tp, sl), ExitReason::TpHit);
+        assert_eq!(classify_exit("short", 105.3, tp, sl), ExitReason::SlHit);
+        assert_eq!(classify_exit("short", 98.0, tp, sl), ExitReason::ManualClose);
+    }
+
+    #[test]
+    fn missing_levels_default_to_manual() {
+        assert_eq!(classify_exit("long", 100.0, None, None), ExitReason::ManualClose);
+        assert_eq!(classify_exit("long", 0.0, Some(110.0), Some(95.0)), ExitReason::ManualClose);
+    }
+
+    /// 手续费按 0.1/张扣除
+    fn fill(ts: i64, side: &str, pos_side: &str, px: f64, sz: f64, pnl: f64, ord_id: &str) -> HistoricalFill {
+        HistoricalFill {
+            symbol: "BTC-USDT-SWAP".to_string(),
+            side: side.to_string(),
+            pos_side: pos_side.to_string(),
+            px,
+            sz,
+            pnl,
+            fee: -0.1 * sz,
+            ts,
+            ord_id: ord_id.to_string(),
+            bill_id: ts.to_string(),
+        }
+    }
+
+    #[test]
+    fn position_closes_when_size_returns_to_zero() {
+        // 倒序输入：开仓 + 加仓，部分止盈后止损触发平掉剩余
+        let fills = vec![
+            fill(5, "sell", "long", 94.8, 2.0, -10.4, "sl"),
+            fill(4, "sell", "long", 110.0, 1.0, 7.5, "tp1"),
+            fill(2, "buy", "long", 105.0, 1.0, 0.0, "add"),
+            fill(1, "buy", "long", 100.0, 2.0, 0.0, "entry"),
+        ];
+        let positions = closed_positions(fills);
+        assert_eq!(positions.len(), 1);
+        let pos = &positions[0];
+        assert_eq!((pos.direction, pos.opened_ts, pos.closed_ts, pos.close_ord_id.as_str()), ("long", 1, 5, "sl"));
+        assert!((pos.close_pnl - -3.2).abs() < 1e-9);
+        // 按平仓成交价推断：剩余仓位是止损平掉的
+        assert_eq!(classify_exit(pos.direction, pos.exit_px, Some(110.0), Some(95.0)), ExitReason::SlHit);
+
+        let settled = pos.settle(0.0);
+        assert!((settled["entry"] - (-0.2 - 3.2 * 2.0 / 3.0)).abs() < 1e-9);
+        assert!((settled["add"] - (-0.1 - 3.2 / 3.0)).abs() < 1e-9);
+        // 持仓期间的资金费同样按张数分摊
+        let settled = pos.settle(-0.6);
+        assert!((settled["entry"] - (-0.2 - 3.8 * 2.0 / 3.0)).abs() < 1e-9);
+        assert!((settled["add"] - (-0.1 - 3.8 / 3.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn open_and_unmatched_positions_are_not_settled() {
+        let fills = vec![
+            // 仍持有：只有部分平仓
+            fill(1, "buy", "long", 100.0, 2.0, 0.0, "entry"),
+            fill(2, "sell", "long", 110.0, 1.0, 10.0, "tp1"),
+            // 开仓早于回看窗口
+            fill(3, "buy", "short", 90.0, 1.0, 5.0, "old-close"),
+        ];
+        assert!(closed_positions(fills).is_empty());
+    }
+
+    #[test]
+    fn entry_fee_alone_is_not_a_loss() {
+        // 开仓成交只有手续费 (fillPnl 为 0)，持仓未平，不产生平仓结果
+        let positions = closed_positions(vec![fill(1, "buy", "long", 100.0, 2.0, 0.0, "entry")]);
+        assert!(positions.is_empty());
+        let pnls: Vec<f64> = positions.iter().map(|p| p.close_pnl).collect();
+        assert_eq!(consecutive_losses(&pnls), 0);
+    }
+
+    #[test]
+    fn reopened_position_is_a_separate_trade() {
+        let fills = vec![
+            fill(1, "sell", "short", 100.0, 1.0, 0.0, "s1"),
+            fill(2, "buy", "short", 95.0, 1.0, 5.0, "c1"),
+            fill(3, "sell", "short", 96.0, 1.0, 0.0, "s2"),
+            fill(4, "buy", "short", 99.0, 1.0, -3.0, "c2"),
+        ];
+        let positions = closed_positions(fills);
+        assert_eq!(positions.len(), 2);
+        assert_eq!(positions[0].entries.keys().collect::<Vec<_>>(), vec!["s1"]);
+        assert_eq!((positions[1].opened_ts, positions[1].close_ord_id.as_str()), (3, "c2"));
+    }
+}
@@ -2,6 +2,8 @@ use std::sync::Arc;
 use sqlx::PgPool;
 use anyhow::Result;
 use crate::modules::perception::MarketDataFetcher;
+use crate::modules::perception::structs::Kline;
+use crate::modules::perception::math::TechnicalAnalysis;
 use crate::modules::brain::MemorySystem;
 use tracing::info;
 use serde_json::json;
@@ -10,11 +12,13 @@ pub struct OpportunityScanner {
     pool: PgPool,
     fetcher: Arc<MarketDataFetcher>,
     memory: Arc<MemorySystem>,
+    // 单根 1H K 线涨幅超过该值视为暴涨 (thresholds.scanner_pump_pct)
+    pump_pct: f64,
 }
 
 impl OpportunityScanner {
-    pub fn new(pool: PgPool, fetcher: Arc<MarketDataFetcher>, memory: Arc<MemorySystem>) -> Self {
-        Self { pool, fetcher, memory }
+    pub fn new(pool: PgPool, fetcher: Arc<MarketDataFetcher>, memory: Arc<MemorySystem>, pump_pct: f64) -> Self {
+        Self { pool, fetcher, memory, pump_pct }
     }
 
     pub async fn scan_missed_opportunities(&self, symbol: &str) -> Result<()> {
@@ -24,19 +28,22 @@ impl OpportunityScanner {
         if klines.len() < 3 { return Ok(()); }
 
         let current = klines.last().unwrap();
-        let prev = &klines[klines.len() - 2]; 
         // 核心修正：取暴涨前的那根 K 线 (pre_pump) 作为上下文
         // 这样 AI 记住的是"暴涨前的宁静"，而不是"暴涨后的高位"
         let pre_pump = &klines[klines.len() - 3]; 
 
-        let prev_close = prev.close_price();
-        if prev_close == 0.0 { return Ok(()); }
-        
-        // 计算最近一小时的涨幅 (判定是否发生了 Pump)
-        let price_change_pct = (current.close_price() - prev_close) / prev_close;
+        // 阈值：最近一小时涨幅超过 scanner_pump_pct 视为机会
+        if let Some(price_change_pct) = detect_pump(&klines, self.pump_pct) { 
+            // 同一根暴涨 K 线在多个进化周期内都会被扫到，已记录过则跳过
+            let already_seen: bool = sqlx::query_scalar(
+                "SELECT EXISTS(SELECT 1 FROM scanner_pumps WHERE symbol = $1 AND pump_ts = $2)"
+            )
+            .bind(symbol)
+            .bind(current.open_time)
+            .fetch_one(&self.pool)
+            .await?;
+            if already_seen { return Ok(()); }
 
-        // 阈值：涨幅超过 5% 视为机会
-        if price_change_pct > 0.05 { 
             // [修复 2] 扩大查询范围到 12 小时
             // 如果过去 12 小时内有买入，说明我们可能已经在车上了，不算踏空
             let recent_trades: i64 = sqlx::query_scalar(
@@ -49,28 +56,97 @@ impl OpportunityScanner {
             .await?;
 
             if recent_trades == 0 {
+                // OI 同步上涨说明是新资金推动，比空头回补更有参考价值；取不到历史则不标注
+                let oi_flow = match self.fetcher.fetch_open_interest_history(symbol).await {
+                    Ok(h) if h.len() >= 2 && h[1] > 0.0 => {
+                        let oi_chg = (h[0] - h[1]) / h[1];
+                        Some(TechnicalAnalysis::classify_oi_flow(price_change_pct, oi_chg))
+                    }
+                    _ => None,
+                };
+                let quality = match oi_flow {
+                    Some(flow) if flow.starts_with("New Money Long") => "HIGH QUALITY (OI-confirmed new money)",
+                    Some(_) => "LOW QUALITY (not backed by OI growth)",
+                    None => "UNCONFIRMED (OI history unavailable)",
+                };
+
                 // [修复 1] 构建暴涨"前"的上下文
                 let simplified_context = json!({
                     "symbol": symbol,
                     "price_before_pump": pre_pump.close_price(),
                     "indicators": {
-                        "note": "Snapshot taken 1h BEFORE the 5% pump",
+                        "note": format!("Snapshot taken 1h BEFORE the {:.0}%+ pump", self.pump_pct * 100.0),
                         "volume": pre_pump.volume, // 记录暴涨前的量能特征
-                        "structure": "Potential accumulation"
+                        "structure": "Potential accumulation",
+                        // 暴涨前 (不含暴涨 K 线) 的 OBV 方向，rising 说明量能提前吸筹
+                        "obv_trend": TechnicalAnalysis::obv_trend(&klines[..klines.len() - 1], 20),
+                        "oi_flow": oi_flow.unwrap_or("Unknown")
                     }
                 });
 
                 // [修复 3] 结论前置
                 let lesson = format!(
-                    "💡 OPPORTUNITY: Price pumped {:.2}% shortly after this state. Look for these signs!\n\nPRE-PUMP CONTEXT: {}",
-                    price_change_pct * 100.0, simplified_context.to_string()
+                    "💡 OPPORTUNITY [{}]: Price pumped {:.2}% shortly after this state. Look for these signs!\n\nPRE-PUMP CONTEXT: {}",
+                    quality, price_change_pct * 100.0, simplified_context
                 );
                 
                 info!("🧬 Scanner found FOMO for {}: Pumped {:.2}%", symbol, price_change_pct * 100.0);
                 self.memory.store_memory("missed_opportunity", &lesson).await?;
             }
+
+            sqlx::query(
+                "INSERT INTO scanner_pumps (symbol, pump_ts, change_pct) VALUES ($1, $2, $3)
+                 ON CONFLICT (symbol, pump_ts) DO NOTHING"
+            )
+            .bind(symbol)
+            .bind(current.open_time)
+            .bind(price_change_pct)
+            .execute(&self.pool)
+            .await?;
         }
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// 最后一根 K 线相对前一根收盘价的涨幅超过 pump_pct 时返回涨幅
+fn detect_pump(klines: &[Kline], pump_pct: f64) -> Option<f64> {
+    if klines.len() < 2 { return None; }
+    let prev_close = klines[klines.len() - 2].close_price();
+    if prev_close == 0.0 { return None; }
+
+    let change = (klines[klines.len() - 1].close_price() - prev_close) / prev_close;
+    if change > pump_pct { Some(change) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closes(values: &[f64]) -> Vec<Kline> {
+        values.iter().enumerate().map(|(i, c)| Kline {
+            open_time: i as i64 * 3_600_000,
+            open: c.to_string(),
+            high: c.to_string(),
+            low: c.to_string(),
+            close: c.to_string(),
+            volume: "100".to_string(),
+        }).collect()
+    }
+
+    #[test]
+    fn pump_threshold_comes_from_config() {
+        // +4% 涨幅: 低于 5% 阈值不触发，3% 阈值触发
+        let k = closes(&[100.0, 100.0, 104.0]);
+        assert_eq!(detect_pump(&k, 0.05), None);
+        let change = detect_pump(&k, 0.03).expect("pump detected");
+        assert!((change - 0.04).abs() < 1e-12);
+    }
+
+    #[test]
+    fn no_pump_on_drop_or_short_series() {
+        assert_eq!(detect_pump(&closes(&[100.0, 100.0, 90.0]), 0.01), None);
+        assert_eq!(detect_pump(&closes(&[100.0]), 0.01), None);
+        assert_eq!(detect_pump(&closes(&[0.0, 10.0]), 0.01), None);
+    }
+}
@@ -0,0 +1,286 @@
+use std::fmt;
+
+use crate::utils::money;
+
+/// 计算 Sharpe/Sortino 所需的最少已平仓交易数，低于此值返回 InsufficientData
+pub const MIN_CLOSED_TRADES: usize = 5;
+/// CLI `stats` 默认统计窗口 (天)
+pub const DEFAULT_WINDOW_DAYS: i32 = 30;
+/// 周报统计窗口 (天)
+pub const REPORT_WINDOW_DAYS: i32 = 7;
+
+/// 单笔已平仓交易 (realized_pnl 已由 PnlMonitor 回填)
+#[derive(Debug, Clone)]
+pub struct ClosedTrade {
+    pub pnl: f64,
+    pub initial_margin: f64,
+}
+
+/// 报告窗口内平仓的一笔交易 (按平仓时间筛选)
+#[derive(Debug, Clone)]
+pub struct ClosedTradeRecord {
+    pub symbol: String,
+    pub direction: String,
+    pub pnl: f64,
+}
+
+/// 报告窗口内的平仓汇总 (定时状态报告使用)
+#[derive(Debug, Clone)]
+pub struct WindowSummary {
+    pub window_secs: u64,
+    pub closed_trades: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub net_pnl: f64,
+    pub best: Option<ClosedTradeRecord>,
+    pub worst: Option<ClosedTradeRecord>,
+}
+
+impl WindowSummary {
+    pub fn compute(trades: &[ClosedTradeRecord], window_secs: u64) -> Self {
+        let by_pnl = |a: &&ClosedTradeRecord, b: &&ClosedTradeRecord| a.pnl.total_cmp(&b.pnl);
+        let wins = trades.iter().filter(|t| t.pnl > 0.0).count();
+        Self {
+            window_secs,
+            closed_trades: trades.len(),
+            wins,
+            losses: trades.len() - wins,
+            net_pnl: money::sum(trades.iter().map(|t| t.pnl)),
+            best: trades.iter().max_by(by_pnl).cloned(),
+            worst: trades.iter().min_by(by_pnl).cloned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TradeStats {
+    pub window_days: i32,
+    pub closed_trades: usize,
+    pub win_rate: f64,
+    pub avg_win: f64,
+    pub avg_loss: f64,
+    pub net_pnl: f64,
+    /// 无亏损交易时为 None (无穷大)
+    pub profit_factor: Option<f64>,
+    /// 按单笔 ROE 计算 (未年化)，收益无波动时为 None
+    pub sharpe: Option<f64>,
+    pub sortino: Option<f64>,
+}
+
+#[derive(Debug, Clone)]
+pub enum PerformanceReport {
+    InsufficientData { window_days: i32, closed_trades: usize },
+    Ready(TradeStats),
+}
+
+impl PerformanceReport {
+    pub fn compute(trades: &[ClosedTrade], window_days: i32) -> Self {
+        if trades.len() < MIN_CLOSED_TRADES {
+            return PerformanceReport::InsufficientData { window_days, closed_trades: trades.len() };
+        }
+
+        let wins: Vec<f64> = trades.iter().map(|t| t.pnl).filter(|p| *p > 0.0).collect();
+        let losses: Vec<f64> = trades.iter().map(|t| t.pnl).filter(|p| *p <= 0.0).collect();
+
+        // 盈亏汇总使用十进制求和，避免多笔累加后的浮点尾差
+        let gross_win = money::sum(wins.iter().copied());
+        let gross_loss = money::sum(losses.iter().copied()).abs();
+
+        let avg_win = if wins.is_empty() { 0.0 } else { gross_win / wins.len() as f64 };
+        let avg_loss = if losses.is_empty() { 0.0 } else { -gross_loss / losses.len() as f64 };
+        let profit_factor = if gross_loss > 0.0 { Some(gross_win / gross_loss) } else { None };
+
+        // 使用 ROE 作为单笔收益率，避免仓位大小不同导致的偏差
+        let returns: Vec<f64> = trades.iter()
+            .filter(|t| t.initial_margin > 0.0)
+            .map(|t| t.pnl / t.initial_margin)
+            .collect();
+
+        let (sharpe, sortino) = Self::risk_adjusted(&returns);
+
+        PerformanceReport::Ready(TradeStats {
+            window_days,
+            closed_trades: trades.len(),
+            win_rate: wins.len() as f64 / trades.len() as f64,
+            avg_win,
+            avg_loss,
+            net_pnl: money::sum([gross_win, -gross_loss]),
+            profit_factor,
+            sharpe,
+            sortino,
+        })
+    }
+
+    fn risk_adjusted(returns: &[f64]) -> (Option<f64>, Option<f64>) {
+        if returns.len() < 2 { return (None, None); }
+
+        let n = returns.len() as f64;
+        let mean = returns.iter().sum::<f64>() / n;
+        let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let std_dev = variance.sqrt();
+        let downside_dev = (returns.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / n).sqrt();
+
+        let sharpe = if std_dev > 0.0 { Some(mean / std_dev) } else { None };
+        let sortino = if downside_dev > 0.0 { Some(mean / downside_dev) } else { None };
+        (sharpe, sortino)
+    }
+}
+
+/// 单个 strategy_version 在窗口内的已平仓表现 (A/B 对比提示词/策略改动)
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionStats {
+    pub version: String,
+    pub closed_trades: usize,
+    pub wins: usize,
+    pub net_pnl: f64,
+}
+
+impl VersionStats {
+    pub fn win_rate(&self) -> f64 {
+        if self.closed_trades == 0 { 0.0 } else { self.wins as f64 / self.closed_trades as f64 }
+    }
+
+    pub fn avg_pnl(&self) -> f64 {
+        if self.closed_trades == 0 { 0.0 } else { self.net_pnl / self.closed_trades as f64 }
+    }
+
+    /// 笔数不足 MIN_CLOSED_TRADES 时胜率没有统计意义，只展示不比较
+    pub fn is_significant(&self) -> bool {
+        self.closed_trades >= MIN_CLOSED_TRADES
+    }
+}
+
+impl fmt::Display for VersionStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} | Trades: {} | Win Rate: {:.1}% | Net PnL: ${:.2} | Avg: ${:.2}",
+            self.version, self.closed_trades, self.win_rate() * 100.0, self.net_pnl, self.avg_pnl())?;
+        if !self.is_significant() {
+            write!(f, " (insufficient data, need >= {})", MIN_CLOSED_TRADES)?;
+        }
+        Ok(())
+    }
+}
+
+fn fmt_opt(v: Option<f64>) -> String {
+    v.map(|x| format!("{:.2}", x)).unwrap_or("n/a".to_string())
+}
+
+impl fmt::Display for PerformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PerformanceReport::InsufficientData { window_days, closed_trades } => write!(f,
+                "Insufficient data: {} closed trades in last {}d (need >= {})",
+                closed_trades, window_days, MIN_CLOSED_TRADES
+            ),
+            PerformanceReport::Ready(s) => write!(f,
+                "Last {}d | Trades: {} | Win Rate: {:.1}% | Avg Win: ${:.2} | Avg Loss: ${:.2} | \
+                Net PnL: ${:.2} | Profit Factor: {} | Sharpe: {} | Sortino: {}",
+                s.window_days, s.closed_trades, s.win_rate * 100.0, s.avg_win, s.avg_loss,
+                s.net_pnl,
+                s.profit_factor.map(|x| format!("{:.2}", x)).unwrap_or("∞".to_string()),
+                fmt_opt(s.sharpe), fmt_opt(s.sortino)
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn closed(symbol: &str, pnl: f64) -> ClosedTradeRecord {
+        ClosedTradeRecord { symbol: symbol.to_string(), direction: "buy".to_string(), pnl }
+    }
+
+    #[test]
+    fn window_summary_counts_and_extremes() {
+        let summary = WindowSummary::compute(&[
+            closed("BTC-USDT-SWAP", 12.5),
+            closed("ETH-USDT-SWAP", -4.0),
+            closed("SOL-USDT-SWAP", 0.0),
+        ], 3600);
+        assert_eq!((summary.closed_trades, summary.wins, summary.losses), (3, 1, 2));
+        assert!((summary.net_pnl - 8.5).abs() < 1e-9);
+        assert_eq!(summary.best.unwrap().symbol, "BTC-USDT-SWAP");
+        assert_eq!(summary.worst.unwrap().symbol, "ETH-USDT-SWAP");
+
+        // 十笔 0.1 的盈亏合计恰为 1.0 (f64 逐笔累加为 0.9999999999999999)
+        let dimes: Vec<_> = (0..10).map(|_| closed("DOGE-USDT-SWAP", 0.1)).collect();
+        assert_eq!(WindowSummary::compute(&dimes, 3600).net_pnl, 1.0);
+
+        let empty = WindowSummary::compute(&[], 3600);
+        assert_eq!(empty.closed_trades, 0);
+        assert!(empty.best.is_none() && empty.worst.is_none());
+    }
+
+    fn trade(pnl: f64, initial_margin: f64) -> ClosedTrade {
+        ClosedTrade { pnl, initial_margin }
+    }
+
+    fn ready(report: PerformanceReport) -> TradeStats {
+        match report {
+            PerformanceReport::Ready(s) => s,
+            other => panic!("expected stats, got {}", other),
+        }
+    }
+
+    #[test]
+    fn performance_needs_minimum_closed_trades() {
+        let trades: Vec<_> = (0..MIN_CLOSED_TRADES - 1).map(|_| trade(10.0, 100.0)).collect();
+        let report = PerformanceReport::compute(&trades, 30);
+        assert!(matches!(report, PerformanceReport::InsufficientData { window_days: 30, closed_trades } if closed_trades == MIN_CLOSED_TRADES - 1));
+        assert!(report.to_string().contains(&format!("need >= {}", MIN_CLOSED_TRADES)));
+    }
+
+    #[test]
+    fn performance_ratios_from_roe() {
+        // ROE: +10%, -5%, +20%, -10%, +5%
+        let s = ready(PerformanceReport::compute(&[
+            trade(10.0, 100.0), trade(-5.0, 100.0), trade(20.0, 100.0), trade(-10.0, 100.0), trade(5.0, 100.0),
+        ], 30));
+        assert_eq!(s.closed_trades, 5);
+        assert!((s.win_rate - 0.6).abs() < 1e-9);
+        assert!((s.avg_win - 35.0 / 3.0).abs() < 1e-9);
+        assert!((s.avg_loss - -7.5).abs() < 1e-9);
+        assert_eq!(s.net_pnl, 20.0);
+        assert!((s.profit_factor.unwrap() - 35.0 / 15.0).abs() < 1e-9);
+        // 均值 0.04，样本标准差 sqrt(0.057 / 4)；下行偏差 sqrt((0.05² + 0.1²) / 5) = 0.05
+        assert!((s.sharpe.unwrap() - 0.04 / (0.057f64 / 4.0).sqrt()).abs() < 1e-9);
+        assert!((s.sortino.unwrap() - 0.8).abs() < 1e-9);
+    }
+
+    #[test]
+    fn performance_without_losses_or_variance_has_no_nan() {
+        // 无亏损：profit factor 为 ∞，下行偏差为 0 时 Sortino 不可算
+        let s = ready(PerformanceReport::compute(&[
+            trade(10.0, 100.0), trade(20.0, 100.0), trade(5.0, 50.0), trade(8.0, 100.0), trade(12.0, 0.0),
+        ], 7));
+        assert_eq!((s.profit_factor, s.sortino, s.avg_loss), (None, None, 0.0));
+        assert!(s.sharpe.is_some_and(f64::is_finite));
+        assert!(PerformanceReport::Ready(s).to_string().contains("Profit Factor: ∞"));
+
+        // 收益完全相同 (标准差为 0) 与全部缺少保证金 (无收益率) 时比率为 None 而不是 NaN
+        let flat = ready(PerformanceReport::compute(&vec![trade(10.0, 100.0); 5], 7));
+        assert_eq!((flat.sharpe, flat.sortino), (None, None));
+        let no_margin = ready(PerformanceReport::compute(&vec![trade(-3.0, 0.0); 5], 7));
+        assert_eq!((no_margin.sharpe, no_margin.sortino), (None, None));
+        assert_eq!(no_margin.profit_factor, Some(0.0));
+        assert!(!PerformanceReport::Ready(no_margin).to_string().contains("NaN"));
+    }
+
+    #[test]
+    fn version_stats_flag_small_samples() {
+        let v = VersionStats { version: "v7-prompt-b".to_string(), closed_trades: 8, wins: 6, net_pnl: 40.0 };
+        assert!((v.win_rate() - 0.75).abs() < 1e-9);
+        assert!((v.avg_pnl() - 5.0).abs() < 1e-9);
+        assert!(v.is_significant());
+        assert!(!v.to_string().contains("insufficient"));
+
+        let small = VersionStats { version: "v8".to_string(), closed_trades: 2, wins: 2, net_pnl: 10.0 };
+        assert!(!small.is_significant());
+        assert!(small.to_string().contains("insufficient data"));
+
+        let empty = VersionStats { version: "v9".to_string(), closed_trades: 0, wins: 0, net_pnl: 0.0 };
+        assert_eq!((empty.win_rate(), empty.avg_pnl()), (0.0, 0.0));
+    }
+}
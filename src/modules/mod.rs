@@ -2,4 +2,6 @@ pub mod perception;
 pub mod brain;
 pub mod action;
 pub mod evolution;
+pub mod risk;
+pub mod api;
 // pub mod web; // 已移除
\ No newline at end of file
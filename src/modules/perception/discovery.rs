@@ -0,0 +1,109 @@
+// 文件名: discovery.rs
+// 自动选币：从安全白名单 (allowed_symbols) 中按 24h 成交额与 ATR% 选出当天的交易池
+
+use std::cmp::Ordering;
+
+use anyhow::Result;
+use tracing::{info, warn};
+
+use super::fetcher::MarketData;
+use super::math::TechnicalAnalysis;
+use crate::config::risk_profile::{DiscoveryConfig, DiscoveryRank};
+
+/// 候选币种的选币指标
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub symbol: String,
+    pub volume_usd: f64,
+    /// 1H ATR% (1.0 = 1%)
+    pub atr_pct: f64,
+}
+
+/// 各候选在 key 上的名次 (0 = 最高)
+fn ranks(candidates: &[Candidate], key: impl Fn(&Candidate) -> f64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..candidates.len()).collect();
+    order.sort_by(|&a, &b| key(&candidates[b]).total_cmp(&key(&candidates[a])));
+    let mut rank = vec![0; candidates.len()];
+    for (pos, idx) in order.into_iter().enumerate() {
+        rank[idx] = pos;
+    }
+    rank
+}
+
+/// 过滤掉不达标的候选后按 rank_by 排序，取前 top_n
+/// blend 按两项名次之和排序，名次相同时成交额大的优先
+pub fn rank_universe(cfg: &DiscoveryConfig, candidates: &[Candidate]) -> Vec<String> {
+    let eligible: Vec<Candidate> = candidates.iter()
+        .filter(|c| c.volume_usd >= cfg.min_volume_usd && c.atr_pct >= cfg.min_atr_pct)
+        .cloned()
+        .collect();
+
+    let volume_rank = ranks(&eligible, |c| c.volume_usd);
+    let atr_rank = ranks(&eligible, |c| c.atr_pct);
+    let score = |i: usize| match cfg.rank_by {
+        DiscoveryRank::Volume => volume_rank[i],
+        DiscoveryRank::Volatility => atr_rank[i],
+        DiscoveryRank::Blend => volume_rank[i] + atr_rank[i],
+    };
+
+    let mut order: Vec<usize> = (0..eligible.len()).collect();
+    order.sort_by(|&a, &b| match score(a).cmp(&score(b)) {
+        Ordering::Equal => volume_rank[a].cmp(&volume_rank[b]),
+        other => other,
+    });
+    order.into_iter().take(cfg.top_n).map(|i| eligible[i].symbol.clone()).collect()
+}
+
+/// 拉取全市场行情与白名单币种的 K 线，返回新的交易池 (保持排序)
+pub async fn discover(fetcher: &dyn MarketData, cfg: &DiscoveryConfig, allowlist: &[String], inst_type: &str) -> Result<Vec<String>> {
+    let tickers = fetcher.fetch_tickers(inst_type).await?;
+
+    let mut candidates = Vec::new();
+    for (symbol, last, volume_usd) in tickers.into_iter().filter(|(s, _, _)| allowlist.contains(s)) {
+        // 成交额不达标的不必再拉 K 线
+        if volume_usd < cfg.min_volume_usd || last <= 0.0 { continue; }
+        match fetcher.fetch_klines(&symbol).await {
+            Ok(klines) => {
+                let atr_pct = TechnicalAnalysis::calculate_atr(&klines, 14) / last * 100.0;
+                candidates.push(Candidate { symbol, volume_usd, atr_pct });
+            }
+            Err(e) => warn!("🔭 [{}] Skipped in discovery, klines unavailable: {}", symbol, e),
+        }
+    }
+
+    let universe = rank_universe(cfg, &candidates);
+    info!("🔭 Discovery ranked {} eligible of {} allowlisted symbols: {:?}", candidates.len(), allowlist.len(), universe);
+    Ok(universe)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(symbol: &str, volume_usd: f64, atr_pct: f64) -> Candidate {
+        Candidate { symbol: symbol.to_string(), volume_usd, atr_pct }
+    }
+
+    #[test]
+    fn ranks_by_configured_criteria_after_filtering() {
+        let candidates = [
+            candidate("BTC", 900.0, 0.5),
+            candidate("ETH", 600.0, 0.8),
+            candidate("SOL", 300.0, 1.5),
+            candidate("DOGE", 50.0, 3.0),
+        ];
+        let mut cfg = DiscoveryConfig { enabled: true, top_n: 2, min_volume_usd: 100.0, ..DiscoveryConfig::default() };
+
+        cfg.rank_by = DiscoveryRank::Volume;
+        assert_eq!(rank_universe(&cfg, &candidates), ["BTC", "ETH"]);
+        // DOGE 成交额不达标，即使波动最大也不入选
+        cfg.rank_by = DiscoveryRank::Volatility;
+        assert_eq!(rank_universe(&cfg, &candidates), ["SOL", "ETH"]);
+        // 名次和: BTC 0+2, ETH 1+1, SOL 2+0 -> 平局按成交额
+        cfg.rank_by = DiscoveryRank::Blend;
+        assert_eq!(rank_universe(&cfg, &candidates), ["BTC", "ETH"]);
+
+        cfg.min_atr_pct = 1.0;
+        assert_eq!(rank_universe(&cfg, &candidates), ["SOL"]);
+    }
+}
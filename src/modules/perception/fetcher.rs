@@ -1,33 +1,105 @@
-use reqwest::Client;
-use anyhow::{Result, Context};
+use reqwest::{Client, RequestBuilder};
+use anyhow::{Result, Context, anyhow};
+use async_trait::async_trait;
 use serde_json::Value;
 use super::structs::{Kline, MarketState};
-use super::math::TechnicalAnalysis;
+use super::math::{IchimokuPeriods, TechnicalAnalysis};
+use super::regime;
+use crate::config::okx::{OkxEndpoints, SIMULATED_HEADER};
+use crate::config::risk_profile::{IndicatorConfig, RegimeConfig};
 use chrono::Utc;
+use dashmap::DashMap;
+use tracing::warn;
+
+/// OKX K 线接口单次返回上限
+const KLINE_PAGE_SIZE: usize = 100;
+/// 分析使用的 K 线周期 (1H) 毫秒数
+const KLINE_BAR_MS: i64 = 3_600_000;
+
+/// 多页 K 线合并为升序序列 (最旧在前)，并去掉翻页边界上的重复 K 线
+fn assemble_klines(mut rows: Vec<Kline>) -> Vec<Kline> {
+    rows.sort_by_key(|k| k.open_time);
+    rows.dedup_by_key(|k| k.open_time);
+    rows
+}
 
 pub struct MarketDataFetcher {
     client: Client,
     base_url: String,
+    simulated: bool,
+    // 上一次的 OI 读数，历史接口不可用时用于计算 OI 变化 (降级方案)
+    last_oi: DashMap<String, f64>,
+    // 最近一次快照的收盘价序列 (升序)，供相关性计算复用，避免重复拉 K 线
+    recent_closes: DashMap<String, Vec<f64>>,
+    // 资金费结算周期 (小时)，用于年化折算
+    funding_interval_hours: f64,
+    ichimoku_periods: IchimokuPeriods,
+    // 每次分析拉取的 K 线根数
+    kline_limit: usize,
+    regime: RegimeConfig,
 }
 
 impl MarketDataFetcher {
-    pub fn new(client: Client) -> Self {
+    pub fn new(client: Client, endpoints: &OkxEndpoints, indicators: &IndicatorConfig, regime: &RegimeConfig) -> Self {
         Self {
             client,
-            base_url: "https://www.okx.com".to_string(),
+            base_url: endpoints.rest_url.clone(),
+            simulated: endpoints.simulated,
+            last_oi: DashMap::new(),
+            recent_closes: DashMap::new(),
+            funding_interval_hours: indicators.funding_interval_hours,
+            ichimoku_periods: indicators.ichimoku_periods(),
+            kline_limit: indicators.kline_limit,
+            regime: regime.clone(),
         }
     }
 
-    /// 获取 K 线数据 (1小时级别)
+    /// 公共接口 GET；模拟盘模式下与执行器一样携带模拟盘请求头
+    fn get(&self, url: &str) -> RequestBuilder {
+        let req = self.client.get(url);
+        if self.simulated { req.header(SIMULATED_HEADER, "1") } else { req }
+    }
+
+    /// 获取 K 线数据 (1小时级别)，按时间升序 (最旧在前)
+    /// 超过单次 100 根上限时用 after 参数向前翻页拼接；近期接口翻到头后改用历史接口
     pub async fn fetch_klines(&self, symbol: &str) -> Result<Vec<Kline>> {
-        let url = format!("{}/api/v5/market/candles", self.base_url);
-        let params = [
-            ("instId", symbol),
-            ("bar", "1H"),
-            ("limit", "100"),
+        let mut rows: Vec<Kline> = Vec::with_capacity(self.kline_limit);
+        let mut endpoint = "candles";
+        let mut after: Option<i64> = None;
+
+        while rows.len() < self.kline_limit {
+            let page_size = (self.kline_limit - rows.len()).min(KLINE_PAGE_SIZE);
+            let page = self.fetch_kline_page(endpoint, symbol, page_size, after).await?;
+            if page.is_empty() {
+                if endpoint == "candles" && after.is_some() {
+                    endpoint = "history-candles";
+                    continue;
+                }
+                break;
+            }
+            after = page.iter().map(|k| k.open_time).min();
+            rows.extend(page);
+        }
+
+        if rows.is_empty() {
+            return Err(anyhow!("No klines returned for {}", symbol));
+        }
+        Ok(assemble_klines(rows))
+    }
+
+    /// 单页 K 线 (OKX 按时间倒序返回)，after 为毫秒时间戳，只返回早于该时间的数据
+    async fn fetch_kline_page(&self, endpoint: &str, symbol: &str, limit: usize, after: Option<i64>) -> Result<Vec<Kline>> {
+        let url = format!("{}/api/v5/market/{}", self.base_url, endpoint);
+        let mut params = vec![
+            ("instId", symbol.to_string()),
+            ("bar", "1H".to_string()),
+            ("limit", limit.to_string()),
         ];
+        if let Some(ts) = after {
+            params.push(("after", ts.to_string()));
+        }
 
-        let resp: Value = self.client.get(&url)
+        let resp: Value = self.get(&url)
             .query(&params)
             .send()
             .await?
@@ -36,23 +108,50 @@ impl MarketDataFetcher {
 
         let data = resp["data"].as_array().context("No data in OKX response")?;
 
-        let mut klines: Vec<Kline> = data.iter().map(|raw| Kline {
+        Ok(data.iter().map(|raw| Kline {
             open_time: raw[0].as_str().unwrap_or("0").parse::<i64>().unwrap_or(0),
             open: raw[1].as_str().unwrap_or("0").to_string(),
             high: raw[2].as_str().unwrap_or("0").to_string(),
             low: raw[3].as_str().unwrap_or("0").to_string(),
             close: raw[4].as_str().unwrap_or("0").to_string(),
             volume: raw[5].as_str().unwrap_or("0").to_string(),
-        }).collect();
+        }).collect())
+    }
+
+    /// 收盘价序列 (升序)：优先使用本轮快照缓存，没有时拉取 K 线
+    pub async fn closes(&self, symbol: &str) -> Result<Vec<f64>> {
+        if let Some(closes) = self.recent_closes.get(symbol) {
+            return Ok(closes.clone());
+        }
+        let closes: Vec<f64> = self.fetch_klines(symbol).await?.iter().map(|k| k.close_price()).collect();
+        self.recent_closes.insert(symbol.to_string(), closes.clone());
+        Ok(closes)
+    }
 
-        klines.reverse(); 
+    /// 全市场 24h 行情：(instId, 最新价, 24h 成交额 [计价币])
+    /// 合约的 volCcy24h 为币数量，需乘以最新价；现货的 volCcyQuote 直接是计价币成交额
+    pub async fn fetch_tickers(&self, inst_type: &str) -> Result<Vec<(String, f64, f64)>> {
+        let url = format!("{}/api/v5/market/tickers", self.base_url);
+        let resp: Value = self.get(&url)
+            .query(&[("instType", inst_type)])
+            .send()
+            .await?
+            .json()
+            .await?;
 
-        Ok(klines)
+        let data = resp["data"].as_array().context("No data in OKX tickers response")?;
+        let num = |v: &Value| v.as_str().unwrap_or("0").parse::<f64>().unwrap_or(0.0);
+        Ok(data.iter().filter_map(|item| {
+            let inst_id = item["instId"].as_str()?.to_string();
+            let last = num(&item["last"]);
+            let volume = if inst_type == "SPOT" { num(&item["volCcyQuote"]) } else { num(&item["volCcy24h"]) * last };
+            Some((inst_id, last, volume))
+        }).collect())
     }
 
     pub async fn fetch_funding_rate(&self, symbol: &str) -> Result<f64> {
         let url = format!("{}/api/v5/public/funding-rate", self.base_url);
-        let resp: Value = self.client.get(&url)
+        let resp: Value = self.get(&url)
             .query(&[("instId", symbol)])
             .send()
             .await?
@@ -68,7 +167,7 @@ impl MarketDataFetcher {
 
     pub async fn fetch_open_interest(&self, symbol: &str) -> Result<f64> {
         let url = format!("{}/api/v5/public/open-interest", self.base_url);
-        let resp: Value = self.client.get(&url)
+        let resp: Value = self.get(&url)
             .query(&[("instId", symbol)])
             .send()
             .await?
@@ -82,9 +181,91 @@ impl MarketDataFetcher {
         Ok(oi)
     }
 
+    /// 获取 1H 粒度的 OI 历史 (最新在前，单位: 张)
+    pub async fn fetch_open_interest_history(&self, symbol: &str) -> Result<Vec<f64>> {
+        let url = format!("{}/api/v5/rubik/stat/contracts/open-interest-history", self.base_url);
+        let resp: Value = self.get(&url)
+            .query(&[("instId", symbol), ("period", "1H"), ("limit", "24")])
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        let data = resp["data"].as_array().context("No OI history in OKX response")?;
+        // 每行格式: [ts, oi, oiCcy, oiUsd]
+        let history: Vec<f64> = data.iter()
+            .filter_map(|row| row[1].as_str().and_then(|v| v.parse::<f64>().ok()))
+            .collect();
+        Ok(history)
+    }
+
+    /// 计算最近 1H 的 OI 变化率；历史接口失败时退化为与上一次读数比较
+    async fn open_interest_change(&self, symbol: &str, current_oi: f64) -> Option<f64> {
+        let change = match self.fetch_open_interest_history(symbol).await {
+            Ok(h) if h.len() >= 2 && h[1] > 0.0 => Some((h[0] - h[1]) / h[1]),
+            res => {
+                if let Err(e) = res {
+                    warn!("OI history unavailable for {}: {}. Falling back to cached reading.", symbol, e);
+                }
+                self.last_oi.get(symbol)
+                    .map(|prev| *prev)
+                    .filter(|prev| *prev > 0.0 && current_oi > 0.0)
+                    .map(|prev| (current_oi - prev) / prev)
+            }
+        };
+        if current_oi > 0.0 {
+            self.last_oi.insert(symbol.to_string(), current_oi);
+        }
+        change
+    }
+
+    /// 历史时刻的行情快照 (backfill 回放用)：只用 ts_ms 之前已收盘的 K 线计算指标
+    /// 资金费与 OI 无历史数据，记为 0
+    pub async fn snapshot_at(&self, symbol: &str, ts_ms: i64) -> Result<MarketState> {
+        let mut rows: Vec<Kline> = Vec::with_capacity(self.kline_limit);
+        // 对齐到所在小时的开盘时间，只取之前的完整 K 线
+        let mut after = Some(ts_ms - ts_ms.rem_euclid(KLINE_BAR_MS));
+        while rows.len() < self.kline_limit {
+            let page_size = (self.kline_limit - rows.len()).min(KLINE_PAGE_SIZE);
+            let page = self.fetch_kline_page("history-candles", symbol, page_size, after).await?;
+            if page.is_empty() {
+                break;
+            }
+            after = page.iter().map(|k| k.open_time).min();
+            rows.extend(page);
+        }
+        if rows.is_empty() {
+            return Err(anyhow!("No historical klines for {} before {}", symbol, ts_ms));
+        }
+        let klines = assemble_klines(rows);
+
+        let last_kline = klines.last().context("No klines fetched")?;
+        let price = last_kline.close_price();
+        let indicators = TechnicalAnalysis::analyze(&klines, self.ichimoku_periods);
+        let regime = regime::label(&self.regime, &indicators, price);
+
+        Ok(MarketState {
+            timestamp: ts_ms / 1000,
+            symbol: symbol.to_string(),
+            price,
+            price_time_ms: last_kline.open_time + KLINE_BAR_MS,
+            indicators,
+            funding_rate: 0.0,
+            funding_annualized: 0.0,
+            funding_interval_hours: self.funding_interval_hours,
+            open_interest: 0.0,
+            oi_change_pct: 0.0,
+            oi_signal: "Unknown (historical replay)".to_string(),
+            reddit_sentiment: "N/A (historical replay)".to_string(),
+            news_sentiment: "N/A (historical replay)".to_string(),
+            regime,
+        })
+    }
+
     pub async fn snapshot(&self, symbol: &str, reddit_sentiment: String, news_sentiment: String) -> Result<MarketState> {
         // [核心修复] 使用 tokio::join! 并行请求，而不是 try_join!
         // 这样即使资金费率或OI获取失败，只要K线还在，我们就能继续交易，不至于全盘崩溃
+        let fetched_at_ms = Utc::now().timestamp_millis();
         let (klines_res, funding_res, oi_res) = tokio::join!(
             self.fetch_klines(symbol),
             self.fetch_funding_rate(symbol),
@@ -95,21 +276,167 @@ impl MarketDataFetcher {
         let klines = klines_res?;
         
         // 次要数据如果失败，降级为默认值 0.0，不阻断流程
-        let funding_rate = funding_res.unwrap_or_else(|_| 0.0);
-        let open_interest = oi_res.unwrap_or_else(|_| 0.0);
+        let funding_rate = funding_res.unwrap_or(0.0);
+        let open_interest = oi_res.unwrap_or(0.0);
+
+        let last_kline = klines.last().context("No klines fetched")?;
+        let current_price = last_kline.close_price();
+        // 未收盘的 K 线收盘价即拉取时的最新价；已收盘 (K 线断更) 时价格停留在收盘时刻
+        let price_time_ms = (last_kline.open_time + KLINE_BAR_MS).min(fetched_at_ms);
+        let indicators = TechnicalAnalysis::analyze(&klines, self.ichimoku_periods);
+        self.recent_closes.insert(symbol.to_string(), klines.iter().map(|k| k.close_price()).collect());
+
+        // 价格与 OI 同周期 (1H) 对比，区分新资金入场与空头回补
+        let price_change_pct = if klines.len() >= 2 {
+            let prev_close = klines[klines.len() - 2].close_price();
+            if prev_close > 0.0 { (current_price - prev_close) / prev_close } else { 0.0 }
+        } else { 0.0 };
+        let oi_change_pct = self.open_interest_change(symbol, open_interest).await;
+        let oi_signal = match oi_change_pct {
+            Some(oi_chg) => TechnicalAnalysis::classify_oi_flow(price_change_pct, oi_chg).to_string(),
+            None => "Unknown (no OI history)".to_string(),
+        };
 
-        let current_price = klines.last().context("No klines fetched")?.close_price();
-        let indicators = TechnicalAnalysis::analyze(&klines);
+        let regime = regime::label(&self.regime, &indicators, current_price);
 
         Ok(MarketState {
             timestamp: Utc::now().timestamp(),
             symbol: symbol.to_string(),
             price: current_price,
+            price_time_ms,
             indicators,
             funding_rate,
+            funding_annualized: TechnicalAnalysis::annualize_funding(funding_rate, self.funding_interval_hours),
+            funding_interval_hours: self.funding_interval_hours,
             open_interest,
+            oi_change_pct: oi_change_pct.unwrap_or(0.0),
+            oi_signal,
             reddit_sentiment,
             news_sentiment,
+            regime,
         })
     }
-}
\ No newline at end of file
+}
+/// 行情抽象：主循环与自动选币只依赖此 trait，便于用固定行情离线测试
+#[async_trait]
+pub trait MarketData: Send + Sync {
+    /// 单币种行情快照 (舆情文本由调用方传入)
+    async fn snapshot(&self, symbol: &str, reddit_sentiment: String, news_sentiment: String) -> Result<MarketState>;
+
+    /// 收盘价序列 (升序)
+    async fn closes(&self, symbol: &str) -> Result<Vec<f64>>;
+
+    /// 全市场 24h 行情：(instId, 最新价, 24h 成交额 [计价币])
+    async fn fetch_tickers(&self, inst_type: &str) -> Result<Vec<(String, f64, f64)>>;
+
+    /// 1H K 线，按时间升序
+    async fn fetch_klines(&self, symbol: &str) -> Result<Vec<Kline>>;
+}
+
+#[async_trait]
+impl MarketData for MarketDataFetcher {
+    async fn snapshot(&self, symbol: &str, reddit_sentiment: String, news_sentiment: String) -> Result<MarketState> {
+        MarketDataFetcher::snapshot(self, symbol, reddit_sentiment, news_sentiment).await
+    }
+
+    async fn closes(&self, symbol: &str) -> Result<Vec<f64>> {
+        MarketDataFetcher::closes(self, symbol).await
+    }
+
+    async fn fetch_tickers(&self, inst_type: &str) -> Result<Vec<(String, f64, f64)>> {
+        MarketDataFetcher::fetch_tickers(self, inst_type).await
+    }
+
+    async fn fetch_klines(&self, symbol: &str) -> Result<Vec<Kline>> {
+        MarketDataFetcher::fetch_klines(self, symbol).await
+    }
+}
+
+/// 测试用行情：每个币种返回预设的快照，价格时间为调用时刻 (不会被判定为过期)
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use std::collections::HashMap;
+    use crate::modules::perception::structs::Indicators;
+
+    #[derive(Default)]
+    pub struct StaticMarket {
+        pub states: HashMap<String, MarketState>,
+    }
+
+    impl StaticMarket {
+        pub fn with_state(mut self, state: MarketState) -> Self {
+            self.states.insert(state.symbol.clone(), state);
+            self
+        }
+    }
+
+    /// 多头趋势中的一个普通快照
+    pub fn market_state(symbol: &str, price: f64) -> MarketState {
+        MarketState {
+            timestamp: 0,
+            symbol: symbol.to_string(),
+            price,
+            price_time_ms: 0,
+            indicators: Indicators {
+                rsi_14: 55.0, atr_14: price * 0.01, ema_20: price * 0.99, ema_50: price * 0.97, vwap: price * 0.995,
+                obv_trend: "rising".to_string(), psar: price * 0.98, tenkan: 0.0, kijun: 0.0, senkou_a: 0.0, senkou_b: 0.0,
+                cloud_position: "n/a".to_string(), adx_14: 28.0, chop_14: 40.0, bb_bandwidth: 0.05, trend_signal: "Bullish".to_string(),
+            },
+            funding_rate: 0.0001,
+            funding_annualized: 0.1095,
+            funding_interval_hours: 8.0,
+            open_interest: 0.0,
+            oi_change_pct: 0.0,
+            oi_signal: String::new(),
+            reddit_sentiment: String::new(),
+            news_sentiment: String::new(),
+            regime: "Trending (Bullish)".to_string(),
+        }
+    }
+
+    #[async_trait]
+    impl MarketData for StaticMarket {
+        async fn snapshot(&self, symbol: &str, reddit_sentiment: String, news_sentiment: String) -> Result<MarketState> {
+            let state = self.states.get(symbol).ok_or_else(|| anyhow!("no mock market state for {}", symbol))?;
+            Ok(MarketState { price_time_ms: Utc::now().timestamp_millis(), reddit_sentiment, news_sentiment, ..state.clone() })
+        }
+
+        async fn closes(&self, symbol: &str) -> Result<Vec<f64>> {
+            Err(anyhow!("no mock klines for {}", symbol))
+        }
+
+        async fn fetch_tickers(&self, _inst_type: &str) -> Result<Vec<(String, f64, f64)>> {
+            Ok(self.states.values().map(|s| (s.symbol.clone(), s.price, 0.0)).collect())
+        }
+
+        async fn fetch_klines(&self, symbol: &str) -> Result<Vec<Kline>> {
+            Err(anyhow!("no mock klines for {}", symbol))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kline(open_time: i64) -> Kline {
+        Kline {
+            open_time,
+            open: "1".to_string(),
+            high: "1".to_string(),
+            low: "1".to_string(),
+            close: open_time.to_string(),
+            volume: "1".to_string(),
+        }
+    }
+
+    #[test]
+    fn pages_are_merged_oldest_first_without_duplicates() {
+        // 第一页 (最新，倒序) + 第二页 (更早，倒序)，边界 K 线重复出现
+        let rows: Vec<Kline> = [5, 4, 3, 3, 2, 1].into_iter().map(kline).collect();
+        let merged = assemble_klines(rows);
+        let times: Vec<i64> = merged.iter().map(|k| k.open_time).collect();
+        assert_eq!(times, vec![1, 2, 3, 4, 5]);
+    }
+}
@@ -2,14 +2,59 @@ use super::structs::{Indicators, Kline};
 
 pub struct TechnicalAnalysis;
 
+/// 一目均衡表周期：转换线 / 基准线 / 先行带 B (位移取基准线周期)
+#[derive(Debug, Clone, Copy)]
+pub struct IchimokuPeriods {
+    pub tenkan: usize,
+    pub kijun: usize,
+    pub senkou_b: usize,
+}
+
+impl Default for IchimokuPeriods {
+    fn default() -> Self {
+        Self { tenkan: 9, kijun: 26, senkou_b: 52 }
+    }
+}
+
+/// 一目均衡表在最新一根 K 线上的取值 (先行带为 kijun 根之前计算、位移到当前的云层)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ichimoku {
+    pub tenkan: f64,
+    pub kijun: f64,
+    pub senkou_a: f64,
+    pub senkou_b: f64,
+}
+
+impl Ichimoku {
+    /// 价格相对云层的位置
+    pub fn cloud_position(&self, price: f64) -> &'static str {
+        let top = self.senkou_a.max(self.senkou_b);
+        let bottom = self.senkou_a.min(self.senkou_b);
+        if price > top { "Above Cloud" }
+        else if price < bottom { "Below Cloud" }
+        else { "Inside Cloud" }
+    }
+}
+
 impl TechnicalAnalysis {
-    pub fn analyze(klines: &[Kline]) -> Indicators {
+    pub fn analyze(klines: &[Kline], ichimoku_periods: IchimokuPeriods) -> Indicators {
         let closes: Vec<f64> = klines.iter().map(|k| k.close_price()).collect();
         
         let rsi = Self::calculate_rsi(&closes, 14);
         let atr = Self::calculate_atr(klines, 14);
         let ema_20 = Self::calculate_ema(&closes, 20);
         let ema_50 = Self::calculate_ema(&closes, 50);
+        let vwap = Self::calculate_vwap(klines);
+        let obv_trend = Self::obv_trend(klines, 20).to_string();
+        let psar = Self::calculate_parabolic_sar(klines, 0.02, 0.2).last().copied().unwrap_or(0.0);
+        let ichimoku = Self::calculate_ichimoku(klines, ichimoku_periods);
+        let adx = Self::calculate_adx(klines, 14);
+        let chop = Self::calculate_choppiness(klines, 14);
+        let bb_bandwidth = Self::bollinger_bandwidth(&closes, 20, 2.0);
+        let cloud_position = match (&ichimoku, closes.last()) {
+            (Some(ich), Some(price)) => ich.cloud_position(*price).to_string(),
+            _ => "n/a".to_string(),
+        };
 
         let trend = if ema_20 > ema_50 {
             "Bullish".to_string()
@@ -24,10 +69,42 @@ impl TechnicalAnalysis {
             atr_14: atr,
             ema_20,
             ema_50,
+            vwap,
+            obv_trend,
+            psar,
+            tenkan: ichimoku.as_ref().map_or(0.0, |i| i.tenkan),
+            kijun: ichimoku.as_ref().map_or(0.0, |i| i.kijun),
+            senkou_a: ichimoku.as_ref().map_or(0.0, |i| i.senkou_a),
+            senkou_b: ichimoku.as_ref().map_or(0.0, |i| i.senkou_b),
+            cloud_position,
+            adx_14: adx,
+            chop_14: chop,
+            bb_bandwidth,
             trend_signal: trend,
         }
     }
 
+    /// 价格与 OI 联动分类
+    /// 价涨+OI涨: 新多入场；价涨+OI跌: 空头回补；价跌+OI涨: 新空入场；价跌+OI跌: 多头平仓
+    pub fn classify_oi_flow(price_change_pct: f64, oi_change_pct: f64) -> &'static str {
+        const FLAT: f64 = 0.001; // 0.1% 以内视为无明显变化
+        if oi_change_pct.abs() < FLAT || price_change_pct.abs() < FLAT {
+            return "Flat (no clear flow)";
+        }
+        match (price_change_pct > 0.0, oi_change_pct > 0.0) {
+            (true, true) => "New Money Long (price up + OI up)",
+            (true, false) => "Short Covering (price up + OI down)",
+            (false, true) => "New Money Short (price down + OI up)",
+            (false, false) => "Long Liquidation (price down + OI down)",
+        }
+    }
+
+    /// 单期资金费率折算为年化 (rate × 每日结算次数 × 365)
+    pub fn annualize_funding(rate: f64, interval_hours: f64) -> f64 {
+        if interval_hours <= 0.0 { return 0.0; }
+        rate * (24.0 / interval_hours) * 365.0
+    }
+
     /// 标准 RSI 计算 (Wilder's Smoothing)
     fn calculate_rsi(prices: &[f64], period: usize) -> f64 {
         if prices.len() < period + 1 { return 50.0; }
@@ -56,22 +133,228 @@ impl TechnicalAnalysis {
         100.0 - (100.0 / (1.0 + rs))
     }
 
-    fn calculate_atr(klines: &[Kline], period: usize) -> f64 {
-        if klines.len() < period + 1 { return 0.0; }
+    /// ATR (Wilder's Smoothing)
+    /// [Fix] 旧实现只对最早的 period 根 K 线求均值，完全忽略最近的波动
+    pub fn calculate_atr(klines: &[Kline], period: usize) -> f64 {
+        if period == 0 || klines.len() < period + 1 { return 0.0; }
         
-        let mut tr_sum = 0.0;
-        for i in 1..=period {
+        let true_ranges: Vec<f64> = (1..klines.len()).map(|i| {
             let high = klines[i].high_price();
             let low = klines[i].low_price();
             let prev_close = klines[i-1].close_price();
             
-            let tr = (high - low)
+            (high - low)
                 .max((high - prev_close).abs())
-                .max((low - prev_close).abs());
-            tr_sum += tr;
-        }
+                .max((low - prev_close).abs())
+        }).collect();
         
-        tr_sum / period as f64
+        // 前 period 个 TR 的均值作为种子，之后逐根平滑
+        let mut atr = true_ranges[..period].iter().sum::<f64>() / period as f64;
+        for tr in &true_ranges[period..] {
+            atr = (atr * (period as f64 - 1.0) + tr) / period as f64;
+        }
+        atr
+    }
+
+    /// 滚动 VWAP = Σ(典型价 × 成交量) / Σ成交量，典型价 = (H + L + C) / 3
+    /// 覆盖传入的全部 K 线 (1H 数据，非按日重置的日内 VWAP)；无成交量时返回 0
+    fn calculate_vwap(klines: &[Kline]) -> f64 {
+        let (pv, vol) = klines.iter().fold((0.0, 0.0), |(pv, vol), k| {
+            let typical = (k.high_price() + k.low_price() + k.close_price()) / 3.0;
+            let v = k.volume_value();
+            (pv + typical * v, vol + v)
+        });
+        if vol > 0.0 { pv / vol } else { 0.0 }
+    }
+
+    /// 窗口 [end - period, end) 内 (最高价 + 最低价) / 2
+    fn midpoint(klines: &[Kline], end: usize, period: usize) -> f64 {
+        let window = &klines[end - period..end];
+        let high = window.iter().map(|k| k.high_price()).fold(f64::MIN, f64::max);
+        let low = window.iter().map(|k| k.low_price()).fold(f64::MAX, f64::min);
+        (high + low) / 2.0
+    }
+
+    /// 一目均衡表：转换线/基准线取最新值；当前云层由 kijun 根之前的先行带 A/B 位移而来
+    /// 需要至少 senkou_b + kijun 根 K 线，不足时返回 None
+    pub fn calculate_ichimoku(klines: &[Kline], periods: IchimokuPeriods) -> Option<Ichimoku> {
+        let IchimokuPeriods { tenkan, kijun, senkou_b } = periods;
+        let n = klines.len();
+        let displacement = kijun;
+        if tenkan == 0 || kijun == 0 || senkou_b == 0 || n < senkou_b.max(tenkan).max(kijun) + displacement {
+            return None;
+        }
+
+        // 位移前 (displacement 根之前) 的 K 线末端
+        let shifted_end = n - displacement;
+        let span_a = (Self::midpoint(klines, shifted_end, tenkan) + Self::midpoint(klines, shifted_end, kijun)) / 2.0;
+        let span_b = Self::midpoint(klines, shifted_end, senkou_b);
+
+        Some(Ichimoku {
+            tenkan: Self::midpoint(klines, n, tenkan),
+            kijun: Self::midpoint(klines, n, kijun),
+            senkou_a: span_a,
+            senkou_b: span_b,
+        })
+    }
+
+    /// 累积 OBV 序列：收涨加成交量，收跌减成交量，平收不变 (首根为 0)
+    pub fn calculate_obv(klines: &[Kline]) -> Vec<f64> {
+        let mut obv = Vec::with_capacity(klines.len());
+        let mut running = 0.0;
+        for (i, k) in klines.iter().enumerate() {
+            if i > 0 {
+                let (close, prev_close) = (k.close_price(), klines[i - 1].close_price());
+                if close > prev_close { running += k.volume_value(); }
+                else if close < prev_close { running -= k.volume_value(); }
+            }
+            obv.push(running);
+        }
+        obv
+    }
+
+    /// 最近 window 根 OBV 的最小二乘斜率，按窗口平均成交量归一化
+    /// 归一化斜率 > 0.1 为 rising，< -0.1 为 falling，否则 flat
+    pub fn obv_trend(klines: &[Kline], window: usize) -> &'static str {
+        let obv = Self::calculate_obv(klines);
+        if window < 2 || obv.len() < window { return "flat"; }
+
+        let recent = &obv[obv.len() - window..];
+        let avg_volume = klines[klines.len() - window..].iter().map(|k| k.volume_value()).sum::<f64>() / window as f64;
+        if avg_volume <= 0.0 { return "flat"; }
+
+        let n = window as f64;
+        let x_mean = (n - 1.0) / 2.0;
+        let y_mean = recent.iter().sum::<f64>() / n;
+        let (num, den) = recent.iter().enumerate().fold((0.0, 0.0), |(num, den), (i, y)| {
+            let dx = i as f64 - x_mean;
+            (num + dx * (y - y_mean), den + dx * dx)
+        });
+        let slope = num / den / avg_volume;
+
+        if slope > 0.1 { "rising" } else if slope < -0.1 { "falling" } else { "flat" }
+    }
+
+    /// Wilder 抛物线 SAR 序列 (与 K 线一一对应，首根为种子值)
+    /// 以前两根收盘价确定初始方向；加速因子从 step 起，每创新极值增加 step，上限 max_af；
+    /// 价格穿越 SAR 时反转，新 SAR 取上一段的极值点。上升段 SAR 不高于前两根最低价，下降段不低于前两根最高价
+    pub fn calculate_parabolic_sar(klines: &[Kline], step: f64, max_af: f64) -> Vec<f64> {
+        if klines.len() < 2 { return Vec::new(); }
+        let highs: Vec<f64> = klines.iter().map(|k| k.high_price()).collect();
+        let lows: Vec<f64> = klines.iter().map(|k| k.low_price()).collect();
+
+        let mut is_long = klines[1].close_price() >= klines[0].close_price();
+        let mut sar = if is_long { lows[0] } else { highs[0] };
+        let mut ep = if is_long { highs[0] } else { lows[0] };
+        let mut af = step;
+
+        let mut series = Vec::with_capacity(klines.len());
+        series.push(sar);
+        for i in 1..klines.len() {
+            if i > 1 {
+                sar += af * (ep - sar);
+                let prev = i - 2..i;
+                sar = if is_long {
+                    lows[prev].iter().fold(sar, |acc, l| acc.min(*l))
+                } else {
+                    highs[prev].iter().fold(sar, |acc, h| acc.max(*h))
+                };
+            }
+
+            if is_long && lows[i] < sar {
+                is_long = false;
+                sar = ep;
+                ep = lows[i];
+                af = step;
+            } else if !is_long && highs[i] > sar {
+                is_long = true;
+                sar = ep;
+                ep = highs[i];
+                af = step;
+            } else if is_long && highs[i] > ep {
+                ep = highs[i];
+                af = (af + step).min(max_af);
+            } else if !is_long && lows[i] < ep {
+                ep = lows[i];
+                af = (af + step).min(max_af);
+            }
+            series.push(sar);
+        }
+        series
+    }
+
+    /// ADX (Wilder's Smoothing)：趋势强度 0-100，不区分方向；K 线不足 2 × period + 1 根时返回 0
+    pub fn calculate_adx(klines: &[Kline], period: usize) -> f64 {
+        if period == 0 || klines.len() < 2 * period + 1 { return 0.0; }
+
+        let mut tr = Vec::with_capacity(klines.len() - 1);
+        let mut plus_dm = Vec::with_capacity(klines.len() - 1);
+        let mut minus_dm = Vec::with_capacity(klines.len() - 1);
+        for i in 1..klines.len() {
+            let (high, low, prev_close) = (klines[i].high_price(), klines[i].low_price(), klines[i - 1].close_price());
+            let up = high - klines[i - 1].high_price();
+            let down = klines[i - 1].low_price() - low;
+            plus_dm.push(if up > down && up > 0.0 { up } else { 0.0 });
+            minus_dm.push(if down > up && down > 0.0 { down } else { 0.0 });
+            tr.push((high - low).max((high - prev_close).abs()).max((low - prev_close).abs()));
+        }
+
+        let p = period as f64;
+        let (mut s_tr, mut s_plus, mut s_minus) = (
+            tr[..period].iter().sum::<f64>(),
+            plus_dm[..period].iter().sum::<f64>(),
+            minus_dm[..period].iter().sum::<f64>(),
+        );
+        let dx = |s_tr: f64, s_plus: f64, s_minus: f64| {
+            if s_tr <= 0.0 { return 0.0; }
+            let (di_plus, di_minus) = (s_plus / s_tr * 100.0, s_minus / s_tr * 100.0);
+            if di_plus + di_minus <= 0.0 { 0.0 } else { (di_plus - di_minus).abs() / (di_plus + di_minus) * 100.0 }
+        };
+
+        let mut dxs = vec![dx(s_tr, s_plus, s_minus)];
+        for i in period..tr.len() {
+            s_tr = s_tr - s_tr / p + tr[i];
+            s_plus = s_plus - s_plus / p + plus_dm[i];
+            s_minus = s_minus - s_minus / p + minus_dm[i];
+            dxs.push(dx(s_tr, s_plus, s_minus));
+        }
+
+        // 前 period 个 DX 的均值作为种子，之后逐根平滑
+        let mut adx = dxs[..period].iter().sum::<f64>() / p;
+        for d in &dxs[period..] {
+            adx = (adx * (p - 1.0) + d) / p;
+        }
+        adx
+    }
+
+    /// Choppiness Index：100 × log10(Σ TR / (最高价 - 最低价)) / log10(period)，取最近 period 根
+    /// 接近 100 为横盘震荡，接近 0 为单边趋势 (常用阈值 61.8 / 38.2)；K 线不足 period + 1 根或区间为 0 时返回 0
+    pub fn calculate_choppiness(klines: &[Kline], period: usize) -> f64 {
+        if period < 2 || klines.len() < period + 1 { return 0.0; }
+
+        let start = klines.len() - period;
+        let mut tr_sum = 0.0;
+        let (mut highest, mut lowest) = (f64::MIN, f64::MAX);
+        for i in start..klines.len() {
+            let (high, low, prev_close) = (klines[i].high_price(), klines[i].low_price(), klines[i - 1].close_price());
+            tr_sum += (high - low).max((high - prev_close).abs()).max((low - prev_close).abs());
+            highest = highest.max(high);
+            lowest = lowest.min(low);
+        }
+
+        let range = highest - lowest;
+        if range <= 0.0 || tr_sum <= 0.0 { return 0.0; }
+        (100.0 * (tr_sum / range).log10() / (period as f64).log10()).clamp(0.0, 100.0)
+    }
+
+    /// 布林带宽度 (上轨 - 下轨) / 中轨，中轨为最近 period 根收盘价的均值，标准差取总体标准差
+    pub fn bollinger_bandwidth(closes: &[f64], period: usize, k: f64) -> f64 {
+        if period == 0 || closes.len() < period { return 0.0; }
+        let window = &closes[closes.len() - period..];
+        let mean = window.iter().sum::<f64>() / period as f64;
+        if mean <= 0.0 { return 0.0; }
+        let std_dev = (window.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / period as f64).sqrt();
+        2.0 * k * std_dev / mean
     }
 
     // [核心修复] 使用 SMA 初始化 EMA，防止早期数据失真
@@ -90,4 +373,277 @@ impl TechnicalAnalysis {
         }
         ema
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 由 (open, high, low, close, volume) 元组构造 K 线
+    fn klines(data: &[(f64, f64, f64, f64, f64)]) -> Vec<Kline> {
+        data.iter().enumerate().map(|(i, (o, h, l, c, v))| Kline {
+            open_time: i as i64 * 3_600_000,
+            open: o.to_string(),
+            high: h.to_string(),
+            low: l.to_string(),
+            close: c.to_string(),
+            volume: v.to_string(),
+        }).collect()
+    }
+
+    /// 以收盘价序列构造 K 线，高低点为收盘价 ± half_range
+    fn klines_from_closes(closes: &[f64], half_range: f64) -> Vec<Kline> {
+        let data: Vec<_> = closes.iter().map(|c| (*c, c + half_range, c - half_range, *c, 100.0)).collect();
+        klines(&data)
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn rsi_all_gains_is_100() {
+        let prices: Vec<f64> = (1..=30).map(|x| x as f64).collect();
+        assert_close(TechnicalAnalysis::calculate_rsi(&prices, 14), 100.0);
+    }
+
+    #[test]
+    fn rsi_all_losses_is_0() {
+        let prices: Vec<f64> = (1..=30).rev().map(|x| x as f64).collect();
+        assert_close(TechnicalAnalysis::calculate_rsi(&prices, 14), 0.0);
+    }
+
+    #[test]
+    fn rsi_insufficient_data_is_neutral() {
+        let prices = [1.0, 2.0, 3.0];
+        assert_close(TechnicalAnalysis::calculate_rsi(&prices, 14), 50.0);
+    }
+
+    #[test]
+    fn rsi_balanced_moves_is_50() {
+        // 涨跌幅度相同且交替出现 => 平均涨幅 == 平均跌幅 (period 为偶数时)
+        let prices: Vec<f64> = (0..=14).map(|i| if i % 2 == 0 { 100.0 } else { 101.0 }).collect();
+        assert_close(TechnicalAnalysis::calculate_rsi(&prices, 14), 50.0);
+    }
+
+    #[test]
+    fn atr_constant_range() {
+        let k = klines_from_closes(&[100.0; 30], 1.0);
+        assert_close(TechnicalAnalysis::calculate_atr(&k, 14), 2.0);
+    }
+
+    #[test]
+    fn atr_insufficient_data_is_zero() {
+        let k = klines_from_closes(&[100.0; 10], 1.0);
+        assert_close(TechnicalAnalysis::calculate_atr(&k, 14), 0.0);
+    }
+
+    #[test]
+    fn atr_uses_gap_against_previous_close() {
+        // 第二根跳空高开: TR = max(h-l, |h-prev_c|, |l-prev_c|) = 110 - 100 = 10
+        let k = klines(&[
+            (100.0, 100.5, 99.5, 100.0, 1.0),
+            (109.0, 110.0, 108.0, 109.0, 1.0),
+        ]);
+        assert_close(TechnicalAnalysis::calculate_atr(&k, 1), 10.0);
+    }
+
+    #[test]
+    fn atr_regression_reflects_recent_volatility() {
+        // 回归测试: 旧实现只看最早的 14 根 K 线 (波幅 1)，会忽略最近 40 根的大波动 (波幅 10)
+        let mut closes = vec![100.0; 15];
+        closes.extend(vec![100.0; 40]);
+        let mut k = klines_from_closes(&closes[..15], 0.5);
+        k.extend(klines_from_closes(&closes[15..], 5.0));
+
+        let atr = TechnicalAnalysis::calculate_atr(&k, 14);
+        assert!(atr > 9.0, "ATR should track recent volatility, got {}", atr);
+    }
+
+    #[test]
+    fn ema_short_series_returns_last_price() {
+        assert_close(TechnicalAnalysis::calculate_ema(&[1.0, 2.0], 3), 2.0);
+        assert_close(TechnicalAnalysis::calculate_ema(&[], 3), 0.0);
+    }
+
+    #[test]
+    fn ema_exact_period_equals_sma_seed() {
+        assert_close(TechnicalAnalysis::calculate_ema(&[1.0, 2.0, 3.0], 3), 2.0);
+    }
+
+    #[test]
+    fn ema_iterates_from_sma_seed() {
+        // seed = (1+2+3)/3 = 2, k = 0.5 => 4*0.5 + 2*0.5 = 3 => 5*0.5 + 3*0.5 = 4
+        assert_close(TechnicalAnalysis::calculate_ema(&[1.0, 2.0, 3.0, 4.0, 5.0], 3), 4.0);
+    }
+
+    #[test]
+    fn analyze_trend_signal() {
+        let rising: Vec<f64> = (1..=100).map(|x| x as f64).collect();
+        assert_eq!(TechnicalAnalysis::analyze(&klines_from_closes(&rising, 0.5), IchimokuPeriods::default()).trend_signal, "Bullish");
+
+        let falling: Vec<f64> = (1..=100).rev().map(|x| x as f64).collect();
+        assert_eq!(TechnicalAnalysis::analyze(&klines_from_closes(&falling, 0.5), IchimokuPeriods::default()).trend_signal, "Bearish");
+    }
+
+    #[test]
+    fn vwap_weights_typical_price_by_volume() {
+        // 典型价: (12+8+10)/3 = 10, (22+18+20)/3 = 20, (32+28+30)/3 = 30
+        // VWAP = (10×100 + 20×200 + 30×700) / 1000 = 26
+        let k = klines(&[
+            (10.0, 12.0, 8.0, 10.0, 100.0),
+            (20.0, 22.0, 18.0, 20.0, 200.0),
+            (30.0, 32.0, 28.0, 30.0, 700.0),
+        ]);
+        assert_close(TechnicalAnalysis::calculate_vwap(&k), 26.0);
+    }
+
+    #[test]
+    fn vwap_ignores_unparseable_volume() {
+        let mut k = klines(&[
+            (10.0, 12.0, 8.0, 10.0, 100.0),
+            (50.0, 52.0, 48.0, 50.0, 100.0),
+        ]);
+        k[1].volume = "n/a".to_string();
+        assert_close(TechnicalAnalysis::calculate_vwap(&k), 10.0);
+    }
+
+    #[test]
+    fn vwap_zero_volume_is_zero() {
+        let k = klines(&[(10.0, 12.0, 8.0, 10.0, 0.0)]);
+        assert_close(TechnicalAnalysis::calculate_vwap(&k), 0.0);
+    }
+
+    #[test]
+    fn obv_accumulates_by_close_direction() {
+        let mut k = klines(&[
+            (10.0, 10.0, 10.0, 10.0, 100.0),
+            (10.0, 11.0, 10.0, 11.0, 200.0), // 涨 +200
+            (11.0, 11.0, 10.0, 10.5, 50.0),  // 跌 -50
+            (10.5, 10.5, 10.5, 10.5, 80.0),  // 平 0
+            (10.5, 12.0, 10.5, 12.0, 30.0),  // 涨 +0 (成交量无效)
+        ]);
+        k[4].volume = "-".to_string();
+        assert_eq!(TechnicalAnalysis::calculate_obv(&k), vec![0.0, 200.0, 150.0, 150.0, 150.0]);
+    }
+
+    #[test]
+    fn obv_trend_directions() {
+        let up: Vec<f64> = (0..25).map(|i| 100.0 + i as f64).collect();
+        let down: Vec<f64> = (0..25).map(|i| 100.0 - i as f64).collect();
+        let chop: Vec<f64> = (0..25).map(|i| if i % 2 == 0 { 100.0 } else { 101.0 }).collect();
+        assert_eq!(TechnicalAnalysis::obv_trend(&klines_from_closes(&up, 1.0), 20), "rising");
+        assert_eq!(TechnicalAnalysis::obv_trend(&klines_from_closes(&down, 1.0), 20), "falling");
+        assert_eq!(TechnicalAnalysis::obv_trend(&klines_from_closes(&chop, 1.0), 20), "flat");
+    }
+
+    #[test]
+    fn obv_trend_insufficient_data_is_flat() {
+        let k = klines_from_closes(&[100.0, 101.0, 102.0], 1.0);
+        assert_eq!(TechnicalAnalysis::obv_trend(&k, 20), "flat");
+    }
+
+    #[test]
+    fn parabolic_sar_matches_reference_sequence() {
+        // 三根上涨 -> 回调 -> 跌破 SAR 反转做空
+        let k = klines(&[
+            (9.5, 10.0, 9.0, 9.5, 1.0),
+            (10.0, 11.0, 10.0, 10.5, 1.0),
+            (11.0, 12.0, 11.0, 11.5, 1.0),
+            (12.0, 13.0, 12.0, 12.5, 1.0),
+            (12.5, 12.5, 10.5, 11.0, 1.0),
+            (11.0, 11.0, 9.0, 9.2, 1.0),
+            (9.2, 10.0, 8.0, 8.5, 1.0),
+        ]);
+        let sar = TechnicalAnalysis::calculate_parabolic_sar(&k, 0.02, 0.2);
+        let expected = [9.0, 9.0, 9.0, 9.18, 9.4856, 13.0, 12.92];
+        assert_eq!(sar.len(), expected.len());
+        for (actual, exp) in sar.iter().zip(expected) {
+            assert!((actual - exp).abs() < 1e-9, "expected {}, got {} in {:?}", exp, actual, sar);
+        }
+    }
+
+    #[test]
+    fn parabolic_sar_acceleration_is_capped() {
+        // 持续创新高：AF 封顶 0.2 后 SAR 仍始终低于最低价
+        let closes: Vec<f64> = (0..40).map(|i| 100.0 + i as f64 * 2.0).collect();
+        let k = klines_from_closes(&closes, 0.5);
+        let sar = TechnicalAnalysis::calculate_parabolic_sar(&k, 0.02, 0.2);
+        for (s, kl) in sar.iter().zip(&k) {
+            assert!(*s <= kl.low_price());
+        }
+        assert!(TechnicalAnalysis::calculate_parabolic_sar(&k[..1], 0.02, 0.2).is_empty());
+    }
+
+    #[test]
+    fn funding_annualizes_by_settlement_interval() {
+        // 0.01%/8h -> 3 次/天 × 365 = 10.95%/年
+        assert_close(TechnicalAnalysis::annualize_funding(0.0001, 8.0), 0.1095);
+        assert_close(TechnicalAnalysis::annualize_funding(-0.0001, 4.0), -0.219);
+        assert_eq!(TechnicalAnalysis::annualize_funding(0.0001, 0.0), 0.0);
+    }
+
+    #[test]
+    fn ichimoku_uses_displaced_spans() {
+        // 收盘价 1..=100 (高低 ±0.5)：最新 9 根中点 = 96，26 根中点 = 87.5
+        // 位移 26 根后的末端为第 74 根：span A = (70 + 61.5) / 2，span B = 52 根中点 48.5
+        let closes: Vec<f64> = (1..=100).map(|x| x as f64).collect();
+        let ich = TechnicalAnalysis::calculate_ichimoku(&klines_from_closes(&closes, 0.5), IchimokuPeriods::default()).unwrap();
+        assert_close(ich.tenkan, 96.0);
+        assert_close(ich.kijun, 87.5);
+        assert_close(ich.senkou_a, 65.75);
+        assert_close(ich.senkou_b, 48.5);
+    }
+
+    #[test]
+    fn ichimoku_cloud_position_follows_trend() {
+        let rising: Vec<f64> = (1..=100).map(|x| x as f64).collect();
+        assert_eq!(TechnicalAnalysis::analyze(&klines_from_closes(&rising, 0.5), IchimokuPeriods::default()).cloud_position, "Above Cloud");
+
+        let falling: Vec<f64> = (1..=100).rev().map(|x| x as f64).collect();
+        assert_eq!(TechnicalAnalysis::analyze(&klines_from_closes(&falling, 0.5), IchimokuPeriods::default()).cloud_position, "Below Cloud");
+
+        // 横盘 (99/100/101/100 循环)：云层收敛于 100，价格落在云层内
+        let flat: Vec<f64> = (0..100).map(|i| [99.0, 100.0, 101.0, 100.0][i % 4]).collect();
+        assert_eq!(TechnicalAnalysis::analyze(&klines_from_closes(&flat, 0.5), IchimokuPeriods::default()).cloud_position, "Inside Cloud");
+
+        // K 线不足 senkou_b + kijun 根时不计算
+        assert!(TechnicalAnalysis::calculate_ichimoku(&klines_from_closes(&rising[..77], 0.5), IchimokuPeriods::default()).is_none());
+        assert_eq!(TechnicalAnalysis::analyze(&klines_from_closes(&rising[..77], 0.5), IchimokuPeriods::default()).cloud_position, "n/a");
+    }
+
+    #[test]
+    fn adx_separates_trend_from_chop() {
+        let rising: Vec<f64> = (1..=60).map(|x| 100.0 + x as f64).collect();
+        let trend_adx = TechnicalAnalysis::calculate_adx(&klines_from_closes(&rising, 0.5), 14);
+        assert!(trend_adx > 90.0, "steady trend should have a high ADX, got {}", trend_adx);
+
+        let flat: Vec<f64> = (0..60).map(|i| [99.0, 100.0, 101.0, 100.0][i % 4]).collect();
+        let chop_adx = TechnicalAnalysis::calculate_adx(&klines_from_closes(&flat, 0.5), 14);
+        assert!(chop_adx < 20.0, "choppy range should have a low ADX, got {}", chop_adx);
+
+        assert_close(TechnicalAnalysis::calculate_adx(&klines_from_closes(&rising[..28], 0.5), 14), 0.0);
+    }
+
+    #[test]
+    fn choppiness_separates_chop_from_trend() {
+        let flat: Vec<f64> = (0..60).map(|i| [99.0, 100.0, 101.0, 100.0][i % 4]).collect();
+        let chop = TechnicalAnalysis::calculate_choppiness(&klines_from_closes(&flat, 0.5), 14);
+        assert!(chop > 61.8, "sideways series should be choppy, got {}", chop);
+
+        let rising: Vec<f64> = (1..=60).map(|x| 100.0 + x as f64).collect();
+        let trend = TechnicalAnalysis::calculate_choppiness(&klines_from_closes(&rising, 0.5), 14);
+        assert!(trend < 38.2, "steady trend should have a low choppiness, got {}", trend);
+
+        assert_close(TechnicalAnalysis::calculate_choppiness(&klines_from_closes(&rising[..14], 0.5), 14), 0.0);
+    }
+
+    #[test]
+    fn bollinger_bandwidth_of_known_window() {
+        // 99/101 交替：均值 100，总体标准差 1 -> 带宽 4 / 100
+        let closes: Vec<f64> = (0..20).map(|i| if i % 2 == 0 { 99.0 } else { 101.0 }).collect();
+        assert_close(TechnicalAnalysis::bollinger_bandwidth(&closes, 20, 2.0), 0.04);
+        assert_close(TechnicalAnalysis::bollinger_bandwidth(&[100.0; 20], 20, 2.0), 0.0);
+        assert_close(TechnicalAnalysis::bollinger_bandwidth(&closes[..10], 20, 2.0), 0.0);
+    }
+}
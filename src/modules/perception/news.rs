@@ -1,20 +1,42 @@
 // 文件名: news.rs
 
 use reqwest::Client;
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::info;
 
 pub struct NewsSentinel {
     client: Client,
+    /// 设置后从本地 RSS 夹具读取，不访问网络
+    fixture_path: Option<String>,
+    /// 最近一次成功抓取的结果 (文本, 抓取时间)，TTL 内直接复用
+    cache: Mutex<Option<(String, Instant)>>,
+    cache_ttl: Duration,
 }
 
 impl NewsSentinel {
-    pub fn new(client: Client) -> Self {
-        Self { client }
+    pub fn new(client: Client, fixture_path: Option<String>, cache_ttl: Duration) -> Self {
+        Self { client, fixture_path, cache: Mutex::new(None), cache_ttl }
     }
 
     /// [修改] 仅负责抓取和清洗标题，不做任何情感判断
     /// 返回格式：纯文本列表
     pub async fn fetch_raw_headlines(&self, _symbol: &str) -> Result<String> {
+        if let Some(path) = &self.fixture_path {
+            let content = tokio::fs::read_to_string(path).await
+                .with_context(|| format!("Failed to read news fixture {}", path))?;
+            return Ok(parse_headlines(&content));
+        }
+
+        let mut cache = self.cache.lock().await;
+        if let Some((text, fetched_at)) = cache.as_ref() {
+            if fetched_at.elapsed() < self.cache_ttl {
+                info!("📰 News cache hit ({}s old)", fetched_at.elapsed().as_secs());
+                return Ok(text.clone());
+            }
+        }
+
         let url = "https://www.coindesk.com/arc/outboundfeeds/rss/";
         
         // 增加重试逻辑
@@ -34,33 +56,54 @@ impl NewsSentinel {
         if content.is_empty() {
             return Ok("No news available (Network Error)".to_string());
         }
-        
-        let mut headlines = Vec::new();
-        let parts: Vec<&str> = content.split("<item>").collect();
-        
-        // 获取前 15 条新闻 (既然上下文够大，就多拿点)
-        for part in parts.iter().skip(1).take(15) {
-            if let Some(start) = part.find("<title>") {
-                if let Some(end) = part.find("</title>") {
-                    let title = &part[start + 7..end];
-                    let clean_title = title.replace("<![CDATA[", "").replace("]]>", "").trim().to_string();
-                    if !clean_title.is_empty() {
-                        headlines.push(clean_title);
-                    }
+
+        // 只缓存成功的抓取结果，网络失败时下一轮重试
+        let headlines = parse_headlines(&content);
+        *cache = Some((headlines.clone(), Instant::now()));
+        Ok(headlines)
+    }
+}
+
+/// 解析 RSS XML 为编号标题列表 (线上与夹具共用，保证输出格式一致)
+fn parse_headlines(content: &str) -> String {
+    let mut headlines = Vec::new();
+    let parts: Vec<&str> = content.split("<item>").collect();
+    
+    // 获取前 15 条新闻 (既然上下文够大，就多拿点)
+    for part in parts.iter().skip(1).take(15) {
+        if let Some(start) = part.find("<title>") {
+            if let Some(end) = part.find("</title>") {
+                let title = &part[start + 7..end];
+                let clean_title = title.replace("<![CDATA[", "").replace("]]>", "").trim().to_string();
+                if !clean_title.is_empty() {
+                    headlines.push(clean_title);
                 }
             }
         }
+    }
 
-        if headlines.is_empty() {
-            return Ok("No news headlines found.".to_string());
-        }
+    if headlines.is_empty() {
+        return "No news headlines found.".to_string();
+    }
 
-        // 格式化为 Markdown 列表供 LLM 阅读
-        let mut output = String::from("Recent Headlines:\n");
-        for (i, h) in headlines.iter().enumerate() {
-            output.push_str(&format!("{}. {}\n", i + 1, h));
-        }
+    // 格式化为 Markdown 列表供 LLM 阅读
+    let mut output = String::from("Recent Headlines:\n");
+    for (i, h) in headlines.iter().enumerate() {
+        output.push_str(&format!("{}. {}\n", i + 1, h));
+    }
 
-        Ok(output)
+    output
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixture_parses_like_live_feed() {
+        let out = parse_headlines(include_str!("../../../fixtures/news_rss.xml"));
+        assert!(out.starts_with("Recent Headlines:\n1. Bitcoin Holds Above $60K"));
+        // channel 自身的 <title> 不计入
+        assert!(!out.contains("CoinDesk:"));
+        assert_eq!(out.lines().count(), 6);
     }
-}
\ No newline at end of file
+}
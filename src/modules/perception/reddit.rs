@@ -2,25 +2,33 @@ use reqwest::Client;
 use anyhow::{Result, Context, anyhow};
 use serde_json::Value;
 use std::env;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::sync::Mutex;
 use std::sync::Arc;
-use tracing::warn;
+use tracing::{info, warn};
 
 pub struct RedditSentinel {
     client: Client,
     client_id: String,
     client_secret: String,
     token_cache: Arc<Mutex<(String, u64)>>, 
+    /// 设置后从本地 hot.json 夹具读取，不访问网络
+    fixture_path: Option<String>,
+    /// 最近一次成功抓取的结果 (文本, 抓取时间)，TTL 内直接复用
+    cache: Mutex<Option<(String, Instant)>>,
+    cache_ttl: Duration,
 }
 
 impl RedditSentinel {
-    pub fn new(client: Client) -> Self {
+    pub fn new(client: Client, fixture_path: Option<String>, cache_ttl: Duration) -> Self {
         Self { 
             client,
             client_id: env::var("REDDIT_CLIENT_ID").unwrap_or_default(),
             client_secret: env::var("REDDIT_CLIENT_SECRET").unwrap_or_default(),
             token_cache: Arc::new(Mutex::new(("".to_string(), 0))),
+            fixture_path,
+            cache: Mutex::new(None),
+            cache_ttl,
         }
     }
 
@@ -89,6 +97,29 @@ impl RedditSentinel {
     }
 
     pub async fn analyze_sentiment(&self) -> Result<String> {
+        if let Some(path) = &self.fixture_path {
+            let raw = tokio::fs::read_to_string(path).await
+                .with_context(|| format!("Failed to read reddit fixture {}", path))?;
+            let json: Value = serde_json::from_str(&raw)
+                .with_context(|| format!("Invalid reddit fixture {}", path))?;
+            return self.parse_json_response(json);
+        }
+
+        let mut cache = self.cache.lock().await;
+        if let Some((text, fetched_at)) = cache.as_ref() {
+            if fetched_at.elapsed() < self.cache_ttl {
+                info!("💬 Reddit cache hit ({}s old)", fetched_at.elapsed().as_secs());
+                return Ok(text.clone());
+            }
+        }
+
+        let text = self.fetch_live().await?;
+        *cache = Some((text.clone(), Instant::now()));
+        Ok(text)
+    }
+
+    /// OAuth 优先，失败走公开 JSON
+    async fn fetch_live(&self) -> Result<String> {
         // 尝试走 OAuth，失败走 Public Fallback
         if !self.client_id.is_empty() {
             match self.get_access_token().await {
@@ -101,15 +132,12 @@ impl RedditSentinel {
                         .send()
                         .await;
                     
-                    match resp {
-                        Ok(r) => {
-                            if r.status().is_success() {
-                                if let Ok(json) = r.json().await {
-                                    return self.parse_json_response(json);
-                                }
+                    if let Ok(r) = resp {
+                        if r.status().is_success() {
+                            if let Ok(json) = r.json().await {
+                                return self.parse_json_response(json);
                             }
-                        },
-                        Err(_) => {} 
+                        }
                     }
                 },
                 Err(e) => warn!("Reddit Key Error: {}. Using fallback...", e),
@@ -0,0 +1,103 @@
+// 文件名: regime.rs
+// 市场状态分类：趋势 / 震荡 / 高波动，让大脑直接拿到明确的状态标签，而不是每次从指标里重新推断
+
+use super::structs::Indicators;
+use crate::config::risk_profile::RegimeConfig;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Regime {
+    Trending,
+    Ranging,
+    HighVolatility,
+}
+
+/// 高波动优先 (ATR% 或布林带宽度任一超限)，其次按 ADX 区分趋势与震荡
+/// atr_pct 口径与 ATR 占价格百分比一致 (1.0 = 1%)
+pub fn classify(cfg: &RegimeConfig, adx: f64, bb_bandwidth: f64, atr_pct: f64) -> Regime {
+    if atr_pct >= cfg.high_vol_atr_pct || bb_bandwidth >= cfg.high_vol_bandwidth {
+        Regime::HighVolatility
+    } else if adx >= cfg.trend_adx {
+        Regime::Trending
+    } else {
+        Regime::Ranging
+    }
+}
+
+/// 注入上下文的标签：趋势市附带 EMA 方向 (ADX 本身不区分方向)
+pub fn label(cfg: &RegimeConfig, indicators: &Indicators, price: f64) -> String {
+    let atr_pct = if price > 0.0 { indicators.atr_14 / price * 100.0 } else { 0.0 };
+    match classify(cfg, indicators.adx_14, indicators.bb_bandwidth, atr_pct) {
+        Regime::Trending => format!("Trending ({})", indicators.trend_signal),
+        Regime::Ranging => "Ranging".to_string(),
+        Regime::HighVolatility => "High Volatility".to_string(),
+    }
+}
+
+/// 强趋势中的逆势开仓过滤 (block_counter_trend)：返回 Err(原因) 表示拒绝
+pub fn check_counter_trend(cfg: &RegimeConfig, adx: f64, trend_signal: &str, pos_side: &str) -> Result<(), String> {
+    if !cfg.block_counter_trend || adx < cfg.counter_trend_adx { return Ok(()); }
+    let against = match trend_signal {
+        "Bullish" => pos_side == "short",
+        "Bearish" => pos_side == "long",
+        _ => false,
+    };
+    if against {
+        return Err(format!("{} entry against a strong {} trend (ADX {:.1} >= {:.1})", pos_side, trend_signal.to_lowercase(), adx, cfg.counter_trend_adx));
+    }
+    Ok(())
+}
+
+/// 横盘震荡中的顺势开仓过滤 (block_choppy_entries)：返回 Err(原因) 表示拒绝
+/// 顺 EMA 趋势方向的开仓属于趋势跟随，震荡中容易被来回扫损；无明确趋势时同样视为追势
+pub fn check_choppiness(cfg: &RegimeConfig, chop: f64, trend_signal: &str, pos_side: &str) -> Result<(), String> {
+    if !cfg.block_choppy_entries || chop < cfg.chop_threshold { return Ok(()); }
+    let with_trend = match trend_signal {
+        "Bullish" => pos_side == "long",
+        "Bearish" => pos_side == "short",
+        _ => true,
+    };
+    if with_trend {
+        return Err(format!("trend-following {} entry in a choppy market (CHOP {:.1} >= {:.1})", pos_side, chop, cfg.chop_threshold));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn volatility_takes_priority_over_trend() {
+        let cfg = RegimeConfig::default(); // ADX 25 / ATR 2% / 带宽 8%
+        assert_eq!(classify(&cfg, 30.0, 0.03, 0.8), Regime::Trending);
+        assert_eq!(classify(&cfg, 15.0, 0.03, 0.8), Regime::Ranging);
+        assert_eq!(classify(&cfg, 30.0, 0.03, 2.5), Regime::HighVolatility);
+        assert_eq!(classify(&cfg, 15.0, 0.09, 0.8), Regime::HighVolatility);
+    }
+
+    #[test]
+    fn counter_trend_entries_blocked_only_when_enabled() {
+        let mut cfg = RegimeConfig::default();
+        assert!(check_counter_trend(&cfg, 50.0, "Bullish", "short").is_ok());
+
+        cfg.block_counter_trend = true;
+        assert!(check_counter_trend(&cfg, 50.0, "Bullish", "short").is_err());
+        assert!(check_counter_trend(&cfg, 50.0, "Bullish", "long").is_ok());
+        assert!(check_counter_trend(&cfg, 50.0, "Bearish", "long").is_err());
+        // 趋势不够强时不限制
+        assert!(check_counter_trend(&cfg, 30.0, "Bullish", "short").is_ok());
+    }
+
+    #[test]
+    fn choppy_markets_block_trend_following_entries() {
+        let mut cfg = RegimeConfig::default();
+        assert!(check_choppiness(&cfg, 70.0, "Bullish", "long").is_ok());
+
+        cfg.block_choppy_entries = true;
+        assert!(check_choppiness(&cfg, 70.0, "Bullish", "long").is_err());
+        assert!(check_choppiness(&cfg, 70.0, "Bearish", "short").is_err());
+        assert!(check_choppiness(&cfg, 70.0, "Bullish", "short").is_ok());
+        // 低于阈值 (趋势市) 时不限制
+        assert!(check_choppiness(&cfg, 45.0, "Bullish", "long").is_ok());
+    }
+}
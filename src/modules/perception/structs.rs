@@ -7,6 +7,28 @@ pub struct Indicators {
     pub atr_14: f64,
     pub ema_20: f64,
     pub ema_50: f64,
+    /// 滚动 VWAP：整个 K 线窗口 (默认 100 根 1H ≈ 4 天) 的成交量加权均价，不按交易时段重置
+    pub vwap: f64,
+    /// OBV 在最近窗口内的斜率方向: rising / falling / flat
+    pub obv_trend: String,
+    /// 当前抛物线 SAR (0.02/0.2)，可作为跟随趋势的移动止损参考
+    pub psar: f64,
+    /// 一目均衡表：转换线 / 基准线 / 当前云层的先行带 A、B (K 线不足时为 0)
+    pub tenkan: f64,
+    pub kijun: f64,
+    pub senkou_a: f64,
+    pub senkou_b: f64,
+    /// 价格相对云层: Above Cloud / Below Cloud / Inside Cloud / n/a
+    pub cloud_position: String,
+    /// ADX(14) 趋势强度 (K 线不足时为 0)
+    #[serde(default)]
+    pub adx_14: f64,
+    /// Choppiness Index(14)：> 61.8 横盘震荡，< 38.2 单边趋势 (K 线不足时为 0)
+    #[serde(default)]
+    pub chop_14: f64,
+    /// 布林带 (20, 2) 宽度 = (上轨 - 下轨) / 中轨
+    #[serde(default)]
+    pub bb_bandwidth: f64,
     pub trend_signal: String, 
 }
 
@@ -15,11 +37,23 @@ pub struct MarketState {
     pub timestamp: i64,
     pub symbol: String,
     pub price: f64,
+    /// REST 价格的观测时间 (毫秒)：最新 K 线收盘时间与拉取时间中较早者，用于判断价格是否过期
+    #[serde(default)]
+    pub price_time_ms: i64,
     pub indicators: Indicators,
     pub funding_rate: f64,
+    /// 年化资金费率 (0.1095 = 10.95%/年)，正值表示多头付费
+    pub funding_annualized: f64,
+    /// 资金费结算周期 (小时)
+    pub funding_interval_hours: f64,
     pub open_interest: f64,
+    pub oi_change_pct: f64, // 最近 1H OI 变化率
+    pub oi_signal: String,  // 价格/OI 联动解读 (新资金入场 / 空头回补 等)
     pub reddit_sentiment: String,
     pub news_sentiment: String,
+    /// 市场状态标签 (见 regime.rs)，如 "Trending (Bullish)" / "Ranging" / "High Volatility"
+    #[serde(default)]
+    pub regime: String,
 }
 
 impl MarketState {
@@ -32,25 +66,44 @@ impl MarketState {
                       else { "Neutral" };
         
         let ema_desc = if self.price > self.indicators.ema_20 { "Above short-term trend" } else { "Below short-term trend" };
+        let vwap_desc = if self.indicators.vwap <= 0.0 { "VWAP unavailable" }
+                       else if self.price > self.indicators.vwap { "Above rolling VWAP" }
+                       else { "Below rolling VWAP" };
+        let sar_desc = if self.indicators.psar <= 0.0 { "SAR unavailable" }
+                      else if self.price > self.indicators.psar { "above SAR (uptrend, SAR = trailing stop for longs)" }
+                      else { "below SAR (downtrend, SAR = trailing stop for shorts)" };
+
+        let ichimoku_desc = if self.indicators.cloud_position == "n/a" { "Ichimoku unavailable".to_string() }
+                           else { format!("Ichimoku: price {} (Tenkan {:.2} / Kijun {:.2})", self.indicators.cloud_position.to_lowercase(), self.indicators.tenkan, self.indicators.kijun) };
 
         let funding_pct = self.funding_rate * 100.0;
         let funding_desc = if funding_pct > 0.01 { "High Positive Funding (Longs paying Shorts)" }
                           else if funding_pct < -0.01 { "High Negative Funding (Shorts paying Longs)" }
                           else { "Neutral Funding" };
+        let funding_ann_pct = self.funding_annualized * 100.0;
 
-        // 3. 组合成自然语言段落
+        // 2. 组合成自然语言段落
+        // 关键数值指标放在最前，舆情放在最后：Embedding 输入超长时从尾部截断，只会丢掉舆情
         format!(
             "Market Context for {}:\n\
-            - Price Action: ${:.2}, Trend is {}. Price is {}.\n\
-            - Momentum: RSI is {:.2} ({}), Volatility (ATR) is {:.2}.\n\
-            - Derivatives: {}, Open Interest is {:.0}.\n\
+            - Key Indicators: Price ${:.2} | RSI {:.2} | ATR {:.2} | EMA20 {:.2} | EMA50 {:.2} | VWAP {:.2} | SAR {:.2} | Cloud {:.2}-{:.2} | Funding {:.4}%/{}h ({:+.1}% APR) | OI {:.0} ({:+.2}% 1H)\n\
+            - Regime: {} (ADX {:.1}, CHOP {:.1}, BB width {:.2}%).\n\
+            - Price Action: Trend is {}. Price is {}, {}, {}. {}.\n\
+            - Momentum: RSI is {}. OBV is {}.\n\
+            - Derivatives: {}, OI flow: {}.\n\
             - Market Sentiment Summary:\n\
             [News Headlines]: {}\n\
             [Social Discussion]: {}",
             self.symbol,
-            self.price, self.indicators.trend_signal, ema_desc,
-            self.indicators.rsi_14, rsi_desc, self.indicators.atr_14,
-            funding_desc, self.open_interest,
+            self.price, self.indicators.rsi_14, self.indicators.atr_14,
+            self.indicators.ema_20, self.indicators.ema_50, self.indicators.vwap, self.indicators.psar,
+            self.indicators.senkou_a.min(self.indicators.senkou_b), self.indicators.senkou_a.max(self.indicators.senkou_b), funding_pct,
+            self.funding_interval_hours, funding_ann_pct,
+            self.open_interest, self.oi_change_pct * 100.0,
+            self.regime, self.indicators.adx_14, self.indicators.chop_14, self.indicators.bb_bandwidth * 100.0,
+            self.indicators.trend_signal, ema_desc, vwap_desc, sar_desc, ichimoku_desc,
+            rsi_desc, self.indicators.obv_trend,
+            funding_desc, self.oi_signal,
             self.news_sentiment.chars().take(2000).collect::<String>(), 
             self.reddit_sentiment.chars().take(2000).collect::<String>()
         )
@@ -78,4 +131,11 @@ impl Kline {
     pub fn low_price(&self) -> f64 {
         self.low.parse().unwrap_or(0.0)
     }
+    /// 成交量解析失败、非有限值或负数时按 0 处理
+    pub fn volume_value(&self) -> f64 {
+        match self.volume.trim().parse::<f64>() {
+            Ok(v) if v.is_finite() && v > 0.0 => v,
+            _ => 0.0,
+        }
+    }
 }
\ No newline at end of file
@@ -5,19 +5,38 @@ impl fmt::Display for MarketState {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let funding_pct = self.funding_rate * 100.0;
         let funding_warning = if funding_pct.abs() > 0.05 { "(HIGH RISK)" } else { "" };
+        // 持仓每跨一个结算点付一次资金费：按天折算，便于判断多日持仓的持有成本
+        let daily_pct = self.funding_annualized / 365.0 * 100.0;
+        let carry = if self.funding_rate > 0.0 {
+            format!("Longs pay {:.3}%/day, shorts receive", daily_pct)
+        } else if self.funding_rate < 0.0 {
+            format!("Shorts pay {:.3}%/day, longs receive", -daily_pct)
+        } else {
+            "No funding carry".to_string()
+        };
+        let vwap_position = if self.indicators.vwap <= 0.0 { "n/a" }
+                           else if self.price > self.indicators.vwap { "price above" }
+                           else { "price below" };
         
         write!(f, 
             "\n--- MARKET SNAPSHOT ---\n\
             [Basic] Symbol: {} | Price: ${:.2}\n\
-            [Technical] Trend: {} | RSI: {:.2} | ATR: {:.2}\n\
-            [Derivatives] Funding: {:.4}% {} | OI: {:.0}\n\
+            [Regime] {} (ADX {:.1} | CHOP {:.1} | BB Width {:.2}%)\n\
+            [Technical] Trend: {} | RSI: {:.2} | ATR: {:.2} | VWAP: {:.2} ({}) | SAR: {:.2} | OBV: {}\n\
+            [Ichimoku] Tenkan: {:.2} | Kijun: {:.2} | Span A: {:.2} | Span B: {:.2} | Price: {}\n\
+            [Derivatives] Funding: {:.4}%/{}h {} | OI: {:.0} ({:+.2}% 1H) | OI Flow: {}\n\
+            [Carry] Annualized Funding: {:+.2}% | {} (settles every {}h)\n\
             [Sentiment Analysis]\n\
             > News: {}\n\n\
             > Reddit: {}\n\
             -----------------------",
             self.symbol, self.price,
+            self.regime, self.indicators.adx_14, self.indicators.chop_14, self.indicators.bb_bandwidth * 100.0,
             self.indicators.trend_signal, self.indicators.rsi_14, self.indicators.atr_14,
-            funding_pct, funding_warning, self.open_interest,
+            self.indicators.vwap, vwap_position, self.indicators.psar, self.indicators.obv_trend,
+            self.indicators.tenkan, self.indicators.kijun, self.indicators.senkou_a, self.indicators.senkou_b, self.indicators.cloud_position,
+            funding_pct, self.funding_interval_hours, funding_warning, self.open_interest, self.oi_change_pct * 100.0, self.oi_signal,
+            self.funding_annualized * 100.0, carry, self.funding_interval_hours,
             self.news_sentiment, self.reddit_sentiment
         )
     }
@@ -1,28 +1,40 @@
 use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
 use futures_util::{StreamExt, SinkExt};
 use url::Url;
-use std::env;
+use crate::config::okx::OkxEndpoints;
 use std::sync::Arc;
 use tracing::{info, error, warn};
 use serde_json::{json, Value};
 use dashmap::DashMap;
 use std::time::Instant;
+use tokio::sync::watch;
 
 pub type PriceCache = Arc<DashMap<String, (f64, Instant)>>;
 
+/// 盘口一档 (来自 tickers 频道的 bidPx/askPx)
+#[derive(Debug, Clone, Copy)]
+pub struct BookTop {
+    pub bid: f64,
+    pub ask: f64,
+    pub ts: Instant,
+}
+
+pub type BookCache = Arc<DashMap<String, BookTop>>;
+
 pub struct OkxWsClient {
     url: String,
     price_cache: PriceCache,
+    book_cache: BookCache,
 }
 
 impl OkxWsClient {
-    pub fn new(price_cache: PriceCache) -> Self {
-        // [修改] 默认地址改为最标准的 ws.okx.com，香港节点连接最稳
-        let url = env::var("OKX_WS_URL").unwrap_or("wss://ws.okx.com:8443/ws/v5/public".to_string());
-        Self { url, price_cache }
+    pub fn new(endpoints: &OkxEndpoints, price_cache: PriceCache, book_cache: BookCache) -> Self {
+        // 未配置 OKX_WS_URL 时按模拟盘标记选择 ws.okx.com / wspap.okx.com (见 OkxEndpoints)
+        Self { url: endpoints.ws_url.clone(), price_cache, book_cache }
     }
 
-    pub async fn run(&self, symbols: Vec<String>) {
+    /// 订阅 symbols 中的币种；交易池变化时断开重连，按新列表重新订阅
+    pub async fn run(&self, mut symbols: watch::Receiver<Vec<String>>) {
         let url = match Url::parse(&self.url) {
             Ok(u) => u,
             Err(e) => {
@@ -38,7 +50,8 @@ impl OkxWsClient {
                     info!("✅ OKX WebSocket Connected.");
                     let (mut write, mut read) = ws_stream.split();
 
-                    let args: Vec<_> = symbols.iter().map(|s| {
+                    let current = symbols.borrow_and_update().clone();
+                    let args: Vec<_> = current.iter().map(|s| {
                         json!({
                             "channel": "tickers",
                             "instId": s
@@ -55,7 +68,19 @@ impl OkxWsClient {
                         continue;
                     }
 
-                    while let Some(msg) = read.next().await {
+                    loop {
+                        let msg = tokio::select! {
+                            msg = read.next() => match msg {
+                                Some(msg) => msg,
+                                None => break,
+                            },
+                            changed = symbols.changed() => {
+                                if changed.is_ok() {
+                                    info!("🔁 Symbol universe changed. Resubscribing WebSocket...");
+                                }
+                                break;
+                            }
+                        };
                         match msg {
                             Ok(Message::Text(text)) => {
                                 if let Ok(parsed) = serde_json::from_str::<Value>(&text) {
@@ -65,6 +90,11 @@ impl OkxWsClient {
                                                 if let Ok(price) = last.parse::<f64>() {
                                                     self.price_cache.insert(inst_id.to_string(), (price, Instant::now()));
                                                 }
+                                                let bid = item["bidPx"].as_str().and_then(|v| v.parse::<f64>().ok());
+                                                let ask = item["askPx"].as_str().and_then(|v| v.parse::<f64>().ok());
+                                                if let (Some(bid), Some(ask)) = (bid, ask) {
+                                                    self.book_cache.insert(inst_id.to_string(), BookTop { bid, ask, ts: Instant::now() });
+                                                }
                                             }
                                         }
                                     }
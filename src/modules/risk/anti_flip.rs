@@ -0,0 +1,73 @@
+use std::time::Duration;
+
+use crate::config::risk_profile::AntiFlipConfig;
+
+/// 判断开仓请求是否构成"反手"：与现有持仓相反，或与该币种最近一次操作方向相反
+pub fn is_flip(pos_side: &str, opposite_pos_open: bool, last_side: Option<&str>) -> bool {
+    opposite_pos_open || last_side.is_some_and(|s| s != pos_side)
+}
+
+/// 距该币种上次操作的时长：优先使用本进程记录的操作时间；
+/// 重启后没有记录时，以反向持仓的开仓时间为准 (否则重启后第一次反手不受冷却限制)
+pub fn since_last_action(last_action: Option<Duration>, opposite_entry: Option<Duration>) -> Option<Duration> {
+    last_action.or(opposite_entry)
+}
+
+/// 反手守卫：冷却窗口内的反手需要更高的胜率门槛，返回 Err(原因) 表示否决
+pub fn check_flip(cfg: &AntiFlipConfig, flip: bool, since_last_action: Option<Duration>, win_rate: f64) -> Result<(), String> {
+    if !cfg.enabled || !flip { return Ok(()); }
+
+    let elapsed = match since_last_action {
+        Some(d) if d < Duration::from_secs(cfg.window_sec) => d,
+        _ => return Ok(()),
+    };
+
+    if win_rate >= cfg.min_win_rate {
+        Ok(())
+    } else {
+        Err(format!(
+            "reversal {}s after last action (window {}s), win rate {:.2} < required {:.2}",
+            elapsed.as_secs(), cfg.window_sec, win_rate, cfg.min_win_rate
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> AntiFlipConfig {
+        AntiFlipConfig { enabled: true, window_sec: 3600, min_win_rate: 0.7 }
+    }
+
+    #[test]
+    fn flip_detection() {
+        assert!(is_flip("long", true, None));
+        assert!(is_flip("long", false, Some("short")));
+        assert!(!is_flip("long", false, Some("long")));
+        assert!(!is_flip("short", false, None));
+    }
+
+    #[test]
+    fn flips_inside_the_window_need_a_higher_win_rate() {
+        let recent = Some(Duration::from_secs(600));
+        assert!(check_flip(&cfg(), true, recent, 0.6).unwrap_err().contains("600s"));
+        assert!(check_flip(&cfg(), true, recent, 0.7).is_ok());
+        // 窗口外、非反手、关闭时不限制
+        assert!(check_flip(&cfg(), true, Some(Duration::from_secs(3600)), 0.5).is_ok());
+        assert!(check_flip(&cfg(), false, recent, 0.5).is_ok());
+        assert!(check_flip(&AntiFlipConfig { enabled: false, ..cfg() }, true, recent, 0.5).is_ok());
+    }
+
+    #[test]
+    fn restart_falls_back_to_the_opposite_entry_time() {
+        let entry = Some(Duration::from_secs(300));
+        // 重启后没有操作记录：按反向持仓的开仓时间判断，仍在窗口内
+        let since = since_last_action(None, entry);
+        assert_eq!(since, entry);
+        assert!(check_flip(&cfg(), is_flip("long", true, None), since, 0.6).is_err());
+        // 本进程的操作记录优先
+        assert_eq!(since_last_action(Some(Duration::from_secs(5000)), entry), Some(Duration::from_secs(5000)));
+        assert_eq!(since_last_action(None, None), None);
+    }
+}
@@ -0,0 +1,124 @@
+use chrono::{DateTime, Datelike, Duration, Utc};
+
+use crate::config::risk_profile::BlackoutConfig;
+
+/// 当前所在的停机窗口 (返回窗口标签)，时间一律按 UTC 比较
+pub fn active_window(cfg: &BlackoutConfig, now: DateTime<Utc>) -> Option<String> {
+    if !cfg.enabled { return None; }
+    let label = |l: &str, fallback: String| if l.is_empty() { fallback } else { l.to_string() };
+
+    if let Some(w) = cfg.windows.iter().find(|w| w.start <= now && now < w.end) {
+        return Some(label(&w.label, format!("{} - {} UTC", w.start.format("%m-%d %H:%M"), w.end.format("%H:%M"))));
+    }
+    for w in &cfg.weekly {
+        let Ok((day, start)) = w.schedule() else { continue };
+        // 最近一次 (不晚于现在) 的开始时间；跨天/跨周的窗口由上一次开始时间覆盖
+        let days_back = (now.weekday().num_days_from_monday() + 7 - day.num_days_from_monday()) % 7;
+        let mut begin = (now.date_naive() - Duration::days(days_back as i64)).and_time(start).and_utc();
+        if begin > now {
+            begin -= Duration::days(7);
+        }
+        if now < begin + Duration::minutes(w.duration_min as i64) {
+            return Some(label(&w.label, format!("weekly {} {} UTC", w.day, w.start)));
+        }
+    }
+    None
+}
+
+/// 收紧后的止损价：止损到现价的距离缩小到 factor 倍；不会放宽止损，止损无效时返回 None
+pub fn tightened_stop(side: &str, sl: f64, mark: f64, factor: f64) -> Option<f64> {
+    if sl <= 0.0 || mark <= 0.0 { return None; }
+    let distance = if side == "short" { sl - mark } else { mark - sl };
+    if distance <= 0.0 { return None; }
+    let new_sl = if side == "short" { mark + distance * factor } else { mark - distance * factor };
+    Some(new_sl)
+}
+
+/// 停机状态切换
+#[derive(Debug, PartialEq, Eq)]
+pub enum BlackoutEvent {
+    /// 刚进入停机窗口，携带窗口标签
+    Entered(String),
+    Exited,
+    Unchanged,
+}
+
+/// 停机状态：每次进入/退出只通知一次
+#[derive(Debug, Default)]
+pub struct BlackoutHold {
+    active: Option<String>,
+}
+
+impl BlackoutHold {
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn update(&mut self, window: Option<String>) -> BlackoutEvent {
+        let event = match (&self.active, &window) {
+            (None, Some(label)) => BlackoutEvent::Entered(label.clone()),
+            (Some(_), None) => BlackoutEvent::Exited,
+            _ => BlackoutEvent::Unchanged,
+        };
+        self.active = window;
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::risk_profile::{BlackoutWindow, WeeklyBlackout};
+
+    fn at(ts: &str) -> DateTime<Utc> {
+        ts.parse().unwrap()
+    }
+
+    #[test]
+    fn explicit_windows_are_compared_in_utc() {
+        let cfg = &BlackoutConfig {
+            windows: vec![BlackoutWindow { start: at("2026-11-12T21:00:00+08:00"), end: at("2026-11-12T22:30:00+08:00"), label: "US CPI".to_string() }],
+            ..BlackoutConfig::default()
+        };
+        // +08:00 的 21:00 即 UTC 13:00
+        assert_eq!(active_window(cfg, at("2026-11-12T13:00:00Z")).as_deref(), Some("US CPI"));
+        assert_eq!(active_window(cfg, at("2026-11-12T14:29:59Z")).as_deref(), Some("US CPI"));
+        assert_eq!(active_window(cfg, at("2026-11-12T14:30:00Z")), None);
+        assert_eq!(active_window(cfg, at("2026-11-12T12:59:00Z")), None);
+    }
+
+    #[test]
+    fn weekly_windows_wrap_across_midnight() {
+        let cfg = &BlackoutConfig {
+            weekly: vec![WeeklyBlackout { day: "Sun".to_string(), start: "23:00".to_string(), duration_min: 120, label: String::new() }],
+            ..BlackoutConfig::default()
+        };
+        // 2026-11-15 是周日，窗口持续到周一 01:00
+        assert!(active_window(cfg, at("2026-11-15T23:30:00Z")).is_some());
+        assert!(active_window(cfg, at("2026-11-16T00:59:00Z")).is_some());
+        assert!(active_window(cfg, at("2026-11-16T01:00:00Z")).is_none());
+        assert!(active_window(cfg, at("2026-11-15T22:59:00Z")).is_none());
+
+        let disabled = BlackoutConfig { enabled: false, ..cfg.clone() };
+        assert!(active_window(&disabled, at("2026-11-15T23:30:00Z")).is_none());
+    }
+
+    #[test]
+    fn stops_are_tightened_toward_price() {
+        assert_eq!(tightened_stop("long", 90.0, 100.0, 0.5), Some(95.0));
+        assert_eq!(tightened_stop("short", 110.0, 100.0, 0.5), Some(105.0));
+        // 止损已越过现价 (即将触发) 时不处理
+        assert_eq!(tightened_stop("long", 101.0, 100.0, 0.5), None);
+        assert_eq!(tightened_stop("long", 0.0, 100.0, 0.5), None);
+    }
+
+    #[test]
+    fn hold_notifies_once_per_transition() {
+        let mut hold = BlackoutHold::default();
+        assert_eq!(hold.update(Some("CPI".into())), BlackoutEvent::Entered("CPI".into()));
+        assert_eq!(hold.update(Some("CPI".into())), BlackoutEvent::Unchanged);
+        assert!(hold.is_active());
+        assert_eq!(hold.update(None), BlackoutEvent::Exited);
+        assert!(!hold.is_active());
+    }
+}
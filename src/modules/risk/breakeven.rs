@@ -0,0 +1,82 @@
+use crate::config::risk_profile::BreakevenConfig;
+
+/// 当前浮盈的 R 倍数 (R = |入场价 - 止损价|)，止损已越过入场价时返回 None
+pub fn unrealized_r(side: &str, entry: f64, sl: f64, mark: f64) -> Option<f64> {
+    let risk = if side == "short" { sl - entry } else { entry - sl };
+    if risk <= 0.0 || mark <= 0.0 { return None; }
+
+    let gain = if side == "short" { entry - mark } else { mark - entry };
+    Some(gain / risk)
+}
+
+/// 保本止损价：入场价向盈利方向偏移 offset_pct (覆盖手续费)
+pub fn breakeven_price(cfg: &BreakevenConfig, side: &str, entry: f64) -> f64 {
+    if side == "short" { entry * (1.0 - cfg.offset_pct) } else { entry * (1.0 + cfg.offset_pct) }
+}
+
+/// 判断是否需要把止损移到保本位，返回新止损价
+/// 止损已在入场价或更优位置时返回 None (说明已移动过)
+pub fn breakeven_stop(cfg: &BreakevenConfig, side: &str, entry: f64, sl: f64, mark: f64) -> Option<f64> {
+    if !cfg.enabled || entry <= 0.0 { return None; }
+
+    let r = unrealized_r(side, entry, sl, mark)?;
+    if r < cfg.trigger_r { return None; }
+
+    let new_sl = breakeven_price(cfg, side, entry);
+
+    // 新止损不能越过当前价，否则会立即触发
+    let valid = if side == "short" { new_sl > mark } else { new_sl < mark };
+    if valid { Some(new_sl) } else { None }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> BreakevenConfig {
+        BreakevenConfig { enabled: true, trigger_r: 1.0, offset_pct: 0.001 }
+    }
+
+    #[test]
+    fn unrealized_r_per_side() {
+        // 多: 入场 100，止损 98 (R = 2)
+        assert_eq!(unrealized_r("long", 100.0, 98.0, 103.0), Some(1.5));
+        assert_eq!(unrealized_r("long", 100.0, 98.0, 99.0), Some(-0.5));
+        // 空: 入场 100，止损 102
+        assert_eq!(unrealized_r("short", 100.0, 102.0, 97.0), Some(1.5));
+        // 止损已越过入场价 (已保本) 或无价格
+        assert_eq!(unrealized_r("long", 100.0, 100.1, 103.0), None);
+        assert_eq!(unrealized_r("short", 100.0, 99.0, 97.0), None);
+        assert_eq!(unrealized_r("long", 100.0, 98.0, 0.0), None);
+    }
+
+    #[test]
+    fn breakeven_price_offsets_toward_profit() {
+        assert!((breakeven_price(&cfg(), "long", 100.0) - 100.1).abs() < 1e-9);
+        assert!((breakeven_price(&cfg(), "short", 100.0) - 99.9).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stop_moves_only_past_trigger() {
+        let c = cfg();
+        // 多: +0.9R 不动，+1R 移到 100.1
+        assert_eq!(breakeven_stop(&c, "long", 100.0, 98.0, 101.8), None);
+        assert!((breakeven_stop(&c, "long", 100.0, 98.0, 102.0).unwrap() - 100.1).abs() < 1e-9);
+        // 空: +1.5R 移到 99.9
+        assert!((breakeven_stop(&c, "short", 100.0, 102.0, 97.0).unwrap() - 99.9).abs() < 1e-9);
+        // 已移动过 (止损在入场价之上) 不再触发
+        assert_eq!(breakeven_stop(&c, "long", 100.0, 100.1, 105.0), None);
+        // 关闭或缺少入场价
+        assert_eq!(breakeven_stop(&BreakevenConfig { enabled: false, ..c.clone() }, "long", 100.0, 98.0, 105.0), None);
+        assert_eq!(breakeven_stop(&c, "long", 0.0, 98.0, 105.0), None);
+    }
+
+    #[test]
+    fn stop_never_crosses_the_mark() {
+        // 止损很近、偏移很大时保本价会越过现价，不能移动 (会立即触发)
+        let c = BreakevenConfig { enabled: true, trigger_r: 1.0, offset_pct: 0.02 };
+        assert_eq!(breakeven_stop(&c, "long", 100.0, 99.5, 101.0), None);
+        assert_eq!(breakeven_stop(&c, "short", 100.0, 100.5, 99.0), None);
+        assert!((breakeven_stop(&c, "long", 100.0, 99.5, 103.0).unwrap() - 102.0).abs() < 1e-9);
+    }
+}
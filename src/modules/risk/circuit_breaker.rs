@@ -0,0 +1,192 @@
+use std::time::{Duration, Instant};
+
+use crate::config::risk_profile::CircuitBreakerConfig;
+
+/// 连续下单失败熔断：单笔订单内部的重试不计数，只有一整笔交易 (重试耗尽或被交易所拒绝) 失败才累计
+/// 连续失败达到上限后停止开新仓，直到人工介入重启；任何一笔成功都会清零
+#[derive(Debug)]
+pub struct OrderFailureBreaker {
+    max_failures: u32,
+    consecutive: u32,
+    halted: bool,
+}
+
+impl OrderFailureBreaker {
+    pub fn new(cfg: &CircuitBreakerConfig) -> Self {
+        Self { max_failures: cfg.max_order_failures, consecutive: 0, halted: false }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted
+    }
+
+    pub fn consecutive(&self) -> u32 {
+        self.consecutive
+    }
+
+    pub fn record_success(&mut self) {
+        self.consecutive = 0;
+    }
+
+    /// 记录一笔失败的交易，返回 true 表示本次刚触发熔断
+    pub fn record_failure(&mut self) -> bool {
+        self.consecutive += 1;
+        if self.max_failures > 0 && !self.halted && self.consecutive >= self.max_failures {
+            self.halted = true;
+            return true;
+        }
+        false
+    }
+}
+
+/// 最近平仓的连续亏损笔数 (pnls 按平仓时间倒序，最新在前)；盈亏为 0 视为未亏损
+pub fn consecutive_losses(pnls: &[f64]) -> u32 {
+    pnls.iter().take_while(|p| **p < 0.0).count() as u32
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LossStreakEvent {
+    /// 刚触发暂停，携带当前连亏笔数
+    Halted(u32),
+    /// 出现盈利平仓，提前恢复
+    ResumedOnWin,
+    /// 冷却结束恢复
+    ResumedAfterCooldown,
+    Unchanged,
+}
+
+/// 连亏暂停：连续亏损平仓达到上限后停止开新仓，冷却结束或出现盈利平仓后恢复
+/// 冷却恢复时把当前连亏笔数记为基线，之后再亏满上限才会再次暂停
+#[derive(Debug)]
+pub struct LossStreakBreaker {
+    max_losses: u32,
+    cooldown: Duration,
+    streak: u32,
+    baseline: u32,
+    halted_since: Option<Instant>,
+}
+
+impl LossStreakBreaker {
+    pub fn new(cfg: &CircuitBreakerConfig) -> Self {
+        Self { max_losses: cfg.max_consecutive_losses, cooldown: cfg.loss_cooldown(), streak: 0, baseline: 0, halted_since: None }
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.halted_since.is_some()
+    }
+
+    pub fn streak(&self) -> u32 {
+        self.streak
+    }
+
+    /// 剩余冷却时间 (未暂停时为 None)
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.halted_since.map(|since| self.cooldown.saturating_sub(now.duration_since(since)))
+    }
+
+    /// 每轮用 PnL 同步得到的最新连亏笔数更新状态
+    pub fn update(&mut self, streak: u32, now: Instant) -> LossStreakEvent {
+        // 连亏笔数变少说明中间出现了盈利平仓
+        let broken = streak < self.streak;
+        self.streak = streak;
+        if broken {
+            self.baseline = 0;
+        }
+        match self.halted_since {
+            Some(_) if broken => {
+                self.halted_since = None;
+                LossStreakEvent::ResumedOnWin
+            }
+            Some(since) if now.duration_since(since) >= self.cooldown => {
+                self.halted_since = None;
+                self.baseline = streak;
+                LossStreakEvent::ResumedAfterCooldown
+            }
+            None if self.max_losses > 0 && streak.saturating_sub(self.baseline) >= self.max_losses => {
+                self.halted_since = Some(now);
+                LossStreakEvent::Halted(streak)
+            }
+            _ => LossStreakEvent::Unchanged,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(max: u32) -> OrderFailureBreaker {
+        OrderFailureBreaker::new(&CircuitBreakerConfig { max_order_failures: max, ..Default::default() })
+    }
+
+    #[test]
+    fn halts_after_consecutive_failures() {
+        let mut b = breaker(3);
+        assert!(!b.record_failure());
+        assert!(!b.record_failure());
+        assert!(b.record_failure());
+        assert!(b.is_halted());
+        // 只在触发时通知一次
+        assert!(!b.record_failure());
+    }
+
+    #[test]
+    fn success_resets_counter() {
+        let mut b = breaker(2);
+        b.record_failure();
+        b.record_success();
+        assert!(!b.record_failure());
+        assert!(!b.is_halted());
+    }
+
+    #[test]
+    fn zero_disables_breaker() {
+        let mut b = breaker(0);
+        for _ in 0..100 { assert!(!b.record_failure()); }
+        assert!(!b.is_halted());
+    }
+
+    fn loss_breaker(max: u32, cooldown_min: u64) -> LossStreakBreaker {
+        LossStreakBreaker::new(&CircuitBreakerConfig { max_consecutive_losses: max, loss_cooldown_min: cooldown_min, ..Default::default() })
+    }
+
+    #[test]
+    fn counts_losses_from_the_latest_close() {
+        assert_eq!(consecutive_losses(&[-1.0, -2.5, -0.1, 4.0, -3.0]), 3);
+        assert_eq!(consecutive_losses(&[0.0, -1.0]), 0);
+        assert_eq!(consecutive_losses(&[]), 0);
+    }
+
+    #[test]
+    fn loss_streak_halts_and_resumes_on_win() {
+        let mut b = loss_breaker(3, 60);
+        let t0 = Instant::now();
+        assert_eq!(b.update(2, t0), LossStreakEvent::Unchanged);
+        assert_eq!(b.update(3, t0), LossStreakEvent::Halted(3));
+        assert!(b.is_halted());
+        assert_eq!(b.update(3, t0 + Duration::from_secs(60)), LossStreakEvent::Unchanged);
+        // 持仓盈利平仓 -> 连亏清零，提前恢复
+        assert_eq!(b.update(0, t0 + Duration::from_secs(120)), LossStreakEvent::ResumedOnWin);
+        assert!(!b.is_halted());
+    }
+
+    #[test]
+    fn loss_streak_cooldown_sets_new_baseline() {
+        let mut b = loss_breaker(3, 60);
+        let t0 = Instant::now();
+        assert_eq!(b.update(3, t0), LossStreakEvent::Halted(3));
+        assert_eq!(b.remaining(t0 + Duration::from_secs(600)), Some(Duration::from_secs(3000)));
+        let t1 = t0 + Duration::from_secs(3600);
+        assert_eq!(b.update(3, t1), LossStreakEvent::ResumedAfterCooldown);
+        // 恢复后需要再亏满 3 笔才会再次暂停
+        assert_eq!(b.update(5, t1), LossStreakEvent::Unchanged);
+        assert_eq!(b.update(6, t1), LossStreakEvent::Halted(6));
+    }
+
+    #[test]
+    fn zero_disables_loss_streak() {
+        let mut b = loss_breaker(0, 60);
+        assert_eq!(b.update(50, Instant::now()), LossStreakEvent::Unchanged);
+        assert!(!b.is_halted());
+    }
+}
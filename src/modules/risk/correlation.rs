@@ -0,0 +1,109 @@
+use crate::config::risk_profile::CorrelationConfig;
+
+/// 相关系数至少需要的收益率样本数，不足时视为无法判断
+const MIN_SAMPLES: usize = 10;
+
+/// 收盘价序列 -> 简单收益率序列
+fn returns(closes: &[f64]) -> Vec<f64> {
+    closes.windows(2)
+        .map(|w| if w[0] > 0.0 { (w[1] - w[0]) / w[0] } else { 0.0 })
+        .collect()
+}
+
+/// 最近 lookback 根 K 线收益率的皮尔逊相关系数 (两个序列按末尾对齐)
+/// 样本不足或任一序列无波动时返回 None
+pub fn return_correlation(closes_a: &[f64], closes_b: &[f64], lookback: usize) -> Option<f64> {
+    let (ra, rb) = (returns(closes_a), returns(closes_b));
+    let n = ra.len().min(rb.len()).min(lookback);
+    if n < MIN_SAMPLES { return None; }
+    let (a, b) = (&ra[ra.len() - n..], &rb[rb.len() - n..]);
+
+    let mean_a = a.iter().sum::<f64>() / n as f64;
+    let mean_b = b.iter().sum::<f64>() / n as f64;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        let (dx, dy) = (x - mean_a, y - mean_b);
+        cov += dx * dy;
+        var_a += dx * dx;
+        var_b += dy * dy;
+    }
+    if var_a <= 0.0 || var_b <= 0.0 { return None; }
+    Some(cov / (var_a.sqrt() * var_b.sqrt()))
+}
+
+/// 一笔已有持仓对新开仓方向的敞口贡献
+pub struct Exposure<'a> {
+    /// 与新开仓币种的收益率相关系数 (同一币种为 1.0)
+    pub correlation: f64,
+    /// long / short
+    pub side: &'a str,
+    pub notional: f64,
+}
+
+/// 相关币种在 side 方向上的净敞口：同向持仓累加，反向持仓视为对冲抵扣
+pub fn correlated_net_exposure(threshold: f64, side: &str, exposures: &[Exposure]) -> f64 {
+    exposures.iter()
+        .filter(|e| e.correlation >= threshold)
+        .map(|e| if e.side == side { e.notional } else { -e.notional })
+        .sum()
+}
+
+/// 相关性守卫：返回新仓位允许的名义价值 (<= new_notional)，0 表示跳过
+pub fn allowed_notional(cfg: &CorrelationConfig, equity: f64, side: &str, new_notional: f64, exposures: &[Exposure]) -> f64 {
+    if !cfg.enabled || new_notional <= 0.0 { return new_notional; }
+    let limit = equity * cfg.max_net_exposure;
+    let existing = correlated_net_exposure(cfg.threshold, side, exposures);
+    new_notional.min((limit - existing).max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> CorrelationConfig {
+        CorrelationConfig { enabled: true, threshold: 0.7, max_net_exposure: 2.0, lookback: 48 }
+    }
+
+    /// 围绕 100 上下波动的收盘价序列，振幅由 scale 控制
+    fn wave(scale: f64) -> Vec<f64> {
+        (0..30).map(|i| 100.0 + scale * ((i as f64) * 0.7).sin()).collect()
+    }
+
+    #[test]
+    fn correlation_of_scaled_and_inverted_series() {
+        let base = wave(2.0);
+        let scaled = wave(4.0);
+        let inverted = wave(-2.0);
+        assert!(return_correlation(&base, &scaled, 48).unwrap() > 0.99);
+        assert!(return_correlation(&base, &inverted, 48).unwrap() < -0.99);
+
+        // 样本不足 / 无波动
+        assert_eq!(return_correlation(&base[..5], &scaled[..5], 48), None);
+        assert_eq!(return_correlation(&base, &[100.0; 30], 48), None);
+    }
+
+    #[test]
+    fn opposite_positions_hedge_and_uncorrelated_are_ignored() {
+        let exposures = [
+            Exposure { correlation: 1.0, side: "long", notional: 1000.0 },
+            Exposure { correlation: 0.9, side: "long", notional: 500.0 },
+            Exposure { correlation: 0.8, side: "short", notional: 300.0 },
+            Exposure { correlation: 0.2, side: "long", notional: 5000.0 },
+        ];
+        assert!((correlated_net_exposure(0.7, "long", &exposures) - 1200.0).abs() < 1e-9);
+        assert!((correlated_net_exposure(0.7, "short", &exposures) + 1200.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn new_position_is_reduced_then_skipped() {
+        let held = [Exposure { correlation: 0.9, side: "long", notional: 1500.0 }];
+        // 上限 2 × 1000 = 2000，已占用 1500
+        assert!((allowed_notional(&cfg(), 1000.0, "long", 1000.0, &held) - 500.0).abs() < 1e-9);
+        assert_eq!(allowed_notional(&cfg(), 1000.0, "long", 400.0, &held), 400.0);
+
+        let full = [Exposure { correlation: 0.9, side: "long", notional: 2500.0 }];
+        assert_eq!(allowed_notional(&cfg(), 1000.0, "long", 1000.0, &full), 0.0);
+        // 反向开仓不受同向敞口限制
+        assert_eq!(allowed_notional(&cfg(), 1000.0, "short", 1000.0, &full), 1000.0);
+    }
+}
@@ -0,0 +1,54 @@
+use crate::config::risk_profile::ExposureConfig;
+use crate::modules::action::executor::PositionSummary;
+
+/// 所有持仓的名义价值之和 (多空都计入，不互相抵扣)
+pub fn gross_notional(positions: &[PositionSummary]) -> f64 {
+    positions.iter()
+        .filter(|p| p.size > 0.0)
+        .map(|p| p.notional_usd.abs())
+        .sum()
+}
+
+/// 总敞口守卫：返回新仓位允许的名义价值 (<= new_notional)，0 表示跳过
+pub fn allowed_gross_notional(cfg: &ExposureConfig, equity: f64, current_gross: f64, new_notional: f64) -> f64 {
+    if !cfg.enabled || new_notional <= 0.0 { return new_notional; }
+    let limit = equity.max(0.0) * cfg.max_gross_multiple;
+    new_notional.min((limit - current_gross).max(0.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(symbol: &str, side: &str, size: f64, notional_usd: f64) -> PositionSummary {
+        PositionSummary {
+            symbol: symbol.to_string(), size, upl: 0.0, side: side.to_string(), avg_px: 100.0,
+            mark_px: 100.0, leverage: 5, notional_usd, margin_usd: notional_usd / 5.0,
+        }
+    }
+
+    #[test]
+    fn gross_sums_both_sides_without_netting() {
+        let positions = [
+            position("BTC", "long", 1.0, 1500.0),
+            position("ETH", "short", 2.0, 1000.0),
+            // 已平仓的残留记录不计入
+            position("SOL", "long", 0.0, 800.0),
+        ];
+        assert_eq!(gross_notional(&positions), 2500.0);
+        assert_eq!(gross_notional(&[]), 0.0);
+    }
+
+    #[test]
+    fn new_position_is_reduced_or_skipped_at_the_cap() {
+        let cfg = ExposureConfig::default(); // 3x 权益
+        // 权益 1000 -> 上限 3000，已有 2500，只剩 500
+        assert_eq!(allowed_gross_notional(&cfg, 1000.0, 2500.0, 400.0), 400.0);
+        assert_eq!(allowed_gross_notional(&cfg, 1000.0, 2500.0, 800.0), 500.0);
+        // 已超限 (例如权益下跌) 时直接跳过
+        assert_eq!(allowed_gross_notional(&cfg, 1000.0, 3200.0, 800.0), 0.0);
+
+        let cfg = ExposureConfig { enabled: false, ..ExposureConfig::default() };
+        assert_eq!(allowed_gross_notional(&cfg, 1000.0, 3200.0, 800.0), 800.0);
+    }
+}
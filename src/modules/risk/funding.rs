@@ -0,0 +1,76 @@
+use crate::config::risk_profile::{FundingAction, FundingCarryConfig, FundingFilterConfig};
+
+/// 资金费率过滤结果
+#[derive(Debug, PartialEq)]
+pub enum FundingVerdict {
+    Allow,
+    /// 跳过开仓，携带原因
+    Skip(String),
+    /// 仓位乘以该系数后开仓，携带原因
+    Downgrade(f64, String),
+}
+
+/// 多头在正费率、空头在负费率下需要支付资金费；超过阈值时按配置跳过或缩小开仓
+pub fn check_funding(cfg: &FundingFilterConfig, pos_side: &str, funding_rate: f64) -> FundingVerdict {
+    if !cfg.enabled { return FundingVerdict::Allow; }
+    let against = if pos_side == "long" {
+        funding_rate > cfg.max_long_rate
+    } else {
+        funding_rate < cfg.min_short_rate
+    };
+    if !against { return FundingVerdict::Allow; }
+
+    let threshold = if pos_side == "long" { cfg.max_long_rate } else { cfg.min_short_rate };
+    let reason = format!("funding {:.4}%/period beyond {} limit {:.4}%", funding_rate * 100.0, pos_side, threshold * 100.0);
+    match cfg.action {
+        FundingAction::Skip => FundingVerdict::Skip(reason),
+        FundingAction::Downgrade => FundingVerdict::Downgrade(cfg.downgrade_factor, reason),
+    }
+}
+
+/// 资金费侵蚀利润检查：funding_paid 为开仓以来净支付的资金费 (收取为负)，upl 为浮动盈亏
+/// 累计支付 >= max_funding_to_profit × 浮盈时返回 Some(原因)；浮亏的持仓交给止损处理，不在这里触发
+pub fn check_carry(cfg: &FundingCarryConfig, funding_paid: f64, upl: f64) -> Option<String> {
+    if !cfg.enabled || funding_paid < cfg.min_paid_usd.max(f64::EPSILON) || upl <= 0.0 { return None; }
+    let ratio = funding_paid / upl;
+    (ratio >= cfg.max_funding_to_profit).then(|| format!(
+        "funding paid ${:.2} is {:.0}% of unrealized profit ${:.2} (limit {:.0}%)",
+        funding_paid, ratio * 100.0, upl, cfg.max_funding_to_profit * 100.0
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adverse_funding_blocks_the_paying_side_only() {
+        let cfg = FundingFilterConfig::default(); // ±0.05%/期
+        assert!(matches!(check_funding(&cfg, "long", 0.001), FundingVerdict::Skip(_)));
+        // 同样的正费率下空头收取资金费，不受限制
+        assert_eq!(check_funding(&cfg, "short", 0.001), FundingVerdict::Allow);
+        assert!(matches!(check_funding(&cfg, "short", -0.001), FundingVerdict::Skip(_)));
+        assert_eq!(check_funding(&cfg, "long", 0.0004), FundingVerdict::Allow);
+
+        let cfg = FundingFilterConfig { action: FundingAction::Downgrade, ..FundingFilterConfig::default() };
+        assert!(matches!(check_funding(&cfg, "long", 0.001), FundingVerdict::Downgrade(f, _) if f == 0.5));
+
+        let cfg = FundingFilterConfig { enabled: false, ..FundingFilterConfig::default() };
+        assert_eq!(check_funding(&cfg, "long", 0.01), FundingVerdict::Allow);
+    }
+
+    #[test]
+    fn carry_triggers_when_funding_eats_the_profit() {
+        let cfg = FundingCarryConfig { enabled: true, ..FundingCarryConfig::default() }; // 50%，至少 $1
+        assert!(check_carry(&cfg, 30.0, 50.0).is_some());
+        assert!(check_carry(&cfg, 20.0, 50.0).is_none());
+        // 收取资金费 (如正费率下的空单) 永不触发
+        assert!(check_carry(&cfg, -30.0, 50.0).is_none());
+        // 支付金额过小 / 浮亏时不触发
+        assert!(check_carry(&cfg, 0.8, 1.0).is_none());
+        assert!(check_carry(&cfg, 30.0, -10.0).is_none());
+
+        let cfg = FundingCarryConfig::default();
+        assert!(check_carry(&cfg, 30.0, 50.0).is_none());
+    }
+}
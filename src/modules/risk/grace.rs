@@ -0,0 +1,56 @@
+use std::time::{Duration, Instant};
+
+/// 启动观察期：重启后先只管理已有持仓 (同步、保护、平仓、报告)，不开新仓，留出人工核对状态的窗口
+#[derive(Debug)]
+pub struct StartupGrace {
+    until: Option<Instant>,
+}
+
+impl StartupGrace {
+    /// duration 为 0 时不设观察期
+    pub fn new(duration: Duration, now: Instant) -> Self {
+        Self { until: (!duration.is_zero()).then(|| now + duration) }
+    }
+
+    /// 观察期剩余时间 (未处于观察期时为 None)
+    pub fn remaining(&self, now: Instant) -> Option<Duration> {
+        self.until.filter(|t| *t > now).map(|t| t - now)
+    }
+
+    pub fn is_active(&self, now: Instant) -> bool {
+        self.remaining(now).is_some()
+    }
+
+    /// 观察期刚结束时返回 true (只返回一次)
+    pub fn take_ended(&mut self, now: Instant) -> bool {
+        match self.until {
+            Some(t) if t <= now => {
+                self.until = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grace_blocks_until_expiry_and_ends_once() {
+        let t0 = Instant::now();
+        let mut grace = StartupGrace::new(Duration::from_secs(600), t0);
+        assert!(grace.is_active(t0 + Duration::from_secs(599)));
+        assert!(!grace.take_ended(t0 + Duration::from_secs(599)));
+
+        let later = t0 + Duration::from_secs(600);
+        assert!(!grace.is_active(later));
+        assert!(grace.take_ended(later));
+        assert!(!grace.take_ended(later));
+
+        let mut none = StartupGrace::new(Duration::ZERO, t0);
+        assert!(!none.is_active(t0));
+        assert!(!none.take_ended(t0));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::config::risk_profile::LeverageRampConfig;
+
+/// 已解锁的最高档位下标，一档都未达到时返回 None
+pub fn unlocked_tier(cfg: &LeverageRampConfig, wins: u64) -> Option<usize> {
+    cfg.tiers.iter().rposition(|t| wins >= t.min_wins)
+}
+
+/// 当前实际杠杆上限：min(已解锁档位上限, max_leverage)，未解锁任何档位时为 1x
+pub fn effective_max_leverage(cfg: &LeverageRampConfig, wins: u64, max_leverage: f64) -> f64 {
+    if !cfg.enabled || cfg.tiers.is_empty() { return max_leverage; }
+    match unlocked_tier(cfg, wins) {
+        Some(i) => cfg.tiers[i].max_leverage.min(max_leverage),
+        None => 1.0_f64.min(max_leverage),
+    }
+}
+
+/// 记录上次观察到的档位，用于解锁通知
+#[derive(Debug, Default)]
+pub struct LeverageRamp {
+    /// None = 尚未初始化 (启动时的档位不通知)
+    tier: Option<Option<usize>>,
+}
+
+impl LeverageRamp {
+    /// 更新盈利笔数，档位升级时返回新档位下标
+    pub fn update(&mut self, cfg: &LeverageRampConfig, wins: u64) -> Option<usize> {
+        let current = unlocked_tier(cfg, wins);
+        let previous = self.tier.replace(current)?;
+        match current {
+            Some(i) if previous.is_none_or(|p| i > p) => Some(i),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::risk_profile::LeverageTier;
+
+    fn cfg() -> LeverageRampConfig {
+        LeverageRampConfig {
+            enabled: true,
+            tiers: vec![
+                LeverageTier { min_wins: 3, max_leverage: 2.0 },
+                LeverageTier { min_wins: 10, max_leverage: 5.0 },
+                LeverageTier { min_wins: 20, max_leverage: 20.0 },
+            ],
+        }
+    }
+
+    #[test]
+    fn cap_follows_unlocked_tier_and_global_max() {
+        assert_eq!(effective_max_leverage(&cfg(), 0, 10.0), 1.0);
+        assert_eq!(effective_max_leverage(&cfg(), 3, 10.0), 2.0);
+        assert_eq!(effective_max_leverage(&cfg(), 12, 10.0), 5.0);
+        // 档位上限高于全局 max_leverage 时取全局
+        assert_eq!(effective_max_leverage(&cfg(), 50, 10.0), 10.0);
+
+        let disabled = LeverageRampConfig { enabled: false, ..cfg() };
+        assert_eq!(effective_max_leverage(&disabled, 0, 10.0), 10.0);
+    }
+
+    #[test]
+    fn only_upgrades_after_startup_are_reported() {
+        let mut ramp = LeverageRamp::default();
+        assert_eq!(ramp.update(&cfg(), 12), None);
+        assert_eq!(ramp.update(&cfg(), 15), None);
+        assert_eq!(ramp.update(&cfg(), 20), Some(2));
+        assert_eq!(ramp.update(&cfg(), 21), None);
+    }
+}
@@ -0,0 +1,70 @@
+use serde_json::Value;
+
+/// 从 /api/v5/system/status 响应中提取正在进行的维护 (返回维护标题)
+pub fn active_maintenance(resp: &Value) -> Option<String> {
+    resp["data"].as_array()?
+        .iter()
+        .find(|item| item["state"].as_str() == Some("ongoing"))
+        .map(|item| item["title"].as_str().unwrap_or("OKX system maintenance").to_string())
+}
+
+/// 维护状态切换
+#[derive(Debug, PartialEq, Eq)]
+pub enum MaintenanceEvent {
+    /// 刚进入维护，携带维护标题
+    Entered(String),
+    /// 维护结束，恢复交易
+    Resumed,
+    Unchanged,
+}
+
+/// 维护暂停状态：维护期间停止交易并降低轮询频率，每次进入/退出只通知一次
+#[derive(Debug, Default)]
+pub struct MaintenanceHold {
+    active: Option<String>,
+}
+
+impl MaintenanceHold {
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+
+    pub fn update(&mut self, maintenance: Option<String>) -> MaintenanceEvent {
+        let event = match (&self.active, &maintenance) {
+            (None, Some(title)) => MaintenanceEvent::Entered(title.clone()),
+            (Some(_), None) => MaintenanceEvent::Resumed,
+            _ => MaintenanceEvent::Unchanged,
+        };
+        self.active = maintenance;
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn detects_only_ongoing_maintenance() {
+        let scheduled = json!({"code": "0", "data": [{"title": "Spot upgrade", "state": "scheduled"}]});
+        assert_eq!(active_maintenance(&scheduled), None);
+
+        let ongoing = json!({"code": "0", "data": [
+            {"title": "Spot upgrade", "state": "scheduled"},
+            {"title": "Unified account upgrade", "state": "ongoing"}
+        ]});
+        assert_eq!(active_maintenance(&ongoing).as_deref(), Some("Unified account upgrade"));
+    }
+
+    #[test]
+    fn hold_notifies_once_per_transition() {
+        let mut hold = MaintenanceHold::default();
+        assert_eq!(hold.update(Some("upgrade".into())), MaintenanceEvent::Entered("upgrade".into()));
+        assert_eq!(hold.update(Some("upgrade".into())), MaintenanceEvent::Unchanged);
+        assert!(hold.is_active());
+        assert_eq!(hold.update(None), MaintenanceEvent::Resumed);
+        assert_eq!(hold.update(None), MaintenanceEvent::Unchanged);
+        assert!(!hold.is_active());
+    }
+}
@@ -0,0 +1,129 @@
+use std::time::Instant;
+use crate::config::risk_profile::MarginConfig;
+use crate::modules::action::executor::PositionSummary;
+
+/// 维持保证金占权益的比例，1.0 即到达强平线；权益无效时返回 0
+pub fn maintenance_usage(maintenance_margin: f64, equity: f64) -> f64 {
+    if equity <= 0.0 || maintenance_margin <= 0.0 { return 0.0; }
+    maintenance_margin / equity
+}
+
+/// 浮亏最大的持仓 (没有浮亏持仓时返回 None)
+pub fn largest_loss(positions: &[PositionSummary]) -> Option<&PositionSummary> {
+    positions.iter()
+        .filter(|p| p.size > 0.0 && p.upl < 0.0)
+        .min_by(|a, b| a.upl.total_cmp(&b.upl))
+}
+
+/// 危险状态切换
+#[derive(Debug, PartialEq)]
+pub enum MarginEvent {
+    /// 刚进入危险状态，携带当前比例
+    Entered(f64),
+    /// 比例回落到 recover_ratio 以下，恢复开仓
+    Recovered(f64),
+    Unchanged,
+}
+
+/// 保证金危险状态 (带回差)：超过 danger_ratio 进入，低于 recover_ratio 才退出，每次切换只通知一次
+#[derive(Debug, Default)]
+pub struct MarginMonitor {
+    danger: bool,
+    /// 本次危险状态中最近一次减仓的时间
+    last_cut: Option<Instant>,
+}
+
+impl MarginMonitor {
+    pub fn is_danger(&self) -> bool {
+        self.danger
+    }
+
+    pub fn update(&mut self, cfg: &MarginConfig, usage: f64) -> MarginEvent {
+        if !cfg.enabled {
+            self.danger = false;
+            return MarginEvent::Unchanged;
+        }
+        match self.danger {
+            false if usage >= cfg.danger_ratio => {
+                self.danger = true;
+                self.last_cut = None;
+                MarginEvent::Entered(usage)
+            }
+            true if usage < cfg.recover_ratio => {
+                self.danger = false;
+                MarginEvent::Recovered(usage)
+            }
+            _ => MarginEvent::Unchanged,
+        }
+    }
+
+    /// 危险状态下是否该减仓：减仓后等待 reduce_cooldown，未配置冷却时每次危险状态只减一次
+    pub fn cut_due(&self, cfg: &MarginConfig, now: Instant) -> bool {
+        if !self.danger || !cfg.reduce_largest_loss { return false; }
+        match (self.last_cut, cfg.reduce_cooldown()) {
+            (None, _) => true,
+            (Some(at), Some(cooldown)) => now.saturating_duration_since(at) >= cooldown,
+            (Some(_), None) => false,
+        }
+    }
+
+    pub fn record_cut(&mut self, now: Instant) {
+        self.last_cut = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn position(symbol: &str, upl: f64) -> PositionSummary {
+        PositionSummary {
+            symbol: symbol.to_string(), size: 1.0, upl, side: "long".to_string(), avg_px: 100.0,
+            mark_px: 100.0, leverage: 5, notional_usd: 100.0, margin_usd: 20.0,
+        }
+    }
+
+    #[test]
+    fn danger_state_has_hysteresis() {
+        let cfg = MarginConfig::default(); // 0.5 进入 / 0.35 退出
+        let mut monitor = MarginMonitor::default();
+        assert_eq!(monitor.update(&cfg, maintenance_usage(30.0, 100.0)), MarginEvent::Unchanged);
+        assert_eq!(monitor.update(&cfg, maintenance_usage(55.0, 100.0)), MarginEvent::Entered(0.55));
+        assert!(monitor.is_danger());
+        // 回落但未低于 recover_ratio，仍保持危险状态
+        assert_eq!(monitor.update(&cfg, 0.4), MarginEvent::Unchanged);
+        assert!(monitor.is_danger());
+        assert_eq!(monitor.update(&cfg, 0.3), MarginEvent::Recovered(0.3));
+        assert!(!monitor.is_danger());
+    }
+
+    #[test]
+    fn cuts_wait_for_the_cooldown() {
+        let cfg = MarginConfig { reduce_largest_loss: true, reduce_cooldown_sec: 60, ..MarginConfig::default() };
+        let t0 = Instant::now();
+        let mut monitor = MarginMonitor::default();
+        assert!(!monitor.cut_due(&cfg, t0));
+        monitor.update(&cfg, 0.6);
+        assert!(monitor.cut_due(&cfg, t0));
+        monitor.record_cut(t0);
+        assert!(!monitor.cut_due(&cfg, t0 + Duration::from_secs(59)));
+        assert!(monitor.cut_due(&cfg, t0 + Duration::from_secs(60)));
+        // 默认不减仓
+        assert!(!monitor.cut_due(&MarginConfig::default(), t0 + Duration::from_secs(60)));
+
+        // 冷却为 0：每次进入危险状态只减一次，恢复后再次进入可以再减
+        let once = MarginConfig { reduce_cooldown_sec: 0, ..cfg };
+        assert!(!monitor.cut_due(&once, t0 + Duration::from_secs(3600)));
+        monitor.update(&once, 0.2);
+        monitor.update(&once, 0.6);
+        assert!(monitor.cut_due(&once, t0 + Duration::from_secs(3600)));
+    }
+
+    #[test]
+    fn picks_the_largest_loss() {
+        let positions = [position("BTC", -20.0), position("ETH", -50.0), position("SOL", 80.0)];
+        assert_eq!(largest_loss(&positions).map(|p| p.symbol.as_str()), Some("ETH"));
+        assert!(largest_loss(&positions[2..]).is_none());
+    }
+}
@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use crate::config::risk_profile::MinHoldConfig;
+
+/// 距最近一次开仓/加仓不足 min_hold_sec 时返回剩余时长；关闭或开仓时间未知时返回 None
+pub fn hold_remaining(cfg: &MinHoldConfig, since_entry: Option<Duration>) -> Option<Duration> {
+    if !cfg.enabled { return None; }
+    Duration::from_secs(cfg.min_hold_sec).checked_sub(since_entry?).filter(|d| !d.is_zero())
+}
+
+/// 浮亏已达 stop_override_r 倍 R：止损优先于最短持仓
+pub fn stop_override(cfg: &MinHoldConfig, unrealized_r: Option<f64>) -> bool {
+    unrealized_r.is_some_and(|r| r <= -cfg.stop_override_r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hold_window_and_stop_override() {
+        let cfg = MinHoldConfig { enabled: true, min_hold_sec: 1800, stop_override_r: 1.0 };
+        assert_eq!(hold_remaining(&cfg, Some(Duration::from_secs(600))), Some(Duration::from_secs(1200)));
+        assert_eq!(hold_remaining(&cfg, Some(Duration::from_secs(1800))), None);
+        assert_eq!(hold_remaining(&cfg, None), None);
+        assert_eq!(hold_remaining(&MinHoldConfig { enabled: false, ..cfg.clone() }, Some(Duration::ZERO)), None);
+
+        assert!(!stop_override(&cfg, Some(-0.5)));
+        assert!(stop_override(&cfg, Some(-1.2)));
+        assert!(!stop_override(&cfg, None));
+    }
+}
@@ -0,0 +1,17 @@
+pub mod pyramiding;
+pub mod breakeven;
+pub mod anti_flip;
+pub mod maintenance;
+pub mod spread;
+pub mod circuit_breaker;
+pub mod correlation;
+pub mod leverage_ramp;
+pub mod margin;
+pub mod min_hold;
+pub mod funding;
+pub mod exposure;
+pub mod blackout;
+pub mod staleness;
+pub mod rebalance;
+pub mod thrashing;
+pub mod grace;
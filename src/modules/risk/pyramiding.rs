@@ -0,0 +1,98 @@
+use crate::config::risk_profile::PyramidingConfig;
+use crate::modules::action::executor::PositionSummary;
+use crate::utils::money;
+
+/// 判断同方向持仓能否加仓 (金字塔加仓)
+/// 返回允许的最大加仓张数 (按 lot_sz 向下取整)，关闭时返回 None (不额外限制，与新开仓相同)；被拒绝时返回原因
+/// 规则: 仅浮盈超过阈值时允许加仓，亏损中一律拒绝 (禁止马丁格尔摊平)
+pub fn evaluate_add(cfg: &PyramidingConfig, pos: &PositionSummary, price: f64, adds_done: u32, lot_sz: f64, min_sz: f64) -> Result<Option<f64>, String> {
+    if !cfg.enabled {
+        return Ok(None);
+    }
+    if adds_done >= cfg.max_adds {
+        return Err(format!("max adds reached ({}/{})", adds_done, cfg.max_adds));
+    }
+    if pos.avg_px <= 0.0 || price <= 0.0 {
+        return Err("entry price unknown".to_string());
+    }
+
+    let profit_pct = if pos.side == "short" {
+        (pos.avg_px - price) / pos.avg_px
+    } else {
+        (price - pos.avg_px) / pos.avg_px
+    };
+
+    if profit_pct <= 0.0 {
+        return Err(format!("position underwater ({:.2}%), no martingale", profit_pct * 100.0));
+    }
+    if profit_pct < cfg.min_profit_pct {
+        return Err(format!("profit {:.2}% below add threshold {:.2}%", profit_pct * 100.0, cfg.min_profit_pct * 100.0));
+    }
+
+    let max_add = money::to_f64(money::snap_to_grid(money::dec(pos.size * cfg.add_size_ratio), money::dec(lot_sz), false));
+    if max_add <= 0.0 || max_add < min_sz {
+        return Err(format!("add cap {} below minimum order size {}", max_add, min_sz));
+    }
+    Ok(Some(max_add))
+}
+
+/// 加仓后的加权平均入场价
+pub fn averaged_entry(pos_size: f64, pos_avg_px: f64, add_size: f64, add_price: f64) -> f64 {
+    let total = pos_size + add_size;
+    if total <= 0.0 { return add_price; }
+    (pos_size * pos_avg_px + add_size * add_price) / total
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> PyramidingConfig {
+        PyramidingConfig { enabled: true, min_profit_pct: 0.01, max_adds: 2, add_size_ratio: 0.5 }
+    }
+
+    fn position(side: &str, size: f64, avg_px: f64) -> PositionSummary {
+        PositionSummary {
+            symbol: "BTC-USDT-SWAP".to_string(), size, upl: 0.0, side: side.to_string(), avg_px, mark_px: avg_px,
+            leverage: 5, notional_usd: 0.0, margin_usd: 0.0,
+        }
+    }
+
+    #[test]
+    fn adds_only_into_sufficient_profit() {
+        let long = position("long", 3.0, 100.0);
+        // 浮盈 2%，上限 3 × 0.5 = 1.5 张，按 lotSz 1 取整为 1
+        assert_eq!(evaluate_add(&cfg(), &long, 102.0, 0, 1.0, 1.0), Ok(Some(1.0)));
+        assert_eq!(evaluate_add(&cfg(), &long, 102.0, 0, 0.1, 0.1), Ok(Some(1.5)));
+        // 空单按反方向计算浮盈
+        assert_eq!(evaluate_add(&cfg(), &position("short", 4.0, 100.0), 98.0, 1, 1.0, 1.0), Ok(Some(2.0)));
+
+        // 亏损中禁止摊平，浮盈不足阈值也拒绝
+        assert!(evaluate_add(&cfg(), &long, 99.0, 0, 1.0, 1.0).unwrap_err().contains("underwater"));
+        assert!(evaluate_add(&cfg(), &long, 100.0, 0, 1.0, 1.0).unwrap_err().contains("underwater"));
+        assert!(evaluate_add(&cfg(), &long, 100.5, 0, 1.0, 1.0).unwrap_err().contains("below add threshold"));
+        assert!(evaluate_add(&cfg(), &position("long", 3.0, 0.0), 102.0, 0, 1.0, 1.0).is_err());
+    }
+
+    #[test]
+    fn add_count_and_min_size_limits() {
+        let long = position("long", 3.0, 100.0);
+        assert!(evaluate_add(&cfg(), &long, 102.0, 2, 1.0, 1.0).unwrap_err().contains("max adds"));
+        // 上限取整后低于最小下单量
+        assert!(evaluate_add(&cfg(), &position("long", 1.0, 100.0), 102.0, 0, 1.0, 1.0).unwrap_err().contains("minimum order size"));
+        assert!(evaluate_add(&cfg(), &long, 102.0, 0, 1.0, 2.0).is_err());
+    }
+
+    #[test]
+    fn disabled_leaves_same_side_entries_unrestricted() {
+        let off = PyramidingConfig { enabled: false, ..cfg() };
+        assert_eq!(evaluate_add(&off, &position("long", 3.0, 100.0), 90.0, 5, 1.0, 1.0), Ok(None));
+    }
+
+    #[test]
+    fn averaged_entry_weights_by_size() {
+        assert!((averaged_entry(3.0, 100.0, 1.0, 104.0) - 101.0).abs() < 1e-9);
+        assert!((averaged_entry(0.0, 0.0, 2.0, 50.0) - 50.0).abs() < 1e-9);
+        assert_eq!(averaged_entry(0.0, 100.0, 0.0, 104.0), 104.0);
+    }
+}
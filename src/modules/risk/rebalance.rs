@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use crate::config::risk_profile::{RebalanceConfig, RebalanceMode};
+use crate::modules::action::executor::PositionSummary;
+
+/// 一笔再平衡削减 (reduce-only)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trim {
+    pub symbol: String,
+    /// long / short
+    pub side: String,
+    /// 削减的数量 (与持仓 size 同单位，未对齐 lotSz)
+    pub qty: f64,
+    pub notional: f64,
+    pub target_notional: f64,
+}
+
+/// 各持仓的原始权重；None 表示不参与再平衡 (保持原样)
+fn raw_weight(cfg: &RebalanceConfig, pos: &PositionSummary, atr_pct: &HashMap<String, f64>) -> Option<f64> {
+    match cfg.mode {
+        RebalanceMode::EqualRisk => atr_pct.get(&pos.symbol).filter(|a| **a > 0.0).map(|a| 1.0 / a),
+        RebalanceMode::TargetWeights => cfg.target_weights.get(&pos.symbol).copied().filter(|w| *w >= 0.0),
+    }
+}
+
+/// 计算需要削减的持仓
+/// - 目标总额 = 当前总名义价值，超出 gross_cap ([exposure] 上限) 时取上限
+/// - 不参与的持仓保持原样，其余持仓按权重瓜分剩下的额度
+/// - 只削减超出目标 (1 + tolerance) 倍且削减额不低于 min_trim_usd 的持仓
+pub fn plan_trims(cfg: &RebalanceConfig, positions: &[PositionSummary], atr_pct: &HashMap<String, f64>, gross_cap: Option<f64>) -> Vec<Trim> {
+    let open: Vec<&PositionSummary> = positions.iter().filter(|p| p.size > 0.0 && p.notional_usd.abs() > 0.0).collect();
+    let gross: f64 = open.iter().map(|p| p.notional_usd.abs()).sum();
+    let target_gross = gross_cap.map_or(gross, |cap| gross.min(cap.max(0.0)));
+
+    let weights: Vec<Option<f64>> = open.iter().map(|p| raw_weight(cfg, p, atr_pct)).collect();
+    let fixed: f64 = open.iter().zip(&weights).filter(|(_, w)| w.is_none()).map(|(p, _)| p.notional_usd.abs()).sum();
+    let weight_sum: f64 = weights.iter().flatten().sum();
+    if weight_sum <= 0.0 {
+        return Vec::new();
+    }
+    let pool = (target_gross - fixed).max(0.0);
+
+    open.iter().zip(&weights)
+        .filter_map(|(p, w)| {
+            let target = pool * (*w)? / weight_sum;
+            let notional = p.notional_usd.abs();
+            let excess = notional - target;
+            if notional <= target * (1.0 + cfg.tolerance) || excess < cfg.min_trim_usd {
+                return None;
+            }
+            Some(Trim {
+                symbol: p.symbol.clone(),
+                side: p.side.clone(),
+                qty: p.size * excess / notional,
+                notional,
+                target_notional: target,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn position(symbol: &str, size: f64, notional_usd: f64) -> PositionSummary {
+        PositionSummary {
+            symbol: symbol.to_string(), size, upl: 0.0, side: "long".to_string(), avg_px: 100.0,
+            mark_px: 100.0, leverage: 5, notional_usd, margin_usd: notional_usd / 5.0,
+        }
+    }
+
+    #[test]
+    fn equal_risk_trims_the_oversized_position() {
+        let cfg = RebalanceConfig { enabled: true, ..RebalanceConfig::default() };
+        let positions = [position("BTC", 10.0, 3000.0), position("ETH", 10.0, 1000.0)];
+        // 同等波动：各自目标 2000，BTC 超出 50% 需要削减 1000
+        let atr = HashMap::from([("BTC".to_string(), 1.0), ("ETH".to_string(), 1.0)]);
+        let trims = plan_trims(&cfg, &positions, &atr, None);
+        assert_eq!(trims.len(), 1);
+        assert_eq!(trims[0].symbol, "BTC");
+        assert!((trims[0].target_notional - 2000.0).abs() < 1e-9);
+        assert!((trims[0].qty - 10.0 / 3.0).abs() < 1e-9);
+
+        // ETH 波动是 BTC 的两倍：BTC 目标 2667，只超出 12.5%，在容忍范围内
+        let atr = HashMap::from([("BTC".to_string(), 1.0), ("ETH".to_string(), 2.0)]);
+        assert!(plan_trims(&cfg, &positions, &atr, None).is_empty());
+    }
+
+    #[test]
+    fn target_weights_leave_unlisted_symbols_alone() {
+        let cfg = RebalanceConfig {
+            enabled: true,
+            mode: RebalanceMode::TargetWeights,
+            target_weights: HashMap::from([("BTC".to_string(), 1.0), ("ETH".to_string(), 1.0)]),
+            ..RebalanceConfig::default()
+        };
+        // SOL 未配置：保持 2000 不动，BTC/ETH 平分剩余 2000
+        let positions = [position("BTC", 4.0, 1600.0), position("ETH", 1.0, 400.0), position("SOL", 1.0, 2000.0)];
+        let trims = plan_trims(&cfg, &positions, &HashMap::new(), None);
+        assert_eq!(trims.len(), 1);
+        assert_eq!(trims[0].symbol, "BTC");
+        assert!((trims[0].qty - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gross_cap_shrinks_all_targets() {
+        let cfg = RebalanceConfig { enabled: true, tolerance: 0.1, ..RebalanceConfig::default() };
+        let positions = [position("BTC", 1.0, 2000.0), position("ETH", 1.0, 2000.0)];
+        let atr = HashMap::from([("BTC".to_string(), 1.0), ("ETH".to_string(), 1.0)]);
+        assert!(plan_trims(&cfg, &positions, &atr, Some(5000.0)).is_empty());
+        // 上限 3000：每个目标 1500，各削减 25%
+        let trims = plan_trims(&cfg, &positions, &atr, Some(3000.0));
+        assert_eq!(trims.len(), 2);
+        assert!(trims.iter().all(|t| (t.qty - 0.25).abs() < 1e-9));
+    }
+}
@@ -0,0 +1,36 @@
+/// 买卖价差 (bp)，以中间价为基准；盘口无效时返回 None
+pub fn spread_bps(bid: f64, ask: f64) -> Option<f64> {
+    if bid <= 0.0 || ask <= 0.0 || ask < bid { return None; }
+    let mid = (bid + ask) / 2.0;
+    Some((ask - bid) / mid * 10_000.0)
+}
+
+/// 价差守卫：超过 max_bps 时返回 Err(原因)，max_bps <= 0 表示不检查
+pub fn check_spread(bid: f64, ask: f64, max_bps: f64) -> Result<f64, String> {
+    let bps = spread_bps(bid, ask).ok_or_else(|| format!("invalid book (bid {}, ask {})", bid, ask))?;
+    if max_bps > 0.0 && bps > max_bps {
+        return Err(format!("spread {:.1}bp > max {:.1}bp (bid {}, ask {})", bps, max_bps, bid, ask));
+    }
+    Ok(bps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spread_in_basis_points() {
+        let bps = spread_bps(99.9, 100.1).unwrap();
+        assert!((bps - 20.0).abs() < 1e-9);
+        assert_eq!(spread_bps(0.0, 100.0), None);
+        assert_eq!(spread_bps(101.0, 100.0), None);
+    }
+
+    #[test]
+    fn wide_spread_is_rejected() {
+        assert!(check_spread(99.9, 100.1, 25.0).is_ok());
+        assert!(check_spread(99.9, 100.1, 10.0).is_err());
+        // 0 = 不检查
+        assert!(check_spread(90.0, 110.0, 0.0).is_ok());
+    }
+}
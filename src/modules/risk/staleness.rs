@@ -0,0 +1,49 @@
+// 文件名: staleness.rs
+// 价格时效守卫：优先使用 WS 实时价，超时回退 REST (K 线) 价格，两者都超过时效时不允许基于该价格交易
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PriceSource {
+    Ws,
+    Rest,
+}
+
+/// 选出时效内的价格；ws / rest 为 (价格, 距今时长)，都不可用时返回 None
+pub fn fresh_price(ws: Option<(f64, Duration)>, rest: (f64, Duration), max_age: Duration) -> Option<(f64, PriceSource)> {
+    let usable = |(price, age): (f64, Duration)| price > 0.0 && age <= max_age;
+    match ws {
+        Some(quote) if usable(quote) => Some((quote.0, PriceSource::Ws)),
+        _ if usable(rest) => Some((rest.0, PriceSource::Rest)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_AGE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn prefers_fresh_ws_price() {
+        let ws = Some((101.0, Duration::from_secs(2)));
+        assert_eq!(fresh_price(ws, (100.0, Duration::from_secs(5)), MAX_AGE), Some((101.0, PriceSource::Ws)));
+    }
+
+    #[test]
+    fn falls_back_to_recent_rest_price() {
+        let stale_ws = Some((101.0, Duration::from_secs(90)));
+        assert_eq!(fresh_price(stale_ws, (100.0, Duration::from_secs(5)), MAX_AGE), Some((100.0, PriceSource::Rest)));
+        assert_eq!(fresh_price(None, (100.0, MAX_AGE), MAX_AGE), Some((100.0, PriceSource::Rest)));
+    }
+
+    #[test]
+    fn skips_when_both_feeds_are_stale() {
+        let stale_ws = Some((101.0, Duration::from_secs(90)));
+        assert_eq!(fresh_price(stale_ws, (100.0, Duration::from_secs(61)), MAX_AGE), None);
+        assert_eq!(fresh_price(None, (100.0, Duration::from_secs(3_600)), MAX_AGE), None);
+        // 无效价格视为不可用
+        assert_eq!(fresh_price(Some((0.0, Duration::ZERO)), (0.0, Duration::ZERO), MAX_AGE), None);
+    }
+}
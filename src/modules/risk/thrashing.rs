@@ -0,0 +1,114 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::config::risk_profile::ThrashingConfig;
+use crate::modules::brain::llm::TradeAction;
+
+/// 决策的方向：+1 = 偏多 (开多 / 平空)，-1 = 偏空 (开空 / 平多)，HOLD 不计入
+fn bias(action: &TradeAction) -> Option<i8> {
+    match action {
+        TradeAction::Buy | TradeAction::CloseShort => Some(1),
+        TradeAction::Sell | TradeAction::CloseLong => Some(-1),
+        TradeAction::Hold => None,
+    }
+}
+
+/// 单个币种最近的非 HOLD 决策 (时间, 方向)，只保留窗口内最多 history_len 条
+#[derive(Debug, Default)]
+pub struct DecisionHistory {
+    entries: VecDeque<(Instant, i8)>,
+}
+
+impl DecisionHistory {
+    /// 记录模型本轮给出的动作 (按模型意图记录，即使随后被压制为 HOLD)
+    pub fn record(&mut self, cfg: &ThrashingConfig, action: &TradeAction, now: Instant) {
+        self.prune(cfg, now);
+        if let Some(b) = bias(action) {
+            self.entries.push_back((now, b));
+            while self.entries.len() > cfg.history_len.max(1) {
+                self.entries.pop_front();
+            }
+        }
+    }
+
+    /// 窗口内相邻决策方向反转的次数 (开多 → 平多 → 开多 = 2 次)
+    pub fn reversals(&self) -> usize {
+        self.entries.iter().zip(self.entries.iter().skip(1)).filter(|(a, b)| a.1 != b.1).count()
+    }
+
+    /// 反复横跳判断：再执行一个与上次方向相反的动作会使反转次数达到 max_reversals 时返回 Err(原因)
+    /// 同方向的动作 (如继续开多) 与 HOLD 不受影响；窗口内旧决策过期后自动解除
+    pub fn check(&mut self, cfg: &ThrashingConfig, action: &TradeAction, now: Instant) -> Result<(), String> {
+        if !cfg.enabled { return Ok(()); }
+        self.prune(cfg, now);
+        let (Some(b), Some(&(_, last))) = (bias(action), self.entries.back()) else { return Ok(()) };
+        if b == last { return Ok(()); }
+        let reversals = self.reversals() + 1;
+        if reversals >= cfg.max_reversals {
+            return Err(format!(
+                "{} direction reversals within {}s (limit {})",
+                reversals, cfg.window_sec, cfg.max_reversals
+            ));
+        }
+        Ok(())
+    }
+
+    fn prune(&mut self, cfg: &ThrashingConfig, now: Instant) {
+        let window = Duration::from_secs(cfg.window_sec);
+        while self.entries.front().is_some_and(|(t, _)| now.duration_since(*t) > window) {
+            self.entries.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> ThrashingConfig {
+        ThrashingConfig { enabled: true, window_sec: 3600, max_reversals: 2, history_len: 6 }
+    }
+
+    #[test]
+    fn buy_close_buy_is_thrashing() {
+        let cfg = cfg();
+        let start = Instant::now();
+        let mut h = DecisionHistory::default();
+        h.record(&cfg, &TradeAction::Buy, start);
+        h.record(&cfg, &TradeAction::Hold, start + Duration::from_secs(300));
+        assert!(h.check(&cfg, &TradeAction::CloseLong, start + Duration::from_secs(600)).is_ok());
+        h.record(&cfg, &TradeAction::CloseLong, start + Duration::from_secs(600));
+        assert_eq!(h.reversals(), 1);
+
+        // 再开多 = 第 2 次反转，压制；同方向 (继续平多 / 开空) 不受影响
+        assert!(h.check(&cfg, &TradeAction::Buy, start + Duration::from_secs(900)).is_err());
+        assert!(h.check(&cfg, &TradeAction::Sell, start + Duration::from_secs(900)).is_ok());
+        assert!(h.check(&cfg, &TradeAction::Hold, start + Duration::from_secs(900)).is_ok());
+    }
+
+    #[test]
+    fn pattern_breaks_once_old_decisions_expire() {
+        let cfg = cfg();
+        let start = Instant::now();
+        let mut h = DecisionHistory::default();
+        h.record(&cfg, &TradeAction::Buy, start);
+        h.record(&cfg, &TradeAction::Sell, start + Duration::from_secs(1800));
+        assert!(h.check(&cfg, &TradeAction::Buy, start + Duration::from_secs(2400)).is_err());
+        // 第一笔开多过期后只剩一次开空，再开多只是 1 次反转
+        assert!(h.check(&cfg, &TradeAction::Buy, start + Duration::from_secs(3700)).is_ok());
+    }
+
+    #[test]
+    fn disabled_or_lenient_config_never_blocks() {
+        let start = Instant::now();
+        let mut h = DecisionHistory::default();
+        let strict = cfg();
+        for (i, a) in [TradeAction::Buy, TradeAction::CloseLong, TradeAction::Buy].iter().enumerate() {
+            h.record(&strict, a, start + Duration::from_secs(i as u64 * 60));
+        }
+        let later = start + Duration::from_secs(600);
+        assert!(h.check(&ThrashingConfig { enabled: false, ..cfg() }, &TradeAction::CloseLong, later).is_ok());
+        assert!(h.check(&ThrashingConfig { max_reversals: 4, ..cfg() }, &TradeAction::CloseLong, later).is_ok());
+        assert!(h.check(&strict, &TradeAction::CloseLong, later).is_err());
+    }
+}
@@ -1,8 +1,54 @@
 use reqwest::Client;
-use std::time::Duration;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use anyhow::Result;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
 use tracing::info;
 
+/// 长连接 Client 的连接池设置，来自环境变量 (见 .env.example 第 8 节)
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpPoolSettings {
+    /// false = 每次请求新建连接 (原先的保守模式，适合不稳定网络，HTTP_POOL_MODE=fresh)
+    pub reuse: bool,
+    pub max_idle_per_host: usize,
+    pub idle_timeout: Duration,
+    pub keepalive: Duration,
+    /// 同一主机的最大并发请求数，0 = 不限制
+    pub max_concurrent_per_host: usize,
+}
+
+impl Default for HttpPoolSettings {
+    fn default() -> Self {
+        Self {
+            reuse: true,
+            max_idle_per_host: 8,
+            idle_timeout: Duration::from_secs(90),
+            keepalive: Duration::from_secs(30),
+            max_concurrent_per_host: 4,
+        }
+    }
+}
+
+impl HttpPoolSettings {
+    pub fn from_env() -> Self {
+        Self::from_vars(|key| std::env::var(key).ok())
+    }
+
+    /// 缺失或无法解析的变量使用默认值
+    fn from_vars(get: impl Fn(&str) -> Option<String>) -> Self {
+        let d = Self::default();
+        let num = |key: &str| get(key).and_then(|v| v.trim().parse::<u64>().ok());
+        Self {
+            reuse: get("HTTP_POOL_MODE").is_none_or(|m| !m.trim().eq_ignore_ascii_case("fresh")),
+            max_idle_per_host: num("HTTP_POOL_MAX_IDLE_PER_HOST").map_or(d.max_idle_per_host, |n| n as usize),
+            idle_timeout: num("HTTP_POOL_IDLE_TIMEOUT_SEC").map_or(d.idle_timeout, Duration::from_secs),
+            keepalive: num("HTTP_KEEPALIVE_SEC").map_or(d.keepalive, Duration::from_secs),
+            max_concurrent_per_host: num("HTTP_MAX_CONCURRENT_PER_HOST").map_or(d.max_concurrent_per_host, |n| n as usize),
+        }
+    }
+}
+
 pub struct HttpClientFactory;
 
 impl HttpClientFactory {
@@ -12,7 +58,7 @@ impl HttpClientFactory {
         // 在香港节点，直接连接即可，无需代理
         // 适当缩短超时时间，因为香港访问 OKX 速度很快
         let builder = Client::builder()
-            .timeout(Duration::from_secs(30)) 
+            .timeout(Duration::from_secs(30))
             .connect_timeout(Duration::from_secs(10))
             .pool_idle_timeout(Duration::from_secs(90))
             .tcp_keepalive(Some(Duration::from_secs(30)));
@@ -25,18 +71,131 @@ impl HttpClientFactory {
     }
 
     /// 创建长连接 HTTP Client (用于 DeepSeek/火山引擎)
-    /// [暴力稳定版] 针对大包传输和长推理时间优化
+    /// 针对大包传输和长推理时间优化，连接池设置见 HttpPoolSettings
     pub fn create_direct() -> Result<Client> {
+        let settings = HttpPoolSettings::from_env();
+        if settings.reuse {
+            info!("🌐 [Http Client] LLM/Embedding pool: keep-alive reuse (max idle/host {}, idle timeout {:?}, max concurrent/host {})",
+                settings.max_idle_per_host, settings.idle_timeout, settings.max_concurrent_per_host);
+        } else {
+            info!("🌐 [Http Client] LLM/Embedding pool: fresh connection per request (HTTP_POOL_MODE=fresh)");
+        }
+        Self::create_direct_with(&settings)
+    }
+
+    pub fn create_direct_with(settings: &HttpPoolSettings) -> Result<Client> {
         let builder = Client::builder()
             // 总超时无限长 (1200s)，防止 DeepSeek 推理一半断开
-            .timeout(Duration::from_secs(1200)) 
+            .timeout(Duration::from_secs(1200))
             // 香港节点连接国内或国际 API 应该都比较快，但为了握手稳定，保留较长超时
             .connect_timeout(Duration::from_secs(30))
             // 强制 HTTP/1.1 (稳定，避免 HTTP/2 在某些云厂商网络下的断流问题)
-            .http1_only()
-            .pool_max_idle_per_host(0); // 关闭连接池复用，每次新建连接，确保最稳
+            .http1_only();
+
+        // 复用连接省掉每次请求的 TCP + TLS 握手 (跨境链路上常见 100ms 以上)，
+        // Embedding 重试和连续多个币种的调用都打到同一主机，收益最明显
+        // 空闲超时短于服务端的 keep-alive 关闭时间，避免拿到已被对端关闭的连接
+        let builder = if settings.reuse {
+            builder
+                .pool_max_idle_per_host(settings.max_idle_per_host)
+                .pool_idle_timeout(settings.idle_timeout)
+                .tcp_keepalive(Some(settings.keepalive))
+        } else {
+            builder.pool_max_idle_per_host(0) // 关闭连接池复用，每次新建连接，确保最稳
+        };
 
         let client = builder.build()?;
         Ok(client)
     }
-}
\ No newline at end of file
+}
+
+/// 按主机限制并发请求数 (reqwest 的连接池本身不限制同一主机的连接数)
+pub struct HostLimiter {
+    permits: usize,
+    hosts: Mutex<HashMap<String, Arc<Semaphore>>>,
+}
+
+impl HostLimiter {
+    pub fn new(permits: usize) -> Self {
+        Self { permits, hosts: Mutex::new(HashMap::new()) }
+    }
+
+    /// 请求期间持有返回的 permit；未限制并发时返回 None
+    pub async fn acquire(&self, url: &str) -> Option<OwnedSemaphorePermit> {
+        if self.permits == 0 {
+            return None;
+        }
+        let host = reqwest::Url::parse(url).ok()
+            .and_then(|u| u.host_str().map(|h| h.to_string()))
+            .unwrap_or_default();
+        let sem = self.hosts.lock().unwrap_or_else(|e| e.into_inner())
+            .entry(host)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.permits)))
+            .clone();
+        sem.acquire_owned().await.ok()
+    }
+}
+
+/// LLM/Embedding 调用共用的主机并发限制 (HTTP_MAX_CONCURRENT_PER_HOST)
+pub fn host_limiter() -> &'static HostLimiter {
+    static LIMITER: OnceLock<HostLimiter> = OnceLock::new();
+    LIMITER.get_or_init(|| HostLimiter::new(HttpPoolSettings::from_env().max_concurrent_per_host))
+}
+
+/// 重试等待加 ±25% 抖动，避免多个请求在服务端恢复的同一时刻一起重试
+pub fn jittered(base: Duration) -> Duration {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.subsec_nanos()).unwrap_or(0);
+    apply_jitter(base, nanos as f64 / 1e9)
+}
+
+/// unit 取 [0, 1)，映射到 base 的 [0.75, 1.25) 倍
+fn apply_jitter(base: Duration, unit: f64) -> Duration {
+    base.mul_f64(0.75 + 0.5 * unit.clamp(0.0, 1.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_settings_from_env() {
+        assert_eq!(HttpPoolSettings::from_vars(|_| None), HttpPoolSettings::default());
+
+        let vars = HashMap::from([
+            ("HTTP_POOL_MODE", "Fresh"),
+            ("HTTP_POOL_MAX_IDLE_PER_HOST", "2"),
+            ("HTTP_MAX_CONCURRENT_PER_HOST", "0"),
+            ("HTTP_KEEPALIVE_SEC", "not-a-number"),
+        ]);
+        let s = HttpPoolSettings::from_vars(|k| vars.get(k).map(|v| v.to_string()));
+        assert!(!s.reuse);
+        assert_eq!(s.max_idle_per_host, 2);
+        assert_eq!(s.max_concurrent_per_host, 0);
+        assert_eq!(s.keepalive, HttpPoolSettings::default().keepalive);
+    }
+
+    #[test]
+    fn jitter_stays_within_a_quarter() {
+        let base = Duration::from_secs(4);
+        assert_eq!(apply_jitter(base, 0.0), Duration::from_secs(3));
+        assert_eq!(apply_jitter(base, 0.5), base);
+        assert!(apply_jitter(base, 0.999) < Duration::from_secs(5));
+        let j = jittered(base);
+        assert!(j >= Duration::from_secs(3) && j <= Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn host_limiter_bounds_concurrency_per_host() {
+        let limiter = HostLimiter::new(1);
+        let first = limiter.acquire("https://api.deepseek.com/chat/completions").await;
+        assert!(first.is_some());
+        // 同一主机的第二个请求需要等待，其他主机不受影响
+        let blocked = tokio::time::timeout(Duration::from_millis(20), limiter.acquire("https://api.deepseek.com/v1")).await;
+        assert!(blocked.is_err());
+        assert!(limiter.acquire("https://ark.cn-beijing.volces.com/api/v3").await.is_some());
+        drop(first);
+        assert!(limiter.acquire("https://api.deepseek.com/v1").await.is_some());
+
+        assert!(HostLimiter::new(0).acquire("https://api.deepseek.com").await.is_none());
+    }
+}
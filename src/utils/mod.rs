@@ -1,2 +1,3 @@
 pub mod http_client;
+pub mod money;
 pub mod notifier; // 新增
\ No newline at end of file
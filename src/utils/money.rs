@@ -0,0 +1,85 @@
+// 文件名: money.rs
+// 价格、数量与资金的十进制运算：f64 在多次累加/除法后会出现 2.9999999 这样的尾差，
+// 导致下单数量越过 lotSz 网格被 OKX 拒绝，或盈亏汇总与账单对不上。
+// 这些计算在 Decimal 中完成，只在边界处与 f64 (行情/指标) 和字符串 (OKX 接口) 互转；指标计算仍使用 f64
+
+use std::str::FromStr;
+
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+/// f64 -> Decimal，按最短往返表示转换 (0.1 -> 0.1，而不是 0.1000000000000000055...)
+/// NaN / 无穷 / 超出 Decimal 范围时返回 0
+pub fn dec(value: f64) -> Decimal {
+    if !value.is_finite() { return Decimal::ZERO; }
+    Decimal::from_str(&value.to_string())
+        .or_else(|_| Decimal::from_f64_retain(value).ok_or(()))
+        .unwrap_or(Decimal::ZERO)
+}
+
+/// Decimal -> f64 (交给行情/指标等 f64 逻辑使用)
+pub fn to_f64(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or(0.0)
+}
+
+/// 精确求和 (盈亏汇总)
+pub fn sum(values: impl IntoIterator<Item = f64>) -> f64 {
+    to_f64(values.into_iter().map(dec).sum())
+}
+
+/// 对齐到 step 的整数倍；nearest = false 时向下取整 (下单数量不能超过可用保证金或持仓)
+/// step <= 0 时原样返回
+pub fn snap_to_grid(value: Decimal, step: Decimal, nearest: bool) -> Decimal {
+    if step <= Decimal::ZERO { return value; }
+    let steps = value / step;
+    let steps = if nearest { steps.round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero) } else { steps.floor() };
+    steps * step
+}
+
+/// 网格步长 (tick_size / lot_sz) 的小数位数 (0.5 -> 1, 2.5 -> 1, 0.0001 -> 4, 10 -> 0)
+pub fn grid_decimals(step: f64) -> usize {
+    dec(step).normalize().scale() as usize
+}
+
+/// 对齐到网格并按网格精度格式化为 OKX 接口使用的字符串
+pub fn format_on_grid(value: f64, step: f64, nearest: bool) -> String {
+    let mut snapped = snap_to_grid(dec(value), dec(step), nearest);
+    snapped.rescale(grid_decimals(step) as u32);
+    snapped.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversion_uses_shortest_representation() {
+        assert_eq!(dec(0.1).to_string(), "0.1");
+        assert_eq!(dec(0.00000001).to_string(), "0.00000001");
+        assert_eq!(dec(f64::NAN), Decimal::ZERO);
+        assert_eq!(dec(f64::INFINITY), Decimal::ZERO);
+    }
+
+    #[test]
+    fn sums_do_not_drift() {
+        // f64: 0.1 × 10 = 0.9999999999999999
+        assert_ne!((0..10).map(|_| 0.1).sum::<f64>(), 1.0);
+        assert_eq!(sum((0..10).map(|_| 0.1)), 1.0);
+        assert_eq!(sum([12.34, -5.67, 0.01]), 6.68);
+    }
+
+    #[test]
+    fn grid_rounding_edge_cases() {
+        // 恰好在网格上的数量不能因浮点尾差被向下多减一格
+        assert_eq!(format_on_grid(0.3, 0.1, false), "0.3");
+        assert_eq!(format_on_grid(0.7, 0.1, false), "0.7");
+        assert_eq!(format_on_grid(4.35, 0.05, false), "4.35");
+        // 中点向远离 0 的方向取整 (与 f64::round 一致)
+        assert_eq!(format_on_grid(100.25, 0.5, true), "100.5");
+        assert_eq!(format_on_grid(1.005, 0.01, true), "1.01");
+        // 步长为 0 时不对齐
+        assert_eq!(snap_to_grid(dec(1.2345), Decimal::ZERO, false), dec(1.2345));
+        // 小于一格的数量向下取整为 0
+        assert_eq!(format_on_grid(0.00009, 0.0001, false), "0.0000");
+    }
+}
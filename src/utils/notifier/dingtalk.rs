@@ -0,0 +1,110 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use std::env;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use base64::{Engine as _, engine::general_purpose};
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::form_urlencoded;
+
+use super::Notifier;
+
+pub struct DingTalkNotifier {
+    client: Client,
+    webhook_url: String,
+    secret: String,
+    keyword: String, 
+}
+
+impl DingTalkNotifier {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            webhook_url: env::var("DINGTALK_WEBHOOK").unwrap_or_default(),
+            secret: env::var("DINGTALK_SECRET").unwrap_or_default(),
+            keyword: env::var("DINGTALK_KEYWORD").unwrap_or("Trading".to_string()),
+        }
+    }
+
+    fn get_signed_url(&self) -> String {
+        if self.secret.is_empty() {
+            return self.webhook_url.clone();
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis()
+            .to_string();
+
+        let string_to_sign = format!("{}\n{}", timestamp, self.secret);
+        
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC can take key of any size");
+        mac.update(string_to_sign.as_bytes());
+        let signature = general_purpose::STANDARD.encode(mac.finalize().into_bytes());
+        
+        let encoded_val: String = form_urlencoded::byte_serialize(signature.as_bytes()).collect();
+
+        if self.webhook_url.contains('?') {
+            format!("{}&timestamp={}&sign={}", self.webhook_url, timestamp, encoded_val)
+        } else {
+            format!("{}?timestamp={}&sign={}", self.webhook_url, timestamp, encoded_val)
+        }
+    }
+
+    fn attach_keyword(&self, content: &str) -> String {
+        if self.keyword.is_empty() {
+            return content.to_string();
+        }
+        if content.contains(&self.keyword) {
+            return content.to_string();
+        }
+        format!("{}\n\n[{}]", content, self.keyword)
+    }
+
+    async fn send(&self, body: &serde_json::Value) -> Result<()> {
+        if self.webhook_url.is_empty() { return Ok(()); }
+        
+        let url = self.get_signed_url();
+        let text = self.client.post(&url).json(body).send().await
+            .map_err(|e| anyhow!("DingTalk Network Error: {}", e))?
+            .text().await
+            .map_err(|e| anyhow!("Failed to read response body: {}", e))?;
+
+        if let Ok(json_resp) = serde_json::from_str::<serde_json::Value>(&text) {
+            if json_resp["errcode"].as_i64().unwrap_or(-1) != 0 {
+                return Err(anyhow!("DingTalk Error: {}", text));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Notifier for DingTalkNotifier {
+    fn name(&self) -> &'static str { "DingTalk" }
+
+    fn is_configured(&self) -> bool { !self.webhook_url.is_empty() }
+
+    async fn send_text(&self, content: &str) -> Result<()> {
+        let body = json!({
+            "msgtype": "text",
+            "text": { "content": self.attach_keyword(content) }
+        });
+        self.send(&body).await
+    }
+
+    async fn send_markdown(&self, title: &str, text: &str) -> Result<()> {
+        let body = json!({
+            "msgtype": "markdown",
+            "markdown": {
+                "title": title,
+                "text": self.attach_keyword(text)
+            }
+        });
+        self.send(&body).await
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde_json::json;
+use std::env;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use base64::{Engine as _, engine::general_purpose};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::Notifier;
+
+/// 飞书 / Lark 自定义机器人
+/// 文档: https://open.feishu.cn/document/client-docs/bot-v3/add-custom-bot
+pub struct FeishuNotifier {
+    client: Client,
+    webhook_url: String,
+    secret: String,
+}
+
+impl FeishuNotifier {
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+            webhook_url: env::var("FEISHU_WEBHOOK").unwrap_or_default(),
+            secret: env::var("FEISHU_SECRET").unwrap_or_default(),
+        }
+    }
+
+    /// 飞书签名: key = "timestamp\nsecret"，对空串做 HmacSHA256 后 Base64 (时间戳单位为秒)
+    fn sign(&self, timestamp: &str) -> String {
+        let string_to_sign = format!("{}\n{}", timestamp, self.secret);
+        let mac = Hmac::<Sha256>::new_from_slice(string_to_sign.as_bytes())
+            .expect("HMAC can take key of any size");
+        general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+    }
+
+    async fn send(&self, mut body: serde_json::Value) -> Result<()> {
+        if self.webhook_url.is_empty() { return Ok(()); }
+
+        if !self.secret.is_empty() {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs()
+                .to_string();
+            body["sign"] = json!(self.sign(&timestamp));
+            body["timestamp"] = json!(timestamp);
+        }
+
+        let text = self.client.post(&self.webhook_url).json(&body).send().await
+            .map_err(|e| anyhow!("Feishu Network Error: {}", e))?
+            .text().await
+            .map_err(|e| anyhow!("Failed to read response body: {}", e))?;
+
+        if let Ok(json_resp) = serde_json::from_str::<serde_json::Value>(&text) {
+            // 新版返回 code，旧版返回 StatusCode
+            let code = json_resp["code"].as_i64()
+                .or_else(|| json_resp["StatusCode"].as_i64())
+                .unwrap_or(-1);
+            if code != 0 {
+                return Err(anyhow!("Feishu Error: {}", text));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 把钉钉风格的 Markdown 模板转换为飞书卡片元素
+/// - `---` 分隔线 => hr 元素
+/// - `###` 标题 => 加粗 (lark_md 不支持标题)
+/// - `<font color='#hex'>` => 飞书命名颜色
+fn markdown_to_card_elements(text: &str) -> Vec<serde_json::Value> {
+    let mut elements = Vec::new();
+    let mut buf: Vec<String> = Vec::new();
+
+    let flush = |buf: &mut Vec<String>, elements: &mut Vec<serde_json::Value>| {
+        let content = buf.join("\n").trim().to_string();
+        if !content.is_empty() {
+            elements.push(json!({ "tag": "div", "text": { "tag": "lark_md", "content": content } }));
+        }
+        buf.clear();
+    };
+
+    for line in text.lines() {
+        let trimmed = line.trim();
+        if trimmed == "---" {
+            flush(&mut buf, &mut elements);
+            elements.push(json!({ "tag": "hr" }));
+            continue;
+        }
+        let line = if trimmed.starts_with('#') {
+            format!("**{}**", trimmed.trim_start_matches('#').trim())
+        } else {
+            line.to_string()
+        };
+        buf.push(map_font_colors(&line));
+    }
+    flush(&mut buf, &mut elements);
+    elements
+}
+
+fn map_font_colors(line: &str) -> String {
+    line.replace("<font color='#FF0000'>", "<font color='red'>")
+        .replace("<font color='#00AA00'>", "<font color='green'>")
+        .replace("<font color='#FF9900'>", "<font color='orange'>")
+        .replace("<font color='#0066FF'>", "<font color='blue'>")
+}
+
+#[async_trait]
+impl Notifier for FeishuNotifier {
+    fn name(&self) -> &'static str { "Feishu" }
+
+    fn is_configured(&self) -> bool { !self.webhook_url.is_empty() }
+
+    async fn send_text(&self, content: &str) -> Result<()> {
+        self.send(json!({
+            "msg_type": "text",
+            "content": { "text": content }
+        })).await
+    }
+
+    async fn send_markdown(&self, title: &str, text: &str) -> Result<()> {
+        self.send(json!({
+            "msg_type": "interactive",
+            "card": {
+                "config": { "wide_screen_mode": true },
+                "header": {
+                    "title": { "tag": "plain_text", "content": title },
+                    "template": "blue"
+                },
+                "elements": markdown_to_card_elements(text)
+            }
+        })).await
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+use crate::config::risk_profile::NotifyConfig;
+
+/// 消息优先级：Critical (回撤熔断、下单失败等) 永远不被限流
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Priority {
+    Normal,
+    Critical,
+}
+
+pub enum Verdict {
+    /// 允许发送，附带该消息在去重窗口内被合并的次数
+    Send { coalesced: u32 },
+    Suppress(&'static str),
+}
+
+/// 通知限流器：去重窗口内相同内容只发一次，超出每分钟预算的普通消息直接丢弃
+pub struct RateLimiter {
+    cfg: NotifyConfig,
+    sent: VecDeque<Instant>,
+    /// key => (首次发送时间, 窗口内被合并次数)
+    recent: HashMap<String, (Instant, u32)>,
+    suppressed_total: u64,
+}
+
+impl RateLimiter {
+    pub fn new(cfg: NotifyConfig) -> Self {
+        Self { cfg, sent: VecDeque::new(), recent: HashMap::new(), suppressed_total: 0 }
+    }
+
+    pub fn suppressed_total(&self) -> u64 {
+        self.suppressed_total
+    }
+
+    pub fn check(&mut self, key: &str, priority: Priority, now: Instant) -> Verdict {
+        let window = Duration::from_secs(self.cfg.dedup_window_sec);
+        let minute = Duration::from_secs(60);
+        while self.sent.front().is_some_and(|t| now.duration_since(*t) >= minute) {
+            self.sent.pop_front();
+        }
+
+        if priority == Priority::Critical {
+            self.sent.push_back(now);
+            return Verdict::Send { coalesced: 0 };
+        }
+
+        // 1. 去重：窗口内相同内容只计数不发送，窗口过后再发时带上合并数量
+        let mut coalesced = 0;
+        if let Some((first_sent, count)) = self.recent.get_mut(key) {
+            if now.duration_since(*first_sent) < window {
+                *count += 1;
+                self.suppressed_total += 1;
+                return Verdict::Suppress("duplicate");
+            }
+            coalesced = *count;
+        }
+
+        // 2. 预算：每分钟最多 max_per_minute 条普通消息
+        if self.sent.len() >= self.cfg.max_per_minute as usize {
+            self.suppressed_total += 1;
+            return Verdict::Suppress("rate limit");
+        }
+
+        self.recent.retain(|_, (t, _)| now.duration_since(*t) < window);
+        self.recent.insert(key.to_string(), (now, 0));
+        self.sent.push_back(now);
+        Verdict::Send { coalesced }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(max_per_minute: u32) -> RateLimiter {
+        RateLimiter::new(NotifyConfig { max_per_minute, dedup_window_sec: 300, ..NotifyConfig::default() })
+    }
+
+    fn sent(v: Verdict) -> Option<u32> {
+        match v {
+            Verdict::Send { coalesced } => Some(coalesced),
+            Verdict::Suppress(_) => None,
+        }
+    }
+
+    #[test]
+    fn duplicates_are_coalesced_until_the_window_passes() {
+        let mut l = limiter(10);
+        let t0 = Instant::now();
+        assert_eq!(sent(l.check("a", Priority::Normal, t0)), Some(0));
+        assert!(matches!(l.check("a", Priority::Normal, t0 + Duration::from_secs(10)), Verdict::Suppress("duplicate")));
+        assert!(matches!(l.check("a", Priority::Normal, t0 + Duration::from_secs(20)), Verdict::Suppress("duplicate")));
+        // 其他内容不受影响
+        assert_eq!(sent(l.check("b", Priority::Normal, t0 + Duration::from_secs(20))), Some(0));
+        // 窗口过后再发，带上合并的数量，并重新开始计数
+        assert_eq!(sent(l.check("a", Priority::Normal, t0 + Duration::from_secs(300))), Some(2));
+        assert!(matches!(l.check("a", Priority::Normal, t0 + Duration::from_secs(301)), Verdict::Suppress("duplicate")));
+        assert_eq!(l.suppressed_total(), 3);
+    }
+
+    #[test]
+    fn normal_messages_over_budget_are_dropped() {
+        let mut l = limiter(2);
+        let t0 = Instant::now();
+        assert!(sent(l.check("a", Priority::Normal, t0)).is_some());
+        assert!(sent(l.check("b", Priority::Normal, t0)).is_some());
+        assert!(matches!(l.check("c", Priority::Normal, t0), Verdict::Suppress("rate limit")));
+        // 被丢弃的消息不计入去重，预算恢复后可以立即发送
+        assert_eq!(sent(l.check("c", Priority::Normal, t0 + Duration::from_secs(60))), Some(0));
+        assert_eq!(l.suppressed_total(), 1);
+    }
+
+    #[test]
+    fn critical_messages_always_pass_but_use_the_budget() {
+        let mut l = limiter(1);
+        let t0 = Instant::now();
+        // Critical 不去重、不受预算限制
+        for _ in 0..3 {
+            assert_eq!(sent(l.check("halt", Priority::Critical, t0)), Some(0));
+        }
+        // 但占用本分钟的预算，之后的普通消息被丢弃
+        assert!(matches!(l.check("a", Priority::Normal, t0), Verdict::Suppress("rate limit")));
+        assert_eq!(l.suppressed_total(), 1);
+    }
+}
@@ -0,0 +1,546 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use reqwest::Client;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+use tracing::{info, warn, error};
+use crate::config::risk_profile::NotifyConfig;
+use crate::utils::http_client::jittered;
+use crate::modules::action::executor::PositionSummary;
+use crate::modules::risk::breakeven;
+use crate::modules::evolution::stats::{ClosedTradeRecord, PerformanceReport, VersionStats, WindowSummary, MIN_CLOSED_TRADES};
+
+pub mod dingtalk;
+pub mod feishu;
+pub mod limiter;
+
+pub use dingtalk::DingTalkNotifier;
+pub use feishu::FeishuNotifier;
+pub use limiter::Priority;
+
+use limiter::{RateLimiter, Verdict};
+
+/// [新增] 用于构建友好的持仓报告
+pub struct PositionReportItem {
+    pub symbol: String,
+    pub side: String,
+    pub notional_usdt: f64, 
+    pub margin_usdt: f64,   
+    pub upl: f64,           
+    pub leverage: u32,      
+    /// 浮动盈亏的 R 倍数 (R = 入场价到止损价的距离)；缺少止损记录或止损已移至保本时为 None
+    pub r_multiple: Option<f64>,
+}
+
+impl PositionReportItem {
+    /// sl_price 为本地记录的止损价 (旧持仓没有记录时传 None)
+    pub fn new(p: &PositionSummary, sl_price: Option<f64>) -> Self {
+        Self {
+            symbol: p.symbol.clone(),
+            side: p.side.clone(),
+            notional_usdt: p.notional_usd,
+            margin_usdt: p.margin_usd,
+            upl: p.upl,
+            leverage: p.leverage,
+            r_multiple: sl_price.filter(|sl| *sl > 0.0).and_then(|sl| breakeven::unrealized_r(&p.side, p.avg_px, sl, p.mark_px)),
+        }
+    }
+
+    fn r_label(&self) -> String {
+        self.r_multiple.map_or("n/a".to_string(), |r| format!("{:+.2}R", r))
+    }
+}
+
+/// 通知后端 (钉钉 / 飞书 ...)，只负责投递，消息模板由 NotifierHub 统一渲染
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// 未配置 Webhook 时返回 false，Hub 不会注册该后端
+    fn is_configured(&self) -> bool;
+    async fn send_text(&self, content: &str) -> Result<()>;
+    /// text 为钉钉风格 Markdown，其他后端自行转换格式
+    async fn send_markdown(&self, title: &str, text: &str) -> Result<()>;
+}
+
+#[derive(Clone)]
+enum Message {
+    Text(String),
+    Markdown { title: String, text: String },
+}
+
+/// 首次投递失败后即时重试前的等待
+const INLINE_RETRY_DELAY: Duration = Duration::from_millis(300);
+
+/// 发送失败的关键消息，按后端记录，避免已成功的后端重复收到
+struct PendingMessage {
+    backend: usize,
+    message: Message,
+    /// 已补发的次数
+    retries: u32,
+}
+
+/// 通知中心：渲染消息模板、限流去重后广播到所有已配置的后端
+pub struct NotifierHub {
+    backends: Vec<Box<dyn Notifier>>,
+    limiter: Mutex<RateLimiter>,
+    pending: Mutex<VecDeque<PendingMessage>>,
+    cfg: NotifyConfig,
+}
+
+impl NotifierHub {
+    pub fn from_env(client: Client, cfg: NotifyConfig) -> Self {
+        let candidates: Vec<Box<dyn Notifier>> = vec![
+            Box::new(DingTalkNotifier::new(client.clone())),
+            Box::new(FeishuNotifier::new(client)),
+        ];
+        Self::with_backends(candidates.into_iter().filter(|b| b.is_configured()).collect(), cfg)
+    }
+
+    /// 使用指定后端构建 (测试中注入记录型后端)
+    pub fn with_backends(backends: Vec<Box<dyn Notifier>>, cfg: NotifyConfig) -> Self {
+        if backends.is_empty() {
+            info!("🔕 No notifier configured. Notifications disabled.");
+        } else {
+            let names: Vec<&str> = backends.iter().map(|b| b.name()).collect();
+            info!("🔔 Notifiers enabled: {}", names.join(", "));
+        }
+        Self {
+            backends,
+            limiter: Mutex::new(RateLimiter::new(cfg.clone())),
+            pending: Mutex::new(VecDeque::new()),
+            cfg,
+        }
+    }
+
+    /// 限流检查，返回 None 表示丢弃；Some(n) 为去重窗口内被合并的同类消息数
+    fn admit(&self, key: &str, priority: Priority) -> Option<u32> {
+        if self.backends.is_empty() { return None; }
+        let mut limiter = self.limiter.lock().unwrap();
+        match limiter.check(key, priority, Instant::now()) {
+            Verdict::Send { coalesced } => Some(coalesced),
+            Verdict::Suppress(reason) => {
+                let preview: String = key.chars().take(60).collect();
+                warn!("🔕 Notification suppressed ({}): {:?}... (total suppressed: {})", reason, preview, limiter.suppressed_total());
+                None
+            }
+        }
+    }
+
+    async fn deliver_once(&self, backend: &dyn Notifier, message: &Message) -> Result<()> {
+        match message {
+            Message::Text(content) => backend.send_text(content).await,
+            Message::Markdown { title, text } => backend.send_markdown(title, text).await,
+        }
+    }
+
+    /// 失败后短暂等待再重试一次 (吸收偶发的网络抖动)：调用方多在交易路径上，不做更长的退避，
+    /// Critical 消息仍失败时交给补发队列
+    async fn deliver(&self, backend: &dyn Notifier, message: &Message) -> Result<()> {
+        if let Err(e) = self.deliver_once(backend, message).await {
+            warn!("⚠️ [{}] Notify failed, retrying once: {}", backend.name(), e);
+            sleep(jittered(INLINE_RETRY_DELAY)).await;
+            return self.deliver_once(backend, message).await;
+        }
+        Ok(())
+    }
+
+    async fn broadcast(&self, message: Message, priority: Priority) {
+        for (idx, backend) in self.backends.iter().enumerate() {
+            if let Err(e) = self.deliver(backend.as_ref(), &message).await {
+                error!("❌ [{}] {}", backend.name(), e);
+                if priority == Priority::Critical {
+                    self.enqueue(PendingMessage { backend: idx, message: message.clone(), retries: 0 });
+                }
+            }
+        }
+    }
+
+    fn enqueue(&self, item: PendingMessage) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.len() >= self.cfg.max_pending {
+            if let Some(dropped) = pending.pop_front() {
+                warn!("🗑️ Notify queue full ({}). Dropped oldest pending message for [{}].",
+                    self.cfg.max_pending, self.backends[dropped.backend].name());
+            }
+        }
+        if self.cfg.max_pending > 0 {
+            pending.push_back(item);
+        }
+    }
+
+    /// 重发补发队列中的关键消息 (每轮主循环调用一次)，仍失败的重新入队，补发 retry_attempts 次后放弃
+    pub async fn flush_pending(&self) {
+        let items: Vec<PendingMessage> = self.pending.lock().unwrap().drain(..).collect();
+        if items.is_empty() { return; }
+
+        info!("📮 Retrying {} pending critical notification(s)...", items.len());
+        for mut item in items {
+            let backend = self.backends[item.backend].as_ref();
+            if let Err(e) = self.deliver_once(backend, &item.message).await {
+                item.retries += 1;
+                if item.retries >= self.cfg.retry_attempts {
+                    error!("🗑️ [{}] Giving up on pending notification after {} retries: {}", backend.name(), item.retries, e);
+                    continue;
+                }
+                warn!("⚠️ [{}] Pending notification still failing (retry {}/{}): {}", backend.name(), item.retries, self.cfg.retry_attempts, e);
+                self.enqueue(item);
+            }
+        }
+    }
+
+    async fn broadcast_text(&self, content: &str, priority: Priority) {
+        let Some(coalesced) = self.admit(content, priority) else { return; };
+        self.broadcast(Message::Text(with_coalesced_note(content, coalesced)), priority).await;
+    }
+
+    async fn broadcast_markdown(&self, title: &str, text: &str, priority: Priority) {
+        let Some(coalesced) = self.admit(&format!("{}\n{}", title, text), priority) else { return; };
+        self.broadcast(Message::Markdown { title: title.to_string(), text: with_coalesced_note(text, coalesced) }, priority).await;
+    }
+
+    pub async fn send_alert(&self, content: &str, priority: Priority) {
+        let prefix = "⚠️ [RustTrader Alert]";
+        
+        self.broadcast_text(&format!("{}\n{}", prefix, content), priority).await;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_trade_signal(
+        &self, 
+        symbol: &str, 
+        action: &str, 
+        size: f64, 
+        price: f64, 
+        reason: &str, 
+        tp_pct: f64, 
+        sl_pct: f64,
+        priority: Priority
+    ) {
+        let title = format!("{} {} (Signal)", action.to_uppercase(), symbol);
+        
+        let side_color = if action.to_lowercase().contains("buy") || action.to_lowercase().contains("long") {
+            "#00AA00" 
+        } else {
+            "#FF0000" 
+        };
+
+        let (tp_price, sl_price) = if action.to_lowercase().contains("buy") {
+            (price * (1.0 + tp_pct), price * (1.0 - sl_pct))
+        } else {
+            (price * (1.0 - tp_pct), price * (1.0 + sl_pct))
+        };
+
+        let raw_text = format!(
+            "### <font color='{}'>🚀 交易执行: {}</font>\n\n\
+            **标的**: {}\n\
+            **数量**: {:.4} 张\n\
+            **成交价**: ${:.2}\n\
+            \n---\n\
+            **🎯 计划止盈**: ${:.2} ({:.1}%)\n\
+            **🛡️ 计划止损**: ${:.2} ({:.1}%)\n\
+            \n---\n\
+            **🧠 AI 决策逻辑**:\n> {}\n",
+            side_color, action.to_uppercase(), symbol, size, price,
+            tp_price, tp_pct * 100.0,
+            sl_price, sl_pct * 100.0,
+            reason
+        );
+
+        self.broadcast_markdown(&title, &raw_text, priority).await;
+    }
+
+    pub async fn send_startup_report(
+        &self,
+        initial_capital: f64,
+        start_time: &str,
+        positions: Vec<PositionReportItem>
+    ) {
+        let title = "🚀 系统已启动 (Boot)";
+        
+        let mut pos_desc = String::new();
+        if positions.is_empty() {
+            pos_desc = "> *当前无持仓 (Flat)*".to_string();
+        } else {
+            for p in positions {
+                let side_icon = if p.side.to_lowercase().contains("long") { "🟢" } else { "🔴" };
+                let pnl_color = if p.upl >= 0.0 { "#FF0000" } else { "#00AA00" };
+                let pnl_sign = if p.upl >= 0.0 { "+" } else { "" };
+                
+                pos_desc.push_str(&format!(
+                    "- {} **{}** ({}x)\n   📦 **仓位价值**: `${:.0}`\n   🔒 **投入本金**: `${:.0}`\n   💰 **浮动盈亏**: <font color='{}'>{}${:.2}</font> (`{}`)\n\n",
+                    side_icon, 
+                    p.symbol.split('-').next().unwrap_or(&p.symbol),
+                    p.leverage,
+                    p.notional_usdt,
+                    p.margin_usdt,
+                    pnl_color, pnl_sign, p.upl, p.r_label()
+                ));
+            }
+        }
+
+        let raw_text = format!(
+            "### Rust Trader V6.0 (HK Node)\n\n\
+            ---\n\
+            💰 **初始本金**: `${:.2}`\n\
+            🕒 **启动时间**: {}\n\
+            📊 **本轮收益**: `0.00%` (基准已建立)\n\
+            \n---\n\
+            #### 🏷️ 初始持仓详情\n\
+            {}",
+            initial_capital, start_time, pos_desc
+        );
+
+        self.broadcast_markdown(title, &raw_text, Priority::Critical).await;
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn send_status_report(
+        &self, 
+        equity: f64, 
+        pnl_pct: f64, 
+        positions: Vec<PositionReportItem>,
+        closed: Option<&WindowSummary>,
+        stats: Option<&PerformanceReport>,
+        versions: &[VersionStats],
+        simulated: bool
+    ) {
+        // 干跑模式下权益来自模拟账本，必须明确标注，避免与真实账户混淆
+        let (title, equity_label) = if simulated {
+            ("📊 运行周报 [模拟盘]", "🧪 **模拟权益 (DRY RUN)**")
+        } else {
+            ("📊 运行周报", "💰 **当前权益**")
+        };
+        let pnl_color = if pnl_pct >= 0.0 { "#FF0000" } else { "#00AA00" }; 
+        let pnl_sign = if pnl_pct >= 0.0 { "+" } else { "" };
+
+        let mut pos_desc = String::new();
+        if positions.is_empty() {
+            pos_desc = "> *当前无持仓 (Flat)*".to_string();
+        } else {
+            for p in positions {
+                let side_icon = if p.side.to_lowercase().contains("long") { "🟢" } else { "🔴" };
+                let item_pnl_color = if p.upl >= 0.0 { "#FF0000" } else { "#00AA00" };
+                
+                pos_desc.push_str(&format!(
+                    "- {} **{}** ({}x)\n   `${:.0}`(仓位) | `${:.0}`(本金) | <font color='{}'>${:.2}</font> | `{}`\n",
+                    side_icon, 
+                    p.symbol.split('-').next().unwrap_or(&p.symbol),
+                    p.leverage,
+                    p.notional_usdt,
+                    p.margin_usdt,
+                    item_pnl_color, p.upl, p.r_label()
+                ));
+            }
+        }
+
+        let closed_desc = match closed {
+            Some(c) if c.closed_trades > 0 => {
+                let fmt_trade = |t: &Option<ClosedTradeRecord>| t.as_ref()
+                    .map(|t| format!("{} {} ${:+.2}", t.symbol.split('-').next().unwrap_or(&t.symbol), if t.direction == "sell" { "空" } else { "多" }, t.pnl))
+                    .unwrap_or("-".to_string());
+                let net_color = if c.net_pnl >= 0.0 { "#FF0000" } else { "#00AA00" };
+                format!(
+                    "\n---\n🧾 **本期平仓 (近{}分钟)**:\n\
+                    - 笔数: `{}` | 盈/亏: `{}/{}`\n\
+                    - 净盈亏: <font color='{}'>${:+.2}</font>\n\
+                    - 最佳: `{}` | 最差: `{}`\n",
+                    c.window_secs / 60, c.closed_trades, c.wins, c.losses,
+                    net_color, c.net_pnl, fmt_trade(&c.best), fmt_trade(&c.worst)
+                )
+            }
+            Some(c) => format!("\n---\n🧾 **本期平仓 (近{}分钟)**:\n> *本期无平仓交易*\n", c.window_secs / 60),
+            None => String::new(),
+        };
+
+        let stats_desc = match stats {
+            Some(PerformanceReport::Ready(s)) => format!(
+                "\n---\n📐 **绩效统计 (近{}天)**:\n\
+                - 平仓笔数: `{}` | 胜率: `{:.1}%`\n\
+                - 平均盈利: `${:.2}` | 平均亏损: `${:.2}`\n\
+                - 净盈亏: `${:.2}` | 盈亏因子: `{}`\n\
+                - Sharpe: `{}` | Sortino: `{}`\n",
+                s.window_days, s.closed_trades, s.win_rate * 100.0,
+                s.avg_win, s.avg_loss, s.net_pnl,
+                s.profit_factor.map(|x| format!("{:.2}", x)).unwrap_or("∞".to_string()),
+                s.sharpe.map(|x| format!("{:.2}", x)).unwrap_or("n/a".to_string()),
+                s.sortino.map(|x| format!("{:.2}", x)).unwrap_or("n/a".to_string())
+            ),
+            Some(PerformanceReport::InsufficientData { window_days, closed_trades }) => format!(
+                "\n---\n📐 **绩效统计 (近{}天)**:\n> *数据不足 (仅 {} 笔已平仓交易)*\n",
+                window_days, closed_trades
+            ),
+            None => String::new(),
+        };
+
+        // 只有一个版本时没有可比较的对象，不展示
+        let versions_desc = if versions.len() > 1 {
+            let mut desc = "\n---\n🧪 **策略版本对比**:\n".to_string();
+            for v in versions {
+                let note = if v.is_significant() { String::new() } else { format!(" *(样本不足 {} 笔)*", MIN_CLOSED_TRADES) };
+                desc.push_str(&format!("- `{}`: {} 笔 | 胜率 `{:.1}%` | 净盈亏 `${:.2}`{}\n",
+                    v.version, v.closed_trades, v.win_rate() * 100.0, v.net_pnl, note));
+            }
+            desc
+        } else {
+            String::new()
+        };
+
+        let raw_text = format!(
+            "### 🤖 系统运行状态\n\n\
+            {}: `${:.2}`\n\
+            📈 **累计收益**: <font color='{}'>{}{:.2}%</font>\n\n\
+            🏷️ **持仓资金分布**:\n{}{}{}{}",
+            equity_label, equity, pnl_color, pnl_sign, pnl_pct, pos_desc, closed_desc, stats_desc, versions_desc
+        );
+        
+        self.broadcast_markdown(title, &raw_text, Priority::Normal).await;
+    }
+
+    /// [修改] 增加 #[allow(dead_code)] 避免未使用的警告
+    #[allow(dead_code)]
+    pub async fn send_evolution_log(&self, log_type: &str, symbol: &str, content: &str) {
+        let title = format!("🧬 AI Evolution: {}", log_type);
+        let color = if log_type == "MISTAKE" { "#FF9900" } else { "#0066FF" };
+        
+        let raw_text = format!(
+            "### <font color='{}'>🧬 进化日志: {}</font>\n\n\
+            **标的**: {}\n\n\
+            **内容摘要**:\n> {}",
+            color, log_type, symbol, content
+        );
+        
+        self.broadcast_markdown(&title, &raw_text, Priority::Normal).await;
+    }
+
+    // [修改] 增加 #[allow(dead_code)] 避免未使用的警告
+    #[allow(dead_code)]
+    pub async fn send_markdown(&self, title: &str, text: &str, priority: Priority) {
+        self.broadcast_markdown(title, text, priority).await;
+    }
+    
+    pub async fn send_text(&self, content: &str, priority: Priority) {
+        self.send_alert(content, priority).await;
+    }
+}
+
+fn with_coalesced_note(content: &str, coalesced: u32) -> String {
+    if coalesced == 0 { return content.to_string(); }
+    format!("{}\n\n(过去窗口内另有 {} 条相同通知已合并)", content, coalesced)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::mock::RecordingNotifier;
+
+    fn position(side: &str, avg_px: f64, mark_px: f64) -> PositionSummary {
+        PositionSummary {
+            symbol: "BTC-USDT-SWAP".to_string(), size: 1.0, upl: 0.0, side: side.to_string(), avg_px,
+            mark_px, leverage: 5, notional_usd: 1000.0, margin_usd: 200.0,
+        }
+    }
+
+    #[test]
+    fn r_multiple_uses_stored_stop() {
+        // 多单 100 入场，止损 95 (R = 5)，现价 110 => +2R
+        assert_eq!(PositionReportItem::new(&position("long", 100.0, 110.0), Some(95.0)).r_multiple, Some(2.0));
+        // 空单 100 入场，止损 104，现价 102 => -0.5R
+        assert_eq!(PositionReportItem::new(&position("short", 100.0, 102.0), Some(104.0)).r_multiple, Some(-0.5));
+        // 旧持仓没有止损记录
+        let item = PositionReportItem::new(&position("long", 100.0, 110.0), None);
+        assert_eq!(item.r_multiple, None);
+        assert_eq!(item.r_label(), "n/a");
+    }
+
+    #[tokio::test]
+    async fn status_report_shows_r_multiple() {
+        let backend = RecordingNotifier::default();
+        let hub = NotifierHub::with_backends(vec![Box::new(backend.clone())], NotifyConfig::default());
+        let items = vec![
+            PositionReportItem::new(&position("long", 100.0, 110.0), Some(95.0)),
+            PositionReportItem::new(&position("short", 100.0, 98.0), None),
+        ];
+        hub.send_status_report(10_000.0, 1.0, items, None, None, &[], false).await;
+        let msg = backend.messages().join("\n");
+        assert!(msg.contains("`+2.00R`"), "{}", msg);
+        assert!(msg.contains("`n/a`"), "{}", msg);
+    }
+
+    /// 始终投递失败的后端，记录调用次数
+    #[derive(Clone, Default)]
+    struct FailingNotifier {
+        calls: std::sync::Arc<Mutex<u32>>,
+    }
+
+    #[async_trait]
+    impl Notifier for FailingNotifier {
+        fn name(&self) -> &'static str { "failing" }
+        fn is_configured(&self) -> bool { true }
+        async fn send_text(&self, _content: &str) -> Result<()> {
+            *self.calls.lock().unwrap() += 1;
+            Err(anyhow::anyhow!("webhook down"))
+        }
+        async fn send_markdown(&self, _title: &str, _text: &str) -> Result<()> {
+            *self.calls.lock().unwrap() += 1;
+            Err(anyhow::anyhow!("webhook down"))
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_sends_retry_once_then_from_the_queue() {
+        let backend = FailingNotifier::default();
+        let cfg = NotifyConfig { retry_attempts: 2, ..NotifyConfig::default() };
+        let hub = NotifierHub::with_backends(vec![Box::new(backend.clone())], cfg);
+
+        // 首次投递失败后即时重试一次，普通消息仍失败直接丢弃，Critical 进入补发队列
+        hub.send_text("normal", Priority::Normal).await;
+        hub.send_text("critical", Priority::Critical).await;
+        assert_eq!(*backend.calls.lock().unwrap(), 4);
+        assert_eq!(hub.pending.lock().unwrap().len(), 1);
+
+        // 每轮补发一次 (不再即时重试)，补发 retry_attempts 次后放弃
+        hub.flush_pending().await;
+        assert_eq!(hub.pending.lock().unwrap().len(), 1);
+        hub.flush_pending().await;
+        assert!(hub.pending.lock().unwrap().is_empty());
+        hub.flush_pending().await;
+        assert_eq!(*backend.calls.lock().unwrap(), 6);
+    }
+}
+
+/// 测试用后端：记录所有投递的消息
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use std::sync::Arc;
+
+    #[derive(Clone, Default)]
+    pub struct RecordingNotifier {
+        pub sent: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl RecordingNotifier {
+        pub fn messages(&self) -> Vec<String> {
+            self.sent.lock().unwrap().clone()
+        }
+    }
+
+    #[async_trait]
+    impl Notifier for RecordingNotifier {
+        fn name(&self) -> &'static str { "recording" }
+
+        fn is_configured(&self) -> bool { true }
+
+        async fn send_text(&self, content: &str) -> Result<()> {
+            self.sent.lock().unwrap().push(content.to_string());
+            Ok(())
+        }
+
+        async fn send_markdown(&self, title: &str, text: &str) -> Result<()> {
+            self.sent.lock().unwrap().push(format!("{}\n{}", title, text));
+            Ok(())
+        }
+    }
+}